@@ -0,0 +1,25 @@
+use std::{env, fs, path::Path};
+
+/// Pass the apple-bom version locked in `Cargo.lock` to the crate as
+/// `APPLE_BOM_VERSION`, for `build_info()`.
+fn main() {
+    let lock = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock.display());
+    let lock = fs::read_to_string(&lock).expect("Cargo.lock is needed to build pyapplebom");
+
+    let version = lock
+        .split("[[package]]")
+        .find(|package| {
+            package
+                .lines()
+                .any(|line| line.trim() == "name = \"apple-bom\"")
+        })
+        .and_then(|package| {
+            package
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("version = "))
+        })
+        .map(|version| version.trim_matches('"'))
+        .expect("Cargo.lock has no apple-bom package");
+    println!("cargo:rustc-env=APPLE_BOM_VERSION={version}");
+}
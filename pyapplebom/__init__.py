@@ -2,66 +2,1284 @@
 
 from __future__ import annotations
 
-from os import PathLike
-from typing import Any
+import json
+from os import PathLike, fsdecode, fspath
+from typing import Any, Callable, Iterable, Iterator, Mapping, Optional, Protocol, Sequence, Tuple
+from typing import Union
 
-from ._native import BomParseError, __version__, parse_bom_bytes as _parse_bom_bytes
+from ._native import BomBuilder, BomParseError, __version__, parse_bom_bytes as _parse_bom_bytes
+from ._native import BomPathIterator, BomStore, FeatureNotAvailable
+from ._native import BlockKind, BomBlocks, BomFile, BomHeader, BomPathEntry
+from ._native import CorpusIndex, KnownFiles, ParseOptions, PathType, bloom_contains
+from ._native import build_info, get_default_options, memory_footprint
+from ._native import register_path_type, registered_path_types, set_default_options
+from ._native import unregister_path_type
+from ._native import add_block as _add_block
+from ._native import ancestors as _ancestors
+from ._native import apply_delta as _apply_delta
+from ._native import block_layout as _block_layout
+from ._native import block_type_histogram as _block_type_histogram
+from ._native import capabilities as _capabilities
+from ._native import compact as _compact
+from ._native import compare_with_bundle as _compare_with_bundle
+from ._native import diff3_boms as _diff3_boms
+from ._native import diff_boms as _diff_boms
+from ._native import effective_access as _effective_access
+from ._native import export_hash_set as _export_hash_set
+from ._native import export_mtree as _export_mtree
+from ._native import get_block as _get_block
+from ._native import get_path as _get_path
+from ._native import iter_paths as _iter_paths
+from ._native import known_file_deviations as _known_file_deviations
+from ._native import format_lsbom as _format_lsbom
+from ._native import make_delta as _make_delta
+from ._native import mtime_profile as _mtime_profile
 from ._native import parse_bom_file as _parse_bom_file
+from ._native import parse_bom_header as _parse_bom_header
+from ._native import path_bloom as _path_bloom
+from ._native import path_by_id as _path_by_id
+from ._native import paths_tree as _paths_tree
+from ._native import remove_variable as _remove_variable
+from ._native import rename_variable as _rename_variable
+from ._native import rewrite_indexes as _rewrite_indexes
+from ._native import risk_score as _risk_score
+from ._native import shell_footprint as _shell_footprint
+from ._native import size_report as _size_report
+from ._native import summarize_diff as _summarize_diff
+from ._native import to_json as _to_json
+from ._native import unified_paths as _unified_paths
+from ._native import verify_bom as _verify_bom
+from ._pkg import read_package_boms, read_payload_sizes, scan_receipts
+from ._shared import SharedPathTable
 
 __all__ = [
+    "BlockKind",
+    "BomBlocks",
+    "BomBuilder",
+    "BomFile",
+    "BomHeader",
+    "BomPathEntry",
+    "BomPathIterator",
+    "BomStore",
     "BomParseError",
+    "CorpusIndex",
+    "EXIT_ERROR",
+    "EXIT_FINDINGS",
+    "EXIT_OK",
+    "FeatureNotAvailable",
+    "KnownFiles",
+    "ParseOptions",
+    "PathType",
+    "SharedPathTable",
     "__version__",
+    "add_block",
+    "ancestors",
+    "apply_delta",
+    "block_layout",
+    "block_type_histogram",
+    "bloom_contains",
+    "build_bom_from_directory",
+    "build_info",
+    "capabilities",
+    "compact",
+    "compare_package_versions",
+    "compare_with_bundle",
+    "diff3_boms",
+    "diff_boms",
+    "effective_access",
+    "exit_code",
+    "export_hash_set",
+    "export_mtree",
+    "format_lsbom",
+    "get_block",
+    "get_default_options",
+    "get_path",
+    "iter_paths",
+    "known_file_deviations",
+    "load_suppressions",
+    "make_delta",
+    "memory_footprint",
+    "mtime_profile",
     "parse_bom",
     "parse_bom_bytes",
     "parse_bom_file",
+    "parse_bom_header",
+    "parse_bom_stream",
+    "path_bloom",
+    "path_by_id",
+    "paths_tree",
+    "read_package_boms",
+    "register_path_type",
+    "registered_path_types",
+    "remove_variable",
+    "rename_variable",
+    "rewrite_indexes",
+    "risk_score",
+    "scan_receipts",
+    "set_default_options",
+    "shell_footprint",
+    "size_report",
+    "summarize_diff",
+    "to_json",
+    "unified_paths",
+    "unregister_path_type",
+    "verify_bom",
+    "walk",
 ]
 
+EXIT_OK = 0
+EXIT_FINDINGS = 1
+EXIT_ERROR = 2
+
+BlockEntry = Union[Mapping[str, Any], Sequence[int]]
+VariableEntry = Union[Mapping[str, Any], Tuple[str, int]]
+
+# Streams are read this many bytes at a time, so those that return short
+# reads (sockets, pipes) and those without a size both work.
+_STREAM_CHUNK_SIZE = 1 << 20
+
+
+class _Readable(Protocol):
+    def read(self, size: int, /) -> Optional[bytes]: ...
+
+
+def _as_bytes(data: bytes | bytearray | memoryview) -> bytes:
+    """Return ``data``, or a copy of any other buffer-protocol object as bytes.
+
+    For the corpus functions, whose worker threads read BOMs without holding
+    the GIL and so only take ``bytes``, which can't change under them.
+    """
+    if isinstance(data, bytes):
+        return data
+
+    try:
+        view = memoryview(data)
+    except TypeError:
+        raise TypeError(
+            f"data must be bytes or support the buffer protocol, not {type(data).__name__}"
+        ) from None
+    with view:
+        return view.tobytes()
+
 
 def parse_bom(
     data: bytes | bytearray | memoryview,
     *,
-    include_blocks: bool = True,
-    include_raw_block_bytes: bool = False,
+    options: ParseOptions | None = None,
+    include_blocks: bool | None = None,
+    sections: Sequence[str] | None = None,
+    include_raw_block_bytes: bool | None = None,
+    raw_block_encoding: str | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    interpret_fields: bool | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+    typed_paths: bool | None = None,
+    lazy_blocks: bool | None = None,
+    on_panic: str | None = None,
+    on_duplicate_variable: str | None = None,
 ) -> dict[str, Any]:
-    """Parse Apple BOM data from a bytes-like object."""
-    if isinstance(data, memoryview):
-        data = data.tobytes()
-    elif isinstance(data, bytearray):
-        data = bytes(data)
+    """Parse Apple BOM data from ``bytes`` or any buffer-protocol object.
 
-    if not isinstance(data, bytes):
-        raise TypeError("data must be bytes, bytearray, or memoryview")
+    ``sections`` names the path sections to compute, out of ``"paths"``,
+    ``"hl_index"``, ``"size64"``, and ``"vindex"`` (all by default); the
+    others are left out of the document.
 
+    ``path_style`` controls how BOM paths are emitted: ``"raw"`` keeps them as
+    stored (``./usr/bin/tool``), ``"relative"`` drops the leading ``./``, and
+    ``"absolute"`` joins them onto ``path_root``. ``path_order`` is ``"tree"``
+    for the order the ``Paths`` tree stores them in (as ``lsbom`` lists them)
+    or ``"sorted"`` for sorted by path component. ``interpret_fields`` adds
+    the best-known meanings of unnamed block fields under ``interpretations``,
+    and ``include_tree_positions`` the ``Paths`` leaf entry each path came from.
+    ``include_raw_records`` adds the bytes of each path's ``PathRecord`` block.
+    ``raw_block_encoding`` is ``"hex"`` to give raw block and record bytes as
+    ``raw_hex`` and ``raw_record_hex`` strings or ``"bytes"`` to give them as
+    ``raw`` and ``raw_record`` bytes objects.
+    ``typed_paths`` emits path entries as :class:`BomPathEntry` objects, and
+    ``lazy_blocks`` makes ``blocks`` a :class:`BomBlocks` sequence that parses
+    each block when it is indexed. ``on_panic`` is ``"wrap"`` to record parser
+    panics under ``parse_errors``, ``"raise"`` to let them propagate, or
+    ``"abort_section"`` to drop the whole blocks section on a block's panic.
+    ``on_duplicate_variable`` is ``"first"`` or ``"last"`` for the declaration
+    of a variable name declared twice to resolve, or ``"error"`` to raise.
+    ``options`` gives all of these as one :class:`ParseOptions` instead.
+    Options left out take the values :func:`set_default_options` set.
+    Builds with the ``buffer`` feature read any C-contiguous byte buffer in
+    place; others copy buffers other than ``bytes`` once.
+    """
     return _parse_bom_bytes(
         data,
+        options=options,
         include_blocks=include_blocks,
+        sections=sections,
         include_raw_block_bytes=include_raw_block_bytes,
+        raw_block_encoding=raw_block_encoding,
+        path_style=path_style,
+        path_root=path_root,
+        path_order=path_order,
+        interpret_fields=interpret_fields,
+        include_tree_positions=include_tree_positions,
+        include_raw_records=include_raw_records,
+        typed_paths=typed_paths,
+        lazy_blocks=lazy_blocks,
+        on_panic=on_panic,
+        on_duplicate_variable=on_duplicate_variable,
     )
 
 
 def parse_bom_bytes(
     data: bytes | bytearray | memoryview,
     *,
-    include_blocks: bool = True,
-    include_raw_block_bytes: bool = False,
+    options: ParseOptions | None = None,
+    include_blocks: bool | None = None,
+    sections: Sequence[str] | None = None,
+    include_raw_block_bytes: bool | None = None,
+    raw_block_encoding: str | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    interpret_fields: bool | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+    typed_paths: bool | None = None,
+    lazy_blocks: bool | None = None,
+    on_panic: str | None = None,
+    on_duplicate_variable: str | None = None,
 ) -> dict[str, Any]:
     """Alias for :func:`parse_bom`."""
     return parse_bom(
         data,
+        options=options,
         include_blocks=include_blocks,
+        sections=sections,
         include_raw_block_bytes=include_raw_block_bytes,
+        raw_block_encoding=raw_block_encoding,
+        path_style=path_style,
+        path_root=path_root,
+        path_order=path_order,
+        interpret_fields=interpret_fields,
+        include_tree_positions=include_tree_positions,
+        include_raw_records=include_raw_records,
+        typed_paths=typed_paths,
+        lazy_blocks=lazy_blocks,
+        on_panic=on_panic,
+        on_duplicate_variable=on_duplicate_variable,
     )
 
 
 def parse_bom_file(
-    path: str | PathLike[str],
+    path: str | bytes | PathLike[str] | PathLike[bytes],
     *,
-    include_blocks: bool = True,
-    include_raw_block_bytes: bool = False,
+    options: ParseOptions | None = None,
+    include_blocks: bool | None = None,
+    sections: Sequence[str] | None = None,
+    include_raw_block_bytes: bool | None = None,
+    raw_block_encoding: str | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    interpret_fields: bool | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+    typed_paths: bool | None = None,
+    lazy_blocks: bool | None = None,
+    on_panic: str | None = None,
+    on_duplicate_variable: str | None = None,
+    mmap: bool = False,
 ) -> dict[str, Any]:
-    """Parse an Apple BOM from a filesystem path."""
+    """Parse an Apple BOM from a filesystem path.
+
+    ``path`` may be ``str``, ``bytes``, or any :class:`os.PathLike`. ``mmap``
+    maps the file into memory instead of reading it, so only the pages the
+    parser touches become resident. Nothing may write to the file meanwhile:
+    truncating it kills the process with ``SIGBUS`` on Unix.
+    """
     return _parse_bom_file(
-        str(path),
+        fsdecode(path),
+        options=options,
+        include_blocks=include_blocks,
+        sections=sections,
+        include_raw_block_bytes=include_raw_block_bytes,
+        raw_block_encoding=raw_block_encoding,
+        path_style=path_style,
+        path_root=path_root,
+        path_order=path_order,
+        interpret_fields=interpret_fields,
+        include_tree_positions=include_tree_positions,
+        include_raw_records=include_raw_records,
+        typed_paths=typed_paths,
+        lazy_blocks=lazy_blocks,
+        on_panic=on_panic,
+        on_duplicate_variable=on_duplicate_variable,
+        mmap=mmap,
+    )
+
+
+def _read_stream(fileobj: _Readable) -> bytes:
+    """Read ``fileobj`` to the end."""
+    chunks = []
+    while True:
+        chunk = fileobj.read(_STREAM_CHUNK_SIZE)
+        if not isinstance(chunk, (bytes, bytearray)):
+            raise TypeError(
+                f"fileobj.read() must return bytes, not {type(chunk).__name__}; "
+                "open the stream in binary mode"
+            )
+        if not chunk:
+            return b"".join(chunks)
+        chunks.append(chunk)
+
+
+def parse_bom_stream(
+    fileobj: _Readable,
+    *,
+    options: ParseOptions | None = None,
+    include_blocks: bool | None = None,
+    sections: Sequence[str] | None = None,
+    include_raw_block_bytes: bool | None = None,
+    raw_block_encoding: str | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    interpret_fields: bool | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+    typed_paths: bool | None = None,
+    lazy_blocks: bool | None = None,
+    on_panic: str | None = None,
+    on_duplicate_variable: str | None = None,
+) -> dict[str, Any]:
+    """Parse an Apple BOM read to the end from a binary file-like object.
+
+    ``fileobj`` is anything with a ``read(size)`` method returning bytes, such
+    as a ``zipfile`` or ``tarfile`` member or an HTTP response. It is read
+    from its current position and left open.
+    """
+    return _parse_bom_bytes(
+        _read_stream(fileobj),
+        options=options,
         include_blocks=include_blocks,
+        sections=sections,
         include_raw_block_bytes=include_raw_block_bytes,
+        raw_block_encoding=raw_block_encoding,
+        path_style=path_style,
+        path_root=path_root,
+        path_order=path_order,
+        interpret_fields=interpret_fields,
+        include_tree_positions=include_tree_positions,
+        include_raw_records=include_raw_records,
+        typed_paths=typed_paths,
+        lazy_blocks=lazy_blocks,
+        on_panic=on_panic,
+        on_duplicate_variable=on_duplicate_variable,
+    )
+
+
+def parse_bom_header(data: bytes | bytearray | memoryview) -> dict[str, Any]:
+    """Return the header and indexes of a BOM without parsing blocks or paths.
+
+    The result holds the ``format``, ``byte_length``, ``header``, and
+    ``variables`` of the ``parse_bom`` document, and ``blocks_index`` with only
+    its ``count``.
+    """
+    return _parse_bom_header(data)
+
+
+def to_json(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    pretty: bool = False,
+    path: str | PathLike[str] | None = None,
+) -> str | None:
+    """Return the ``parse_bom`` document for ``options`` as JSON text.
+
+    The text is written in Rust, as ``json.dumps`` writes the document with
+    ``indent=2`` when ``pretty`` (and without indentation otherwise). With
+    ``path``, it is written to that file instead and ``None`` is returned.
+    """
+    return _to_json(data, options=options, pretty=pretty, path=path)
+
+
+def get_block(
+    data: bytes | bytearray | memoryview,
+    index: int,
+    *,
+    options: ParseOptions | None = None,
+    raw: bool | None = None,
+    raw_block_encoding: str | None = None,
+    interpret_fields: bool | None = None,
+) -> dict[str, Any]:
+    """Return the ``blocks`` entry for the block at ``index``.
+
+    Only that block is parsed. ``raw`` and ``raw_block_encoding`` add its bytes
+    as ``include_raw_block_bytes`` and ``raw_block_encoding`` do for ``parse_bom``.
+    ``options`` gives them, and ``interpret_fields``, as one :class:`ParseOptions`.
+    """
+    return _get_block(
+        data,
+        index,
+        options=options,
+        raw=raw,
+        raw_block_encoding=raw_block_encoding,
+        interpret_fields=interpret_fields,
+    )
+
+
+def get_path(
+    data: bytes | bytearray | memoryview,
+    path: str,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> dict[str, Any] | None:
+    """Return the ``paths`` entry for ``path``, given in ``path_style``, or ``None``.
+
+    Only ``File`` block names are scanned to find the path, and only its own
+    record is resolved, so single lookups in large BOMs stay cheap.
+    """
+    return _get_path(data, path, options=options, path_style=path_style, path_root=path_root)
+
+
+def iter_paths(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+    typed_paths: bool | None = None,
+) -> BomPathIterator:
+    """Iterate over the ``paths`` entries of a BOM, serializing each only as it is reached.
+
+    Yields what :func:`parse_bom` lists under ``paths`` without building the
+    whole list, so large BOMs can be scanned, and abandoned early, cheaply.
+    """
+    return _iter_paths(
+        data,
+        options=options,
+        path_style=path_style,
+        path_root=path_root,
+        path_order=path_order,
+        include_tree_positions=include_tree_positions,
+        include_raw_records=include_raw_records,
+        typed_paths=typed_paths,
+    )
+
+
+def unified_paths(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+) -> list[dict[str, Any]]:
+    """Return the ``paths`` entries of a BOM joined with its other path sections.
+
+    Each entry carries ``in_size64``, ``in_hl_index``, and ``in_vindex``,
+    whether the ``size64``, ``hl_index``, and ``vindex`` sections list it,
+    and ``hard_link_group``, the paths it shares its file with.
+    """
+    return _unified_paths(
+        data,
+        options=options,
+        path_style=path_style,
+        path_root=path_root,
+        path_order=path_order,
+        include_tree_positions=include_tree_positions,
+        include_raw_records=include_raw_records,
+    )
+
+
+def paths_tree(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+    typed_paths: bool | None = None,
+) -> dict[str, Any]:
+    """Return the ``paths`` entries of a BOM as a nested directory tree.
+
+    Each node is a dictionary holding the path's ``entry`` and its
+    ``children`` nodes keyed by name, starting from the node for ``.``.
+    """
+    return _paths_tree(
+        data,
+        options=options,
+        path_style=path_style,
+        path_root=path_root,
+        path_order=path_order,
+        include_tree_positions=include_tree_positions,
+        include_raw_records=include_raw_records,
+        typed_paths=typed_paths,
+    )
+
+
+def _child_path(dirpath: str, name: str) -> str:
+    """Join ``name`` onto a directory path written in any path style."""
+    if not dirpath or dirpath.endswith("/"):
+        return dirpath + name
+    return f"{dirpath}/{name}"
+
+
+def walk(
+    data: bytes | bytearray | memoryview,
+    top: str = ".",
+    *,
+    topdown: bool = True,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    typed_paths: bool | None = None,
+) -> Iterator[tuple[str, list[Any], list[Any]]]:
+    """Walk the directory tree of a BOM as :func:`os.walk` walks a filesystem.
+
+    Yields ``(dirpath, dir_entries, file_entries)`` for ``top`` and each
+    directory beneath it, where the entries are the ``paths`` entries of the
+    directory's subdirectories and of everything else in it. ``top`` is
+    written raw, relative, or rooted at ``/``. With ``topdown``, removing
+    entries from ``dir_entries`` skips those subdirectories.
+    """
+    node = _paths_tree(
+        data,
+        options=options,
+        path_style=path_style,
+        path_root=path_root,
+        path_order=path_order,
+        typed_paths=typed_paths,
+    )
+    names = [name for name in top.split("/") if name]
+    if names[:1] == ["."]:
+        del names[0]
+    dirpath = node["entry"]["path"] if node["entry"] is not None else ""
+    for name in names:
+        if name not in node["children"]:
+            return
+        node = node["children"][name]
+        dirpath = _child_path(dirpath, name)
+    entry = node["entry"]
+    if entry is not None and entry["path_type"] != "directory" and not node["children"]:
+        return
+    yield from _walk_node(node, dirpath, topdown)
+
+
+def _walk_node(
+    node: dict[str, Any], dirpath: str, topdown: bool
+) -> Iterator[tuple[str, list[Any], list[Any]]]:
+    subdirectories = []
+    dir_entries = []
+    file_entries = []
+    for name, child in node["children"].items():
+        entry = child["entry"]
+        if entry is not None and entry["path_type"] != "directory" and not child["children"]:
+            file_entries.append(entry)
+            continue
+        subdirectories.append((name, child))
+        if entry is not None:
+            dir_entries.append(entry)
+
+    if topdown:
+        yield dirpath, dir_entries, file_entries
+        # Directories the caller removed from dir_entries are pruned.
+        kept = {id(entry) for entry in dir_entries}
+        subdirectories = [
+            (name, child)
+            for name, child in subdirectories
+            if child["entry"] is None or id(child["entry"]) in kept
+        ]
+    for name, child in subdirectories:
+        path = child["entry"]["path"] if child["entry"] is not None else _child_path(dirpath, name)
+        yield from _walk_node(child, path, topdown)
+    if not topdown:
+        yield dirpath, dir_entries, file_entries
+
+
+def path_by_id(
+    data: bytes | bytearray | memoryview,
+    path_id: int,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> dict[str, Any]:
+    """Return the ``paths`` entry with the given path ID, or raise ``KeyError``."""
+    return _path_by_id(data, path_id, options=options, path_style=path_style, path_root=path_root)
+
+
+def path_bloom(
+    data: bytes | bytearray | memoryview,
+    fp_rate: float = 0.001,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> bytes:
+    """Return a bloom filter of the BOM's paths, for :func:`bloom_contains`.
+
+    The filter is sized so a path not in the BOM tests positive with
+    probability ``fp_rate``. Query it with paths in ``path_style``.
+    """
+    return _path_bloom(data, fp_rate, options=options, path_style=path_style, path_root=path_root)
+
+
+def ancestors(
+    data: bytes | bytearray | memoryview,
+    path_or_id: int | str,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> list[dict[str, Any]]:
+    """Return the directory entries containing a path, from the root down to its parent.
+
+    ``path_or_id`` is a path ID or a path in ``path_style``. The chain is
+    resolved through parent path IDs, so each entry carries the metadata of
+    the directory as recorded in the BOM.
+    """
+    return _ancestors(data, path_or_id, options=options, path_style=path_style, path_root=path_root)
+
+
+def compare_with_bundle(
+    data: bytes | bytearray | memoryview,
+    app_path: str | PathLike[str],
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    case_sensitive: bool = True,
+    follow_symlinks: bool = False,
+    checksum: str = "none",
+    read_buffer_size: int = 65536,
+    per_device_concurrency: int = 1,
+) -> dict[str, Any]:
+    """Compare the ``.app`` bundle installed by a BOM with one on disk.
+
+    Reports bundle-relative paths present on disk but absent from the BOM
+    (``only_in_bundle``) and vice versa (``only_in_bom``). Pass
+    ``case_sensitive=False`` when the bundle lives on a case-insensitive
+    volume (the APFS default). ``follow_symlinks`` should match the policy the
+    BOM was built with. With ``checksum="full"`` or ``"sampled"``, files and
+    symlinks present on both sides are also checksummed and those that differ
+    are listed in ``checksum_mismatches``; ``read_buffer_size`` and
+    ``per_device_concurrency`` tune how files are read.
+    """
+    return _compare_with_bundle(
+        data,
+        str(app_path),
+        options=options,
+        path_style=path_style,
+        path_root=path_root,
+        case_sensitive=case_sensitive,
+        follow_symlinks=follow_symlinks,
+        checksum=checksum,
+        read_buffer_size=read_buffer_size,
+        per_device_concurrency=per_device_concurrency,
+    )
+
+
+def diff_boms(
+    old: bytes | bytearray | memoryview | None,
+    new: bytes | bytearray | memoryview | None,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    ignore_fields: Sequence[str] = ("mtime",),
+    detect_renames: bool = True,
+    case_sensitive: bool = True,
+) -> dict[str, Any]:
+    """Diff two versions of a BOM.
+
+    Paths are reported as ``added``, ``removed``, or ``modified`` (with the
+    changed ``fields``); ``resized``, ``permission_changes``, and
+    ``new_setuid`` list the paths worth a closer look in a release review.
+    ``None`` stands for a BOM without paths. Fields named in
+    ``ignore_fields`` are not compared. With ``detect_renames``, removed and
+    added entries with the same type, size, and checksum are paired up and
+    reported as ``renamed`` instead. Pass ``case_sensitive=False`` to match
+    up paths that differ only in case, as a case-insensitive volume would.
+    """
+    return _diff_boms(
+        old,
+        new,
+        options=options,
+        path_style=path_style,
+        path_root=path_root,
+        ignore_fields=list(ignore_fields),
+        detect_renames=detect_renames,
+        case_sensitive=case_sensitive,
+    )
+
+
+def compare_package_versions(
+    pkg_old: str | PathLike[str],
+    pkg_new: str | PathLike[str],
+    *,
+    path_style: str = "raw",
+    path_root: str = "/",
+    ignore_fields: Sequence[str] = ("mtime",),
+    detect_renames: bool = True,
+    case_sensitive: bool = True,
+) -> dict[str, Any]:
+    """Diff the contents of two releases of an installer package.
+
+    Each component package's BOM is extracted with ``read_package_boms`` and
+    diffed with ``diff_boms``, which is passed ``case_sensitive``. Components
+    are matched by name, except that two single-component packages are always
+    compared with each other.
+    """
+    old = read_package_boms(pkg_old)
+    new = read_package_boms(pkg_new)
+    if len(old) == len(new) == 1:
+        pairs = {next(iter(new)): (next(iter(old.values())), next(iter(new.values())))}
+    else:
+        pairs = {name: (old.get(name), new.get(name)) for name in sorted(old.keys() | new.keys())}
+
+    components = {
+        name: diff_boms(
+            old_bom,
+            new_bom,
+            path_style=path_style,
+            path_root=path_root,
+            ignore_fields=ignore_fields,
+            detect_renames=detect_renames,
+            case_sensitive=case_sensitive,
+        )
+        for name, (old_bom, new_bom) in pairs.items()
+    }
+    counts = {
+        category: sum(report["summary"]["counts"][category] for report in components.values())
+        for category in ("added", "removed", "modified", "renamed", "type_changes", "link_changes")
+    }
+    return {
+        "old": str(pkg_old),
+        "new": str(pkg_new),
+        "components": components,
+        "summary": {
+            "ok": not any(counts.values()),
+            "total": sum(counts.values()),
+            "counts": counts,
+        },
+    }
+
+
+def summarize_diff(diff: Mapping[str, Any], *, max_names: int = 5) -> str:
+    """Summarize a ``diff_boms`` or ``compare_package_versions`` result in one line.
+
+    For example ``+214 files (12.3 MB), −9 files, 31 modified; new launch
+    daemons: com.example.agent.plist``, suitable for pasting into a ticket.
+    Named items are sorted, and at most ``max_names`` are listed per category.
+    """
+    return _summarize_diff(dict(diff), max_names=max_names)
+
+
+def diff3_boms(
+    base: bytes | bytearray | memoryview,
+    a: bytes | bytearray | memoryview,
+    b: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    ignore_fields: Sequence[str] = ("mtime",),
+    case_sensitive: bool = True,
+) -> dict[str, Any]:
+    """Three-way diff of two BOMs ``a`` and ``b`` derived from ``base``.
+
+    Every path that changed on at least one side relative to ``base`` lands
+    in one of ``only_in_a``, ``only_in_b``, ``both_same`` (both sides made the
+    same change), or ``conflicting``. Fields named in ``ignore_fields`` are
+    not compared; modification times are ignored by default. Paths are
+    matched up as in :func:`diff_boms`, including ``case_sensitive``.
+    """
+    return _diff3_boms(
+        base,
+        a,
+        b,
+        options=options,
+        path_style=path_style,
+        path_root=path_root,
+        ignore_fields=list(ignore_fields),
+        case_sensitive=case_sensitive,
+    )
+
+
+def make_delta(
+    old: bytes | bytearray | memoryview, new: bytes | bytearray | memoryview
+) -> bytes:
+    """Encode the path-level changes from ``old`` to ``new`` as a compact binary delta.
+
+    The delta holds removed paths, the records of added and modified paths,
+    and hard link groups when they changed; :func:`apply_delta` replays it.
+    """
+    return _make_delta(old, new)
+
+
+def apply_delta(
+    old: bytes | bytearray | memoryview, delta: bytes | bytearray | memoryview
+) -> bytes:
+    """Apply a :func:`make_delta` delta to the BOM it was made against.
+
+    Returns a BOM with the other side's paths, written by the BOM writer, so
+    its bytes differ from the original. Raises ``ValueError`` if ``old`` is
+    not the BOM the delta was made against.
+    """
+    return _apply_delta(old, delta)
+
+
+def verify_bom(
+    data: bytes | bytearray | memoryview,
+    root: str | PathLike[str] = "/",
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    separator: str = "native",
+    case: str = "sensitive",
+    follow_symlinks: bool = False,
+    checksum: str = "full",
+    read_buffer_size: int = 65536,
+    per_device_concurrency: int = 1,
+    checkpoint: str | PathLike[str] | None = None,
+    checkpoint_interval: int = 1000,
+    on_finding: Callable[[dict[str, Any]], object] | None = None,
+    collect_findings: bool = True,
+    suppressions: Iterable[str | Mapping[str, Any]] | str | PathLike[str] | None = None,
+    known_files: KnownFiles | None = None,
+    os_build: str | None = None,
+    sha256: bool = False,
+) -> dict[str, Any]:
+    """Verify every path a BOM installs against the tree under ``root``.
+
+    Each deviation (missing paths, wrong type, mode, owner, size, checksum, or
+    link target) is reported in ``findings``. With ``checkpoint``, progress is
+    saved to that file every ``checkpoint_interval`` paths and an interrupted
+    run is resumed from it; the file is removed once the run completes.
+    ``on_finding`` is called with each finding as soon as it is found; pass
+    ``collect_findings=False`` to only stream them. Findings matching
+    ``suppressions`` (rules, or a file for :func:`load_suppressions`) are
+    reported in ``suppressed`` instead. Installed files that differ from their
+    ``known_files`` values for ``os_build`` are reported as well. With
+    ``sha256``, the SHA-256 of every file read in full is reported by path.
+    ``separator`` (``"native"``, ``"posix"``, or ``"windows"``) and ``case``
+    (``"sensitive"`` or ``"insensitive"``) say how BOM paths are looked up
+    under ``root`` when verifying a staged tree off macOS. With
+    ``follow_symlinks``, installed symlinks are checked as their targets.
+    """
+    if isinstance(suppressions, (str, PathLike)):
+        suppressions = load_suppressions(suppressions)
+    return _verify_bom(
+        data,
+        root,
+        options=options,
+        path_style=path_style,
+        path_root=path_root,
+        separator=separator,
+        case=case,
+        follow_symlinks=follow_symlinks,
+        checksum=checksum,
+        read_buffer_size=read_buffer_size,
+        per_device_concurrency=per_device_concurrency,
+        checkpoint=checkpoint,
+        checkpoint_interval=checkpoint_interval,
+        on_finding=on_finding,
+        collect_findings=collect_findings,
+        suppressions=None if suppressions is None else list(suppressions),
+        known_files=known_files,
+        os_build=os_build,
+        sha256=sha256,
+    )
+
+
+def export_hash_set(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    sha256: Mapping[str, str] | None = None,
+    product_code: int = 0,
+    os_code: str = "",
+    special_code: str = "",
+) -> str:
+    """Export the files a BOM installs as an NSRL RDS-style CSV hash set.
+
+    Rows carry each file's path and size, plus its SHA-256 from ``sha256``
+    (such as ``verify_bom(..., sha256=True)["sha256"]``, keyed by path in
+    ``path_style``) when present. ``CRC32`` is left empty, since BOMs record
+    a POSIX cksum rather than a zlib CRC-32. Paths are absolute unless
+    ``path_style`` or ``options`` says otherwise.
+    """
+    return _export_hash_set(
+        data,
+        options=options,
+        path_style=path_style,
+        path_root=path_root,
+        sha256=None if sha256 is None else dict(sha256),
+        product_code=product_code,
+        os_code=os_code,
+        special_code=special_code,
+    )
+
+
+def export_mtree(
+    data: bytes | bytearray | memoryview,
+    *,
+    keywords: Iterable[str] = ("type", "mode", "uid", "gid", "size", "time", "link", "cksum"),
+    options: ParseOptions | None = None,
+    path_order: str | None = None,
+) -> str:
+    """Export a BOM as a BSD ``mtree(8)`` specification, one line per path.
+
+    ``keywords`` selects the keywords written for each path; ``size`` and
+    ``cksum`` are given for files and ``link`` for links only. Paths are
+    listed in ``path_order``, and keep mtree's ``./`` form whatever the path
+    style of ``options``.
+    """
+    return _export_mtree(data, keywords=list(keywords), options=options, path_order=path_order)
+
+
+def format_lsbom(
+    data: bytes | bytearray | memoryview,
+    *,
+    include: Iterable[str] = ("f", "d", "l"),
+    columns: str | None = None,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+) -> str:
+    """List a BOM as Apple's ``lsbom`` does, one tab-separated line per path.
+
+    ``include`` selects path types by ``lsbom``'s flags (``"f"`` files,
+    ``"d"`` directories, ``"l"`` links, ``"b"`` and ``"c"`` devices), and
+    ``columns`` gives the fields as ``lsbom -p`` does, such as ``"fm/sc"``.
+    """
+    return _format_lsbom(
+        data,
+        include=list(include),
+        columns=columns,
+        options=options,
+        path_style=path_style,
+        path_root=path_root,
+        path_order=path_order,
+    )
+
+
+def load_suppressions(path: str | PathLike[str]) -> list[str | dict[str, Any]]:
+    """Load suppression rules from a JSON or TOML file.
+
+    JSON files hold a list of rules or an object with a ``suppress`` list;
+    TOML files hold ``[[suppress]]`` tables. Files ending in ``.toml`` are
+    read as TOML, which needs Python 3.11 or the ``tomli`` package.
+    """
+    with open(path, "rb") as handle:
+        raw = handle.read()
+
+    if str(path).endswith(".toml"):
+        try:
+            import tomllib
+        except ModuleNotFoundError:  # Python < 3.11
+            import tomli as tomllib
+        document: Any = tomllib.loads(raw.decode("utf-8"))
+    else:
+        document = json.loads(raw)
+
+    if isinstance(document, Mapping):
+        document = document.get("suppress", [])
+    if not isinstance(document, list):
+        raise ValueError(f"{path}: suppressions must be a list")
+    return document
+
+
+def block_layout(data: bytes | bytearray | memoryview) -> dict[str, Any]:
+    """Report how a BOM's blocks are aligned, spaced, and padded."""
+    return _block_layout(data)
+
+
+def size_report(
+    data: bytes | bytearray | memoryview,
+    payload: bytes | bytearray | memoryview | str | PathLike[str] | None = None,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> dict[str, Any]:
+    """Reconcile the sizes a BOM records for its files.
+
+    The sum of the path sizes is checked against the ``BomInfo`` totals and
+    the ``Size64`` entries, and, when the package's ``Payload`` is given (as
+    its bytes or a path to read), against the size of each file in it. Each
+    mismatch is listed under ``discrepancies``.
+    """
+    payload_sizes = None
+    if payload is not None:
+        if not isinstance(payload, (bytes, bytearray, memoryview)):
+            with open(payload, "rb") as fileobj:
+                payload = fileobj.read()
+        payload_sizes = read_payload_sizes(bytes(payload))
+    return _size_report(
+        data, payload_sizes, options=options, path_style=path_style, path_root=path_root
+    )
+
+
+def mtime_profile(
+    data: bytes | bytearray | memoryview,
+    *,
+    coverage: float = 0.9,
+    tolerance: int = 86400,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> dict[str, Any]:
+    """Profile a BOM's modification times and flag entries outside its build window.
+
+    The build window is the shortest span of time holding ``coverage`` of the
+    recorded modification times. Entries more than ``tolerance`` seconds
+    before or after it are listed under ``outliers``, as likely additions or
+    replacements made after the build.
+    """
+    return _mtime_profile(
+        data,
+        coverage=coverage,
+        tolerance=tolerance,
+        options=options,
+        path_style=path_style,
+        path_root=path_root,
+    )
+
+
+def block_type_histogram(
+    boms: Iterable[bytes | bytearray | memoryview | str | PathLike[str]],
+    *,
+    workers: int | None = None,
+    quarantine: str | PathLike[str] | None = None,
+) -> dict[str, Any]:
+    """Aggregate block kind counts and sizes across a corpus of BOMs.
+
+    Each BOM is given as its bytes or as a path to read. BOMs are read and
+    parsed on ``workers`` threads (one per CPU by default); ones that fail to
+    parse are listed under ``errors`` by their position in ``boms``. With
+    ``quarantine``, those are also copied into that directory next to a JSON
+    report of the failure.
+    """
+    specimens = [
+        _as_bytes(bom) if isinstance(bom, (bytes, bytearray, memoryview)) else fspath(bom)
+        for bom in boms
+    ]
+    return _block_type_histogram(
+        specimens, workers=workers, quarantine=None if quarantine is None else fspath(quarantine)
+    )
+
+
+def risk_score(
+    doc: Mapping[str, Any], *, weights: Mapping[str, int] | None = None
+) -> dict[str, Any]:
+    """Score a ``parse_bom`` document for triage, with a per-category breakdown.
+
+    Each setuid file, world-writable path, launch daemon or agent, kernel
+    extension, file in a ``PATH`` directory, and path in a TCC or MDM location
+    adds its category's weight; ``weights`` overrides the defaults by category
+    name.
+    """
+    return _risk_score(dict(doc), weights=None if weights is None else dict(weights))
+
+
+def shell_footprint(doc: Mapping[str, Any]) -> dict[str, Any]:
+    """Report files a ``parse_bom`` document installs that change shell environments.
+
+    Covers ``PATH`` directories, ``/etc/paths.d`` and ``/etc/manpaths.d``,
+    shell startup files, and ``bin`` directories under ``Application Support``.
+    """
+    return _shell_footprint(dict(doc))
+
+
+def capabilities(doc: Mapping[str, Any]) -> dict[str, list[str]]:
+    """Summarize what a ``parse_bom`` document installs as a capability manifest.
+
+    Each key lists the paths that give the package that capability, so an
+    empty list means the package doesn't have it.
+    """
+    return _capabilities(dict(doc))
+
+
+def known_file_deviations(
+    doc: Mapping[str, Any], known_files: KnownFiles, *, os_build: str | None = None
+) -> dict[str, Any]:
+    """List files of a ``parse_bom`` document that differ from known-good values.
+
+    Each file with values in ``known_files`` (for ``os_build``, falling back to
+    values for every build) has its recorded ``size`` and ``crc32`` compared.
+    """
+    return _known_file_deviations(dict(doc), known_files, os_build=os_build)
+
+
+def effective_access(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> dict[str, Any]:
+    """Report which non-root users and groups can modify each file a BOM installs.
+
+    A file is writable through its own mode, and replaceable by whoever can
+    modify any directory above it, which is reported as ``via``.
+    """
+    return _effective_access(data, options=options, path_style=path_style, path_root=path_root)
+
+
+def exit_code(result: Mapping[str, Any] | BaseException) -> int:
+    """Map a verification or comparison result to a process exit status.
+
+    Like ``diff`` and ``cmp``: ``EXIT_OK`` (0) when the report's summary is
+    ok, ``EXIT_FINDINGS`` (1) when it isn't, and ``EXIT_ERROR`` (2) when
+    given the exception that stopped the run.
+    """
+    if isinstance(result, BaseException):
+        return EXIT_ERROR
+    summary = result.get("summary", result)
+    return EXIT_OK if summary["ok"] else EXIT_FINDINGS
+
+
+def _block_entries(blocks: Iterable[BlockEntry]) -> list[tuple[int, int]]:
+    entries = []
+    for block in blocks:
+        if isinstance(block, Mapping):
+            entries.append((block["file_offset"], block["length"]))
+        else:
+            file_offset, length = block
+            entries.append((file_offset, length))
+    return entries
+
+
+def _variable_entries(variables: Iterable[VariableEntry]) -> list[tuple[str, int]]:
+    entries = []
+    for variable in variables:
+        if isinstance(variable, Mapping):
+            entries.append((variable["name"], variable["block_index"]))
+        else:
+            name, block_index = variable
+            entries.append((name, block_index))
+    return entries
+
+
+def rewrite_indexes(
+    data: bytes | bytearray | memoryview,
+    *,
+    version: int | None = None,
+    blocks: Iterable[BlockEntry] | None = None,
+    variables: Iterable[VariableEntry] | None = None,
+) -> bytes:
+    """Rewrite only the header and index regions of an existing BOM.
+
+    ``blocks`` replaces the blocks index with ``(file_offset, length)`` pairs
+    and ``variables`` replaces the vars index with ``(name, block_index)``
+    pairs. Both also accept the dictionaries found in a parsed document's
+    ``blocks_index["entries"]`` and ``variables``. Block data is not touched.
+    """
+    return _rewrite_indexes(
+        data,
+        version=version,
+        blocks=None if blocks is None else _block_entries(blocks),
+        variables=None if variables is None else _variable_entries(variables),
+    )
+
+
+def add_block(
+    data: bytes | bytearray | memoryview,
+    payload: bytes | bytearray | memoryview,
+    variable_name: str | None = None,
+) -> bytes:
+    """Append a block to an existing BOM, optionally binding a new variable to it.
+
+    The new block's index is the previous ``blocks_index["count"]``.
+    """
+    return _add_block(data, payload, variable_name)
+
+
+def remove_variable(
+    data: bytes | bytearray | memoryview,
+    name: str,
+    *,
+    gc: bool = False,
+) -> bytes:
+    """Remove a variable from an existing BOM.
+
+    With ``gc=True``, blocks that only the removed variable referenced are
+    dropped from the blocks index.
+    """
+    return _remove_variable(data, name, gc=gc)
+
+
+def rename_variable(
+    data: bytes | bytearray | memoryview,
+    old_name: str,
+    new_name: str,
+) -> bytes:
+    """Rename a variable in an existing BOM."""
+    return _rename_variable(data, old_name, new_name)
+
+
+def compact(
+    data: bytes | bytearray | memoryview,
+    *,
+    drop_unreferenced: bool = False,
+) -> dict[str, Any]:
+    """Re-serialize a BOM with its block data packed contiguously.
+
+    Returns a dictionary with the new ``data`` plus ``bytes_reclaimed`` and
+    ``blocks_dropped``. With ``drop_unreferenced=True``, blocks that no
+    variable reaches are dropped as well.
+    """
+    return _compact(data, drop_unreferenced=drop_unreferenced)
+
+
+def build_bom_from_directory(
+    root: str | PathLike[str],
+    *,
+    exclude: Iterable[str] = (),
+    skip_special: bool = False,
+    follow_symlinks: bool = False,
+    inspect_binaries: bool = True,
+    checksum: str = "full",
+    read_buffer_size: int = 65536,
+    per_device_concurrency: int = 1,
+    **builder_options: Any,
+) -> bytes:
+    """Build a BOM describing a staging directory, the way ``mkbom`` does.
+
+    ``exclude`` holds globs such as ``".DS_Store"``, ``"*.pyc"``, or
+    ``".git/**"``; ``skip_special`` skips sockets and FIFOs;
+    ``follow_symlinks`` records link targets instead of the links; and
+    ``inspect_binaries=False`` skips reading Mach-O headers. ``checksum`` is
+    ``"full"``, ``"sampled"``, or ``"none"``; ``read_buffer_size`` and
+    ``per_device_concurrency`` tune how files are read. Remaining keyword
+    arguments are passed to :class:`BomBuilder`.
+    """
+    builder = BomBuilder(**builder_options)
+    builder.add_directory(
+        root,
+        exclude=list(exclude),
+        skip_special=skip_special,
+        follow_symlinks=follow_symlinks,
+        inspect_binaries=inspect_binaries,
+        checksum=checksum,
+        read_buffer_size=read_buffer_size,
+        per_device_concurrency=per_device_concurrency,
     )
+    return builder.build()
@@ -1,19 +1,763 @@
-from typing import Any
+from os import PathLike
+from types import TracebackType
+from typing import (
+    Any,
+    BinaryIO,
+    Callable,
+    ClassVar,
+    Iterable,
+    Iterator,
+    Mapping,
+    Sequence,
+    overload,
+)
 
 class BomParseError(Exception): ...
 
+class FeatureNotAvailable(Exception): ...
+
+class PathType:
+    FILE: ClassVar[str]
+    DIRECTORY: ClassVar[str]
+    LINK: ClassVar[str]
+    DEVICE: ClassVar[str]
+    OTHER: ClassVar[str]
+
+class BlockKind:
+    EMPTY: ClassVar[str]
+    BOM_INFO: ClassVar[str]
+    FILE: ClassVar[str]
+    PATH_INFO_INDEX: ClassVar[str]
+    PATH_RECORD: ClassVar[str]
+    PATH_RECORD_POINTER: ClassVar[str]
+    PATHS: ClassVar[str]
+    TREE: ClassVar[str]
+    TREE_POINTER: ClassVar[str]
+    V_INDEX: ClassVar[str]
+    UNKNOWN: ClassVar[str]
+
+class BomFile:
+    def __init__(self, data: bytes | bytearray | memoryview) -> None: ...
+    @staticmethod
+    def open(path: str | PathLike[str]) -> BomFile: ...
+    @property
+    def source_path(self) -> str | None: ...
+    @property
+    def byte_length(self) -> int: ...
+    @property
+    def header(self) -> BomHeader: ...
+    @property
+    def variables(self) -> list[dict[str, Any]]: ...
+    def variable_names(self) -> list[str]: ...
+    def get_variable(self, name: str) -> bytes: ...
+    @property
+    def bom_info(self) -> dict[str, Any] | None: ...
+    @property
+    def block_count(self) -> int: ...
+    def block(
+        self,
+        index: int,
+        *,
+        include_raw_block_bytes: bool = False,
+        raw_block_encoding: str = "hex",
+        interpret_fields: bool = False,
+    ) -> dict[str, Any]: ...
+    def block_view(self, index: int) -> memoryview: ...
+    def paths(
+        self,
+        *,
+        offset: int = 0,
+        limit: int | None = None,
+        options: ParseOptions | None = None,
+        path_style: str | None = None,
+        path_root: str | None = None,
+        path_order: str | None = None,
+        include_tree_positions: bool | None = None,
+        include_raw_records: bool | None = None,
+        typed_paths: bool | None = None,
+    ) -> list[dict[str, Any]] | list[BomPathEntry] | None: ...
+    def count_paths(self) -> int | None: ...
+    def export_paths_ndjson(
+        self,
+        destination: str | PathLike[str] | BinaryIO,
+        *,
+        options: ParseOptions | None = None,
+        path_style: str | None = None,
+        path_root: str | None = None,
+        path_order: str | None = None,
+        include_tree_positions: bool | None = None,
+        include_raw_records: bool | None = None,
+        statistics: bool = False,
+    ) -> int | dict[str, Any]: ...
+    def export_paths_csv(
+        self,
+        destination: str | PathLike[str] | BinaryIO,
+        *,
+        delimiter: str = ",",
+        columns: Sequence[str] | None = None,
+        options: ParseOptions | None = None,
+        path_style: str | None = None,
+        path_root: str | None = None,
+        path_order: str | None = None,
+        include_raw_records: bool | None = None,
+        statistics: bool = False,
+    ) -> int | dict[str, Any]: ...
+    def get_path(
+        self,
+        path: str,
+        *,
+        options: ParseOptions | None = None,
+        path_style: str | None = None,
+        path_root: str | None = None,
+    ) -> dict[str, Any] | None: ...
+    def get_path_by_id(
+        self,
+        path_id: int,
+        *,
+        options: ParseOptions | None = None,
+        path_style: str | None = None,
+        path_root: str | None = None,
+    ) -> dict[str, Any]: ...
+    def iter_paths(
+        self,
+        *,
+        options: ParseOptions | None = None,
+        path_style: str | None = None,
+        path_root: str | None = None,
+        path_order: str | None = None,
+        include_tree_positions: bool | None = None,
+        include_raw_records: bool | None = None,
+        typed_paths: bool | None = None,
+    ) -> BomPathIterator: ...
+    def paths_tree(
+        self,
+        *,
+        options: ParseOptions | None = None,
+        path_style: str | None = None,
+        path_root: str | None = None,
+        path_order: str | None = None,
+        include_tree_positions: bool | None = None,
+        include_raw_records: bool | None = None,
+        typed_paths: bool | None = None,
+    ) -> dict[str, Any]: ...
+    def to_dict(self, *, options: ParseOptions | None = None) -> dict[str, Any]: ...
+    def to_json(
+        self, *, options: ParseOptions | None = None, indent: int | None = None
+    ) -> str: ...
+    @property
+    def closed(self) -> bool: ...
+    def close(self) -> None: ...
+    def __len__(self) -> int: ...
+    def __iter__(self) -> Iterator[str]: ...
+    def __contains__(self, path: object) -> bool: ...
+    def __getitem__(self, path: str) -> dict[str, Any]: ...
+    def __enter__(self) -> BomFile: ...
+    def __exit__(
+        self,
+        exc_type: type[BaseException] | None,
+        exc_value: BaseException | None,
+        traceback: TracebackType | None,
+    ) -> None: ...
+
+class BomBlocks:
+    def __len__(self) -> int: ...
+    @overload
+    def __getitem__(self, index: int) -> dict[str, Any]: ...
+    @overload
+    def __getitem__(self, index: slice) -> list[dict[str, Any]]: ...
+    def __iter__(self) -> Iterator[dict[str, Any]]: ...
+    @property
+    def parsed_count(self) -> int: ...
+
+class BomHeader:
+    magic: str
+    version: int
+    number_of_blocks: int
+    blocks_index_offset: int
+    blocks_index_length: int
+    vars_index_offset: int
+    vars_index_length: int
+    def to_dict(self) -> dict[str, Any]: ...
+    def to_json(self, *, indent: int | None = None) -> str: ...
+    def __getitem__(self, key: str) -> Any: ...
+
+class BomPathEntry:
+    path: str
+    path_type: str
+    path_type_raw: int
+    file_mode: int
+    symbolic_mode: str
+    uid: int
+    gid: int
+    mtime: int
+    mtime_iso8601: str
+    size: int
+    crc32: int | None
+    link_name: str | None
+    path_id: int | None
+    parent_path_id: int | None
+    tree_position: tuple[int, int] | None
+    raw_record: bytes | None
+    def to_dict(self) -> dict[str, Any]: ...
+    def to_json(self, *, indent: int | None = None) -> str: ...
+    def __getitem__(self, key: str) -> Any: ...
+    def __hash__(self) -> int: ...
+
+class BomPathIterator:
+    def __iter__(self) -> BomPathIterator: ...
+    def __next__(self) -> dict[str, Any] | BomPathEntry: ...
+    def __length_hint__(self) -> int: ...
+    def close(self) -> None: ...
+
+class BomStore:
+    def __init__(self, directory: str | PathLike[str]) -> None: ...
+    @property
+    def directory(self) -> str: ...
+    def add(
+        self,
+        bom: bytes | str | PathLike[str],
+        *,
+        source: str | None = None,
+        metadata: Mapping[str, str] | None = None,
+        quarantine: str | PathLike[str] | None = None,
+    ) -> str: ...
+    def remove(self, digest: str) -> None: ...
+    def digests(self) -> list[str]: ...
+    def sources(self, digest: str) -> list[dict[str, Any]]: ...
+    def path(self, digest: str) -> str: ...
+    def read(self, digest: str) -> bytes: ...
+    def open(self, digest: str) -> BomFile: ...
+    def which_pkg(
+        self, path: str, *, path_style: str = "raw", path_root: str = "/"
+    ) -> list[str]: ...
+    def glob(
+        self, pattern: str, *, path_style: str = "raw", path_root: str = "/"
+    ) -> list[dict[str, str]]: ...
+    def export_glob(
+        self,
+        pattern: str,
+        destination: str | PathLike[str],
+        *,
+        format: str = "ndjson",
+        path_style: str = "raw",
+        path_root: str = "/",
+    ) -> int: ...
+    def __len__(self) -> int: ...
+    def __contains__(self, digest: str) -> bool: ...
+    @property
+    def closed(self) -> bool: ...
+    def close(self) -> None: ...
+    def __enter__(self) -> BomStore: ...
+    def __exit__(
+        self,
+        exc_type: type[BaseException] | None,
+        exc_value: BaseException | None,
+        traceback: TracebackType | None,
+    ) -> None: ...
+
+class CorpusIndex:
+    def __init__(self) -> None: ...
+    def add(
+        self,
+        key: str,
+        bom: bytes | str | PathLike[str],
+        *,
+        quarantine: str | PathLike[str] | None = None,
+    ) -> None: ...
+    def remove(self, key: str) -> None: ...
+    def keys(self) -> list[str]: ...
+    def which_pkg(
+        self, path: str, *, path_style: str = "raw", path_root: str = "/"
+    ) -> list[str]: ...
+    def glob(
+        self, pattern: str, *, path_style: str = "raw", path_root: str = "/"
+    ) -> list[dict[str, str]]: ...
+    def export_glob(
+        self,
+        pattern: str,
+        destination: str | PathLike[str],
+        *,
+        format: str = "ndjson",
+        path_style: str = "raw",
+        path_root: str = "/",
+    ) -> int: ...
+    def __len__(self) -> int: ...
+    def __contains__(self, key: str) -> bool: ...
+    @property
+    def closed(self) -> bool: ...
+    def close(self) -> None: ...
+    def __enter__(self) -> CorpusIndex: ...
+    def __exit__(
+        self,
+        exc_type: type[BaseException] | None,
+        exc_value: BaseException | None,
+        traceback: TracebackType | None,
+    ) -> None: ...
+
+class KnownFiles:
+    def __init__(self, entries: Iterable[Mapping[str, Any] | object] | None = None) -> None: ...
+    def add(
+        self,
+        path: str,
+        *,
+        crc32: int | None = None,
+        size: int | None = None,
+        os_build: str | None = None,
+    ) -> None: ...
+    def extend(self, entries: Iterable[Mapping[str, Any] | object]) -> None: ...
+    def lookup(self, path: str, os_build: str | None = None) -> dict[str, Any] | None: ...
+    @property
+    def os_builds(self) -> list[str]: ...
+    def __len__(self) -> int: ...
+
+class ParseOptions:
+    def __init__(
+        self,
+        *,
+        include_blocks: bool = True,
+        sections: Sequence[str] = ...,
+        include_raw_block_bytes: bool = False,
+        raw_block_encoding: str = "hex",
+        path_style: str = "raw",
+        path_root: str = "/",
+        path_order: str = "tree",
+        interpret_fields: bool = False,
+        include_tree_positions: bool = False,
+        include_raw_records: bool = False,
+        typed_paths: bool = False,
+        lazy_blocks: bool = False,
+        on_panic: str = "wrap",
+        on_duplicate_variable: str = "first",
+    ) -> None: ...
+    @property
+    def include_blocks(self) -> bool: ...
+    @property
+    def sections(self) -> tuple[str, ...]: ...
+    @property
+    def include_raw_block_bytes(self) -> bool: ...
+    @property
+    def raw_block_encoding(self) -> str: ...
+    @property
+    def path_style(self) -> str: ...
+    @property
+    def path_root(self) -> str: ...
+    @property
+    def path_order(self) -> str: ...
+    @property
+    def interpret_fields(self) -> bool: ...
+    @property
+    def include_tree_positions(self) -> bool: ...
+    @property
+    def include_raw_records(self) -> bool: ...
+    @property
+    def typed_paths(self) -> bool: ...
+    @property
+    def lazy_blocks(self) -> bool: ...
+    @property
+    def on_panic(self) -> str: ...
+    @property
+    def on_duplicate_variable(self) -> str: ...
+    def replace(
+        self,
+        *,
+        include_blocks: bool | None = None,
+        sections: Sequence[str] | None = None,
+        include_raw_block_bytes: bool | None = None,
+        raw_block_encoding: str | None = None,
+        path_style: str | None = None,
+        path_root: str | None = None,
+        path_order: str | None = None,
+        interpret_fields: bool | None = None,
+        include_tree_positions: bool | None = None,
+        include_raw_records: bool | None = None,
+        typed_paths: bool | None = None,
+        lazy_blocks: bool | None = None,
+        on_panic: str | None = None,
+        on_duplicate_variable: str | None = None,
+    ) -> ParseOptions: ...
+    def __hash__(self) -> int: ...
+
+class BomBuilder:
+    def __init__(
+        self,
+        *,
+        block_size: int = 4096,
+        max_paths_per_leaf: int | None = None,
+        default_uid: int = 0,
+        default_gid: int = 0,
+        force_owner: tuple[int, int] | None = None,
+        umask: int = 0,
+        spill_threshold: int | None = None,
+        spill_directory: str | PathLike[str] | None = None,
+    ) -> None: ...
+    def add_override(
+        self,
+        pattern: str,
+        *,
+        mode: int | None = None,
+        uid: int | None = None,
+        gid: int | None = None,
+    ) -> None: ...
+    def add_path(
+        self,
+        path: str,
+        path_type: str = "file",
+        *,
+        mode: int | None = None,
+        uid: int | None = None,
+        gid: int | None = None,
+        mtime: int = 0,
+        size: int = 0,
+        crc32: int | None = None,
+        link_name: str | None = None,
+    ) -> None: ...
+    def extend(self, entries: Iterable[Mapping[str, Any] | object]) -> None: ...
+    def add_directory(
+        self,
+        root: str | PathLike[str],
+        *,
+        exclude: list[str] = ...,
+        skip_special: bool = False,
+        follow_symlinks: bool = False,
+        inspect_binaries: bool = True,
+        checksum: str = "full",
+        read_buffer_size: int = 65536,
+        per_device_concurrency: int = 1,
+    ) -> int: ...
+    def build(self) -> bytes: ...
+    def write(self, path: str | PathLike[str]) -> int: ...
+    def __len__(self) -> int: ...
+
 def parse_bom_bytes(
-    data: bytes,
+    data: bytes | bytearray | memoryview,
     *,
-    include_blocks: bool = True,
-    include_raw_block_bytes: bool = False,
+    options: ParseOptions | None = None,
+    include_blocks: bool | None = None,
+    sections: Sequence[str] | None = None,
+    include_raw_block_bytes: bool | None = None,
+    raw_block_encoding: str | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    interpret_fields: bool | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+    typed_paths: bool | None = None,
+    lazy_blocks: bool | None = None,
+    on_panic: str | None = None,
+    on_duplicate_variable: str | None = None,
 ) -> dict[str, Any]: ...
 
 def parse_bom_file(
+    path: str | PathLike[str],
+    *,
+    options: ParseOptions | None = None,
+    include_blocks: bool | None = None,
+    sections: Sequence[str] | None = None,
+    include_raw_block_bytes: bool | None = None,
+    raw_block_encoding: str | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    interpret_fields: bool | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+    typed_paths: bool | None = None,
+    lazy_blocks: bool | None = None,
+    on_panic: str | None = None,
+    on_duplicate_variable: str | None = None,
+    mmap: bool = False,
+) -> dict[str, Any]: ...
+
+def parse_bom_header(data: bytes | bytearray | memoryview) -> dict[str, Any]: ...
+
+def to_json(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    pretty: bool = False,
+    path: str | PathLike[str] | None = None,
+) -> str | None: ...
+
+def add_block(
+    data: bytes | bytearray | memoryview,
+    payload: bytes | bytearray | memoryview,
+    variable_name: str | None = None,
+) -> bytes: ...
+
+def ancestors(
+    data: bytes | bytearray | memoryview,
+    path_or_id: int | str,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> list[dict[str, Any]]: ...
+
+def apply_delta(
+    old: bytes | bytearray | memoryview,
+    delta: bytes | bytearray | memoryview,
+) -> bytes: ...
+
+def block_layout(data: bytes | bytearray | memoryview) -> dict[str, Any]: ...
+
+def block_type_histogram(
+    boms: list[bytes | str], *, workers: int | None = None, quarantine: str | None = None
+) -> dict[str, Any]: ...
+
+def bloom_contains(filter: bytes, path: str) -> bool: ...
+
+def build_info() -> dict[str, Any]: ...
+
+def capabilities(doc: dict[str, Any]) -> dict[str, list[str]]: ...
+
+def compact(
+    data: bytes | bytearray | memoryview,
+    *,
+    drop_unreferenced: bool = False,
+) -> dict[str, Any]: ...
+
+def compare_with_bundle(
+    data: bytes | bytearray | memoryview,
+    app_path: str,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    case_sensitive: bool = True,
+    follow_symlinks: bool = False,
+    checksum: str = "none",
+    read_buffer_size: int = 65536,
+    per_device_concurrency: int = 1,
+) -> dict[str, Any]: ...
+
+def diff_boms(
+    old: bytes | bytearray | memoryview | None,
+    new: bytes | bytearray | memoryview | None,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    ignore_fields: Sequence[str] = ("mtime",),
+    detect_renames: bool = True,
+    case_sensitive: bool = True,
+) -> dict[str, Any]: ...
+
+def diff3_boms(
+    base: bytes | bytearray | memoryview,
+    a: bytes | bytearray | memoryview,
+    b: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    ignore_fields: Sequence[str] = ("mtime",),
+    case_sensitive: bool = True,
+) -> dict[str, Any]: ...
+
+def effective_access(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> dict[str, Any]: ...
+
+def export_hash_set(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    sha256: dict[str, str] | None = None,
+    product_code: int = 0,
+    os_code: str = "",
+    special_code: str = "",
+) -> str: ...
+
+def export_mtree(
+    data: bytes | bytearray | memoryview,
+    *,
+    keywords: list[str] = ...,
+    options: ParseOptions | None = None,
+    path_order: str | None = None,
+) -> str: ...
+
+def get_block(
+    data: bytes,
+    index: int,
+    *,
+    options: ParseOptions | None = None,
+    raw: bool | None = None,
+    raw_block_encoding: str | None = None,
+    interpret_fields: bool | None = None,
+) -> dict[str, Any]: ...
+
+def get_default_options() -> ParseOptions: ...
+
+def get_path(
+    data: bytes | bytearray | memoryview,
     path: str,
     *,
-    include_blocks: bool = True,
-    include_raw_block_bytes: bool = False,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> dict[str, Any] | None: ...
+
+def iter_paths(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+    typed_paths: bool | None = None,
+) -> BomPathIterator: ...
+
+def unified_paths(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+) -> list[dict[str, Any]]: ...
+
+def paths_tree(
+    data: bytes | bytearray | memoryview,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+    include_tree_positions: bool | None = None,
+    include_raw_records: bool | None = None,
+    typed_paths: bool | None = None,
+) -> dict[str, Any]: ...
+
+def known_file_deviations(
+    doc: dict[str, Any], known_files: KnownFiles, *, os_build: str | None = None
+) -> dict[str, Any]: ...
+
+def format_lsbom(
+    data: bytes | bytearray | memoryview,
+    *,
+    include: list[str] = ...,
+    columns: str | None = None,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    path_order: str | None = None,
+) -> str: ...
+
+def make_delta(
+    old: bytes | bytearray | memoryview,
+    new: bytes | bytearray | memoryview,
+) -> bytes: ...
+
+def memory_footprint(handle_or_doc: object) -> dict[str, int]: ...
+
+def mtime_profile(
+    data: bytes | bytearray | memoryview,
+    *,
+    coverage: float = 0.9,
+    tolerance: int = 86400,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> dict[str, Any]: ...
+
+def path_bloom(
+    data: bytes | bytearray | memoryview,
+    fp_rate: float = 0.001,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> bytes: ...
+
+def path_by_id(
+    data: bytes | bytearray | memoryview,
+    path_id: int,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> dict[str, Any]: ...
+
+def quarantine_boms(
+    boms: list[bytes | str], quarantine: str, *, operation: str, workers: int | None = None
+) -> list[dict[str, Any]]: ...
+
+def register_path_type(code: int, name: str) -> None: ...
+
+def registered_path_types() -> dict[int, str]: ...
+
+def remove_variable(
+    data: bytes | bytearray | memoryview,
+    name: str,
+    *,
+    gc: bool = False,
+) -> bytes: ...
+
+def rename_variable(
+    data: bytes | bytearray | memoryview,
+    old_name: str,
+    new_name: str,
+) -> bytes: ...
+
+def rewrite_indexes(
+    data: bytes | bytearray | memoryview,
+    *,
+    version: int | None = None,
+    blocks: list[tuple[int, int]] | None = None,
+    variables: list[tuple[str, int]] | None = None,
+) -> bytes: ...
+
+def risk_score(doc: dict[str, Any], *, weights: dict[str, int] | None = None) -> dict[str, Any]: ...
+
+def set_default_options(options: ParseOptions | None) -> None: ...
+
+def shell_footprint(doc: dict[str, Any]) -> dict[str, Any]: ...
+
+def size_report(
+    data: bytes | bytearray | memoryview,
+    payload_sizes: Mapping[str, int] | None = None,
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+) -> dict[str, Any]: ...
+
+def summarize_diff(diff: dict[str, Any], *, max_names: int = 5) -> str: ...
+
+def unregister_path_type(code: int) -> None: ...
+
+def verify_bom(
+    data: bytes | bytearray | memoryview,
+    root: str | PathLike[str] = "/",
+    *,
+    options: ParseOptions | None = None,
+    path_style: str | None = None,
+    path_root: str | None = None,
+    separator: str = "native",
+    case: str = "sensitive",
+    follow_symlinks: bool = False,
+    checksum: str = "full",
+    read_buffer_size: int = 65536,
+    per_device_concurrency: int = 1,
+    checkpoint: str | PathLike[str] | None = None,
+    checkpoint_interval: int = 1000,
+    on_finding: Callable[[dict[str, Any]], object] | None = None,
+    collect_findings: bool = True,
+    suppressions: list[str | Mapping[str, Any]] | None = None,
+    known_files: KnownFiles | None = None,
+    os_build: str | None = None,
+    sha256: bool = False,
 ) -> dict[str, Any]: ...
 
 __version__: str
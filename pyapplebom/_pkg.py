@@ -0,0 +1,286 @@
+"""Extract BOMs from macOS installer packages."""
+
+from __future__ import annotations
+
+import bz2
+import lzma
+import plistlib
+import struct
+import zlib
+from os import PathLike, fspath
+from pathlib import Path
+from typing import Any, Dict, Iterator, List, Optional, Tuple
+from xml.etree import ElementTree
+
+from ._native import quarantine_boms as _quarantine_boms
+
+_XAR_MAGIC = b"xar!"
+_XAR_HEADER = struct.Struct(">4sHHQQI")
+_PBZX_MAGIC = b"pbzx"
+_PBZX_CHUNK = struct.Struct(">QQ")
+_XZ_MAGIC = b"\xfd7zXZ\x00"
+_CPIO_TRAILER = "TRAILER!!!"
+_S_IFMT = 0o170000
+_S_IFREG = 0o100000
+_S_IFLNK = 0o120000
+
+# Where macOS keeps the receipts of installed packages: flat receipts since
+# 10.6, and bundle-style package receipts before that.
+_FLAT_RECEIPTS = Path("var", "db", "receipts")
+_BUNDLE_RECEIPTS = Path("Library", "Receipts")
+
+
+def read_package_boms(path: str | PathLike[str]) -> Dict[str, bytes]:
+    """Return the BOM of each component package in an installer package.
+
+    Flat packages are xar archives holding either a single ``Bom`` (a
+    component package, keyed ``""``) or one ``<name>.pkg/Bom`` per component
+    (a product archive, keyed by ``<name>.pkg``). Bundle-style packages are
+    directories with the BOM at ``Contents/Archive.bom``.
+    """
+    path = Path(path)
+    if path.is_dir():
+        bom = path / "Contents" / "Archive.bom"
+        if not bom.is_file():
+            raise ValueError(f"{path} is a directory without Contents/Archive.bom")
+        return {"": bom.read_bytes()}
+
+    data = path.read_bytes()
+    if len(data) < _XAR_HEADER.size or not data.startswith(_XAR_MAGIC):
+        raise ValueError(f"{path} is not a flat package (xar archive)")
+    _, header_size, _, toc_length, _, _ = _XAR_HEADER.unpack_from(data)
+    heap = header_size + toc_length
+    try:
+        toc = ElementTree.fromstring(zlib.decompress(data[header_size:heap]))
+    except (zlib.error, ElementTree.ParseError) as err:
+        raise ValueError(f"{path} has an unreadable table of contents: {err}") from None
+
+    boms = {}
+    for component, entry in _bom_entries(toc.find("toc"), ""):
+        boms[component] = _read_entry(path, data, heap, entry)
+    if not boms:
+        raise ValueError(f"{path} contains no BOM")
+    return boms
+
+
+def scan_receipts(
+    root: str | PathLike[str] = "/", *, quarantine: str | PathLike[str] | None = None
+) -> List[Dict[str, Any]]:
+    """Return the installer receipts on the system mounted at ``root``.
+
+    Both forms are found: flat receipts (``var/db/receipts/<id>.bom`` with
+    its ``<id>.plist``) and the legacy bundle receipts of older systems
+    (``Library/Receipts/<name>.pkg`` directories holding
+    ``Contents/Archive.bom``). Each receipt is a dictionary with its
+    ``form`` (``"flat"`` or ``"bundle"``), the ``receipt`` path, the
+    ``bom_path`` to read its BOM from, and the ``identifier`` and ``version``
+    from its property list (the file or bundle name and ``None`` without
+    one). Flat receipts come first, each form sorted by path; bundles without
+    a BOM are skipped.
+
+    With ``quarantine``, each receipt's BOM is also parsed, and ones that
+    fail are copied into that directory as ``block_type_histogram`` does;
+    every receipt then has an ``error``, ``None`` when its BOM parsed.
+    """
+    root = Path(root)
+    receipts = []
+    flat = root / _FLAT_RECEIPTS
+    if flat.is_dir():
+        for bom in sorted(flat.glob("*.bom")):
+            info = _read_plist(bom.with_suffix(".plist"))
+            receipts.append(
+                _receipt(
+                    "flat",
+                    bom,
+                    bom,
+                    info.get("PackageIdentifier", bom.stem),
+                    info.get("PackageVersion"),
+                )
+            )
+    bundles = root / _BUNDLE_RECEIPTS
+    if bundles.is_dir():
+        for bundle in sorted(bundles.glob("*.pkg")):
+            bom = bundle / "Contents" / "Archive.bom"
+            if not bom.is_file():
+                continue
+            info = _read_plist(bundle / "Contents" / "Info.plist")
+            receipts.append(
+                _receipt(
+                    "bundle",
+                    bundle,
+                    bom,
+                    info.get("CFBundleIdentifier", bundle.stem),
+                    info.get("CFBundleShortVersionString"),
+                )
+            )
+
+    if quarantine is not None:
+        errors = _quarantine_boms(
+            [receipt["bom_path"] for receipt in receipts],
+            fspath(quarantine),
+            operation="scan_receipts",
+        )
+        for receipt in receipts:
+            receipt["error"] = None
+        for error in errors:
+            receipts[error.pop("index")]["error"] = error
+    return receipts
+
+
+def _receipt(
+    form: str, receipt: Path, bom: Path, identifier: str, version: Optional[str]
+) -> Dict[str, Any]:
+    return {
+        "form": form,
+        "receipt": str(receipt),
+        "bom_path": str(bom),
+        "identifier": identifier,
+        "version": version,
+    }
+
+
+def _read_plist(path: Path) -> Dict[str, Any]:
+    """Return the dictionary in the property list at ``path``, if readable."""
+    try:
+        with path.open("rb") as file:
+            info = plistlib.load(file)
+    except (OSError, plistlib.InvalidFileException, ValueError):
+        return {}
+    return info if isinstance(info, dict) else {}
+
+
+def _bom_entries(
+    parent: ElementTree.Element | None, component: str
+) -> Iterator[Tuple[str, ElementTree.Element]]:
+    """Yield the ``Bom`` file entries at the top level and one level down."""
+    if parent is None:
+        return
+    for entry in parent.findall("file"):
+        name = entry.findtext("name", "")
+        kind = entry.findtext("type", "file")
+        if kind == "file" and name == "Bom":
+            yield component, entry
+        elif kind == "directory" and not component and name.endswith(".pkg"):
+            yield from _bom_entries(entry, name)
+
+
+def read_payload_sizes(payload: bytes) -> Dict[str, int]:
+    """Return the size of each file and symlink in a component package's ``Payload``.
+
+    Payloads are cpio archives in the portable (``070707``) or new
+    (``070701``/``070702``) format, either uncompressed or compressed with
+    gzip, bzip2, xz, or Apple's chunked ``pbzx``. Paths are keyed as BOMs
+    write them, starting with ``./``. Hard links share the size of whichever
+    link of the group carries the data.
+    """
+    archive = _decompress_payload(payload)
+    sizes: Dict[str, int] = {}
+    links: Dict[Tuple[int, int], List[str]] = {}
+    offset = 0
+    while True:
+        name, mode, nlink, inode, size, offset = _cpio_entry(archive, offset)
+        if name == _CPIO_TRAILER:
+            break
+        if mode & _S_IFMT not in (_S_IFREG, _S_IFLNK):
+            continue
+        name = name.lstrip("/")
+        path = name if name.startswith("./") or name == "." else f"./{name}"
+        sizes[path] = size
+        if nlink > 1:
+            links.setdefault(inode, []).append(path)
+    for group in links.values():
+        size = max(sizes[path] for path in group)
+        for path in group:
+            sizes[path] = size
+    return sizes
+
+
+def _decompress_payload(payload: bytes) -> bytes:
+    try:
+        if payload.startswith(b"\x1f\x8b"):
+            return zlib.decompress(payload, 16 + zlib.MAX_WBITS)
+        if payload.startswith(b"BZh"):
+            return bz2.decompress(payload)
+        if payload.startswith(_XZ_MAGIC):
+            return lzma.decompress(payload)
+        if payload.startswith(_PBZX_MAGIC):
+            return _decompress_pbzx(payload)
+    except (zlib.error, OSError, lzma.LZMAError) as err:
+        raise ValueError(f"payload can't be decompressed: {err}") from None
+    return payload
+
+
+def _decompress_pbzx(payload: bytes) -> bytes:
+    """Join the chunks of a ``pbzx`` stream, each xz-compressed or stored."""
+    chunks = []
+    offset = len(_PBZX_MAGIC) + 8
+    while offset < len(payload):
+        if offset + _PBZX_CHUNK.size > len(payload):
+            raise ValueError("payload is truncated")
+        _, length = _PBZX_CHUNK.unpack_from(payload, offset)
+        offset += _PBZX_CHUNK.size
+        chunk = payload[offset : offset + length]
+        if len(chunk) != length:
+            raise ValueError("payload is truncated")
+        chunks.append(lzma.decompress(chunk) if chunk.startswith(_XZ_MAGIC) else chunk)
+        offset += length
+    return b"".join(chunks)
+
+
+def _cpio_entry(archive: bytes, offset: int) -> Tuple[str, int, int, Tuple[int, int], int, int]:
+    """Read the cpio header at ``offset``.
+
+    Returns the entry's name, mode, link count, ``(device, inode)``, and data
+    size, and the offset of the next header.
+    """
+    magic = archive[offset : offset + 6]
+    if magic == b"070707":
+        fields = _cpio_fields(archive, offset + 6, [6] * 7 + [11, 6, 11], 8)
+        dev, ino, mode, _, _, nlink, _, _, name_size, size = fields
+        start, name_end = offset + 76, offset + 76 + name_size
+        data_start = name_end
+    elif magic in (b"070701", b"070702"):
+        fields = _cpio_fields(archive, offset + 6, [8] * 13, 16)
+        ino, mode, _, _, nlink, _, size, dev_major, dev_minor, _, _, name_size, _ = fields
+        dev = (dev_major << 32) | dev_minor
+        start, name_end = offset + 110, offset + 110 + name_size
+        data_start = (name_end + 3) & ~3
+    else:
+        raise ValueError(f"payload has no cpio header at offset {offset}")
+    data_end = data_start + size
+    if magic != b"070707":
+        data_end = (data_end + 3) & ~3
+    if name_end > len(archive) or data_start + size > len(archive):
+        raise ValueError("payload is truncated")
+    name = archive[start:name_end].rstrip(b"\x00").decode("utf-8", "surrogateescape")
+    return name, mode, nlink, (dev, ino), size, data_end
+
+
+def _cpio_fields(archive: bytes, offset: int, widths: List[int], base: int) -> List[int]:
+    fields = []
+    for width in widths:
+        text = archive[offset : offset + width]
+        try:
+            fields.append(int(text, base))
+        except ValueError:
+            raise ValueError(f"payload has a malformed cpio header at offset {offset}") from None
+        offset += width
+    return fields
+
+
+def _read_entry(path: Path, data: bytes, heap: int, entry: ElementTree.Element) -> bytes:
+    offset = int(entry.findtext("data/offset", "0"))
+    length = int(entry.findtext("data/length", "0"))
+    encoded = data[heap + offset : heap + offset + length]
+    if len(encoded) != length:
+        raise ValueError(f"{path} is truncated")
+
+    encoding = entry.find("data/encoding")
+    style = "" if encoding is None else encoding.get("style", "")
+    if style in ("", "application/octet-stream"):
+        return encoded
+    if style == "application/x-gzip":
+        return zlib.decompress(encoded)
+    if style == "application/x-bzip2":
+        return bz2.decompress(encoded)
+    raise ValueError(f"{path} uses unsupported encoding {style!r}")
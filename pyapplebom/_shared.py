@@ -0,0 +1,194 @@
+"""Share a BOM's path table between processes through shared memory."""
+
+from __future__ import annotations
+
+import json
+import os
+import struct
+import sys
+from multiprocessing import resource_tracker
+from multiprocessing.shared_memory import SharedMemory
+from types import TracebackType
+from typing import Any, Dict, Iterator, List, Optional, Type
+
+# Leads every table; the last byte is the layout version.
+_MAGIC = b"BOMSPT\x00\x01"
+
+# The magic followed by the number of entries.
+_HEADER = struct.Struct(">8sI")
+
+# Per entry, sorted by path: the offset of the path, its length, and the
+# length of the entry's JSON, which follows the path.
+_RECORD = struct.Struct(">QII")
+
+
+def _encode_table(entries: List[Dict[str, Any]]) -> bytes:
+    encoded = sorted(
+        (entry["path"].encode(), json.dumps(entry, separators=(",", ":")).encode())
+        for entry in entries
+    )
+    records = bytearray(_HEADER.pack(_MAGIC, len(encoded)))
+    heap = bytearray()
+    heap_start = _HEADER.size + _RECORD.size * len(encoded)
+    for path, entry in encoded:
+        records += _RECORD.pack(heap_start + len(heap), len(path), len(entry))
+        heap += path
+        heap += entry
+    return bytes(records + heap)
+
+
+def _open_segment(name: Optional[str], size: int = 0) -> SharedMemory:
+    """Create a segment of ``size`` bytes, or attach to ``name`` when ``size`` is 0."""
+    create = size > 0
+    if sys.version_info >= (3, 13):
+        return SharedMemory(name, create=create, size=size, track=False)
+
+    segment = SharedMemory(name, create=create, size=size)
+    if os.name == "posix":
+        # Before 3.13 every process using a segment registers it for removal
+        # when it exits, which would pull it out from under the other workers.
+        # The owner removes it with unlink() instead.
+        resource_tracker.unregister(segment._name, "shared_memory")  # type: ignore[attr-defined]
+    return segment
+
+
+class SharedPathTable:
+    """A BOM's ``paths`` section in a named shared-memory segment.
+
+    One process, typically a pre-fork server's parent, builds the table with
+    :meth:`create`; every other process attaches with ``SharedPathTable(name)``
+    and reads it in place, so N workers share one copy. Lookups binary-search
+    the segment and decode only the entry they return.
+    """
+
+    def __init__(self, name: str) -> None:
+        self._attach(_open_segment(name))
+
+    def _attach(self, segment: SharedMemory) -> None:
+        self._segment = segment
+        self._closed = False
+        try:
+            magic, self._count = _HEADER.unpack_from(segment.buf)
+        except struct.error:
+            magic = None
+        if magic != _MAGIC:
+            segment.close()
+            raise ValueError(f"shared memory segment {segment.name!r} is not a path table")
+
+    @classmethod
+    def create(
+        cls,
+        data: bytes | bytearray | memoryview,
+        *,
+        name: Optional[str] = None,
+        path_style: str = "raw",
+        path_root: str = "/",
+    ) -> SharedPathTable:
+        """Parse ``data`` and copy its path table into a new segment.
+
+        ``name`` defaults to a random one, available as :attr:`name`. Lookups
+        take paths in ``path_style``.
+        """
+        from . import ParseOptions, parse_bom
+
+        # Spelled out in full so the defaults set_default_options installs
+        # can't change the entries stored.
+        options = ParseOptions(include_blocks=False, path_style=path_style, path_root=path_root)
+        doc = parse_bom(data, options=options)
+        table = _encode_table(doc["paths"] or [])
+        segment = _open_segment(name, len(table))
+        segment.buf[: len(table)] = table
+        shared = cls.__new__(cls)
+        shared._attach(segment)
+        return shared
+
+    def _buffer(self) -> memoryview:
+        if self._closed:
+            raise ValueError("operation on a closed SharedPathTable")
+        return self._segment.buf
+
+    def _record(self, index: int) -> tuple[int, int, int]:
+        return _RECORD.unpack_from(self._buffer(), _HEADER.size + _RECORD.size * index)
+
+    def _path(self, index: int) -> bytes:
+        offset, path_length, _ = self._record(index)
+        return self._buffer()[offset : offset + path_length].tobytes()
+
+    def _find(self, path: str) -> Optional[int]:
+        key = path.encode()
+        low, high = 0, self._count
+        while low < high:
+            middle = (low + high) // 2
+            if self._path(middle) < key:
+                low = middle + 1
+            else:
+                high = middle
+        if low < self._count and self._path(low) == key:
+            return low
+        return None
+
+    @property
+    def name(self) -> str:
+        """The segment name other processes attach with."""
+        return self._segment.name
+
+    @property
+    def closed(self) -> bool:
+        return self._closed
+
+    def get_path(self, path: str) -> Optional[Dict[str, Any]]:
+        """Return the ``paths`` entry for ``path``, or ``None``."""
+        index = self._find(path)
+        if index is None:
+            return None
+        offset, path_length, entry_length = self._record(index)
+        start = offset + path_length
+        return json.loads(self._buffer()[start : start + entry_length].tobytes())
+
+    def __contains__(self, path: object) -> bool:
+        return isinstance(path, str) and self._find(path) is not None
+
+    def __len__(self) -> int:
+        self._buffer()
+        return self._count
+
+    def __iter__(self) -> Iterator[str]:
+        """Iterate over the paths, sorted."""
+        for index in range(len(self)):
+            yield self._path(index).decode()
+
+    def close(self) -> None:
+        """Detach from the segment. Closing again does nothing."""
+        if not self._closed:
+            self._segment.close()
+            self._closed = True
+
+    def unlink(self) -> None:
+        """Remove the segment once every process has closed it.
+
+        Only the process that created the table should call this, and it may
+        do so before or after closing it. Does nothing on Windows, which
+        removes a segment when its last handle closes.
+        """
+        segment = self._segment
+        if sys.version_info < (3, 13) and os.name == "posix":
+            # Balances the unregister in _open_segment, as unlink()
+            # unregisters the segment again.
+            resource_tracker.register(segment._name, "shared_memory")  # type: ignore[attr-defined]
+        segment.unlink()
+
+    def __enter__(self) -> SharedPathTable:
+        return self
+
+    def __exit__(
+        self,
+        exc_type: Optional[Type[BaseException]],
+        exc_value: Optional[BaseException],
+        traceback: Optional[TracebackType],
+    ) -> None:
+        self.close()
+
+    def __repr__(self) -> str:
+        if self._closed:
+            return f"<closed SharedPathTable({self._segment.name!r})>"
+        return f"SharedPathTable({self._segment.name!r})"
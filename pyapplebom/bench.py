@@ -0,0 +1,235 @@
+"""Measure parsing and export speed on your own BOMs.
+
+Run ``python -m pyapplebom.bench FILE...`` to time each way of reading the
+given BOMs (such as the receipts under ``/var/db/receipts``) and print the
+best time of each, with its throughput in megabytes and paths per second.
+"""
+
+from __future__ import annotations
+
+import argparse
+import csv
+import io
+import json
+import sys
+import time
+from os import PathLike, fsdecode
+from pathlib import Path
+from typing import Any, Callable, NamedTuple, Sequence
+
+from . import (
+    BomFile,
+    BomParseError,
+    export_hash_set,
+    iter_paths,
+    parse_bom,
+    paths_tree,
+    to_json,
+    walk,
+)
+
+
+class Case(NamedTuple):
+    """One way of reading a BOM: what it measures and how to run it, and the
+    case it is compared with, if any."""
+
+    name: str
+    group: str
+    description: str
+    run: Callable[[bytes], Any]
+    baseline: str | None = None
+
+
+CASES = [
+    Case("parse", "parse", "parse_bom with the default options", parse_bom),
+    Case(
+        "parse-no-blocks",
+        "parse",
+        "parse_bom without the blocks section",
+        lambda data: parse_bom(data, include_blocks=False),
+    ),
+    Case(
+        "parse-lazy-blocks",
+        "parse",
+        "parse_bom with blocks parsed on access",
+        lambda data: parse_bom(data, lazy_blocks=True),
+    ),
+    Case(
+        "parse-raw-blocks",
+        "parse",
+        "parse_bom with each block's bytes",
+        lambda data: parse_bom(data, include_raw_block_bytes=True, raw_block_encoding="bytes"),
+    ),
+    Case(
+        "paths-dicts",
+        "paths",
+        "every path entry as a dictionary",
+        lambda data: BomFile(data).paths(),
+    ),
+    Case(
+        "paths-typed",
+        "paths",
+        "every path entry as a BomPathEntry",
+        lambda data: BomFile(data).paths(typed_paths=True),
+    ),
+    Case(
+        "paths-iter",
+        "paths",
+        "iter_paths, one entry at a time",
+        lambda data: sum(1 for _ in iter_paths(data)),
+    ),
+    Case("paths-tree", "paths", "paths_tree nesting", paths_tree),
+    Case("paths-walk", "paths", "walk over every directory", lambda data: list(walk(data))),
+    Case("export-json", "export", "to_json", to_json, "export-json-dumps"),
+    Case(
+        "export-json-dumps",
+        "export",
+        "parse_bom and json.dumps, for comparison",
+        lambda data: json.dumps(parse_bom(data), default=lambda value: value.to_dict()),
+    ),
+    Case("export-hash-set", "export", "export_hash_set", export_hash_set),
+    Case(
+        "export-csv",
+        "export",
+        "BomFile.export_paths_csv",
+        lambda data: BomFile(data).export_paths_csv(io.BytesIO()),
+        "export-csv-writer",
+    ),
+    Case(
+        "export-csv-writer",
+        "export",
+        "paths and csv.DictWriter, for comparison",
+        lambda data: _write_csv(BomFile(data).paths()),
+    ),
+]
+
+
+def _write_csv(entries: list[dict[str, Any]]) -> str:
+    out = io.StringIO()
+    writer = csv.DictWriter(out, list(entries[0]) if entries else [], lineterminator="\n")
+    writer.writeheader()
+    writer.writerows(entries)
+    return out.getvalue()
+
+
+def _case(name: str) -> Case:
+    for case in CASES:
+        if case.name == name:
+            return case
+    names = ", ".join(case.name for case in CASES)
+    raise ValueError(f"unknown case {name!r}; expected one of {names}")
+
+
+def run(
+    files: Sequence[str | PathLike[str]],
+    *,
+    cases: Sequence[str] | None = None,
+    repeat: int = 5,
+) -> list[dict[str, Any]]:
+    """Time each case over ``files`` and return a result per case.
+
+    Each case reads every file in turn, ``repeat`` times, and reports its
+    best pass: ``seconds``, ``bytes_per_second``, and ``paths_per_second``
+    over all files, along with the ``files``, ``bytes``, and ``paths`` read,
+    and its ``speedup`` over the case it is compared with when that case ran
+    too (``None`` otherwise). ``cases`` names the cases to run, all of them
+    by default.
+    """
+    if repeat < 1:
+        raise ValueError(f"repeat must be at least 1, not {repeat}")
+    selected = CASES if cases is None else [_case(name) for name in cases]
+    boms = []
+    total_paths = 0
+    for path in files:
+        data = Path(path).read_bytes()
+        try:
+            total_paths += len(BomFile(data).paths() or ())
+        except BomParseError as err:
+            raise BomParseError(f"{fsdecode(path)}: {err}") from err
+        boms.append(data)
+    total_bytes = sum(len(data) for data in boms)
+
+    results = []
+    for case in selected:
+        best = None
+        for _ in range(repeat):
+            start = time.perf_counter()
+            for data in boms:
+                case.run(data)
+            elapsed = time.perf_counter() - start
+            best = elapsed if best is None else min(best, elapsed)
+        results.append(
+            {
+                "case": case.name,
+                "group": case.group,
+                "files": len(boms),
+                "bytes": total_bytes,
+                "paths": total_paths,
+                "seconds": best,
+                "bytes_per_second": total_bytes / best if best else None,
+                "paths_per_second": total_paths / best if best else None,
+            }
+        )
+
+    seconds = {result["case"]: result["seconds"] for result in results}
+    for case, result in zip(selected, results):
+        baseline = seconds.get(case.baseline) if case.baseline else None
+        result["speedup"] = baseline / result["seconds"] if baseline else None
+    return results
+
+
+def _format_rate(rate: float | None, scale: float) -> str:
+    return "-" if rate is None else f"{rate / scale:,.1f}"
+
+
+def main(argv: Sequence[str] | None = None) -> int:
+    parser = argparse.ArgumentParser(
+        prog="python -m pyapplebom.bench",
+        description="Measure parsing and export speed on the given BOM files.",
+    )
+    parser.add_argument("files", nargs="*", help="BOM files to read")
+    parser.add_argument(
+        "--case",
+        action="append",
+        dest="cases",
+        metavar="NAME",
+        help="run only this case (repeatable)",
+    )
+    parser.add_argument(
+        "--repeat", type=int, default=5, help="passes per case, keeping the best"
+    )
+    parser.add_argument("--json", action="store_true", help="print the results as JSON")
+    parser.add_argument("--list", action="store_true", help="list the cases and exit")
+    args = parser.parse_args(argv)
+
+    if args.list:
+        for case in CASES:
+            print(f"{case.name:<20} {case.description}")
+        return 0
+    if not args.files:
+        parser.error("no BOM files given")
+    try:
+        results = run(args.files, cases=args.cases, repeat=args.repeat)
+    except (BomParseError, OSError, ValueError) as err:
+        parser.error(str(err))
+
+    if args.json:
+        print(json.dumps(results, indent=2))
+        return 0
+    paths = results[0]["paths"] if results else 0
+    size = results[0]["bytes"] if results else 0
+    print(f"{len(args.files)} files, {size:,} bytes, {paths:,} paths; best of {args.repeat}")
+    print(f"{'case':<20} {'ms':>10} {'MB/s':>10} {'kpaths/s':>10} {'speedup':>10}")
+    for result in results:
+        speedup = "-" if result["speedup"] is None else f"{result['speedup']:.1f}x"
+        print(
+            f"{result['case']:<20} {result['seconds'] * 1000:>10.2f}"
+            f" {_format_rate(result['bytes_per_second'], 1e6):>10}"
+            f" {_format_rate(result['paths_per_second'], 1e3):>10}"
+            f" {speedup:>10}"
+        )
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,165 @@
+use crate::{
+    buffer::BomData,
+    lookup::{lookup_style, PathIndex},
+    options::ParseOptions,
+    summary,
+};
+use apple_bom::{BomPath, BomPathType};
+use pyo3::{
+    prelude::*,
+    types::{PyDict, PyList},
+};
+
+const S_ISVTX: u16 = 0o1000;
+
+/// Well-known macOS group names, for readable principals.
+const GROUP_NAMES: [(u32, &str); 2] = [(20, "staff"), (80, "admin")];
+
+/// Someone other than root who may hold an access right. The `wheel` group
+/// (GID 0) only holds root on macOS, so it is treated as root too.
+#[derive(Clone, PartialEq)]
+enum Principal {
+    User(u32),
+    Group(u32),
+    Everyone,
+}
+
+impl Principal {
+    fn name(&self) -> String {
+        match self {
+            Self::User(uid) => format!("user:{uid}"),
+            Self::Group(gid) => match GROUP_NAMES.iter().find(|(known, _)| known == gid) {
+                Some((_, name)) => format!("group:{name}"),
+                None => format!("group:{gid}"),
+            },
+            Self::Everyone => "everyone".to_string(),
+        }
+    }
+}
+
+/// The non-root principals whose permission class on `path` has all of
+/// `bits` (given for the owner class, e.g. `0o200`).
+fn principals_with(path: &BomPath, bits: u16) -> Vec<Principal> {
+    let mode = path.file_mode();
+    let mut principals = Vec::new();
+    if mode & bits == bits && path.user_id() != 0 {
+        principals.push(Principal::User(path.user_id()));
+    }
+    if mode & (bits >> 3) == bits >> 3 && path.group_id() != 0 {
+        principals.push(Principal::Group(path.group_id()));
+    }
+    if mode & (bits >> 6) == bits >> 6 {
+        principals.push(Principal::Everyone);
+    }
+    principals
+}
+
+/// Who can replace (rename or delete) `child` because they can modify its
+/// directory `parent`.
+///
+/// Modifying a directory takes write and search permission. In a sticky
+/// directory only the directory's owner and the child's owner may replace
+/// the child.
+fn replacers(parent: &BomPath, child: &BomPath) -> Vec<Principal> {
+    let writers = principals_with(parent, 0o300);
+    if parent.file_mode() & S_ISVTX == 0 || writers.is_empty() {
+        return writers;
+    }
+
+    let mut owners: Vec<Principal> = [parent.user_id(), child.user_id()]
+        .into_iter()
+        .filter(|uid| *uid != 0)
+        .map(Principal::User)
+        .collect();
+    owners.dedup();
+    owners
+}
+
+/// One access right a principal holds over a path, and the path that grants it.
+struct Grant {
+    principal: Principal,
+    access: &'static str,
+    via: usize,
+}
+
+/// Report which non-root principals can modify each file a BOM installs,
+/// from its own permissions and those of every directory above it.
+#[pyfunction(signature = (data, *, options = None, path_style = None, path_root = None))]
+pub(crate) fn effective_access(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+) -> PyResult<PyObject> {
+    let data = BomData::new(data, true)?;
+    let style = lookup_style(options, path_style, path_root)?;
+    let index = PathIndex::parse(&data)?;
+
+    let findings = PyList::empty_bound(py);
+    let (mut writable, mut replaceable) = (0, 0);
+    for position in 0..index.len() {
+        let path = index.path(position);
+        if matches!(path.path_type(), BomPathType::Directory) {
+            continue;
+        }
+
+        let mut grants: Vec<Grant> = Vec::new();
+        let mut grant = |principal: Principal, access: &'static str, via: usize| {
+            if !grants
+                .iter()
+                .any(|held| held.principal == principal && held.access == access)
+            {
+                grants.push(Grant {
+                    principal,
+                    access,
+                    via,
+                });
+            }
+        };
+
+        // Links are never written through their own mode.
+        if !matches!(path.path_type(), BomPathType::Link) {
+            for principal in principals_with(path, 0o200) {
+                grant(principal, "write", position);
+            }
+        }
+        // Whoever can modify a directory can swap out everything below it,
+        // so the directory nearest the root is reported as the cause.
+        let chain = index.ancestors(position)?;
+        for (depth, &directory) in chain.iter().enumerate() {
+            let child = chain.get(depth + 1).copied().unwrap_or(position);
+            for principal in replacers(index.path(directory), index.path(child)) {
+                grant(principal, "replace", directory);
+            }
+        }
+
+        if grants.is_empty() {
+            continue;
+        }
+        writable += usize::from(grants.iter().any(|held| held.access == "write"));
+        replaceable += usize::from(grants.iter().any(|held| held.access == "replace"));
+
+        let access = PyList::empty_bound(py);
+        for held in &grants {
+            let item = PyDict::new_bound(py);
+            item.set_item("principal", held.principal.name())?;
+            item.set_item("access", held.access)?;
+            item.set_item("via", style.render(index.path(held.via).path()))?;
+            access.append(item)?;
+        }
+        let finding = PyDict::new_bound(py);
+        finding.set_item("path", style.render(path.path()))?;
+        finding.set_item("path_id", index.leaf(position).path_id)?;
+        finding.set_item("access", access)?;
+        findings.append(finding)?;
+    }
+
+    let report = PyDict::new_bound(py);
+    report.set_item("paths", findings)?;
+    report.set_item(
+        "summary",
+        summary(py, [("writable", writable), ("replaceable", replaceable)])?,
+    )?;
+    Ok(report.into_py(py))
+}
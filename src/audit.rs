@@ -0,0 +1,489 @@
+use crate::{
+    known::KnownFiles,
+    summary,
+    writer::{extract_field, path_components},
+};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use std::collections::HashMap;
+
+/// Directories on the default command search path of macOS shells, plus
+/// Homebrew's.
+pub(crate) const PATH_DIRECTORIES: [&str; 8] = [
+    "bin",
+    "sbin",
+    "usr/bin",
+    "usr/sbin",
+    "usr/local/bin",
+    "usr/local/sbin",
+    "opt/homebrew/bin",
+    "opt/homebrew/sbin",
+];
+
+/// Drop-in directories whose files add entries to the `PATH` and `MANPATH`
+/// that `path_helper` builds for login shells.
+const PATH_HELPER_DIRECTORIES: [&str; 2] = ["paths.d", "manpaths.d"];
+
+/// Shell startup files read from `/etc`.
+const SYSTEM_SHELL_INIT: [&str; 11] = [
+    "profile",
+    "bashrc",
+    "zshenv",
+    "zprofile",
+    "zshrc",
+    "zlogin",
+    "zlogout",
+    "csh.cshrc",
+    "csh.login",
+    "csh.logout",
+    "fish/config.fish",
+];
+
+/// Drop-in directories in `/etc` whose files shells source at startup.
+const SYSTEM_SHELL_INIT_DIRECTORIES: [&str; 2] = ["profile.d", "fish/conf.d"];
+
+/// Per-user shell startup files.
+const USER_SHELL_INIT: [&str; 9] = [
+    ".profile",
+    ".bash_profile",
+    ".bash_login",
+    ".bashrc",
+    ".zshenv",
+    ".zprofile",
+    ".zshrc",
+    ".zlogin",
+    ".cshrc",
+];
+
+/// Categories reported by [shell_footprint].
+const FOOTPRINT_CATEGORIES: [&str; 4] = [
+    "path_executables",
+    "path_entries",
+    "shell_init",
+    "support_binaries",
+];
+
+/// Privacy (TCC) databases and MDM-managed configuration, relative to the
+/// install root.
+const PRIVACY_LOCATIONS: [&str; 3] = [
+    "Library/Managed Preferences",
+    "var/db/ConfigurationProfiles",
+    "private/var/db/ConfigurationProfiles",
+];
+
+/// Privacy locations that also exist in every home directory.
+const PRIVACY_LOCATIONS_ANYWHERE: [&str; 1] = ["Library/Application Support/com.apple.TCC"];
+
+/// Keys of the manifest returned by [capabilities].
+const CAPABILITIES: [&str; 7] = [
+    "installs_daemons",
+    "installs_agents",
+    "installs_kexts",
+    "modifies_path",
+    "setuid_binaries",
+    "app_bundles",
+    "frameworks",
+];
+
+/// Audit categories scored by [risk_score], with their default weights.
+const RISK_CATEGORIES: [(&str, u32); 7] = [
+    ("setuid", 25),
+    ("world_writable", 10),
+    ("launch_daemons", 15),
+    ("launch_agents", 10),
+    ("kernel_extensions", 30),
+    ("path_executables", 5),
+    ("privacy_sensitive", 20),
+];
+
+/// Fields compared by [known_file_deviations].
+const KNOWN_FIELDS: [&str; 2] = ["size", "crc32"];
+
+/// Scores at or above each threshold get the matching level.
+const RISK_LEVELS: [(u32, &str); 4] = [(75, "high"), (25, "medium"), (1, "low"), (0, "none")];
+
+const S_ISUID_ISGID: u16 = 0o6000;
+const S_ISVTX: u16 = 0o1000;
+const S_IWOTH: u16 = 0o002;
+
+/// A path entry of a parsed BOM document, as far as audits care.
+pub(crate) struct AuditEntry {
+    /// The path as it appears in the document.
+    pub path: String,
+    pub components: Vec<String>,
+    pub path_type: String,
+    pub mode: u16,
+    pub size: Option<u64>,
+    pub crc32: Option<u32>,
+}
+
+impl AuditEntry {
+    fn is_directory(&self) -> bool {
+        self.path_type == "directory"
+    }
+
+    fn parent(&self) -> &[String] {
+        self.components
+            .split_last()
+            .map_or(&[][..], |(_, parent)| parent)
+    }
+
+    /// Whether the parent directory is `directory`, given as `/`-separated
+    /// components relative to the install root.
+    pub(crate) fn is_in(&self, directory: &str) -> bool {
+        self.parent()
+            .iter()
+            .map(String::as_str)
+            .eq(directory.split('/'))
+    }
+
+    /// Whether the path is `directory` or inside it, given as `/`-separated
+    /// components relative to the install root.
+    fn is_within(&self, directory: &str) -> bool {
+        let directory: Vec<&str> = directory.split('/').collect();
+        self.components.len() >= directory.len()
+            && self.components[..directory.len()]
+                .iter()
+                .map(String::as_str)
+                .eq(directory)
+    }
+
+    /// Whether the path is `directory` or inside it, wherever `directory`
+    /// sits.
+    fn is_within_any(&self, directory: &str) -> bool {
+        let directory: Vec<&str> = directory.split('/').collect();
+        self.components.windows(directory.len()).any(|window| {
+            window
+                .iter()
+                .map(String::as_str)
+                .eq(directory.iter().copied())
+        })
+    }
+
+    /// Whether the parent directory ends with `directory`, wherever that sits
+    /// (`Library/LaunchAgents` also lives in home directories).
+    pub(crate) fn is_in_any(&self, directory: &str) -> bool {
+        let parent = self.parent();
+        let directory: Vec<&str> = directory.split('/').collect();
+        parent.len() >= directory.len()
+            && parent[parent.len() - directory.len()..]
+                .iter()
+                .map(String::as_str)
+                .eq(directory)
+    }
+
+    /// The components below `/etc`, which is `/private/etc` on disk.
+    fn etc_relative(&self) -> Option<&[String]> {
+        match self.components.as_slice() {
+            [etc, rest @ ..] if etc == "etc" => Some(rest),
+            [private, etc, rest @ ..] if private == "private" && etc == "etc" => Some(rest),
+            _ => None,
+        }
+    }
+
+    /// Whether the path sits in a `bin` directory somewhere under
+    /// `Library/Application Support`.
+    fn is_support_binary(&self) -> bool {
+        let parent = self.parent();
+        parent.last().is_some_and(|name| name == "bin")
+            && parent
+                .windows(2)
+                .any(|pair| pair[0] == "Library" && pair[1] == "Application Support")
+    }
+
+    /// The shell footprint categories this path falls in.
+    fn footprint_categories(&self) -> impl Iterator<Item = &'static str> + '_ {
+        let name = self.components.last().map_or("", String::as_str);
+        let etc_path = self.etc_relative().map(|rest| rest.join("/"));
+        let etc_parent = self
+            .etc_relative()
+            .and_then(|rest| rest.split_last())
+            .map(|(_, parent)| parent.join("/"));
+        let in_etc = |directories: &[&str]| {
+            etc_parent
+                .as_deref()
+                .is_some_and(|parent| directories.contains(&parent))
+        };
+        let is_system_init = etc_path
+            .as_deref()
+            .is_some_and(|path| SYSTEM_SHELL_INIT.contains(&path))
+            || in_etc(&SYSTEM_SHELL_INIT_DIRECTORIES);
+
+        let is_file = !self.is_directory();
+        [
+            (
+                "path_executables",
+                is_file
+                    && PATH_DIRECTORIES
+                        .iter()
+                        .any(|directory| self.is_in(directory)),
+            ),
+            ("path_entries", is_file && in_etc(&PATH_HELPER_DIRECTORIES)),
+            (
+                "shell_init",
+                is_file && (is_system_init || USER_SHELL_INIT.contains(&name)),
+            ),
+            ("support_binaries", is_file && self.is_support_binary()),
+        ]
+        .into_iter()
+        .filter_map(|(category, matches)| matches.then_some(category))
+    }
+
+    /// Whether the path is a bundle directory with the given extension that
+    /// isn't nested in another such bundle.
+    fn is_outermost_bundle(&self, extension: &str) -> bool {
+        let name = self.components.last().map_or("", String::as_str);
+        self.is_directory()
+            && name.ends_with(extension)
+            && !self
+                .parent()
+                .iter()
+                .any(|component| component.ends_with(extension))
+    }
+
+    /// The capabilities this path gives the package.
+    fn capabilities(&self) -> impl Iterator<Item = &'static str> + '_ {
+        let risks: Vec<_> = self.risk_categories().collect();
+        let footprint: Vec<_> = self.footprint_categories().collect();
+
+        [
+            ("installs_daemons", risks.contains(&"launch_daemons")),
+            ("installs_agents", risks.contains(&"launch_agents")),
+            ("installs_kexts", risks.contains(&"kernel_extensions")),
+            (
+                "modifies_path",
+                footprint.iter().any(|category| {
+                    ["path_executables", "path_entries", "shell_init"].contains(category)
+                }),
+            ),
+            ("setuid_binaries", risks.contains(&"setuid")),
+            ("app_bundles", self.is_outermost_bundle(".app")),
+            ("frameworks", self.is_outermost_bundle(".framework")),
+        ]
+        .into_iter()
+        .filter_map(|(capability, matches)| matches.then_some(capability))
+    }
+
+    /// The audit categories this path falls in.
+    fn risk_categories(&self) -> impl Iterator<Item = &'static str> + '_ {
+        let is_file = self.path_type == "file";
+
+        [
+            ("setuid", is_file && self.mode & S_ISUID_ISGID != 0),
+            (
+                "world_writable",
+                self.path_type != "link"
+                    && self.mode & S_IWOTH != 0
+                    && !(self.is_directory() && self.mode & S_ISVTX != 0),
+            ),
+            (
+                "launch_daemons",
+                !self.is_directory() && self.is_in_any("Library/LaunchDaemons"),
+            ),
+            (
+                "launch_agents",
+                !self.is_directory() && self.is_in_any("Library/LaunchAgents"),
+            ),
+            ("kernel_extensions", self.is_outermost_bundle(".kext")),
+            (
+                "path_executables",
+                !self.is_directory()
+                    && PATH_DIRECTORIES
+                        .iter()
+                        .any(|directory| self.is_in(directory)),
+            ),
+            (
+                "privacy_sensitive",
+                PRIVACY_LOCATIONS
+                    .iter()
+                    .any(|directory| self.is_within(directory))
+                    || PRIVACY_LOCATIONS_ANYWHERE
+                        .iter()
+                        .any(|directory| self.is_within_any(directory)),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(category, matches)| matches.then_some(category))
+    }
+}
+
+/// Read the path entries of a document returned by `parse_bom`.
+pub(crate) fn audit_entries(doc: &Bound<'_, PyDict>) -> PyResult<Vec<AuditEntry>> {
+    let paths = match doc.get_item("paths")? {
+        Some(paths) if !paths.is_none() => paths.downcast_into::<PyList>()?,
+        _ => return Err(PyValueError::new_err("document has no 'paths' list")),
+    };
+
+    let mut entries = Vec::with_capacity(paths.len());
+    for entry in paths.iter() {
+        let path: String = entry.get_item("path")?.extract()?;
+        // Paths that can't be installed are skipped rather than guessed at.
+        let Ok(components) = path_components(&path) else {
+            continue;
+        };
+        entries.push(AuditEntry {
+            components,
+            path_type: entry.get_item("path_type")?.extract()?,
+            mode: entry.get_item("file_mode")?.extract()?,
+            size: extract_field(&entry, &["size"])?,
+            crc32: extract_field(&entry, &["crc32"])?,
+            path,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Heuristic risk score of a parsed BOM document, for triaging large
+/// package intakes.
+#[pyfunction(signature = (doc, *, weights = None))]
+pub(crate) fn risk_score(
+    py: Python<'_>,
+    doc: &Bound<'_, PyDict>,
+    weights: Option<HashMap<String, u32>>,
+) -> PyResult<PyObject> {
+    let weights = weights.unwrap_or_default();
+    if let Some(unknown) = weights
+        .keys()
+        .find(|name| !RISK_CATEGORIES.iter().any(|(category, _)| category == name))
+    {
+        return Err(PyValueError::new_err(format!(
+            "unknown risk category {unknown:?}; expected one of {}",
+            RISK_CATEGORIES.map(|(category, _)| category).join(", ")
+        )));
+    }
+
+    let mut matches: HashMap<&str, Vec<&str>> = HashMap::new();
+    let entries = audit_entries(doc)?;
+    for entry in &entries {
+        for category in entry.risk_categories() {
+            matches.entry(category).or_default().push(&entry.path);
+        }
+    }
+
+    let breakdown = PyDict::new_bound(py);
+    let mut score = 0;
+    for (category, default_weight) in RISK_CATEGORIES {
+        let weight = weights.get(category).copied().unwrap_or(default_weight);
+        let paths = matches.remove(category).unwrap_or_default();
+        let points = weight * paths.len() as u32;
+        score += points;
+
+        let item = PyDict::new_bound(py);
+        item.set_item("count", paths.len())?;
+        item.set_item("weight", weight)?;
+        item.set_item("points", points)?;
+        item.set_item("paths", paths)?;
+        breakdown.set_item(category, item)?;
+    }
+
+    let report = PyDict::new_bound(py);
+    report.set_item("score", score)?;
+    report.set_item(
+        "level",
+        RISK_LEVELS
+            .iter()
+            .find(|(threshold, _)| score >= *threshold)
+            .map_or("none", |(_, level)| level),
+    )?;
+    report.set_item("breakdown", breakdown)?;
+
+    Ok(report.into_py(py))
+}
+
+/// Files a parsed BOM document installs into places that change users'
+/// shell environments: `PATH` directories, `path_helper` drop-ins, shell
+/// startup files, and `bin` directories under `Application Support`.
+#[pyfunction(signature = (doc))]
+pub(crate) fn shell_footprint(py: Python<'_>, doc: &Bound<'_, PyDict>) -> PyResult<PyObject> {
+    let mut matches: HashMap<&str, Vec<&str>> = HashMap::new();
+    let entries = audit_entries(doc)?;
+    for entry in &entries {
+        for category in entry.footprint_categories() {
+            matches.entry(category).or_default().push(&entry.path);
+        }
+    }
+
+    let report = PyDict::new_bound(py);
+    let mut counts = Vec::with_capacity(FOOTPRINT_CATEGORIES.len());
+    for category in FOOTPRINT_CATEGORIES {
+        let paths = matches.remove(category).unwrap_or_default();
+        counts.push((category, paths.len()));
+        report.set_item(category, paths)?;
+    }
+    report.set_item("summary", summary(py, counts)?)?;
+
+    Ok(report.into_py(py))
+}
+
+/// Manifest of what a parsed BOM document installs, for policy decisions:
+/// each capability lists the paths that give the package it.
+#[pyfunction(signature = (doc))]
+pub(crate) fn capabilities(py: Python<'_>, doc: &Bound<'_, PyDict>) -> PyResult<PyObject> {
+    let mut matches: HashMap<&str, Vec<&str>> = HashMap::new();
+    let entries = audit_entries(doc)?;
+    for entry in &entries {
+        for capability in entry.capabilities() {
+            matches.entry(capability).or_default().push(&entry.path);
+        }
+    }
+
+    let manifest = PyDict::new_bound(py);
+    for capability in CAPABILITIES {
+        manifest.set_item(capability, matches.remove(capability).unwrap_or_default())?;
+    }
+
+    Ok(manifest.into_py(py))
+}
+
+/// Files of a parsed BOM document whose size or checksum differs from the
+/// known-good values recorded for them, for spotting tampered or
+/// mislabeled copies of OS-shipped files.
+#[pyfunction(signature = (doc, known_files, *, os_build = None))]
+pub(crate) fn known_file_deviations(
+    py: Python<'_>,
+    doc: &Bound<'_, PyDict>,
+    known_files: PyRef<'_, KnownFiles>,
+    os_build: Option<&str>,
+) -> PyResult<PyObject> {
+    let deviations = PyList::empty_bound(py);
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut checked = 0;
+    for entry in audit_entries(doc)? {
+        if entry.path_type != "file" {
+            continue;
+        }
+        let Some(known) = known_files.get(&entry.components, os_build) else {
+            continue;
+        };
+
+        checked += 1;
+        for deviation in known.deviations(entry.size, entry.crc32) {
+            *counts.entry(deviation.field).or_default() += 1;
+            let item = PyDict::new_bound(py);
+            item.set_item("path", &entry.path)?;
+            item.set_item("field", deviation.field)?;
+            item.set_item("expected", deviation.expected)?;
+            item.set_item("actual", deviation.actual)?;
+            deviations.append(item)?;
+        }
+    }
+
+    let report = PyDict::new_bound(py);
+    report.set_item("checked", checked)?;
+    report.set_item("deviations", deviations)?;
+    report.set_item(
+        "summary",
+        summary(
+            py,
+            KNOWN_FIELDS
+                .iter()
+                .map(|field| (*field, counts.get(field).copied().unwrap_or(0))),
+        )?,
+    )?;
+
+    Ok(report.into_py(py))
+}
@@ -0,0 +1,113 @@
+use crate::{block_entry, footprint::Footprint, lock, BlockOptions};
+use apple_bom::format::ParsedBom;
+use pyo3::{
+    exceptions::PyIndexError,
+    prelude::*,
+    types::{PyDict, PyList, PySlice},
+};
+use std::{os::raw::c_long, sync::Mutex};
+
+/// An index into a [BlockSequence], as Python sequences take them.
+#[derive(FromPyObject)]
+pub(crate) enum BlockKey<'py> {
+    Index(isize),
+    Slice(Bound<'py, PySlice>),
+}
+
+/// The `blocks` section of a `parse_bom` document under `lazy_blocks=True`:
+/// a sequence that parses each block the first time it is indexed and keeps
+/// the entry for later.
+///
+/// It holds its own copy of the BOM, so it outlives the data it was parsed
+/// from.
+#[pyclass(frozen, sequence, module = "pyapplebom._native", name = "BomBlocks")]
+pub(crate) struct BlockSequence {
+    bom: ParsedBom<'static>,
+    options: BlockOptions,
+
+    /// The entries parsed so far, by block index.
+    entries: Mutex<Vec<Option<Py<PyDict>>>>,
+}
+
+impl BlockSequence {
+    pub(crate) fn new(bom: ParsedBom<'static>, options: BlockOptions) -> Self {
+        let count = bom.blocks.blocks.len();
+        Self {
+            bom,
+            options,
+            entries: Mutex::new(vec![None; count]),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.bom.blocks.blocks.len()
+    }
+
+    pub(crate) fn entry<'py>(&self, py: Python<'py>, index: usize) -> PyResult<Bound<'py, PyDict>> {
+        if let Some(entry) = &lock(&self.entries)[index] {
+            return Ok(entry.bind(py).clone());
+        }
+        let entry = block_entry(py, &self.bom, index, self.options)?;
+        // Another thread may have parsed it meanwhile; the first entry stored
+        // is the one every caller sees.
+        let entry = lock(&self.entries)[index]
+            .get_or_insert_with(|| entry.unbind())
+            .bind(py)
+            .clone();
+        Ok(entry)
+    }
+
+    /// The BOM copy is a buffer; the parsed entries are Python objects.
+    pub(crate) fn footprint(&self) -> Footprint {
+        Footprint {
+            buffers: self.bom.data.len(),
+            ..Default::default()
+        }
+    }
+}
+
+#[pymethods]
+impl BlockSequence {
+    fn __len__(&self) -> usize {
+        self.len()
+    }
+
+    fn __getitem__<'py>(&self, py: Python<'py>, key: BlockKey<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let count = self.bom.blocks.blocks.len();
+        match key {
+            BlockKey::Index(index) => {
+                let position = if index < 0 {
+                    index + count as isize
+                } else {
+                    index
+                };
+                if !(0..count as isize).contains(&position) {
+                    return Err(PyIndexError::new_err(format!(
+                        "block index {index} out of range for {count} blocks"
+                    )));
+                }
+                Ok(self.entry(py, position as usize)?.into_any())
+            }
+            BlockKey::Slice(slice) => {
+                let indices = slice.indices(count as c_long)?;
+                let entries = PyList::empty_bound(py);
+                let mut index = indices.start;
+                for _ in 0..indices.slicelength {
+                    entries.append(self.entry(py, index as usize)?)?;
+                    index += indices.step;
+                }
+                Ok(entries.into_any())
+            }
+        }
+    }
+
+    /// The number of blocks parsed so far.
+    #[getter]
+    fn parsed_count(&self) -> usize {
+        lock(&self.entries).iter().flatten().count()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<BomBlocks of {} blocks>", self.bom.blocks.blocks.len())
+    }
+}
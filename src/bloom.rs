@@ -0,0 +1,92 @@
+use crate::{
+    bom_error_to_py, buffer::BomData, lookup::lookup_style, options::ParseOptions, required_paths,
+};
+use apple_bom::format::ParsedBom;
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+use sha2::{Digest, Sha256};
+use std::f64::consts::LN_2;
+
+/// Leads every serialized filter; the last byte is the layout version.
+const MAGIC: &[u8; 8] = b"BOMBLM\x00\x01";
+
+/// The magic followed by the hash count, a big-endian `u32`. The bit array
+/// fills the rest of the filter.
+const HEADER_LENGTH: usize = MAGIC.len() + 4;
+
+/// The bits `path` sets in a filter of `bit_count` bits, by double hashing
+/// its SHA-256 so filters compare equal across platforms and releases.
+fn bit_positions(path: &str, hash_count: u32, bit_count: u64) -> impl Iterator<Item = u64> {
+    let digest = Sha256::digest(path.as_bytes());
+    let first = u64::from_be_bytes(digest[..8].try_into().unwrap());
+    // An odd step never revisits a bit before covering the whole array.
+    let step = u64::from_be_bytes(digest[8..16].try_into().unwrap()) | 1;
+
+    (0..u64::from(hash_count))
+        .map(move |index| first.wrapping_add(index.wrapping_mul(step)) % bit_count)
+}
+
+/// Build a bloom filter of the paths in a BOM's `Paths` tree.
+#[pyfunction(signature = (
+    data,
+    fp_rate = 0.001,
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+))]
+pub(crate) fn path_bloom<'py>(
+    py: Python<'py>,
+    data: &Bound<'_, PyAny>,
+    fp_rate: f64,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let data = BomData::new(data, true)?;
+    if !(fp_rate > 0.0 && fp_rate < 1.0) {
+        return Err(PyValueError::new_err(format!(
+            "fp_rate must be between 0 and 1, not {fp_rate}"
+        )));
+    }
+    let style = lookup_style(options, path_style, path_root)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let paths = required_paths(&bom)?;
+
+    // The standard sizing for `n` items at false positive rate `p`:
+    // m = -n ln p / (ln 2)^2 bits and k = (m / n) ln 2 hashes.
+    let count = paths.len().max(1) as f64;
+    let byte_count = (-count * fp_rate.ln() / (LN_2 * LN_2) / 8.0)
+        .ceil()
+        .max(1.0) as usize;
+    let bit_count = byte_count as u64 * 8;
+    let hash_count = ((bit_count as f64 / count) * LN_2).round().max(1.0) as u32;
+
+    let mut filter = Vec::with_capacity(HEADER_LENGTH + byte_count);
+    filter.extend_from_slice(MAGIC);
+    filter.extend_from_slice(&hash_count.to_be_bytes());
+    filter.resize(HEADER_LENGTH + byte_count, 0);
+    let bits = &mut filter[HEADER_LENGTH..];
+    for path in &paths {
+        for bit in bit_positions(&style.render(path.path()), hash_count, bit_count) {
+            bits[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+    }
+
+    Ok(PyBytes::new_bound(py, &filter))
+}
+
+/// Whether `path` may be in the set `filter` was built from. `False` is
+/// certain; `True` is wrong at about the filter's false positive rate.
+#[pyfunction]
+pub(crate) fn bloom_contains(filter: &[u8], path: &str) -> PyResult<bool> {
+    let hash_count = match filter.get(..HEADER_LENGTH) {
+        Some(header) if header.starts_with(MAGIC) && filter.len() > HEADER_LENGTH => {
+            u32::from_be_bytes(header[MAGIC.len()..].try_into().unwrap())
+        }
+        _ => return Err(PyValueError::new_err("not a path_bloom filter")),
+    };
+    let bits = &filter[HEADER_LENGTH..];
+
+    Ok(bit_positions(path, hash_count, bits.len() as u64 * 8)
+        .all(|bit| bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0))
+}
@@ -0,0 +1,667 @@
+use crate::{
+    bom_error_to_py, bom_info_dict,
+    buffer::BomData,
+    check_block_index, checked_block_entry, closed_error,
+    csv::{self, CsvLayout},
+    document::Document,
+    footprint::Footprint,
+    header::BomHeader,
+    json::{self, Destination},
+    lock,
+    lookup::{self, PathIndex},
+    options::{OptionChanges, ParseOptions},
+    parse_bom_document,
+    pathiter::PathIterator,
+    paths::PathStyle,
+    safe_bom_call, serialize_path_list, size64,
+    stats::TableStats,
+    tree, variables_list, BlockOptions, BomParseError, PanicPolicy, PathExtras, PathOptions,
+    RawBlockEncoding, SafeBomCall,
+};
+use apple_bom::{format::ParsedBom, BomPath};
+use pyo3::{
+    exceptions::{PyKeyError, PyOSError},
+    prelude::*,
+    types::{PyBytes, PyDict, PyIterator, PyList},
+};
+use std::{
+    fs::File,
+    io::{self, Read},
+    ops::Deref,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+/// The bytes behind a [BomFile]: the caller's, shared rather than copied,
+/// or ones made for a file it opened or a copy of a writable buffer.
+struct Contents {
+    data: BomData,
+
+    /// Whether `data` was made for the [BomFile] rather than passed in.
+    owned: bool,
+}
+
+impl Contents {
+    fn new(data: BomData, owned: bool) -> Self {
+        Self { data, owned }
+    }
+
+    fn bom(&self) -> PyResult<ParsedBom<'_>> {
+        ParsedBom::parse(self).map_err(bom_error_to_py)
+    }
+}
+
+impl Deref for Contents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// A BOM whose sections are serialized only when they are asked for.
+///
+/// Only the header and the block and variable indexes are read up front, to
+/// reject data that isn't a BOM; every accessor builds its result afresh.
+///
+/// `close` drops the contents at once; an accessor already running keeps its
+/// own reference until it returns.
+#[pyclass(frozen, module = "pyapplebom._native")]
+pub(crate) struct BomFile {
+    /// `None` once closed.
+    contents: Mutex<Option<Arc<Contents>>>,
+    source_path: Option<String>,
+}
+
+impl BomFile {
+    fn new_checked(contents: Contents, source_path: Option<String>) -> PyResult<Self> {
+        contents.bom()?;
+        Ok(Self {
+            contents: Mutex::new(Some(Arc::new(contents))),
+            source_path,
+        })
+    }
+
+    fn contents(&self) -> PyResult<Arc<Contents>> {
+        lock(&self.contents)
+            .clone()
+            .ok_or_else(|| closed_error("BomFile"))
+    }
+
+    /// The entry for a path used as a key, in the style it is written in, or
+    /// `None` if the BOM has no such path or no `Paths` tree.
+    fn keyed_entry<'py>(
+        &self,
+        py: Python<'py>,
+        path: &str,
+    ) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let contents = self.contents()?;
+        let bom = contents.bom()?;
+        if bom.find_variable("Paths").is_err() {
+            return Ok(None);
+        }
+        lookup::path_entry(py, &bom, path, &PathStyle::of_path(path))
+    }
+
+    pub(crate) fn footprint(&self) -> Footprint {
+        match lock(&self.contents).as_deref() {
+            Some(contents) if contents.owned => Footprint {
+                buffers: contents.len(),
+                ..Default::default()
+            },
+            Some(contents) => Footprint {
+                shared: contents.len(),
+                ..Default::default()
+            },
+            None => Footprint::default(),
+        }
+    }
+}
+
+#[pymethods]
+impl BomFile {
+    #[new]
+    fn new(data: &Bound<'_, PyAny>) -> PyResult<Self> {
+        // Writable buffers could change under the parsed BOM, so they're
+        // copied.
+        let data = BomData::new(data, false)?;
+        let owned = data.copied();
+        Self::new_checked(Contents::new(data, owned), None)
+    }
+
+    /// Open the BOM at `path`.
+    #[staticmethod]
+    pub(crate) fn open(py: Python<'_>, path: PathBuf) -> PyResult<Self> {
+        let read_error = |err: io::Error| {
+            PyOSError::new_err(format!("failed reading {}: {err}", path.display()))
+        };
+        let mut file = File::open(&path).map_err(read_error)?;
+        let length = file.metadata().map_err(read_error)?.len() as usize;
+        let object = PyBytes::new_bound_with(py, length, |buffer| {
+            file.read_exact(buffer).map_err(read_error)
+        })?;
+        Self::new_checked(
+            Contents::new(BomData::from_bytes(object, false), true),
+            Some(path.display().to_string()),
+        )
+    }
+
+    #[getter]
+    fn source_path(&self) -> Option<&str> {
+        self.source_path.as_deref()
+    }
+
+    #[getter]
+    fn byte_length(&self) -> PyResult<usize> {
+        Ok(self.contents()?.len())
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        lock(&self.contents).is_none()
+    }
+
+    /// Release the BOM's bytes. Accessors raise `ValueError` afterwards;
+    /// closing again does nothing.
+    fn close(&self) {
+        lock(&self.contents).take();
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &self,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc_value: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) {
+        self.close();
+    }
+
+    #[getter]
+    fn header(&self) -> PyResult<BomHeader> {
+        Ok(BomHeader::new(&self.contents()?.bom()?))
+    }
+
+    #[getter]
+    fn variables<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        variables_list(py, &self.contents()?.bom()?)
+    }
+
+    /// The names of the variables, in declaration order.
+    fn variable_names(&self) -> PyResult<Vec<String>> {
+        Ok(self
+            .contents()?
+            .bom()?
+            .vars
+            .vars
+            .iter()
+            .map(|var| var.name.clone())
+            .collect())
+    }
+
+    /// The raw bytes of the block the variable `name` points to, for
+    /// inspecting vendor-specific or unknown variables.
+    fn get_variable<'py>(&self, py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyBytes>> {
+        let contents = self.contents()?;
+        let bom = contents.bom()?;
+        let var = bom
+            .vars
+            .vars
+            .iter()
+            .find(|var| var.name == name)
+            .ok_or_else(|| PyKeyError::new_err(format!("variable {name:?} not found")))?;
+        let data = bom
+            .block_data(var.block_index as _)
+            .map_err(bom_error_to_py)?;
+        Ok(PyBytes::new_bound(py, data))
+    }
+
+    /// The `BomInfo` block, or `None` if the BOM has no `BomInfo` variable.
+    #[getter]
+    fn bom_info<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let contents = self.contents()?;
+        let bom = contents.bom()?;
+        match safe_bom_call(|| bom.bom_info()) {
+            SafeBomCall::Value(info) => Ok(Some(bom_info_dict(py, &info)?)),
+            SafeBomCall::MissingVariable => Ok(None),
+            SafeBomCall::Error(err) => Err(BomParseError::new_err(err)),
+        }
+    }
+
+    /// The number of entries in the blocks index.
+    #[getter]
+    fn block_count(&self) -> PyResult<usize> {
+        Ok(self.contents()?.bom()?.blocks.blocks.len())
+    }
+
+    /// The block at `index`, as `parse_bom` lists it under `blocks`.
+    #[pyo3(signature = (
+        index,
+        *,
+        include_raw_block_bytes = false,
+        raw_block_encoding = "hex",
+        interpret_fields = false,
+    ))]
+    fn block<'py>(
+        &self,
+        py: Python<'py>,
+        index: usize,
+        include_raw_block_bytes: bool,
+        raw_block_encoding: &str,
+        interpret_fields: bool,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let options = BlockOptions {
+            raw_bytes: RawBlockEncoding::from_options(include_raw_block_bytes, raw_block_encoding)?,
+            interpretations: interpret_fields,
+            on_panic: PanicPolicy::Wrap,
+        };
+        checked_block_entry(py, &self.contents()?.bom()?, index, options)
+    }
+
+    /// The bytes of the block at `index` as a read-only `memoryview` of the
+    /// BOM's `bytes` object, so large blocks can be inspected without being
+    /// copied.
+    fn block_view<'py>(&self, py: Python<'py>, index: usize) -> PyResult<Bound<'py, PyAny>> {
+        let contents = self.contents()?;
+        let bom = contents.bom()?;
+        check_block_index(&bom, index)?;
+        let entry = &bom.blocks.blocks[index];
+        let start = entry.file_offset as usize;
+        let end = start + entry.length as usize;
+        if end > contents.len() {
+            return Err(BomParseError::new_err(format!(
+                "block {index} extends past the end of the BOM"
+            )));
+        }
+
+        contents.data.view(py, start, end)
+    }
+
+    /// The entries of the `Paths` tree, as `parse_bom` lists them under
+    /// `paths`, or `None` if the BOM has no `Paths` variable.
+    ///
+    /// `offset` and `limit` select a page of the entries in `path_order`, and
+    /// only that page is serialized.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        *,
+        offset = 0,
+        limit = None,
+        options = None,
+        path_style = None,
+        path_root = None,
+        path_order = None,
+        include_tree_positions = None,
+        include_raw_records = None,
+        typed_paths = None,
+    ))]
+    fn paths<'py>(
+        &self,
+        py: Python<'py>,
+        offset: usize,
+        limit: Option<usize>,
+        options: Option<ParseOptions>,
+        path_style: Option<String>,
+        path_root: Option<String>,
+        path_order: Option<String>,
+        include_tree_positions: Option<bool>,
+        include_raw_records: Option<bool>,
+        typed_paths: Option<bool>,
+    ) -> PyResult<Option<Bound<'py, PyList>>> {
+        let options = ParseOptions::resolve(
+            options,
+            OptionChanges::for_paths(
+                path_style,
+                path_root,
+                path_order,
+                include_tree_positions,
+                include_raw_records,
+                typed_paths,
+            ),
+        )?;
+        let path_options = options.path_options()?;
+        let PathOptions { order, typed, .. } = path_options;
+        let contents = self.contents()?;
+        let bom = contents.bom()?;
+
+        let mut paths = match safe_bom_call(|| bom.paths()) {
+            SafeBomCall::Value(paths) => paths,
+            SafeBomCall::MissingVariable => return Ok(None),
+            SafeBomCall::Error(err) => return Err(BomParseError::new_err(err)),
+        };
+        let extras = PathExtras::for_paths(&bom, &paths, &path_options);
+
+        order.apply(&mut paths);
+        let start = offset.min(paths.len());
+        let end = limit.map_or(paths.len(), |limit| {
+            start.saturating_add(limit).min(paths.len())
+        });
+        serialize_path_list(py, &paths[start..end], &options.style()?, &extras, typed).map(Some)
+    }
+
+    /// Write the entries [BomFile::paths] lists to `destination`, a file path
+    /// or a binary file-like object, as NDJSON without building Python
+    /// objects for them, and return the number of entries written, or with
+    /// `statistics` their column statistics.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        destination,
+        *,
+        options = None,
+        path_style = None,
+        path_root = None,
+        path_order = None,
+        include_tree_positions = None,
+        include_raw_records = None,
+        statistics = false,
+    ))]
+    fn export_paths_ndjson(
+        &self,
+        py: Python<'_>,
+        destination: Destination<'_>,
+        options: Option<ParseOptions>,
+        path_style: Option<String>,
+        path_root: Option<String>,
+        path_order: Option<String>,
+        include_tree_positions: Option<bool>,
+        include_raw_records: Option<bool>,
+        statistics: bool,
+    ) -> PyResult<PyObject> {
+        let options = ParseOptions::resolve(
+            options,
+            OptionChanges::for_paths(
+                path_style,
+                path_root,
+                path_order,
+                include_tree_positions,
+                include_raw_records,
+                None,
+            ),
+        )?;
+        let path_options = options.path_options()?;
+        let style = options.style()?;
+        let contents = self.contents()?;
+        let bom = contents.bom()?;
+
+        let mut paths = match safe_bom_call(|| bom.paths()) {
+            SafeBomCall::Value(paths) => paths,
+            SafeBomCall::MissingVariable => Vec::new(),
+            SafeBomCall::Error(err) => return Err(BomParseError::new_err(err)),
+        };
+        let extras = PathExtras::for_paths(&bom, &paths, &path_options);
+        path_options.order.apply(&mut paths);
+        let entries = paths.iter().map(|path| extras.entry(path, &style));
+        if statistics {
+            let mut stats = TableStats::default();
+            json::write_ndjson(destination, entries, Some(&mut stats))?;
+            Ok(stats.to_dict(py)?.into_any().unbind())
+        } else {
+            Ok(json::write_ndjson(destination, entries, None)?.into_py(py))
+        }
+    }
+
+    /// Write the entries [BomFile::paths] lists to `destination` as a CSV
+    /// table of `columns` separated by `delimiter`, without building Python
+    /// objects for them, and return the number of rows written, or with
+    /// `statistics` their column statistics.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        destination,
+        *,
+        delimiter = ",",
+        columns = None,
+        options = None,
+        path_style = None,
+        path_root = None,
+        path_order = None,
+        include_raw_records = None,
+        statistics = false,
+    ))]
+    fn export_paths_csv(
+        &self,
+        py: Python<'_>,
+        destination: Destination<'_>,
+        delimiter: &str,
+        columns: Option<Vec<String>>,
+        options: Option<ParseOptions>,
+        path_style: Option<String>,
+        path_root: Option<String>,
+        path_order: Option<String>,
+        include_raw_records: Option<bool>,
+        statistics: bool,
+    ) -> PyResult<PyObject> {
+        let options = ParseOptions::resolve(
+            options,
+            OptionChanges::for_paths(
+                path_style,
+                path_root,
+                path_order,
+                None,
+                include_raw_records,
+                None,
+            ),
+        )?;
+        let path_options = options.path_options()?;
+        let style = options.style()?;
+        let layout = CsvLayout::from_options(
+            delimiter,
+            columns,
+            path_options.raw_records == Some(RawBlockEncoding::Hex),
+        )?;
+        let contents = self.contents()?;
+        let bom = contents.bom()?;
+
+        let mut paths = match safe_bom_call(|| bom.paths()) {
+            SafeBomCall::Value(paths) => paths,
+            SafeBomCall::MissingVariable => Vec::new(),
+            SafeBomCall::Error(err) => return Err(BomParseError::new_err(err)),
+        };
+        let extras = PathExtras::for_paths(&bom, &paths, &path_options);
+        path_options.order.apply(&mut paths);
+        let entries = paths.iter().map(|path| extras.entry(path, &style));
+        if statistics {
+            let mut stats = TableStats::default();
+            csv::write_csv(destination, &layout, entries, Some(&mut stats))?;
+            Ok(stats.to_dict(py)?.into_any().unbind())
+        } else {
+            Ok(csv::write_csv(destination, &layout, entries, None)?.into_py(py))
+        }
+    }
+
+    /// The number of entries [BomFile::paths] lists, counted from the leaves
+    /// of the `Paths` tree without reading their records, or `None` if the
+    /// BOM has no `Paths` variable.
+    fn count_paths(&self) -> PyResult<Option<usize>> {
+        let contents = self.contents()?;
+        let bom = contents.bom()?;
+        match safe_bom_call(|| size64::leaf_entries(&bom, "Paths")) {
+            SafeBomCall::Value(leaves) => Ok(Some(leaves.len())),
+            SafeBomCall::MissingVariable => Ok(None),
+            SafeBomCall::Error(err) => Err(BomParseError::new_err(err)),
+        }
+    }
+
+    /// The entry for `path`, given in `path_style`, as [BomFile::paths] lists
+    /// it, or `None` if the BOM has no such path.
+    #[pyo3(signature = (path, *, options = None, path_style = None, path_root = None))]
+    fn get_path<'py>(
+        &self,
+        py: Python<'py>,
+        path: &str,
+        options: Option<ParseOptions>,
+        path_style: Option<String>,
+        path_root: Option<String>,
+    ) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let style = lookup::lookup_style(options, path_style, path_root)?;
+        lookup::path_entry(py, &self.contents()?.bom()?, path, &style)
+    }
+
+    /// The entry whose `path_id` is `path_id`, as `path_by_id` returns it.
+    #[pyo3(signature = (path_id, *, options = None, path_style = None, path_root = None))]
+    fn get_path_by_id<'py>(
+        &self,
+        py: Python<'py>,
+        path_id: u32,
+        options: Option<ParseOptions>,
+        path_style: Option<String>,
+        path_root: Option<String>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let style = lookup::lookup_style(options, path_style, path_root)?;
+        let index = PathIndex::new(&self.contents()?.bom()?)?;
+        index.entry(py, index.find(path_id)?, &style)
+    }
+
+    /// Iterate over the entries [BomFile::paths] lists, one at a time.
+    #[pyo3(signature = (
+        *,
+        options = None,
+        path_style = None,
+        path_root = None,
+        path_order = None,
+        include_tree_positions = None,
+        include_raw_records = None,
+        typed_paths = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn iter_paths(
+        &self,
+        options: Option<ParseOptions>,
+        path_style: Option<String>,
+        path_root: Option<String>,
+        path_order: Option<String>,
+        include_tree_positions: Option<bool>,
+        include_raw_records: Option<bool>,
+        typed_paths: Option<bool>,
+    ) -> PyResult<PathIterator> {
+        let options = ParseOptions::resolve(
+            options,
+            OptionChanges::for_paths(
+                path_style,
+                path_root,
+                path_order,
+                include_tree_positions,
+                include_raw_records,
+                typed_paths,
+            ),
+        )?;
+        PathIterator::new(
+            &self.contents()?.bom()?,
+            options.style()?,
+            options.path_options()?,
+        )
+    }
+
+    /// The entries [BomFile::paths] lists as a nested directory tree, as
+    /// `paths_tree` returns it.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        *,
+        options = None,
+        path_style = None,
+        path_root = None,
+        path_order = None,
+        include_tree_positions = None,
+        include_raw_records = None,
+        typed_paths = None,
+    ))]
+    fn paths_tree<'py>(
+        &self,
+        py: Python<'py>,
+        options: Option<ParseOptions>,
+        path_style: Option<String>,
+        path_root: Option<String>,
+        path_order: Option<String>,
+        include_tree_positions: Option<bool>,
+        include_raw_records: Option<bool>,
+        typed_paths: Option<bool>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let options = ParseOptions::resolve(
+            options,
+            OptionChanges::for_paths(
+                path_style,
+                path_root,
+                path_order,
+                include_tree_positions,
+                include_raw_records,
+                typed_paths,
+            ),
+        )?;
+        tree::build(py, &self.contents()?.bom()?, &options)
+    }
+
+    /// The whole document, as `parse_bom` returns it for the same data with
+    /// `options`, for code written against dictionaries: the header, typed
+    /// entries, and lazy blocks are the dictionaries and list they stand in
+    /// for.
+    #[pyo3(signature = (*, options = None))]
+    fn to_dict<'py>(
+        &self,
+        py: Python<'py>,
+        options: Option<ParseOptions>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let options =
+            ParseOptions::resolve(options, OptionChanges::default())?.with(OptionChanges {
+                typed_paths: Some(false),
+                lazy_blocks: Some(false),
+                ..Default::default()
+            })?;
+        let contents = self.contents()?;
+        let doc = parse_bom_document(py, &contents, self.source_path.as_deref(), &options)?;
+        doc.set_item("header", BomHeader::new(&contents.bom()?).to_dict(py)?)?;
+        Ok(doc)
+    }
+
+    /// [BomFile::to_dict] as JSON text, with typed objects written as their
+    /// dictionaries and lazy blocks as a list.
+    #[pyo3(signature = (*, options = None, indent = None))]
+    fn to_json(&self, options: Option<ParseOptions>, indent: Option<usize>) -> PyResult<String> {
+        let options = ParseOptions::resolve(options, OptionChanges::default())?;
+        let document = Document::new(&self.contents()?, self.source_path.as_deref(), &options)?;
+        json::to_string(&document, indent)
+    }
+
+    /// The number of entries in the `Paths` tree.
+    fn __len__(&self) -> PyResult<usize> {
+        Ok(self.count_paths()?.unwrap_or(0))
+    }
+
+    /// Iterate over the raw paths, in tree order.
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        let contents = self.contents()?;
+        let bom = contents.bom()?;
+        let paths = match safe_bom_call(|| bom.paths()) {
+            SafeBomCall::Value(paths) => paths,
+            SafeBomCall::MissingVariable => Vec::new(),
+            SafeBomCall::Error(err) => return Err(BomParseError::new_err(err)),
+        };
+        PyList::new_bound(py, paths.iter().map(BomPath::path))
+            .as_any()
+            .iter()
+    }
+
+    fn __contains__(&self, py: Python<'_>, path: &Bound<'_, PyAny>) -> PyResult<bool> {
+        match path.extract::<String>() {
+            Ok(path) => Ok(self.keyed_entry(py, &path)?.is_some()),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// The entry for `path`, written raw (`./a`), relative (`a`), or absolute
+    /// (`/a`), with its `path` written the same way.
+    fn __getitem__<'py>(&self, py: Python<'py>, path: &str) -> PyResult<Bound<'py, PyDict>> {
+        self.keyed_entry(py, path)?
+            .ok_or_else(|| PyKeyError::new_err(path.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        match (&self.source_path, lock(&self.contents).as_deref()) {
+            (Some(path), Some(_)) => format!("BomFile.open({path:?})"),
+            (Some(path), None) => format!("<closed BomFile.open({path:?})>"),
+            (None, Some(contents)) => format!("<BomFile of {} bytes>", contents.len()),
+            (None, None) => "<closed BomFile>".to_string(),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+#[cfg(feature = "buffer")]
+use pyo3::buffer::PyBuffer;
+use pyo3::{
+    exceptions::PyTypeError,
+    prelude::*,
+    pybacked::PyBackedBytes,
+    types::{PyBytes, PyMemoryView, PySlice},
+};
+use std::ops::Deref;
+
+#[cfg(all(feature = "abi3", feature = "buffer"))]
+compile_error!(
+    "the 'buffer' feature replaces 'abi3' with Python 3.11's stable ABI; build it with \
+     --no-default-features"
+);
+
+/// Where the bytes of [BomData] are read from.
+enum Storage {
+    Bytes(PyBackedBytes),
+
+    /// A C-contiguous byte buffer, held exported so it can't be resized or
+    /// freed while it is read.
+    #[cfg(feature = "buffer")]
+    Buffer(PyBuffer<u8>),
+}
+
+/// BOM data passed in from Python. `bytes` is read in place, and so, with
+/// the `buffer` feature, is any C-contiguous byte buffer; the stable ABI
+/// can't borrow other buffers before Python 3.11, so without it they are
+/// copied once into `bytes`.
+pub(crate) struct BomData {
+    /// The object the bytes are read from: the caller's, or the copy.
+    object: PyObject,
+    storage: Storage,
+    copied: bool,
+}
+
+impl BomData {
+    /// The bytes of `object`, read in place where that is possible. A
+    /// writable buffer is only read in place when `writable`, for callers
+    /// that are done with it before Python code can run again.
+    pub(crate) fn new(object: &Bound<'_, PyAny>, writable: bool) -> PyResult<Self> {
+        if let Ok(bytes) = object.downcast::<PyBytes>() {
+            return Ok(Self::from_bytes(bytes.clone(), false));
+        }
+
+        #[cfg(feature = "buffer")]
+        if let Ok(buffer) = PyBuffer::<u8>::get_bound(object) {
+            if buffer.is_c_contiguous() && (writable || buffer.readonly()) {
+                return Ok(Self {
+                    object: object.clone().unbind(),
+                    storage: Storage::Buffer(buffer),
+                    copied: false,
+                });
+            }
+        }
+        #[cfg(not(feature = "buffer"))]
+        let _ = writable;
+
+        let view = PyMemoryView::from_bound(object).map_err(|_| {
+            PyTypeError::new_err(format!(
+                "data must be bytes or support the buffer protocol, not {}",
+                object.get_type().qualname().unwrap_or_default()
+            ))
+        })?;
+        let copy = view.call_method0("tobytes")?.downcast_into::<PyBytes>()?;
+        view.call_method0("release")?;
+        Ok(Self::from_bytes(copy, true))
+    }
+
+    /// The bytes of `object`, which was `copied` from the caller's data or
+    /// made for it when set.
+    pub(crate) fn from_bytes(object: Bound<'_, PyBytes>, copied: bool) -> Self {
+        Self {
+            object: object.clone().into_any().unbind(),
+            storage: Storage::Bytes(PyBackedBytes::from(object)),
+            copied,
+        }
+    }
+
+    /// Whether the bytes are a copy rather than the caller's memory.
+    pub(crate) fn copied(&self) -> bool {
+        self.copied
+    }
+
+    /// A read-only `memoryview` of `start..end`, sharing the memory rather
+    /// than copying it.
+    pub(crate) fn view<'py>(
+        &self,
+        py: Python<'py>,
+        start: usize,
+        end: usize,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        PyMemoryView::from_bound(self.object.bind(py))?
+            .call_method1("cast", ("B",))?
+            .get_item(PySlice::new_bound(py, start as isize, end as isize, 1))?
+            .call_method0("toreadonly")
+    }
+}
+
+impl Deref for BomData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.storage {
+            Storage::Bytes(bytes) => bytes,
+            // SAFETY: the buffer stays exported while `self` holds it, and it
+            // was checked to be C-contiguous bytes, so `len_bytes` bytes from
+            // `buf_ptr` are its contents.
+            #[cfg(feature = "buffer")]
+            Storage::Buffer(buffer) => unsafe {
+                std::slice::from_raw_parts(buffer.buf_ptr().cast::<u8>(), buffer.len_bytes())
+            },
+        }
+    }
+}
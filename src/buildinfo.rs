@@ -0,0 +1,20 @@
+use crate::{features::FEATURES, mapping};
+use pyo3::{prelude::*, types::PyDict};
+
+/// What this build of the extension can do, so callers can check for a
+/// feature rather than probe for it with `try`/`except`.
+#[pyfunction]
+pub(crate) fn build_info(py: Python<'_>) -> PyResult<Bound<'_, PyDict>> {
+    let features = PyDict::new_bound(py);
+    for (feature, enabled) in FEATURES {
+        features.set_item(feature, enabled)?;
+    }
+    features.set_item("mmap", mapping::MAPS_FILES)?;
+
+    let info = PyDict::new_bound(py);
+    info.set_item("version", env!("CARGO_PKG_VERSION"))?;
+    // Read from `Cargo.lock` by the build script.
+    info.set_item("apple_bom_version", env!("APPLE_BOM_VERSION"))?;
+    info.set_item("features", features)?;
+    Ok(info)
+}
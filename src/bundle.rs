@@ -0,0 +1,224 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    cksum::{cksum, ChecksumMode},
+    lookup::lookup_style,
+    options::ParseOptions,
+    paths::match_key,
+    reader::{device_id, ReadOptions, DEFAULT_READ_BUFFER_SIZE},
+    required_paths, summary,
+    walk::{walk_entries, WalkEntry, WalkOptions},
+};
+use apple_bom::{format::ParsedBom, BomPath, BomPathType};
+use pyo3::{
+    exceptions::{PyOSError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::Path,
+};
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Locate the BOM directory entry for the `.app` bundle that `app_path` refers to.
+///
+/// Top-level bundles (those not nested inside another `.app`) are candidates. The
+/// candidate sharing the on-disk bundle's name wins; otherwise a lone candidate is
+/// used as-is.
+fn find_bundle_root<'a>(
+    paths: &'a [BomPath],
+    app_path: &str,
+    case_sensitive: bool,
+) -> PyResult<&'a str> {
+    let mut candidates: Vec<&str> = Vec::new();
+
+    for path in paths {
+        if !matches!(path.path_type(), BomPathType::Directory) || !path.path().ends_with(".app") {
+            continue;
+        }
+
+        let nested = candidates
+            .iter()
+            .any(|root| path.path().starts_with(&format!("{root}/")));
+        if !nested {
+            candidates.push(path.path());
+        }
+    }
+
+    let wanted = file_name(app_path.trim_end_matches(['/', '\\']));
+    let wanted = wanted.rsplit('\\').next().unwrap_or(wanted);
+
+    let wanted_key = match_key(wanted, case_sensitive);
+    if let Some(root) = candidates
+        .iter()
+        .find(|root| match_key(file_name(root), case_sensitive) == wanted_key)
+    {
+        return Ok(root);
+    }
+
+    match candidates.as_slice() {
+        [] => Err(PyValueError::new_err("BOM does not install an .app bundle")),
+        [root] => Ok(root),
+        _ => Err(PyValueError::new_err(format!(
+            "BOM installs multiple .app bundles and none is named {wanted:?}: {}",
+            candidates.join(", ")
+        ))),
+    }
+}
+
+fn only_in<'a>(
+    left: &BTreeMap<Cow<'_, str>, &'a str>,
+    right: &BTreeMap<Cow<'_, str>, &'a str>,
+) -> Vec<&'a str> {
+    let mut only: Vec<&str> = left
+        .iter()
+        .filter(|(key, _)| !right.contains_key(*key))
+        .map(|(_, path)| *path)
+        .collect();
+    only.sort_unstable();
+    only
+}
+
+/// Whether the checksum recorded for `path` disagrees with the entry on disk.
+///
+/// Only files and symlinks present as the same type on both sides are checked.
+fn checksum_differs(
+    path: &BomPath,
+    entry: &WalkEntry,
+    mode: ChecksumMode,
+    buffer_size: usize,
+) -> io::Result<bool> {
+    let file_type = entry.metadata.file_type();
+    let actual = match path.path_type() {
+        BomPathType::File if file_type.is_file() => {
+            mode.file(&entry.path, entry.metadata.len(), buffer_size)?
+        }
+        BomPathType::Link if file_type.is_symlink() && mode != ChecksumMode::None => Some(cksum(
+            fs::read_link(&entry.path)?.to_string_lossy().as_bytes(),
+        )),
+        _ => None,
+    };
+
+    Ok(actual.is_some_and(|actual| Some(actual) != path.crc32()))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[pyfunction(signature = (
+    data,
+    app_path,
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+    case_sensitive = true,
+    follow_symlinks = false,
+    checksum = "none",
+    read_buffer_size = DEFAULT_READ_BUFFER_SIZE,
+    per_device_concurrency = 1,
+))]
+pub(crate) fn compare_with_bundle(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    app_path: &str,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+    case_sensitive: bool,
+    follow_symlinks: bool,
+    checksum: &str,
+    read_buffer_size: usize,
+    per_device_concurrency: usize,
+) -> PyResult<PyObject> {
+    let data = BomData::new(data, true)?;
+    let checksum = ChecksumMode::from_name(checksum)?;
+    let reads = ReadOptions::new(read_buffer_size, per_device_concurrency)?;
+    let style = lookup_style(options, path_style, path_root)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let paths = required_paths(&bom)?;
+    let bundle_root = find_bundle_root(&paths, app_path, case_sensitive)?;
+    let prefix = format!("{bundle_root}/");
+
+    let bom_paths: HashMap<&str, &BomPath> = paths
+        .iter()
+        .filter_map(|path| Some((path.path().strip_prefix(&prefix)?, path)))
+        .collect();
+    let bom_entries: BTreeMap<Cow<'_, str>, &str> = bom_paths
+        .keys()
+        .map(|relative| (match_key(relative, case_sensitive), *relative))
+        .collect();
+
+    let walk_options = WalkOptions::new(&[], false, follow_symlinks)?;
+    let walked = walk_entries(Path::new(app_path), &walk_options)
+        .map_err(|err| PyOSError::new_err(format!("failed reading {app_path}: {err}")))?;
+    let relatives: Vec<String> = walked.iter().map(WalkEntry::relative).collect();
+    let disk_entries: BTreeMap<Cow<'_, str>, &str> = relatives
+        .iter()
+        .map(|relative| (match_key(relative, case_sensitive), relative.as_str()))
+        .collect();
+
+    let shared: Vec<(&WalkEntry, &str)> = walked
+        .iter()
+        .zip(&relatives)
+        .filter_map(|(entry, relative)| {
+            Some((
+                entry,
+                *bom_entries.get(&match_key(relative, case_sensitive))?,
+            ))
+        })
+        .collect();
+    let differs = reads.map_per_device(
+        &shared,
+        |(entry, _)| device_id(&entry.metadata),
+        |(entry, bom_relative)| {
+            checksum_differs(bom_paths[bom_relative], entry, checksum, reads.buffer_size).map_err(
+                |err| PyOSError::new_err(format!("failed reading {}: {err}", entry.path.display())),
+            )
+        },
+    );
+
+    let mut checksum_mismatches = Vec::new();
+    for ((_, bom_relative), differs) in shared.iter().zip(differs) {
+        if differs? {
+            checksum_mismatches.push(*bom_relative);
+        }
+    }
+    checksum_mismatches.sort_unstable();
+
+    let only_in_bundle = only_in(&disk_entries, &bom_entries);
+    let only_in_bom = only_in(&bom_entries, &disk_entries);
+    let matched = bom_entries
+        .keys()
+        .filter(|key| disk_entries.contains_key(*key))
+        .count();
+
+    let report = PyDict::new_bound(py);
+    report.set_item("app_path", app_path)?;
+    report.set_item("bundle_path", style.render(bundle_root))?;
+    report.set_item("only_in_bundle", PyList::new_bound(py, &only_in_bundle))?;
+    report.set_item("only_in_bom", PyList::new_bound(py, &only_in_bom))?;
+    report.set_item("matched", matched)?;
+    report.set_item("checksum", checksum.name())?;
+    report.set_item(
+        "checksum_mismatches",
+        PyList::new_bound(py, &checksum_mismatches),
+    )?;
+    report.set_item(
+        "summary",
+        summary(
+            py,
+            [
+                ("only_in_bundle", only_in_bundle.len()),
+                ("only_in_bom", only_in_bom.len()),
+                ("checksum_mismatches", checksum_mismatches.len()),
+            ],
+        )?,
+    )?;
+
+    Ok(report.into_py(py))
+}
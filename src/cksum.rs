@@ -0,0 +1,190 @@
+use pyo3::{exceptions::PyValueError, PyResult};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Generator polynomial of the POSIX `cksum` CRC.
+const POLYNOMIAL: u32 = 0x04c1_1db7;
+
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = (index as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ POLYNOMIAL
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+/// Incremental POSIX `cksum` checksum, the value `mkbom` records for files
+/// and symlink targets.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Cksum {
+    crc: u32,
+    length: u64,
+}
+
+impl Cksum {
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.push(*byte);
+        }
+        self.length += data.len() as u64;
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.crc = (self.crc << 8) ^ TABLE[((self.crc >> 24) as u8 ^ byte) as usize];
+    }
+
+    /// Fold in the data length, as `cksum` does, and return the checksum.
+    pub(crate) fn finish(mut self) -> u32 {
+        let mut length = self.length;
+        while length != 0 {
+            self.push(length as u8);
+            length >>= 8;
+        }
+        !self.crc
+    }
+}
+
+pub(crate) fn cksum(data: &[u8]) -> u32 {
+    let mut sum = Cksum::default();
+    sum.update(data);
+    sum.finish()
+}
+
+/// Files larger than this are sampled under [ChecksumMode::Sampled].
+const SAMPLE_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Bytes read from each of the start, middle, and end of a sampled file.
+const SAMPLE_LENGTH: u64 = 1024 * 1024;
+
+/// How file contents are checksummed when building from or verifying against
+/// files on disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ChecksumMode {
+    /// The POSIX `cksum` of every byte, as `mkbom` records.
+    Full,
+
+    /// Like `Full` for files up to 64 MiB. Larger files are checksummed over
+    /// 1 MiB from each of their start, middle, and end, folded with the full
+    /// length, so the value no longer matches `mkbom`'s.
+    Sampled,
+
+    /// No checksums.
+    None,
+}
+
+impl ChecksumMode {
+    pub(crate) fn from_name(name: &str) -> PyResult<Self> {
+        match name {
+            "full" => Ok(Self::Full),
+            "sampled" => Ok(Self::Sampled),
+            "none" => Ok(Self::None),
+            _ => Err(PyValueError::new_err(format!(
+                "checksum must be 'full', 'sampled', or 'none', not {name:?}"
+            ))),
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Sampled => "sampled",
+            Self::None => "none",
+        }
+    }
+
+    /// Checksum the whole file at `path`, which is `length` bytes long.
+    pub(crate) fn file(
+        self,
+        path: &Path,
+        length: u64,
+        buffer_size: usize,
+    ) -> io::Result<Option<u32>> {
+        self.range(path, 0, length, buffer_size)
+    }
+
+    /// Checksum the whole file at `path`, which is `length` bytes long, and
+    /// also take its SHA-256 if `sha256` is set and every byte gets read.
+    pub(crate) fn file_with_sha256(
+        self,
+        path: &Path,
+        length: u64,
+        buffer_size: usize,
+        sha256: bool,
+    ) -> io::Result<(Option<u32>, Option<[u8; 32]>)> {
+        self.read_range(path, 0, length, buffer_size, sha256)
+    }
+
+    /// Checksum `length` bytes of the file at `path`, starting at `offset`,
+    /// reading `buffer_size` bytes at a time.
+    pub(crate) fn range(
+        self,
+        path: &Path,
+        offset: u64,
+        length: u64,
+        buffer_size: usize,
+    ) -> io::Result<Option<u32>> {
+        Ok(self.read_range(path, offset, length, buffer_size, false)?.0)
+    }
+
+    fn read_range(
+        self,
+        path: &Path,
+        offset: u64,
+        length: u64,
+        buffer_size: usize,
+        sha256: bool,
+    ) -> io::Result<(Option<u32>, Option<[u8; 32]>)> {
+        let samples = match self {
+            Self::None => return Ok((None, None)),
+            Self::Sampled if length > SAMPLE_THRESHOLD => vec![
+                (0, SAMPLE_LENGTH),
+                ((length - SAMPLE_LENGTH) / 2, SAMPLE_LENGTH),
+                (length - SAMPLE_LENGTH, SAMPLE_LENGTH),
+            ],
+            Self::Full | Self::Sampled => vec![(0, length)],
+        };
+
+        let mut file = File::open(path)?;
+        let mut sum = Cksum::default();
+        // A digest of samples would match nothing outside this crate.
+        let mut digest = (sha256 && samples.len() == 1).then(Sha256::default);
+        let mut buffer = vec![0; buffer_size];
+
+        for (start, sample_length) in samples {
+            file.seek(SeekFrom::Start(offset + start))?;
+            let mut reader = (&mut file).take(sample_length);
+            loop {
+                let read = reader.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                sum.update(&buffer[..read]);
+                if let Some(digest) = &mut digest {
+                    digest.update(&buffer[..read]);
+                }
+            }
+        }
+
+        sum.length = length;
+        Ok((
+            Some(sum.finish()),
+            digest.map(|digest| digest.finalize().into()),
+        ))
+    }
+}
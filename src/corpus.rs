@@ -0,0 +1,746 @@
+use crate::{
+    closed_error, detect_block,
+    footprint::{Footprint, HeapSize},
+    paths::{PathGlob, PathStyle},
+    quarantine::{parse_or_hold, rejection_error, Failure, FailureKind, Quarantine, Rejection},
+    read_lock, safe_bom_call, write_lock, SafeBomCall,
+};
+use apple_bom::{
+    format::{BomBlock, ParsedBom},
+    BomPath,
+};
+use pyo3::{
+    exceptions::{PyKeyError, PyOSError, PyValueError},
+    prelude::*,
+    pybacked::PyBackedBytes,
+    types::{PyDict, PyList},
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    num::NonZeroUsize,
+    panic::{self, catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+    thread,
+};
+
+/// Block kinds as `parse_bom` names them, in the order reported.
+const BLOCK_KINDS: [&str; 11] = [
+    "Empty",
+    "BomInfo",
+    "File",
+    "PathInfoIndex",
+    "PathRecord",
+    "PathRecordPointer",
+    "Paths",
+    "Tree",
+    "TreePointer",
+    "VIndex",
+    "Unknown",
+];
+
+/// A BOM in a corpus, given either as its bytes or as a path to read.
+#[derive(FromPyObject)]
+pub(crate) enum Specimen {
+    Data(PyBackedBytes),
+    Path(PathBuf),
+}
+
+impl Specimen {
+    /// The path the specimen is read from, if it isn't given as bytes.
+    pub(crate) fn source(&self) -> Option<&Path> {
+        match self {
+            Self::Data(_) => None,
+            Self::Path(path) => Some(path),
+        }
+    }
+
+    pub(crate) fn read(&self) -> Result<Cow<'_, [u8]>, String> {
+        match self {
+            Self::Data(data) => Ok(Cow::Borrowed(data)),
+            Self::Path(path) => fs::read(path)
+                .map(Cow::Owned)
+                .map_err(|err| format!("failed reading {}: {err}", path.display())),
+        }
+    }
+}
+
+/// Block statistics of one specimen: the kind and length of every block.
+struct BlockCensus {
+    blocks: Vec<(usize, u32)>,
+}
+
+/// Read `specimen`, the input at `index` of a batch, and run `parse` on it
+/// as [parse_or_hold] does.
+fn read_and_parse<T>(
+    specimen: &Specimen,
+    index: Option<usize>,
+    quarantine: Option<&Quarantine>,
+    parse: impl FnOnce(&[u8]) -> Result<T, String>,
+) -> Result<T, Rejection> {
+    let data = specimen.read().map_err(|message| {
+        let failure = Failure {
+            kind: FailureKind::Read,
+            message,
+        };
+        (failure, None)
+    })?;
+    parse_or_hold(&data, index, specimen.source(), quarantine, parse)
+}
+
+/// Read and `parse` every one of `specimens` on `workers` threads, as
+/// [read_and_parse] does, returning the results in order.
+fn read_and_parse_all<T: Send>(
+    py: Python<'_>,
+    specimens: &[Specimen],
+    workers: Option<usize>,
+    quarantine: Option<&Quarantine>,
+    parse: impl Fn(&[u8]) -> Result<T, String> + Sync,
+) -> PyResult<Vec<Result<T, Rejection>>> {
+    let workers = match workers {
+        Some(0) => return Err(PyValueError::new_err("workers must be positive")),
+        Some(workers) => workers,
+        None => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+    };
+
+    Ok(py.allow_threads(|| {
+        let cursor = AtomicUsize::new(0);
+        let mut results: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers.min(specimens.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut taken = Vec::new();
+                        loop {
+                            let index = cursor.fetch_add(1, Ordering::Relaxed);
+                            let Some(specimen) = specimens.get(index) else {
+                                break taken;
+                            };
+                            let result = read_and_parse(specimen, Some(index), quarantine, &parse);
+                            taken.push((index, result));
+                        }
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|panic| panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }))
+}
+
+/// The report of a specimen a batch operation gave up on, with its `index`
+/// in the batch.
+fn rejection_dict<'py>(
+    py: Python<'py>,
+    index: usize,
+    (failure, held): Rejection,
+) -> PyResult<Bound<'py, PyDict>> {
+    let item = PyDict::new_bound(py);
+    item.set_item("index", index)?;
+    item.set_item("kind", failure.kind.name())?;
+    item.set_item("error", failure.message)?;
+    match held {
+        Some(Ok(copy)) => item.set_item("quarantined", copy)?,
+        Some(Err(err)) => {
+            item.set_item("quarantined", py.None())?;
+            item.set_item("quarantine_error", err)?;
+        }
+        None => item.set_item("quarantined", py.None())?,
+    }
+    Ok(item)
+}
+
+/// The paths `data` lists, failing when it has none as `required_paths`
+/// does.
+fn listed_paths(data: &[u8]) -> Result<Vec<BomPath>, String> {
+    let bom = ParsedBom::parse(data).map_err(|err| err.to_string())?;
+    match safe_bom_call(|| bom.paths()) {
+        SafeBomCall::Value(paths) => Ok(paths),
+        SafeBomCall::MissingVariable => Err("BOM has no Paths variable".to_string()),
+        SafeBomCall::Error(err) => Err(err),
+    }
+}
+
+impl BlockCensus {
+    fn parse(data: &[u8]) -> Result<Self, String> {
+        let bom = ParsedBom::parse(data).map_err(|err| err.to_string())?;
+        let blocks = (0..bom.blocks.blocks.len())
+            .map(|index| (block_kind(&bom, index), bom.blocks.blocks[index].length))
+            .collect();
+        Ok(Self { blocks })
+    }
+}
+
+/// The position in [BLOCK_KINDS] of the kind `parse_bom` reports for a
+/// block.
+fn block_kind(bom: &ParsedBom<'_>, index: usize) -> usize {
+    let kind = match bom.block_data(index) {
+        Ok([]) => "Empty",
+        // Too small for apple-bom's type detection, as in `parse_bom`.
+        Ok(data) if data.len() < 4 => "Unknown",
+        Ok(_) => match catch_unwind(AssertUnwindSafe(|| detect_block(bom, index))) {
+            Ok(Ok(BomBlock::Empty)) => "Empty",
+            Ok(Ok(BomBlock::BomInfo(_))) => "BomInfo",
+            Ok(Ok(BomBlock::File(_))) => "File",
+            Ok(Ok(BomBlock::PathInfoIndex(_))) => "PathInfoIndex",
+            Ok(Ok(BomBlock::PathRecord(_))) => "PathRecord",
+            Ok(Ok(BomBlock::PathRecordPointer(_))) => "PathRecordPointer",
+            Ok(Ok(BomBlock::Paths(_))) => "Paths",
+            Ok(Ok(BomBlock::Tree(_))) => "Tree",
+            Ok(Ok(BomBlock::TreePointer(_))) => "TreePointer",
+            Ok(Ok(BomBlock::VIndex(_))) => "VIndex",
+            Ok(Err(_)) | Err(_) => "Unknown",
+        },
+        Err(_) => "Unknown",
+    };
+    BLOCK_KINDS
+        .iter()
+        .position(|name| *name == kind)
+        .unwrap_or(BLOCK_KINDS.len() - 1)
+}
+
+/// Running totals for one block kind across the corpus.
+#[derive(Default)]
+struct KindTotals {
+    blocks: usize,
+    bytes: u64,
+    min_length: Option<u32>,
+    max_length: Option<u32>,
+    specimens: BTreeSet<usize>,
+}
+
+impl KindTotals {
+    fn add(&mut self, specimen: usize, length: u32) {
+        self.blocks += 1;
+        self.bytes += u64::from(length);
+        self.min_length = Some(self.min_length.map_or(length, |min| min.min(length)));
+        self.max_length = Some(self.max_length.map_or(length, |max| max.max(length)));
+        self.specimens.insert(specimen);
+    }
+}
+
+/// Aggregate block kind counts and sizes across a corpus of BOMs, parsing
+/// them on `workers` threads, and copy the ones that fail to parse into
+/// `quarantine` when given.
+#[pyfunction(signature = (boms, *, workers = None, quarantine = None))]
+pub(crate) fn block_type_histogram(
+    py: Python<'_>,
+    boms: Vec<Specimen>,
+    workers: Option<usize>,
+    quarantine: Option<PathBuf>,
+) -> PyResult<PyObject> {
+    let quarantine = quarantine
+        .map(|directory| Quarantine::create(directory, "block_type_histogram"))
+        .transpose()?;
+    let censuses = read_and_parse_all(py, &boms, workers, quarantine.as_ref(), BlockCensus::parse)?;
+
+    let mut totals: Vec<KindTotals> = BLOCK_KINDS.iter().map(|_| KindTotals::default()).collect();
+    let errors = PyList::empty_bound(py);
+    let mut parsed = 0;
+    let mut blocks = 0;
+    for (index, census) in censuses.into_iter().enumerate() {
+        match census {
+            Ok(census) => {
+                parsed += 1;
+                blocks += census.blocks.len();
+                for (kind, length) in census.blocks {
+                    totals[kind].add(index, length);
+                }
+            }
+            Err(rejection) => errors.append(rejection_dict(py, index, rejection)?)?,
+        }
+    }
+
+    let kinds = PyDict::new_bound(py);
+    for (name, kind) in BLOCK_KINDS.iter().zip(&totals) {
+        let item = PyDict::new_bound(py);
+        item.set_item("blocks", kind.blocks)?;
+        item.set_item("bytes", kind.bytes)?;
+        item.set_item("min_length", kind.min_length)?;
+        item.set_item("max_length", kind.max_length)?;
+        item.set_item("files", kind.specimens.len())?;
+        kinds.set_item(name, item)?;
+    }
+
+    // Specimens with blocks nothing recognizes are the interesting ones.
+    let unknown_specimens = &totals[BLOCK_KINDS.len() - 1].specimens;
+    let unknown = PyDict::new_bound(py);
+    unknown.set_item("files", unknown_specimens.len())?;
+    unknown.set_item(
+        "fraction",
+        if parsed == 0 {
+            0.0
+        } else {
+            unknown_specimens.len() as f64 / parsed as f64
+        },
+    )?;
+    unknown.set_item("specimens", unknown_specimens.iter().collect::<Vec<_>>())?;
+
+    let report = PyDict::new_bound(py);
+    report.set_item("files", parsed)?;
+    report.set_item("blocks", blocks)?;
+    report.set_item("kinds", kinds)?;
+    report.set_item("unknown", unknown)?;
+    report.set_item("errors", errors)?;
+
+    Ok(report.into_py(py))
+}
+
+/// Parse `boms` on `workers` threads, copying the ones that fail into
+/// `quarantine` with reports naming `operation`, and return the failures as
+/// `block_type_histogram` lists them. For batch operations written in Python.
+#[pyfunction(signature = (boms, quarantine, *, operation, workers = None))]
+pub(crate) fn quarantine_boms(
+    py: Python<'_>,
+    boms: Vec<Specimen>,
+    quarantine: PathBuf,
+    operation: &str,
+    workers: Option<usize>,
+) -> PyResult<PyObject> {
+    let quarantine = Quarantine::create(quarantine, operation)?;
+    let results = read_and_parse_all(py, &boms, workers, Some(&quarantine), |data| {
+        ParsedBom::parse(data)
+            .map(drop)
+            .map_err(|err| err.to_string())
+    })?;
+
+    let errors = PyList::empty_bound(py);
+    for (index, result) in results.into_iter().enumerate() {
+        if let Err(rejection) = result {
+            errors.append(rejection_dict(py, index, rejection)?)?;
+        }
+    }
+    Ok(errors.into_py(py))
+}
+
+/// Which BOMs of a corpus list which paths, kept up to date as BOMs are added
+/// and removed rather than rebuilt.
+///
+/// Each distinct path is stored once, with the set of BOMs listing it, and
+/// each BOM keeps the IDs of its paths so removing it only touches those.
+#[derive(Default)]
+pub(crate) struct CorpusPaths {
+    /// Internal IDs of the indexed BOMs, by key.
+    bom_ids: BTreeMap<String, u32>,
+    bom_keys: HashMap<u32, String>,
+    next_bom_id: u32,
+
+    /// Path IDs of each BOM's paths.
+    bom_paths: HashMap<u32, Vec<u32>>,
+
+    path_ids: HashMap<String, u32>,
+
+    /// Raw path and the IDs of the BOMs listing it, by path ID.
+    paths: Vec<(String, BTreeSet<u32>)>,
+}
+
+/// A [CorpusPaths] that Python threads can share.
+///
+/// Queries hold a read lock with the GIL released, so queries from several
+/// threads run at once; `add` and `remove` wait for them, and read and parse
+/// the BOM before taking the lock. The lock is never held while Python code
+/// runs.
+#[pyclass(frozen, module = "pyapplebom._native")]
+pub(crate) struct CorpusIndex {
+    /// `None` once closed.
+    paths: RwLock<Option<CorpusPaths>>,
+}
+
+/// File formats query results can be exported in.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ExportFormat {
+    /// One JSON object per line.
+    Ndjson,
+
+    /// RFC 4180 CSV with a header row.
+    Csv,
+}
+
+impl ExportFormat {
+    pub(crate) fn from_option(format: &str) -> PyResult<Self> {
+        match format {
+            "ndjson" => Ok(Self::Ndjson),
+            "csv" => Ok(Self::Csv),
+            _ => Err(PyValueError::new_err(format!(
+                "format must be 'ndjson' or 'csv', not {format:?}"
+            ))),
+        }
+    }
+}
+
+/// `value` as a JSON string literal.
+pub(crate) fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `value` as a CSV field, quoted only when it has to be.
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Write `rows` of `(key, path)` pairs to `out` in `format`, naming the key
+/// column `key_column`.
+fn write_rows<'a>(
+    out: &mut impl Write,
+    format: ExportFormat,
+    key_column: &str,
+    rows: impl Iterator<Item = (&'a str, String)>,
+) -> io::Result<u64> {
+    if let ExportFormat::Csv = format {
+        writeln!(out, "{},path", csv_field(key_column))?;
+    }
+    let mut count = 0;
+    for (key, path) in rows {
+        match format {
+            ExportFormat::Ndjson => writeln!(
+                out,
+                "{{{}: {}, \"path\": {}}}",
+                json_string(key_column),
+                json_string(key),
+                json_string(&path)
+            )?,
+            ExportFormat::Csv => writeln!(out, "{},{}", csv_field(key), csv_field(&path))?,
+        }
+        count += 1;
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+/// `(key, path)` of every path in `paths` matching `glob`, rendered in
+/// `style` and copied out so no lock is held while Python objects are built.
+pub(crate) fn rendered_matches(
+    paths: &CorpusPaths,
+    glob: &PathGlob,
+    style: &PathStyle,
+) -> Vec<(String, String)> {
+    paths
+        .matching(glob)
+        .into_iter()
+        .map(|(key, path)| (key.to_string(), style.render(path)))
+        .collect()
+}
+
+/// Glob results as the dictionaries `glob` returns, with the key under
+/// `key_column`.
+pub(crate) fn glob_results<'py>(
+    py: Python<'py>,
+    key_column: &str,
+    rows: Vec<(String, String)>,
+) -> PyResult<Bound<'py, PyList>> {
+    let matches = PyList::empty_bound(py);
+    for (key, path) in rows {
+        let item = PyDict::new_bound(py);
+        item.set_item(key_column, key)?;
+        item.set_item("path", path)?;
+        matches.append(item)?;
+    }
+    Ok(matches)
+}
+
+impl CorpusPaths {
+    pub(crate) fn footprint(&self) -> Footprint {
+        Footprint {
+            indices: self.bom_ids.heap_size()
+                + self.bom_keys.heap_size()
+                + self.bom_paths.heap_size()
+                + self.path_ids.heap_size()
+                + self.paths.heap_size(),
+            ..Default::default()
+        }
+    }
+
+    /// Write every path matching `glob`, rendered in `style`, to `destination`
+    /// as [CorpusPaths::matching] orders them, returning the number of rows.
+    pub(crate) fn export(
+        &self,
+        glob: &PathGlob,
+        style: &PathStyle,
+        format: ExportFormat,
+        key_column: &str,
+        destination: &Path,
+    ) -> PyResult<u64> {
+        let rows = self
+            .matching(glob)
+            .into_iter()
+            .map(|(key, path)| (key, style.render(path)));
+        File::create(destination)
+            .and_then(|file| write_rows(&mut BufWriter::new(file), format, key_column, rows))
+            .map_err(|err| {
+                PyOSError::new_err(format!("failed writing {}: {err}", destination.display()))
+            })
+    }
+
+    /// Index `listed`, the paths of a BOM, under `key`, replacing any BOM
+    /// indexed there.
+    pub(crate) fn insert(&mut self, key: &str, listed: &[BomPath]) {
+        self.remove_key(key);
+
+        let bom_id = self.next_bom_id;
+        self.next_bom_id += 1;
+        let mut path_ids = Vec::with_capacity(listed.len());
+        for path in listed {
+            let path_id = match self.path_ids.get(path.path()) {
+                Some(path_id) => *path_id,
+                None => {
+                    let path_id = self.paths.len() as u32;
+                    self.path_ids.insert(path.path().to_string(), path_id);
+                    self.paths.push((path.path().to_string(), BTreeSet::new()));
+                    path_id
+                }
+            };
+            self.paths[path_id as usize].1.insert(bom_id);
+            path_ids.push(path_id);
+        }
+
+        self.bom_ids.insert(key.to_string(), bom_id);
+        self.bom_keys.insert(bom_id, key.to_string());
+        self.bom_paths.insert(bom_id, path_ids);
+    }
+
+    /// Drop the BOM indexed under `key`, returning whether there was one.
+    ///
+    /// Paths no other BOM lists keep their IDs, with no holders, so a BOM
+    /// added back later reuses them.
+    pub(crate) fn remove_key(&mut self, key: &str) -> bool {
+        let Some(bom_id) = self.bom_ids.remove(key) else {
+            return false;
+        };
+        self.bom_keys.remove(&bom_id);
+        for path_id in self.bom_paths.remove(&bom_id).unwrap_or_default() {
+            self.paths[path_id as usize].1.remove(&bom_id);
+        }
+        true
+    }
+
+    /// The keys of the BOMs listing the raw path `path`, sorted.
+    pub(crate) fn holders(&self, path: &str) -> Vec<&str> {
+        let Some(path_id) = self.path_ids.get(path) else {
+            return Vec::new();
+        };
+        let mut keys: Vec<&str> = self.paths[*path_id as usize]
+            .1
+            .iter()
+            .map(|bom_id| self.bom_keys[bom_id].as_str())
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// `(key, raw path)` of every indexed path matching `glob`, sorted.
+    pub(crate) fn matching(&self, glob: &PathGlob) -> Vec<(&str, &str)> {
+        let mut matches: Vec<(&str, &str)> = self
+            .paths
+            .iter()
+            .filter(|(path, holders)| !holders.is_empty() && glob.matches_path(path))
+            .flat_map(|(path, holders)| {
+                holders
+                    .iter()
+                    .map(move |bom_id| (self.bom_keys[bom_id].as_str(), path.as_str()))
+            })
+            .collect();
+        matches.sort_unstable();
+        matches
+    }
+}
+
+impl CorpusIndex {
+    /// Run `f` on the index under the read lock, with the GIL released.
+    fn with_paths<T: Send>(
+        &self,
+        py: Python<'_>,
+        f: impl FnOnce(&CorpusPaths) -> T + Send,
+    ) -> PyResult<T> {
+        py.allow_threads(|| read_lock(&self.paths).as_ref().map(f))
+            .ok_or_else(|| closed_error("CorpusIndex"))
+    }
+
+    /// Run `f` on the index under the write lock, with the GIL released.
+    fn with_paths_mut<T: Send>(
+        &self,
+        py: Python<'_>,
+        f: impl FnOnce(&mut CorpusPaths) -> T + Send,
+    ) -> PyResult<T> {
+        py.allow_threads(|| write_lock(&self.paths).as_mut().map(f))
+            .ok_or_else(|| closed_error("CorpusIndex"))
+    }
+
+    pub(crate) fn footprint(&self) -> Footprint {
+        read_lock(&self.paths)
+            .as_ref()
+            .map_or_else(Footprint::default, CorpusPaths::footprint)
+    }
+}
+
+#[pymethods]
+impl CorpusIndex {
+    #[new]
+    fn new() -> Self {
+        Self {
+            paths: RwLock::new(Some(CorpusPaths::default())),
+        }
+    }
+
+    /// Index a BOM, given as bytes or a path, under `key`, replacing any BOM
+    /// already indexed there. A BOM that fails to parse is copied into
+    /// `quarantine` when given.
+    #[pyo3(signature = (key, bom, *, quarantine = None))]
+    fn add(
+        &self,
+        py: Python<'_>,
+        key: &str,
+        bom: Specimen,
+        quarantine: Option<PathBuf>,
+    ) -> PyResult<()> {
+        let quarantine = quarantine
+            .map(|directory| Quarantine::create(directory, "CorpusIndex.add"))
+            .transpose()?;
+        let listed = py
+            .allow_threads(|| read_and_parse(&bom, None, quarantine.as_ref(), listed_paths))
+            .map_err(rejection_error)?;
+        self.with_paths_mut(py, |paths| paths.insert(key, &listed))
+    }
+
+    fn remove(&self, py: Python<'_>, key: &str) -> PyResult<()> {
+        if self.with_paths_mut(py, |paths| paths.remove_key(key))? {
+            Ok(())
+        } else {
+            Err(PyKeyError::new_err(format!("no BOM {key:?} in index")))
+        }
+    }
+
+    /// The keys of the indexed BOMs, sorted.
+    fn keys(&self, py: Python<'_>) -> PyResult<Vec<String>> {
+        self.with_paths(py, |paths| paths.bom_ids.keys().cloned().collect())
+    }
+
+    /// The keys of the BOMs listing `path`, given in `path_style`.
+    #[pyo3(signature = (path, *, path_style = "raw", path_root = "/"))]
+    fn which_pkg(
+        &self,
+        py: Python<'_>,
+        path: &str,
+        path_style: &str,
+        path_root: &str,
+    ) -> PyResult<Vec<String>> {
+        let style = PathStyle::from_options(path_style, path_root)?;
+        let raw = style.raw_path(path);
+        self.with_paths(py, |paths| {
+            raw.map_or(Vec::new(), |raw| {
+                paths
+                    .holders(&raw)
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect()
+            })
+        })
+    }
+
+    /// Every path matching the glob `pattern`, as `key` and `path` (in
+    /// `path_style`) pairs.
+    #[pyo3(signature = (pattern, *, path_style = "raw", path_root = "/"))]
+    fn glob<'py>(
+        &self,
+        py: Python<'py>,
+        pattern: &str,
+        path_style: &str,
+        path_root: &str,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let style = PathStyle::from_options(path_style, path_root)?;
+        let glob = PathGlob::new(pattern)?;
+        let rows = self.with_paths(py, |paths| rendered_matches(paths, &glob, &style))?;
+        glob_results(py, "key", rows)
+    }
+
+    /// Write the results of `glob(pattern)` to the file `destination` as
+    /// NDJSON or CSV, without building Python objects for them, and return
+    /// the number of rows written.
+    #[pyo3(signature = (
+        pattern,
+        destination,
+        *,
+        format = "ndjson",
+        path_style = "raw",
+        path_root = "/",
+    ))]
+    fn export_glob(
+        &self,
+        py: Python<'_>,
+        pattern: &str,
+        destination: PathBuf,
+        format: &str,
+        path_style: &str,
+        path_root: &str,
+    ) -> PyResult<u64> {
+        let style = PathStyle::from_options(path_style, path_root)?;
+        let format = ExportFormat::from_option(format)?;
+        let glob = PathGlob::new(pattern)?;
+        self.with_paths(py, |paths| {
+            paths.export(&glob, &style, format, "key", &destination)
+        })?
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        read_lock(&self.paths).is_none()
+    }
+
+    /// Free the index. Methods raise `ValueError` afterwards; closing again
+    /// does nothing.
+    fn close(&self, py: Python<'_>) {
+        py.allow_threads(|| *write_lock(&self.paths) = None);
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &self,
+        py: Python<'_>,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc_value: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) {
+        self.close(py);
+    }
+
+    fn __len__(&self, py: Python<'_>) -> PyResult<usize> {
+        self.with_paths(py, |paths| paths.bom_ids.len())
+    }
+
+    fn __contains__(&self, py: Python<'_>, key: &str) -> PyResult<bool> {
+        self.with_paths(py, |paths| paths.bom_ids.contains_key(key))
+    }
+}
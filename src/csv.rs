@@ -0,0 +1,127 @@
+use crate::{
+    entry::CELL_NAMES,
+    json::{Destination, Sink, CHUNK_SIZE},
+    stats::{Cell, TableStats},
+    PathEntry,
+};
+use pyo3::{exceptions::PyValueError, prelude::*};
+use std::fmt::Write as _;
+
+/// How a CSV table is laid out: the character between fields and the
+/// columns written, in order.
+pub(crate) struct CsvLayout {
+    delimiter: char,
+    columns: Vec<&'static str>,
+}
+
+impl CsvLayout {
+    /// The layout for `delimiter` and `columns`, which default to every
+    /// column of a path entry but `raw_record_hex` unless `raw_records`.
+    pub(crate) fn from_options(
+        delimiter: &str,
+        columns: Option<Vec<String>>,
+        raw_records: bool,
+    ) -> PyResult<Self> {
+        let mut chars = delimiter.chars();
+        let delimiter = match (chars.next(), chars.next()) {
+            (Some(c), None) if !matches!(c, '"' | '\n' | '\r') => c,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "delimiter must be one character other than '\"' or a line break, not \
+                     {delimiter:?}"
+                )))
+            }
+        };
+        let columns = match columns {
+            Some(columns) => columns
+                .iter()
+                .map(|column| {
+                    CELL_NAMES
+                        .into_iter()
+                        .find(|name| name == column)
+                        .ok_or_else(|| {
+                            PyValueError::new_err(format!(
+                                "columns must name path entry fields such as 'path' or \
+                                 'size', not {column:?}"
+                            ))
+                        })
+                })
+                .collect::<PyResult<_>>()?,
+            None => CELL_NAMES
+                .into_iter()
+                .filter(|name| raw_records || *name != "raw_record_hex")
+                .collect(),
+        };
+        Ok(Self { delimiter, columns })
+    }
+
+    /// Append `value` to `out` as a field, quoted only when it has to be.
+    fn field(&self, out: &mut String, value: &str) {
+        if value.contains([self.delimiter, '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&value.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(value);
+        }
+    }
+
+    /// Append the row of `cells` to `out`, with empty fields for nulls and
+    /// columns the row doesn't have.
+    fn row(&self, out: &mut String, cells: &[(&'static str, Cell)]) {
+        for (index, column) in self.columns.iter().enumerate() {
+            if index > 0 {
+                out.push(self.delimiter);
+            }
+            match cells.iter().find(|(name, _)| name == column) {
+                Some((_, Cell::Int(value))) => {
+                    let _ = write!(out, "{value}");
+                }
+                Some((_, Cell::Text(value))) => self.field(out, value),
+                Some((_, Cell::Null)) | None => {}
+            }
+        }
+        out.push('\n');
+    }
+}
+
+/// Write `entries` to `destination` as a CSV table in `layout`, headed by
+/// the column names, adding the columns written to `stats` when given, and
+/// return the number of rows written.
+pub(crate) fn write_csv(
+    destination: Destination<'_>,
+    layout: &CsvLayout,
+    entries: impl IntoIterator<Item = PathEntry>,
+    mut stats: Option<&mut TableStats>,
+) -> PyResult<u64> {
+    let mut sink = Sink::open(destination)?;
+    let mut out = String::new();
+    for (index, column) in layout.columns.iter().enumerate() {
+        if index > 0 {
+            out.push(layout.delimiter);
+        }
+        layout.field(&mut out, column);
+    }
+    out.push('\n');
+
+    let mut rows = 0;
+    for entry in entries {
+        let cells = entry.cells();
+        layout.row(&mut out, &cells);
+        if let Some(stats) = stats.as_deref_mut() {
+            stats.add(layout.columns.iter().map(|column| {
+                let cell = cells.iter().find(|(name, _)| name == column);
+                (*column, cell.map_or(Cell::Null, |(_, cell)| cell.clone()))
+            }));
+        }
+        rows += 1;
+        if out.len() >= CHUNK_SIZE {
+            sink.write(std::mem::take(&mut out).as_bytes())?;
+        }
+    }
+    if !out.is_empty() {
+        sink.write(out.as_bytes())?;
+    }
+    sink.finish()?;
+    Ok(rows)
+}
@@ -0,0 +1,338 @@
+use crate::{
+    buffer::BomData,
+    cksum::cksum,
+    diff::BomEntries,
+    writer::{
+        path_components, write_bom, EntrySpec, OwnershipPolicy, WriterOptions, DEFAULT_ARCHITECTURE,
+    },
+};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+use std::{collections::BTreeMap, io::Cursor};
+
+/// Leads every delta; the last byte is the layout version.
+const MAGIC: &[u8; 8] = b"BOMDLT\x00\x02";
+
+/// Length of the checksum that ends every delta, taken over everything
+/// before it.
+const CHECKSUM_LENGTH: usize = 4;
+
+/// Set in a record's flags when it carries a checksum.
+const HAS_CRC32: u8 = 1;
+
+/// Set in a record's flags when it carries a link target.
+const HAS_LINK_NAME: u8 = 2;
+
+/// Append `value` as an unsigned LEB128 varint.
+fn push_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Append `path` as the length of the prefix it shares with `previous`
+/// followed by the rest, which keeps sorted path lists small.
+fn push_path(out: &mut Vec<u8>, previous: &mut Vec<u8>, path: &str) {
+    let shared = previous
+        .iter()
+        .zip(path.as_bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    push_varint(out, shared as u64);
+    push_varint(out, (path.len() - shared) as u64);
+    out.extend_from_slice(&path.as_bytes()[shared..]);
+    previous.clear();
+    previous.extend_from_slice(path.as_bytes());
+}
+
+/// The path record fields of `entry`, everything a delta carries but the path.
+fn encode_record(entry: &EntrySpec) -> Vec<u8> {
+    let mut out = vec![entry.path_type];
+    push_varint(&mut out, entry.mode.into());
+    push_varint(&mut out, entry.uid.unwrap_or(0).into());
+    push_varint(&mut out, entry.gid.unwrap_or(0).into());
+    push_varint(&mut out, entry.mtime.into());
+    push_varint(&mut out, entry.size);
+
+    let mut flags = 0;
+    if entry.crc32.is_some() {
+        flags |= HAS_CRC32;
+    }
+    if entry.link_name.is_some() {
+        flags |= HAS_LINK_NAME;
+    }
+    out.push(flags);
+    if let Some(crc32) = entry.crc32 {
+        out.extend_from_slice(&crc32.to_be_bytes());
+    }
+    if let Some(link_name) = &entry.link_name {
+        push_varint(&mut out, link_name.len() as u64);
+        out.extend_from_slice(link_name.as_bytes());
+    }
+
+    out
+}
+
+fn malformed() -> PyErr {
+    PyValueError::new_err("malformed BOM delta")
+}
+
+/// Reads the values [make_delta] writes, failing on truncated or
+/// out-of-range data.
+struct DeltaReader<'a> {
+    data: &'a [u8],
+    previous: Vec<u8>,
+}
+
+impl<'a> DeltaReader<'a> {
+    fn bytes(&mut self, length: usize) -> PyResult<&'a [u8]> {
+        if length > self.data.len() {
+            return Err(malformed());
+        }
+        let (taken, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> PyResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varint(&mut self) -> PyResult<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(malformed())
+    }
+
+    fn number<T: TryFrom<u64>>(&mut self) -> PyResult<T> {
+        T::try_from(self.varint()?).map_err(|_| malformed())
+    }
+
+    fn string(&mut self, length: usize) -> PyResult<String> {
+        String::from_utf8(self.bytes(length)?.to_vec()).map_err(|_| malformed())
+    }
+
+    fn path(&mut self) -> PyResult<String> {
+        let shared: usize = self.number()?;
+        let length: usize = self.number()?;
+        if shared > self.previous.len() {
+            return Err(malformed());
+        }
+        self.previous.truncate(shared);
+        let rest = self.bytes(length)?;
+        self.previous.extend_from_slice(rest);
+        String::from_utf8(self.previous.clone()).map_err(|_| malformed())
+    }
+
+    fn paths(&mut self) -> PyResult<Vec<String>> {
+        let count: usize = self.number()?;
+        self.previous.clear();
+        // Every path takes at least two bytes, so the count can't run past
+        // the data before the allocation is made.
+        if count > self.data.len() / 2 {
+            return Err(malformed());
+        }
+        (0..count).map(|_| self.path()).collect()
+    }
+
+    fn record(&mut self) -> PyResult<EntrySpec> {
+        let path_type = self.byte()?;
+        let mode = self.number()?;
+        let uid = self.number()?;
+        let gid = self.number()?;
+        let mtime = self.number()?;
+        let size = self.varint()?;
+        let flags = self.byte()?;
+        let crc32 = match flags & HAS_CRC32 {
+            0 => None,
+            _ => Some(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap())),
+        };
+        let link_name = match flags & HAS_LINK_NAME {
+            0 => None,
+            _ => {
+                let length = self.number()?;
+                Some(self.string(length)?)
+            }
+        };
+
+        Ok(EntrySpec {
+            path_type,
+            mode,
+            uid: Some(uid),
+            gid: Some(gid),
+            mtime,
+            size,
+            crc32,
+            link_name,
+            architecture: DEFAULT_ARCHITECTURE,
+            slices: Vec::new(),
+            hard_link: None,
+        })
+    }
+}
+
+/// Encode the path-level changes from `old` to `new` as a compact delta.
+#[pyfunction]
+pub(crate) fn make_delta<'py>(
+    py: Python<'py>,
+    old: &Bound<'_, PyAny>,
+    new: &Bound<'_, PyAny>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let old = BomData::new(old, true)?;
+    let new = BomData::new(new, true)?;
+    let old_entries = BomEntries::parse(&old)?;
+    let new_entries = BomEntries::parse(&new)?;
+    let old_paths = old_entries.by_path();
+    let new_paths = new_entries.by_path();
+
+    let mut out = MAGIC.to_vec();
+    push_varint(&mut out, old.len() as u64);
+    out.extend_from_slice(&cksum(&old).to_be_bytes());
+
+    let removed: Vec<&str> = old_paths
+        .keys()
+        .filter(|path| !new_paths.contains_key(*path))
+        .copied()
+        .collect();
+    let mut previous = Vec::new();
+    push_varint(&mut out, removed.len() as u64);
+    for path in removed {
+        push_path(&mut out, &mut previous, path);
+    }
+
+    let record = |entries: &BomEntries, path| {
+        encode_record(&EntrySpec::from_record(path, entries.size(path)))
+    };
+    let upserted: Vec<(&str, Vec<u8>)> = new_paths
+        .iter()
+        .map(|(name, path)| (*name, record(&new_entries, path)))
+        .filter(|(name, encoded)| {
+            old_paths
+                .get(name)
+                .is_none_or(|path| record(&old_entries, path) != *encoded)
+        })
+        .collect();
+    previous.clear();
+    push_varint(&mut out, upserted.len() as u64);
+    for (path, encoded) in upserted {
+        push_path(&mut out, &mut previous, path);
+        out.extend_from_slice(&encoded);
+    }
+
+    // Hard link groups are rare and small, so a change resends all of them.
+    if old_entries.links() == new_entries.links() {
+        out.push(0);
+    } else {
+        out.push(1);
+        push_varint(&mut out, new_entries.links().len() as u64);
+        for group in new_entries.links() {
+            previous.clear();
+            push_varint(&mut out, group.len() as u64);
+            for path in group {
+                push_path(&mut out, &mut previous, path);
+            }
+        }
+    }
+
+    let checksum = cksum(&out);
+    out.extend_from_slice(&checksum.to_be_bytes());
+    Ok(PyBytes::new_bound(py, &out))
+}
+
+/// Apply a delta from [make_delta] to the BOM it was made against, returning
+/// the other BOM as rebuilt by the writer.
+#[pyfunction]
+pub(crate) fn apply_delta<'py>(
+    py: Python<'py>,
+    old: &Bound<'_, PyAny>,
+    delta: &Bound<'_, PyAny>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let old = BomData::new(old, true)?;
+    let delta = BomData::new(delta, true)?;
+    if !delta.starts_with(MAGIC) || delta.len() < MAGIC.len() + CHECKSUM_LENGTH {
+        return Err(PyValueError::new_err("not a BOM delta"));
+    }
+    let (signed, checksum) = delta.split_at(delta.len() - CHECKSUM_LENGTH);
+    if cksum(signed).to_be_bytes() != checksum {
+        return Err(PyValueError::new_err(
+            "malformed BOM delta: checksum mismatch",
+        ));
+    }
+    let body = &signed[MAGIC.len()..];
+    let mut reader = DeltaReader {
+        data: body,
+        previous: Vec::new(),
+    };
+    let base_length: u64 = reader.varint()?;
+    let base_cksum = u32::from_be_bytes(reader.bytes(4)?.try_into().unwrap());
+    if base_length != old.len() as u64 || base_cksum != cksum(&old) {
+        return Err(PyValueError::new_err(
+            "delta was made against a different BOM",
+        ));
+    }
+
+    let old_entries = BomEntries::parse(&old)?;
+    let mut entries: BTreeMap<String, EntrySpec> = old_entries
+        .by_path()
+        .into_iter()
+        .map(|(name, path)| {
+            (
+                name.to_string(),
+                EntrySpec::from_record(path, old_entries.size(path)),
+            )
+        })
+        .collect();
+
+    for path in reader.paths()? {
+        entries.remove(&path);
+    }
+    let count: usize = reader.number()?;
+    reader.previous.clear();
+    for _ in 0..count {
+        let path = reader.path()?;
+        entries.insert(path, reader.record()?);
+    }
+
+    let links = match reader.byte()? {
+        0 => old_entries.links().to_vec(),
+        1 => {
+            let count: usize = reader.number()?;
+            (0..count)
+                .map(|_| reader.paths())
+                .collect::<PyResult<_>>()?
+        }
+        _ => return Err(malformed()),
+    };
+    if !reader.data.is_empty() {
+        return Err(malformed());
+    }
+    for (group, members) in links.iter().enumerate() {
+        for member in members {
+            if let Some(entry) = entries.get_mut(member) {
+                entry.hard_link = Some((group as u64, 0));
+            }
+        }
+    }
+
+    let entries = entries
+        .into_iter()
+        .map(|(path, entry)| Ok((path_components(&path)?, entry)))
+        .collect::<PyResult<BTreeMap<_, _>>>()?;
+    let (data, _) = write_bom(
+        &entries,
+        &WriterOptions::new(4096, None)?,
+        &OwnershipPolicy::default(),
+        None,
+        Cursor::new(Vec::new()),
+        "BOM",
+    )?;
+
+    Ok(PyBytes::new_bound(py, data.get_ref()))
+}
@@ -0,0 +1,824 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    hardlinks,
+    kinds::interned,
+    lookup::lookup_style,
+    options::ParseOptions,
+    path_to_dict, path_type_name,
+    paths::{match_key, PathStyle},
+    required_paths, safe_bom_call, size64, summary, SafeBomCall,
+};
+use apple_bom::{format::ParsedBom, BomPath, BomPathType};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+};
+
+/// Path entry fields compared between BOMs, named like the keys of parsed
+/// path entries.
+pub(crate) const DIFF_FIELDS: [&str; 8] = [
+    "path_type",
+    "file_mode",
+    "user_id",
+    "group_id",
+    "size",
+    "crc32",
+    "link_name",
+    "mtime",
+];
+
+/// The paths of one BOM, keyed by raw path.
+pub(crate) struct BomEntries {
+    paths: Vec<BomPath>,
+
+    /// Full sizes of paths listed in `Size64`.
+    pub sizes: HashMap<String, u64>,
+
+    /// Hard link groups from `HLIndex`, and the group of each member.
+    links: Vec<Vec<String>>,
+    link_groups: HashMap<String, usize>,
+}
+
+impl BomEntries {
+    pub(crate) fn parse(data: &[u8]) -> PyResult<Self> {
+        let bom = ParsedBom::parse(data).map_err(bom_error_to_py)?;
+        let paths = required_paths(&bom)?;
+        let sizes = match safe_bom_call(|| size64::resolve(&bom)) {
+            SafeBomCall::Value(section) => section.sizes,
+            SafeBomCall::MissingVariable | SafeBomCall::Error(_) => HashMap::new(),
+        };
+        let links = match safe_bom_call(|| hardlinks::resolve(&bom)) {
+            SafeBomCall::Value(section) => section.groups,
+            SafeBomCall::MissingVariable | SafeBomCall::Error(_) => Vec::new(),
+        };
+
+        Ok(Self::new(paths, sizes, links))
+    }
+
+    fn new(paths: Vec<BomPath>, sizes: HashMap<String, u64>, links: Vec<Vec<String>>) -> Self {
+        let link_groups = links
+            .iter()
+            .enumerate()
+            .flat_map(|(group, members)| members.iter().map(move |path| (path.clone(), group)))
+            .collect();
+
+        Self {
+            paths,
+            sizes,
+            links,
+            link_groups,
+        }
+    }
+
+    /// Parse `data`, treating `None` as a BOM without paths.
+    pub(crate) fn parse_optional(data: Option<&[u8]>) -> PyResult<Self> {
+        match data {
+            Some(data) => Self::parse(data),
+            None => Ok(Self::new(Vec::new(), HashMap::new(), Vec::new())),
+        }
+    }
+
+    #[cfg_attr(not(feature = "delta"), allow(dead_code))]
+    pub(crate) fn by_path(&self) -> BTreeMap<&str, &BomPath> {
+        self.paths.iter().map(|path| (path.path(), path)).collect()
+    }
+
+    /// The paths keyed by [match_key], so that unless `case_sensitive`,
+    /// paths differing only in case share a key.
+    fn by_key(&self, case_sensitive: bool) -> BTreeMap<Cow<'_, str>, &BomPath> {
+        self.paths
+            .iter()
+            .map(|path| (match_key(path.path(), case_sensitive), path))
+            .collect()
+    }
+
+    /// Hard link groups from `HLIndex`: sorted members, ordered by first member.
+    #[cfg_attr(not(feature = "delta"), allow(dead_code))]
+    pub(crate) fn links(&self) -> &[Vec<String>] {
+        &self.links
+    }
+
+    /// The sorted members of the hard link group `path` belongs to.
+    pub(crate) fn links_of(&self, path: &str) -> Option<&[String]> {
+        self.link_groups
+            .get(path)
+            .map(|&group| self.links[group].as_slice())
+    }
+
+    pub(crate) fn size(&self, path: &BomPath) -> u64 {
+        self.sizes
+            .get(path.path())
+            .copied()
+            .unwrap_or(path.size() as u64)
+    }
+
+    pub(crate) fn to_dict<'py>(
+        &self,
+        py: Python<'py>,
+        path: &BomPath,
+        style: &PathStyle,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        path_to_dict(py, path, style, &self.sizes)
+    }
+}
+
+/// Resolve `ignore_fields` to the fields that are compared.
+pub(crate) fn compared_fields(ignore_fields: &[String]) -> PyResult<Vec<&'static str>> {
+    if let Some(unknown) = ignore_fields
+        .iter()
+        .find(|field| !DIFF_FIELDS.contains(&field.as_str()))
+    {
+        return Err(PyValueError::new_err(format!(
+            "unknown field {unknown:?} in ignore_fields; expected one of {}",
+            DIFF_FIELDS.join(", ")
+        )));
+    }
+
+    Ok(DIFF_FIELDS
+        .into_iter()
+        .filter(|field| !ignore_fields.iter().any(|ignored| ignored == field))
+        .collect())
+}
+
+/// One version of a path: the record and the BOM it came from.
+#[derive(Clone, Copy)]
+pub(crate) struct Version<'a> {
+    pub path: &'a BomPath,
+    pub entries: &'a BomEntries,
+}
+
+impl<'a> Version<'a> {
+    fn find(
+        paths: &BTreeMap<Cow<'_, str>, &'a BomPath>,
+        entries: &'a BomEntries,
+        key: &str,
+    ) -> Option<Self> {
+        paths.get(key).map(|&path| Self { path, entries })
+    }
+
+    fn size(&self) -> u64 {
+        self.entries.size(self.path)
+    }
+}
+
+/// The `fields` that differ between two versions of a path.
+pub(crate) fn changed_fields(
+    old: Version<'_>,
+    new: Version<'_>,
+    fields: &[&'static str],
+) -> Vec<&'static str> {
+    let (a, b) = (old.path, new.path);
+    fields
+        .iter()
+        .copied()
+        .filter(|field| match *field {
+            "path_type" => u8::from(a.path_type()) != u8::from(b.path_type()),
+            "file_mode" => a.file_mode() != b.file_mode(),
+            "user_id" => a.user_id() != b.user_id(),
+            "group_id" => a.group_id() != b.group_id(),
+            "size" => old.size() != new.size(),
+            "crc32" => a.crc32() != b.crc32(),
+            "link_name" => a.link_name() != b.link_name(),
+            "mtime" => a.modified_time() != b.modified_time(),
+            _ => false,
+        })
+        .collect()
+}
+
+/// How a path changed between a base version and another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Change {
+    Added,
+    Removed,
+    Modified(Vec<&'static str>),
+}
+
+impl Change {
+    pub(crate) fn between(
+        old: Option<Version<'_>>,
+        new: Option<Version<'_>>,
+        fields: &[&'static str],
+    ) -> Option<Self> {
+        match (old, new) {
+            (None, None) => None,
+            (None, Some(_)) => Some(Self::Added),
+            (Some(_), None) => Some(Self::Removed),
+            (Some(old), Some(new)) => {
+                let changed = changed_fields(old, new, fields);
+                (!changed.is_empty()).then_some(Self::Modified(changed))
+            }
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Modified(_) => "modified",
+        }
+    }
+
+    pub(crate) fn fields(&self) -> &[&'static str] {
+        match self {
+            Self::Modified(fields) => fields,
+            Self::Added | Self::Removed => &[],
+        }
+    }
+}
+
+fn version_dict<'py>(
+    py: Python<'py>,
+    version: Option<Version<'_>>,
+    style: &PathStyle,
+) -> PyResult<PyObject> {
+    Ok(match version {
+        Some(version) => version
+            .entries
+            .to_dict(py, version.path, style)?
+            .into_py(py),
+        None => py.None(),
+    })
+}
+
+/// Fields whose change counts as a permission change.
+const PERMISSION_FIELDS: [&str; 3] = ["file_mode", "user_id", "group_id"];
+
+/// Set-user-ID and set-group-ID mode bits.
+const SETID_BITS: u16 = 0o6000;
+
+fn is_setid_file(version: Option<Version<'_>>) -> bool {
+    version.is_some_and(|version| {
+        matches!(version.path.path_type(), BomPathType::File)
+            && version.path.file_mode() & SETID_BITS != 0
+    })
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Pair removed and added entries with the same type, size, and checksum as
+/// probable renames, taking them out of `removed` and `added`.
+///
+/// An added entry pairs with the first matching removed one in path order,
+/// preferring one with the same file name so that several identical files
+/// moved between directories keep their names. Directories and empty entries
+/// carry no content to match on and are never paired.
+fn pair_renames<'a>(
+    removed: &mut Vec<Version<'a>>,
+    added: &mut Vec<Version<'a>>,
+) -> Vec<(Version<'a>, Version<'a>)> {
+    let key = |version: &Version<'_>| {
+        (
+            u8::from(version.path.path_type()),
+            version.size(),
+            version.path.crc32(),
+        )
+    };
+
+    let mut candidates: HashMap<_, Vec<usize>> = HashMap::new();
+    for (index, version) in removed.iter().enumerate() {
+        if !matches!(version.path.path_type(), BomPathType::Directory) && version.size() > 0 {
+            candidates.entry(key(version)).or_default().push(index);
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let mut paired = vec![false; removed.len()];
+    added.retain(|new| {
+        let Some(indexes) = candidates
+            .get_mut(&key(new))
+            .filter(|indexes| !indexes.is_empty())
+        else {
+            return true;
+        };
+        let position = indexes
+            .iter()
+            .position(|&index| file_name(removed[index].path.path()) == file_name(new.path.path()))
+            .unwrap_or(0);
+        let index = indexes.remove(position);
+        paired[index] = true;
+        pairs.push((removed[index], *new));
+        false
+    });
+
+    let mut paired = paired.into_iter();
+    removed.retain(|_| !paired.next().unwrap_or_default());
+    pairs
+}
+
+/// Diff of two versions of a BOM, such as consecutive releases of a package.
+///
+/// Either side may be `None` for a component that only exists in the other
+/// release.
+#[pyfunction(signature = (
+    old,
+    new,
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+    ignore_fields = vec!["mtime".to_string()],
+    detect_renames = true,
+    case_sensitive = true,
+))]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn diff_boms(
+    py: Python<'_>,
+    old: Option<&Bound<'_, PyAny>>,
+    new: Option<&Bound<'_, PyAny>>,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+    ignore_fields: Vec<String>,
+    detect_renames: bool,
+    case_sensitive: bool,
+) -> PyResult<PyObject> {
+    let old = old.map(|old| BomData::new(old, true)).transpose()?;
+    let new = new.map(|new| BomData::new(new, true)).transpose()?;
+    let style = lookup_style(options, path_style, path_root)?;
+    let fields = compared_fields(&ignore_fields)?;
+    let (old, new) = (
+        BomEntries::parse_optional(old.as_deref())?,
+        BomEntries::parse_optional(new.as_deref())?,
+    );
+    let key = |path: &str| match_key(path, case_sensitive).into_owned();
+    // Paths are matched up by key, and reported as the new BOM spells them.
+    let (old_paths, new_paths) = (old.by_key(case_sensitive), new.by_key(case_sensitive));
+    let all_paths: BTreeSet<&str> = old_paths
+        .keys()
+        .chain(new_paths.keys())
+        .map(AsRef::as_ref)
+        .collect();
+
+    let new_setuid = PyList::empty_bound(py);
+    let link_changes = PyList::empty_bound(py);
+    let mut reported_links = HashSet::new();
+    let mut changes = BTreeMap::new();
+    let mut unchanged = 0;
+
+    for path in all_paths {
+        let old_version = Version::find(&old_paths, &old, path);
+        let new_version = Version::find(&new_paths, &new, path);
+
+        if is_setid_file(new_version) && !is_setid_file(old_version) {
+            if let Some(new_version) = new_version {
+                new_setuid.append(style.render(new_version.path.path()))?;
+            }
+        }
+        if let (Some(old_version), Some(new_version)) = (old_version, new_version) {
+            let (old_path, new_path) = (old_version.path.path(), new_version.path.path());
+            let (old_single, new_single) = ([old_path.to_string()], [new_path.to_string()]);
+            let old_links = old.links_of(old_path).unwrap_or(&old_single);
+            let new_links = new.links_of(new_path).unwrap_or(&new_single);
+            let keys = |links: &[String]| links.iter().map(|link| key(link)).collect::<Vec<_>>();
+            if keys(old_links) != keys(new_links)
+                && reported_links.insert((old_links.to_vec(), new_links.to_vec()))
+            {
+                let item = PyDict::new_bound(py);
+                item.set_item("path", style.render(new_path))?;
+                for (name, links) in [("old_links", old_links), ("new_links", new_links)] {
+                    let links: Vec<String> = links.iter().map(|link| style.render(link)).collect();
+                    item.set_item(name, links)?;
+                }
+                link_changes.append(item)?;
+            }
+        }
+        match Change::between(old_version, new_version, &fields) {
+            Some(change) => {
+                changes.insert(path, (old_version, new_version, change));
+            }
+            None => unchanged += 1,
+        }
+    }
+
+    let renames = if detect_renames {
+        let (mut removed_versions, mut added_versions) = (Vec::new(), Vec::new());
+        for (old_version, new_version, change) in changes.values() {
+            match change {
+                Change::Added => added_versions.extend(*new_version),
+                Change::Removed => removed_versions.extend(*old_version),
+                Change::Modified(_) => {}
+            }
+        }
+        pair_renames(&mut removed_versions, &mut added_versions)
+    } else {
+        Vec::new()
+    };
+    let renamed_paths: HashSet<String> = renames
+        .iter()
+        .flat_map(|(old_version, new_version)| [old_version.path.path(), new_version.path.path()])
+        .map(key)
+        .collect();
+
+    // Hard links that changed the same way as the first link of their group
+    // are reported once, with that link.
+    let mut followers: HashMap<String, Vec<String>> = HashMap::new();
+    let mut collapsed = HashSet::new();
+    for (&path, (old_version, new_version, change)) in &changes {
+        let Some(version) = new_version.or(*old_version) else {
+            continue;
+        };
+        let Some(leader) = version
+            .entries
+            .links_of(version.path.path())
+            .map(|links| key(&links[0]))
+        else {
+            continue;
+        };
+        let same_change = changes
+            .get(leader.as_str())
+            .is_some_and(|(_, _, leader_change)| leader_change == change);
+        if leader != path
+            && same_change
+            && !renamed_paths.contains(path)
+            && !renamed_paths.contains(&leader)
+        {
+            followers
+                .entry(leader)
+                .or_default()
+                .push(style.render(version.path.path()));
+            collapsed.insert(path);
+        }
+    }
+
+    let added = PyList::empty_bound(py);
+    let removed = PyList::empty_bound(py);
+    let modified = PyList::empty_bound(py);
+    let resized = PyList::empty_bound(py);
+    let permission_changes = PyList::empty_bound(py);
+    let type_changes = PyList::empty_bound(py);
+    for (&path, (old_version, new_version, change)) in &changes {
+        if renamed_paths.contains(path) || collapsed.contains(path) {
+            continue;
+        }
+        let Some(version) = new_version.or(*old_version) else {
+            continue;
+        };
+        let rendered = style.render(version.path.path());
+        let hard_links = followers.remove(path).unwrap_or_default();
+
+        match change {
+            Change::Added | Change::Removed => {
+                let item = version.entries.to_dict(py, version.path, &style)?;
+                item.set_item("hard_links", hard_links)?;
+                if matches!(change, Change::Added) {
+                    added.append(item)?;
+                } else {
+                    removed.append(item)?;
+                }
+            }
+            // A path replaced by another type of entry (say, a file by a
+            // symlink) is a classic hijack, so it gets its own category.
+            Change::Modified(changed) if changed.contains(&"path_type") => {
+                let item = PyDict::new_bound(py);
+                item.set_item("path", &rendered)?;
+                for (key, version) in [("old_type", old_version), ("new_type", new_version)] {
+                    item.set_item(
+                        key,
+                        version
+                            .map(|version| interned(py, &path_type_name(version.path.path_type()))),
+                    )?;
+                }
+                item.set_item("old", version_dict(py, *old_version, &style)?)?;
+                item.set_item("new", version_dict(py, *new_version, &style)?)?;
+                item.set_item("fields", changed)?;
+                item.set_item("hard_links", hard_links)?;
+                type_changes.append(item)?;
+            }
+            Change::Modified(changed) => {
+                if changed.contains(&"size") {
+                    resized.append(&rendered)?;
+                }
+                if changed
+                    .iter()
+                    .any(|field| PERMISSION_FIELDS.contains(field))
+                {
+                    permission_changes.append(&rendered)?;
+                }
+
+                let item = PyDict::new_bound(py);
+                item.set_item("path", &rendered)?;
+                item.set_item("old", version_dict(py, *old_version, &style)?)?;
+                item.set_item("new", version_dict(py, *new_version, &style)?)?;
+                item.set_item("fields", changed)?;
+                item.set_item("hard_links", hard_links)?;
+                modified.append(item)?;
+            }
+        }
+    }
+
+    let renamed = PyList::empty_bound(py);
+    for (old_version, new_version) in renames {
+        let item = PyDict::new_bound(py);
+        item.set_item("old_path", style.render(old_version.path.path()))?;
+        item.set_item("new_path", style.render(new_version.path.path()))?;
+        item.set_item("old", version_dict(py, Some(old_version), &style)?)?;
+        item.set_item("new", version_dict(py, Some(new_version), &style)?)?;
+        item.set_item("fields", changed_fields(old_version, new_version, &fields))?;
+        renamed.append(item)?;
+    }
+
+    let report = PyDict::new_bound(py);
+    report.set_item("added", &added)?;
+    report.set_item("removed", &removed)?;
+    report.set_item("modified", &modified)?;
+    report.set_item("renamed", &renamed)?;
+    report.set_item("type_changes", &type_changes)?;
+    report.set_item("unchanged", unchanged)?;
+    report.set_item("resized", &resized)?;
+    report.set_item("permission_changes", &permission_changes)?;
+    report.set_item("new_setuid", &new_setuid)?;
+    report.set_item("link_changes", &link_changes)?;
+    report.set_item(
+        "summary",
+        summary(
+            py,
+            [
+                ("added", added.len()),
+                ("removed", removed.len()),
+                ("modified", modified.len()),
+                ("renamed", renamed.len()),
+                ("type_changes", type_changes.len()),
+                ("link_changes", link_changes.len()),
+            ],
+        )?,
+    )?;
+
+    Ok(report.into_py(py))
+}
+
+/// Three-way diff of two BOMs derived from a common base, such as a vendor's
+/// new release (`a`) and a locally patched copy of the old one (`b`).
+#[pyfunction(signature = (
+    base,
+    a,
+    b,
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+    ignore_fields = vec!["mtime".to_string()],
+    case_sensitive = true,
+))]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn diff3_boms(
+    py: Python<'_>,
+    base: &Bound<'_, PyAny>,
+    a: &Bound<'_, PyAny>,
+    b: &Bound<'_, PyAny>,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+    ignore_fields: Vec<String>,
+    case_sensitive: bool,
+) -> PyResult<PyObject> {
+    let base = BomData::new(base, true)?;
+    let a = BomData::new(a, true)?;
+    let b = BomData::new(b, true)?;
+    let style = lookup_style(options, path_style, path_root)?;
+    let fields = compared_fields(&ignore_fields)?;
+    let (base, a, b) = (
+        BomEntries::parse(&base)?,
+        BomEntries::parse(&a)?,
+        BomEntries::parse(&b)?,
+    );
+    let (base_paths, a_paths, b_paths) = (
+        base.by_key(case_sensitive),
+        a.by_key(case_sensitive),
+        b.by_key(case_sensitive),
+    );
+    let all_paths: BTreeSet<&str> = base_paths
+        .keys()
+        .chain(a_paths.keys())
+        .chain(b_paths.keys())
+        .map(AsRef::as_ref)
+        .collect();
+
+    let categories = ["only_in_a", "only_in_b", "both_same", "conflicting"];
+    let lists: Vec<Bound<'_, PyList>> =
+        categories.iter().map(|_| PyList::empty_bound(py)).collect();
+    let mut unchanged = 0;
+
+    for path in all_paths {
+        let (base_version, a_version, b_version) = (
+            Version::find(&base_paths, &base, path),
+            Version::find(&a_paths, &a, path),
+            Version::find(&b_paths, &b, path),
+        );
+        let a_change = Change::between(base_version, a_version, &fields);
+        let b_change = Change::between(base_version, b_version, &fields);
+
+        let category = match (&a_change, &b_change) {
+            (None, None) => {
+                unchanged += 1;
+                continue;
+            }
+            (Some(_), None) => 0,
+            (None, Some(_)) => 1,
+            (Some(_), Some(_)) if Change::between(a_version, b_version, &fields).is_none() => 2,
+            (Some(_), Some(_)) => 3,
+        };
+
+        let Some(version) = a_version.or(b_version).or(base_version) else {
+            continue;
+        };
+        let item = PyDict::new_bound(py);
+        item.set_item("path", style.render(version.path.path()))?;
+        item.set_item("base", version_dict(py, base_version, &style)?)?;
+        item.set_item("a", version_dict(py, a_version, &style)?)?;
+        item.set_item("b", version_dict(py, b_version, &style)?)?;
+        for (prefix, change) in [("a", &a_change), ("b", &b_change)] {
+            item.set_item(
+                format!("{prefix}_change"),
+                change.as_ref().map(Change::name),
+            )?;
+            item.set_item(
+                format!("{prefix}_fields"),
+                change.as_ref().map_or(&[][..], Change::fields),
+            )?;
+        }
+        lists[category].append(item)?;
+    }
+
+    let report = PyDict::new_bound(py);
+    for (category, list) in categories.iter().zip(&lists) {
+        report.set_item(*category, list)?;
+    }
+    report.set_item("unchanged", unchanged)?;
+    report.set_item(
+        "summary",
+        summary(
+            py,
+            categories
+                .iter()
+                .zip(&lists)
+                .map(|(category, list)| (*category, list.len())),
+        )?,
+    )?;
+
+    Ok(report.into_py(py))
+}
+
+/// Directories whose new entries are called out in [summarize_diff].
+const HIGHLIGHTED_DIRECTORIES: [(&str, &str); 2] = [
+    ("Library/LaunchDaemons/", "new launch daemons"),
+    ("Library/LaunchAgents/", "new launch agents"),
+];
+
+/// Format a byte count with decimal units, like the Finder.
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["bytes", "KB", "MB", "GB", "TB"];
+
+    if size < 1000 {
+        return format!("{size} bytes");
+    }
+    let mut scaled = size as f64;
+    let mut unit = 0;
+    while scaled >= 999.95 && unit + 1 < UNITS.len() {
+        scaled /= 1000.0;
+        unit += 1;
+    }
+    format!("{scaled:.1} {}", UNITS[unit])
+}
+
+/// Name up to `max_names` of `names`, then say how many were left out.
+fn format_names(names: &BTreeSet<String>, max_names: usize) -> String {
+    let shown: Vec<&str> = names.iter().take(max_names).map(String::as_str).collect();
+    match names.len() - shown.len() {
+        0 => shown.join(", "),
+        more if shown.is_empty() => more.to_string(),
+        more => format!("{}, and {more} more", shown.join(", ")),
+    }
+}
+
+/// The per-component reports of a diff: the result itself for
+/// [diff_boms], or its `components` for a package comparison.
+fn diff_reports<'py>(diff: &Bound<'py, PyDict>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    match diff.get_item("components")? {
+        Some(components) => components
+            .downcast::<PyDict>()?
+            .values()
+            .iter()
+            .map(|report| Ok(report.downcast_into::<PyDict>()?))
+            .collect(),
+        None => Ok(vec![diff.clone()]),
+    }
+}
+
+fn report_list<'py>(report: &Bound<'py, PyDict>, key: &str) -> PyResult<Bound<'py, PyList>> {
+    match report.get_item(key)? {
+        Some(list) => Ok(list.downcast_into::<PyList>()?),
+        None => Err(PyValueError::new_err(format!(
+            "diff result has no {key:?} list"
+        ))),
+    }
+}
+
+/// Changelog-style one-line summary of a [diff_boms] or package comparison
+/// result.
+#[pyfunction(signature = (diff, *, max_names = 5))]
+pub(crate) fn summarize_diff(diff: &Bound<'_, PyDict>, max_names: usize) -> PyResult<String> {
+    let (mut added_files, mut added_size, mut removed_files) = (0, 0, 0);
+    let (mut modified, mut renamed, mut link_changes) = (0, 0, 0);
+    let mut highlights: Vec<BTreeSet<String>> = HIGHLIGHTED_DIRECTORIES
+        .iter()
+        .map(|_| BTreeSet::new())
+        .collect();
+    let mut setuid = BTreeSet::new();
+    let mut type_changes = BTreeSet::new();
+
+    for report in diff_reports(diff)? {
+        for entry in report_list(&report, "added")?.iter() {
+            let path: String = entry.get_item("path")?.extract()?;
+            let path_type: String = entry.get_item("path_type")?.extract()?;
+            if path_type == "directory" {
+                continue;
+            }
+            added_files += 1;
+            added_size += entry.get_item("size")?.extract::<u64>()?;
+
+            for ((directory, _), names) in HIGHLIGHTED_DIRECTORIES.iter().zip(&mut highlights) {
+                if let Some((_, name)) = path.split_once(directory) {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+        for entry in report_list(&report, "removed")?.iter() {
+            let path_type: String = entry.get_item("path_type")?.extract()?;
+            if path_type != "directory" {
+                removed_files += 1;
+            }
+        }
+        modified += report_list(&report, "modified")?.len();
+        renamed += report_list(&report, "renamed")?.len();
+        link_changes += report_list(&report, "link_changes")?.len();
+        for entry in report_list(&report, "type_changes")?.iter() {
+            let path: String = entry.get_item("path")?.extract()?;
+            let old_type: String = entry.get_item("old_type")?.extract()?;
+            let new_type: String = entry.get_item("new_type")?.extract()?;
+            type_changes.insert(format!("{path} ({old_type} \u{2192} {new_type})"));
+        }
+        for path in report_list(&report, "new_setuid")?.iter() {
+            setuid.insert(path.extract::<String>()?);
+        }
+    }
+
+    let mut counts = Vec::new();
+    if added_files > 0 {
+        counts.push(format!(
+            "+{added_files} {} ({})",
+            if added_files == 1 { "file" } else { "files" },
+            format_size(added_size)
+        ));
+    }
+    if removed_files > 0 {
+        counts.push(format!(
+            "\u{2212}{removed_files} {}",
+            if removed_files == 1 { "file" } else { "files" }
+        ));
+    }
+    if modified > 0 {
+        counts.push(format!("{modified} modified"));
+    }
+    if renamed > 0 {
+        counts.push(format!("{renamed} renamed"));
+    }
+    if link_changes > 0 {
+        counts.push(format!(
+            "{link_changes} hard link {}",
+            if link_changes == 1 {
+                "change"
+            } else {
+                "changes"
+            }
+        ));
+    }
+    if counts.is_empty() {
+        counts.push("no changes".to_string());
+    }
+
+    let mut sections = vec![counts.join(", ")];
+    let named = [("type changes", &type_changes)]
+        .into_iter()
+        .chain(
+            HIGHLIGHTED_DIRECTORIES
+                .iter()
+                .map(|(_, label)| *label)
+                .zip(&highlights),
+        )
+        .chain([("new setuid binaries", &setuid)]);
+    for (label, names) in named {
+        if !names.is_empty() {
+            sections.push(format!("{label}: {}", format_names(names, max_names)));
+        }
+    }
+
+    Ok(sections.join("; "))
+}
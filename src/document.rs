@@ -0,0 +1,550 @@
+use crate::{
+    bom_error_to_py, detect_block, duplicates, gaps, hardlinks,
+    interpret::{self, Interpretation},
+    options::PATH_SECTIONS,
+    path_type_name, size64, BlockOptions, BomHeader, PanicPolicy, ParseOptions, PathEntry,
+    PathExtras, RawBlockEncoding, SafeBomCall,
+};
+use apple_bom::{
+    format::{BomBlock, BomBlockBomInfo, ParsedBom},
+    BomPath, BomPathType,
+};
+use pyo3::{exceptions::PyTypeError, prelude::*};
+use serde::{Serialize, Serializer};
+use std::{
+    collections::{BTreeMap, HashMap},
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+/// Pairs written as a JSON object, in their order.
+struct Ordered<K, V>(Vec<(K, V)>);
+
+impl<K: Serialize, V: Serialize> Serialize for Ordered<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(key, value)| (key, value)))
+    }
+}
+
+#[derive(Serialize)]
+struct BlocksIndexEntry {
+    index: usize,
+    file_offset: u32,
+    length: u32,
+}
+
+#[derive(Serialize)]
+struct BlocksIndex {
+    count: u32,
+    entries: Vec<BlocksIndexEntry>,
+}
+
+#[derive(Serialize)]
+struct Variable {
+    name: String,
+    name_length: u8,
+    block_index: u32,
+    order: usize,
+    offset: usize,
+}
+
+#[derive(Serialize)]
+struct GapEntry {
+    offset: usize,
+    length: usize,
+    after: String,
+    before: Option<String>,
+    all_zero: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw_hex: Option<String>,
+}
+
+#[derive(Serialize)]
+struct InfoEntry {
+    a: u32,
+    b: u32,
+    c: u32,
+    d: u32,
+}
+
+#[derive(Serialize)]
+struct BomInfo {
+    version: u32,
+    number_of_paths: u32,
+    number_of_info_entries: u32,
+    entries: Vec<InfoEntry>,
+}
+
+impl From<&BomBlockBomInfo> for BomInfo {
+    fn from(info: &BomBlockBomInfo) -> Self {
+        Self {
+            version: info.version,
+            number_of_paths: info.number_of_paths,
+            number_of_info_entries: info.number_of_info_entries,
+            entries: info
+                .entries
+                .iter()
+                .map(|entry| InfoEntry {
+                    a: entry.a,
+                    b: entry.b,
+                    c: entry.c,
+                    d: entry.d,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TreePosition {
+    block_index: u32,
+    file_index: u32,
+}
+
+/// A block's kind and fields, as its `blocks` entry gives them.
+#[derive(Serialize)]
+#[serde(tag = "kind")]
+enum Block {
+    Empty,
+    Unknown {
+        parse_error: String,
+    },
+    BomInfo(BomInfo),
+    File {
+        parent_path_id: u32,
+        name: String,
+    },
+    PathInfoIndex {
+        path_id: u32,
+        path_record_index: u32,
+    },
+    PathRecord {
+        path_type: String,
+        path_type_raw: u8,
+        a: u8,
+        architecture: u16,
+        mode: u16,
+        user: u32,
+        group: u32,
+        mtime: u32,
+        size: u32,
+        b: u8,
+        checksum_or_type: u32,
+        link_name_length: u32,
+        link_name: Option<String>,
+    },
+    PathRecordPointer {
+        block_path_record_index: u32,
+    },
+    Paths {
+        is_path_info: u16,
+        count: u16,
+        next_paths_block_index: u32,
+        previous_paths_block_index: u32,
+        paths: Vec<TreePosition>,
+    },
+    Tree {
+        tree: String,
+        version: u32,
+        block_paths_index: u32,
+        block_size: u32,
+        path_count: u32,
+        a: u8,
+    },
+    TreePointer {
+        block_tree_index: u32,
+    },
+    VIndex {
+        a: u32,
+        tree_block_index: u32,
+        b: u32,
+        c: u8,
+    },
+}
+
+impl Block {
+    fn new(block: BomBlock<'_>) -> Self {
+        match block {
+            BomBlock::Empty => Self::Empty,
+            BomBlock::BomInfo(info) => Self::BomInfo(BomInfo::from(&info)),
+            BomBlock::File(file) => Self::File {
+                parent_path_id: file.parent_path_id,
+                name: file.string_file_name(),
+            },
+            BomBlock::PathInfoIndex(path_info) => Self::PathInfoIndex {
+                path_id: path_info.path_id,
+                path_record_index: path_info.path_record_index,
+            },
+            BomBlock::PathRecord(record) => Self::PathRecord {
+                path_type: path_type_name(BomPathType::from(record.path_type)).into_owned(),
+                path_type_raw: record.path_type,
+                a: record.a,
+                architecture: record.architecture,
+                mode: record.mode,
+                user: record.user,
+                group: record.group,
+                mtime: record.mtime,
+                size: record.size,
+                b: record.b,
+                checksum_or_type: record.checksum_or_type,
+                link_name_length: record.link_name_length,
+                link_name: record.string_link_name(),
+            },
+            BomBlock::PathRecordPointer(pointer) => Self::PathRecordPointer {
+                block_path_record_index: pointer.block_path_record_index,
+            },
+            BomBlock::Paths(paths) => Self::Paths {
+                is_path_info: paths.is_path_info,
+                count: paths.count,
+                next_paths_block_index: paths.next_paths_block_index,
+                previous_paths_block_index: paths.previous_paths_block_index,
+                paths: paths
+                    .paths
+                    .iter()
+                    .map(|path| TreePosition {
+                        block_index: path.block_index,
+                        file_index: path.file_index,
+                    })
+                    .collect(),
+            },
+            BomBlock::Tree(tree) => Self::Tree {
+                tree: String::from_utf8_lossy(&tree.tree).to_string(),
+                version: tree.version,
+                block_paths_index: tree.block_paths_index,
+                block_size: tree.block_size,
+                path_count: tree.path_count,
+                a: tree.a,
+            },
+            BomBlock::TreePointer(pointer) => Self::TreePointer {
+                block_tree_index: pointer.block_tree_index,
+            },
+            BomBlock::VIndex(vindex) => Self::VIndex {
+                a: vindex.a,
+                tree_block_index: vindex.tree_block_index,
+                b: vindex.b,
+                c: vindex.c,
+            },
+        }
+    }
+
+    /// The kind the entry gives, for looking up interpretations.
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Empty => "Empty",
+            Self::Unknown { .. } => "Unknown",
+            Self::BomInfo(_) => "BomInfo",
+            Self::File { .. } => "File",
+            Self::PathInfoIndex { .. } => "PathInfoIndex",
+            Self::PathRecord { .. } => "PathRecord",
+            Self::PathRecordPointer { .. } => "PathRecordPointer",
+            Self::Paths { .. } => "Paths",
+            Self::Tree { .. } => "Tree",
+            Self::TreePointer { .. } => "TreePointer",
+            Self::VIndex { .. } => "VIndex",
+        }
+    }
+
+    /// The value of the unnamed field `name`, if the block has it.
+    fn field(&self, name: &str) -> Option<u32> {
+        match (self, name) {
+            (Self::PathRecord { a, .. } | Self::Tree { a, .. }, "a") => Some(u32::from(*a)),
+            (Self::PathRecord { b, .. }, "b") => Some(u32::from(*b)),
+            (Self::VIndex { a, .. }, "a") => Some(*a),
+            (Self::VIndex { b, .. }, "b") => Some(*b),
+            (Self::VIndex { c, .. }, "c") => Some(u32::from(*c)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BlockEntry {
+    index: usize,
+    file_offset: u32,
+    length: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw_hex: Option<String>,
+    #[serde(flatten)]
+    block: Block,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    interpretations: BTreeMap<&'static str, Interpretation>,
+}
+
+/// The entry of the block at `index`, as `parse_bom` gives it in `blocks`,
+/// or under [PanicPolicy::AbortSection] the parse error of a panic, which
+/// ends the section.
+fn block_entry(
+    bom: &ParsedBom<'_>,
+    index: usize,
+    options: BlockOptions,
+) -> PyResult<Result<BlockEntry, String>> {
+    let position = &bom.blocks.blocks[index];
+    let mut entry = BlockEntry {
+        index,
+        file_offset: position.file_offset,
+        length: position.length,
+        raw_hex: None,
+        block: Block::Empty,
+        interpretations: BTreeMap::new(),
+    };
+    let panicked = |payload| {
+        let message = format!(
+            "block parser panicked: {}",
+            options.on_panic.caught(payload)
+        );
+        if options.on_panic == PanicPolicy::AbortSection {
+            return Err(format!("block {index}: {message}"));
+        }
+        Ok(Block::Unknown {
+            parse_error: message,
+        })
+    };
+
+    // A blocks index entry past the end of the data panics the slicing.
+    let raw_data = match catch_unwind(AssertUnwindSafe(|| bom.block_data(index))) {
+        Ok(raw_data) => raw_data.map_err(bom_error_to_py)?,
+        Err(payload) => {
+            entry.block = match panicked(payload) {
+                Ok(block) => block,
+                Err(message) => return Ok(Err(message)),
+            };
+            return Ok(Ok(entry));
+        }
+    };
+    if options.raw_bytes == Some(RawBlockEncoding::Hex) {
+        entry.raw_hex = Some(hex::encode(raw_data));
+    }
+
+    entry.block = if raw_data.is_empty() {
+        Block::Empty
+    } else if raw_data.len() < 4 {
+        Block::Unknown {
+            parse_error: "block too small for type detection".to_string(),
+        }
+    } else {
+        match catch_unwind(AssertUnwindSafe(|| detect_block(bom, index))) {
+            Ok(Ok(block)) => Block::new(block),
+            Ok(Err(err)) => Block::Unknown {
+                parse_error: err.to_string(),
+            },
+            Err(payload) => match panicked(payload) {
+                Ok(block) => block,
+                Err(message) => return Ok(Err(message)),
+            },
+        }
+    };
+    if options.interpretations {
+        entry.interpretations =
+            interpret::interpretations(entry.block.kind(), |field| entry.block.field(field));
+    }
+    Ok(Ok(entry))
+}
+
+/// The document `parse_bom` returns, built straight from the parsed BOM to
+/// be written as JSON without going through Python objects.
+#[derive(Serialize)]
+pub(crate) struct Document {
+    format: &'static str,
+    byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_path: Option<String>,
+    path_order: &'static str,
+    header: BomHeader,
+    blocks_index: BlocksIndex,
+    variables: Vec<Variable>,
+    duplicate_variables: Option<Ordered<String, Vec<u32>>>,
+    gaps: Vec<GapEntry>,
+    bom_info: Option<BomInfo>,
+    #[serde(flatten)]
+    sections: Ordered<&'static str, Option<Vec<PathEntry>>>,
+    blocks: Option<Vec<BlockEntry>>,
+    parse_errors: Option<Ordered<&'static str, String>>,
+}
+
+impl Document {
+    /// The document for `data` with `options`. Raw bytes must be hex
+    /// encoded, since JSON has no bytes.
+    pub(crate) fn new(
+        data: &[u8],
+        source_path: Option<&str>,
+        options: &ParseOptions,
+    ) -> PyResult<Self> {
+        let path_options = options.path_options()?;
+        if options.raw_bytes()? == Some(RawBlockEncoding::Bytes)
+            || path_options.raw_records == Some(RawBlockEncoding::Bytes)
+        {
+            return Err(PyTypeError::new_err(
+                "Object of type bytes is not JSON serializable",
+            ));
+        }
+        let style = options.style()?;
+        let order = path_options.order;
+        let on_panic = options.on_panic()?;
+        let mut bom = ParsedBom::parse(data).map_err(bom_error_to_py)?;
+        let mut parse_errors = Vec::new();
+
+        let blocks_index = BlocksIndex {
+            count: bom.blocks.count,
+            entries: bom
+                .blocks
+                .blocks
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| BlocksIndexEntry {
+                    index,
+                    file_offset: entry.file_offset,
+                    length: entry.length,
+                })
+                .collect(),
+        };
+
+        // Each record is a u32 block index, a u8 name length, then the name
+        // bytes, following the u32 variable count.
+        let mut offset = 4;
+        let mut variables = Vec::new();
+        for (order, var) in bom.vars.vars.iter().enumerate() {
+            variables.push(Variable {
+                name: var.name.clone(),
+                name_length: var.name_length,
+                block_index: var.block_index,
+                order,
+                offset,
+            });
+            offset += 5 + var.name_length as usize;
+        }
+        let duplicates = duplicates::resolve(&mut bom, options.on_duplicate_variable()?)?;
+
+        let raw_gaps = options.raw_bytes()?.is_some();
+        let gaps = gaps::gaps(&bom)
+            .into_iter()
+            .map(|gap| {
+                let data = &bom.data[gap.offset..gap.offset + gap.length];
+                GapEntry {
+                    offset: gap.offset,
+                    length: gap.length,
+                    after: gap.after.name(),
+                    before: gap.before.map(gaps::Region::name),
+                    all_zero: data.iter().all(|&byte| byte == 0),
+                    raw_hex: raw_gaps.then(|| hex::encode(data)),
+                }
+            })
+            .collect();
+
+        let bom_info = match on_panic.call(|| bom.bom_info()) {
+            SafeBomCall::Value(info) => Some(BomInfo::from(&info)),
+            SafeBomCall::MissingVariable => None,
+            SafeBomCall::Error(err) => {
+                parse_errors.push(("bom_info", err));
+                None
+            }
+        };
+
+        let included = PATH_SECTIONS.map(|name| options.includes_section(name));
+        let [with_paths, with_hl_index, with_size64, with_vindex] = included;
+
+        // Files over 4 GiB carry a truncated 32-bit size; the Size64 tree
+        // supplies the full value used for every section.
+        let (size64, sizes) = if included.contains(&true) {
+            match on_panic.call(|| size64::resolve(&bom)) {
+                SafeBomCall::Value(section) => (SafeBomCall::Value(section.paths), section.sizes),
+                SafeBomCall::MissingVariable => (SafeBomCall::MissingVariable, HashMap::new()),
+                SafeBomCall::Error(err) => (SafeBomCall::Error(err), HashMap::new()),
+            }
+        } else {
+            (SafeBomCall::MissingVariable, HashMap::new())
+        };
+        let paths = if with_paths || with_hl_index || with_size64 {
+            on_panic.call(|| bom.paths())
+        } else {
+            SafeBomCall::MissingVariable
+        };
+        let mut extras = PathExtras {
+            sizes,
+            ..Default::default()
+        };
+        let vindex_extras = PathExtras {
+            sizes: extras.sizes.clone(),
+            ..Default::default()
+        };
+        if let SafeBomCall::Value(paths) = &paths {
+            extras.add_tree_entries(&bom, paths, &path_options);
+        }
+        let hl_index = if with_hl_index {
+            on_panic.call(|| hardlinks::resolve(&bom).map(|section| section.paths))
+        } else {
+            SafeBomCall::MissingVariable
+        };
+        let vindex = if with_vindex {
+            on_panic.call(|| bom.vindex())
+        } else {
+            SafeBomCall::MissingVariable
+        };
+
+        let mut sections = Vec::new();
+        for ((name, section, extras), included) in [
+            ("paths", paths, &extras),
+            ("hl_index", hl_index, &extras),
+            ("size64", size64, &extras),
+            ("vindex", vindex, &vindex_extras),
+        ]
+        .into_iter()
+        .zip(included)
+        {
+            if !included {
+                continue;
+            }
+            let entries = match section {
+                SafeBomCall::Value(mut paths) => {
+                    order.apply(&mut paths);
+                    let entry = |path: &BomPath| extras.entry(path, &style);
+                    Some(paths.iter().map(entry).collect())
+                }
+                SafeBomCall::MissingVariable => None,
+                SafeBomCall::Error(err) => {
+                    parse_errors.push((name, err));
+                    None
+                }
+            };
+            sections.push((name, entries));
+        }
+
+        let blocks = match options.blocks()? {
+            Some(block_options) => {
+                let mut entries = Vec::with_capacity(bom.blocks.blocks.len());
+                let mut aborted = None;
+                for index in 0..bom.blocks.blocks.len() {
+                    match block_entry(&bom, index, block_options)? {
+                        Ok(entry) => entries.push(entry),
+                        Err(message) => {
+                            aborted = Some(message);
+                            break;
+                        }
+                    }
+                }
+                match aborted {
+                    Some(err) => {
+                        parse_errors.push(("blocks", err));
+                        None
+                    }
+                    None => Some(entries),
+                }
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            format: "apple-bom",
+            byte_length: data.len(),
+            source_path: source_path.map(str::to_string),
+            path_order: order.name(),
+            header: BomHeader::new(&bom),
+            blocks_index,
+            variables,
+            duplicate_variables: (!duplicates.is_empty()).then_some(Ordered(duplicates)),
+            gaps,
+            bom_info,
+            sections: Ordered(sections),
+            blocks,
+            parse_errors: (!parse_errors.is_empty()).then_some(Ordered(parse_errors)),
+        })
+    }
+}
@@ -0,0 +1,90 @@
+use crate::BomParseError;
+use apple_bom::format::ParsedBom;
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use std::collections::{HashMap, HashSet};
+
+/// Which declaration of a variable name declared more than once is resolved,
+/// as `on_duplicate_variable` names it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DuplicatePolicy {
+    /// The first declaration, as apple-bom resolves names.
+    First,
+    Last,
+    /// Raise `BomParseError`.
+    Error,
+}
+
+impl DuplicatePolicy {
+    pub(crate) fn from_option(policy: &str) -> PyResult<Self> {
+        match policy {
+            "first" => Ok(Self::First),
+            "last" => Ok(Self::Last),
+            "error" => Ok(Self::Error),
+            _ => Err(PyValueError::new_err(format!(
+                "on_duplicate_variable must be 'first', 'last', or 'error', not {policy:?}"
+            ))),
+        }
+    }
+}
+
+/// The block indexes of every declaration of each variable name declared
+/// more than once, in declaration order, by order of first declaration.
+pub(crate) fn duplicates(bom: &ParsedBom<'_>) -> Vec<(String, Vec<u32>)> {
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    let mut declared: Vec<(String, Vec<u32>)> = Vec::new();
+    for var in &bom.vars.vars {
+        match positions.get(var.name.as_str()) {
+            Some(&position) => declared[position].1.push(var.block_index),
+            None => {
+                positions.insert(var.name.as_str(), declared.len());
+                declared.push((var.name.clone(), vec![var.block_index]));
+            }
+        }
+    }
+    declared.retain(|(_, blocks)| blocks.len() > 1);
+    declared
+}
+
+/// Make lookups in `bom` resolve each duplicated variable name to the
+/// declaration `policy` picks, and return the duplicates.
+pub(crate) fn resolve(
+    bom: &mut ParsedBom<'_>,
+    policy: DuplicatePolicy,
+) -> PyResult<Vec<(String, Vec<u32>)>> {
+    let duplicates = duplicates(bom);
+    match (policy, duplicates.first()) {
+        (_, None) | (DuplicatePolicy::First, _) => {}
+        (DuplicatePolicy::Error, Some((name, blocks))) => {
+            return Err(BomParseError::new_err(format!(
+                "variable {name:?} is declared {} times",
+                blocks.len()
+            )));
+        }
+        (DuplicatePolicy::Last, Some(_)) => {
+            let mut seen = HashSet::new();
+            let mut vars = bom.vars.vars.clone();
+            vars.reverse();
+            vars.retain(|var| seen.insert(var.name.clone()));
+            vars.reverse();
+            bom.vars.vars = vars;
+        }
+    }
+    Ok(duplicates)
+}
+
+/// The `duplicate_variables` section of a `parse_bom` document: the block
+/// indexes each duplicated name was declared with, or `None` when every name
+/// is declared once.
+pub(crate) fn duplicates_dict(
+    py: Python<'_>,
+    duplicates: &[(String, Vec<u32>)],
+) -> PyResult<PyObject> {
+    if duplicates.is_empty() {
+        return Ok(py.None());
+    }
+    let dict = PyDict::new_bound(py);
+    for (name, blocks) in duplicates {
+        dict.set_item(name, blocks)?;
+    }
+    Ok(dict.into_py(py))
+}
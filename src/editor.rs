@@ -0,0 +1,656 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    detect_block, safe_bom_call,
+    spill::{spill_error, Run, SpillOptions},
+    BomParseError, SafeBomCall,
+};
+use apple_bom::format::{BomBlock, BomBlocksEntry, BomHeader, ParsedBom};
+use pyo3::{
+    exceptions::{PyKeyError, PyOSError, PyValueError},
+    prelude::*,
+    types::{PyBytes, PyDict},
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{Seek, SeekFrom, Write},
+    mem,
+};
+
+const HEADER_LENGTH: usize = 32;
+
+/// Bytes of a spilled blocks index gathered before each write.
+const WRITE_CHUNK_LENGTH: usize = 64 * 1024;
+
+/// The mutable index state of a BOM: everything the header and the two
+/// indexes describe, independent of where the regions live in the file.
+pub(crate) struct BomIndexes {
+    pub version: u32,
+    pub blocks: Vec<BomBlocksEntry>,
+    pub vars: Vec<(String, u32)>,
+}
+
+impl BomIndexes {
+    pub(crate) fn from_bom(bom: &ParsedBom<'_>) -> Self {
+        Self {
+            version: bom.header.version,
+            blocks: bom.blocks.blocks.clone(),
+            vars: bom
+                .vars
+                .vars
+                .iter()
+                .map(|var| (var.name.clone(), var.block_index))
+                .collect(),
+        }
+    }
+
+    /// Number of populated blocks, which is what the header's block count records.
+    fn populated_blocks(&self) -> u32 {
+        self.blocks.iter().filter(|entry| entry.length > 0).count() as u32
+    }
+}
+
+/// Tracks which blocks are reachable by following the references of the
+/// structures apple-bom knows how to decode.
+struct Reachability<'a, 'b> {
+    bom: &'a ParsedBom<'b>,
+    reached: BTreeSet<u32>,
+}
+
+impl<'a, 'b> Reachability<'a, 'b> {
+    fn new(bom: &'a ParsedBom<'b>) -> Self {
+        Self {
+            bom,
+            reached: BTreeSet::new(),
+        }
+    }
+
+    /// Mark `index`, returning whether it was newly reached.
+    fn mark(&mut self, index: u32) -> bool {
+        index != 0 && (index as usize) < self.bom.blocks.blocks.len() && self.reached.insert(index)
+    }
+
+    fn variable(&mut self, name: &str, index: u32) {
+        match name {
+            "Paths" | "HLIndex" | "Size64" => self.tree(index),
+            "VIndex" => {
+                if self.mark(index) {
+                    if let Ok(vindex) = self.bom.block_as_vindex(index as usize) {
+                        self.tree(vindex.tree_block_index);
+                    }
+                }
+            }
+            _ => {
+                self.mark(index);
+            }
+        }
+    }
+
+    fn tree(&mut self, index: u32) {
+        if self.mark(index) {
+            if let Ok(tree) = self.bom.block_as_tree(index as usize) {
+                self.paths(tree.block_paths_index);
+            }
+        }
+    }
+
+    fn paths(&mut self, index: u32) {
+        let mut pending = vec![index];
+
+        while let Some(index) = pending.pop() {
+            if !self.mark(index) {
+                continue;
+            }
+            let Ok(paths) = self.bom.block_as_paths(index as usize) else {
+                continue;
+            };
+
+            for entry in &paths.paths {
+                self.mark(entry.file_index);
+                if paths.is_path_info == 0 {
+                    pending.push(entry.block_index);
+                } else if self.mark(entry.block_index) {
+                    if let Ok(path_info) = self.bom.block_as_path_info_index(entry.block_index as _)
+                    {
+                        self.mark(path_info.path_record_index);
+                    }
+                }
+            }
+            pending.push(paths.next_paths_block_index);
+            pending.push(paths.previous_paths_block_index);
+        }
+    }
+
+    /// Treat Apple's per-path pointer blocks as roots.
+    ///
+    /// Nothing known refers to these 4 byte `PathRecordPointer` and
+    /// `TreePointer` blocks, but Apple tooling writes one set per path, so they
+    /// are kept along with whatever they point at.
+    fn auxiliary_pointers(&mut self) {
+        for index in 1..self.bom.blocks.blocks.len() {
+            if self.bom.blocks.blocks[index].length != 4 {
+                continue;
+            }
+            match detect_block(self.bom, index) {
+                Ok(BomBlock::PathRecordPointer(pointer)) => {
+                    self.mark(index as u32);
+                    self.mark(pointer.block_path_record_index);
+                }
+                Ok(BomBlock::TreePointer(pointer)) => {
+                    self.mark(index as u32);
+                    self.tree(pointer.block_tree_index);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Run `func` on apple-bom, raising `BomParseError` for its errors and for
+/// the panics its slicing hits on malformed input.
+fn bom_call<T>(func: impl FnOnce() -> Result<T, apple_bom::Error>) -> PyResult<T> {
+    match safe_bom_call(func) {
+        SafeBomCall::Value(value) => Ok(value),
+        SafeBomCall::MissingVariable => Err(BomParseError::new_err("BOM variable not found")),
+        SafeBomCall::Error(err) => Err(BomParseError::new_err(err)),
+    }
+}
+
+/// Blocks reachable from `vars` and the auxiliary per-path pointer blocks.
+fn live_blocks(bom: &ParsedBom<'_>, vars: &[(String, u32)]) -> PyResult<BTreeSet<u32>> {
+    bom_call(|| {
+        let mut reachability = Reachability::new(bom);
+        for (name, index) in vars {
+            reachability.variable(name, *index);
+        }
+        reachability.auxiliary_pointers();
+        Ok(reachability.reached)
+    })
+}
+
+fn variable_position(indexes: &BomIndexes, name: &str) -> PyResult<usize> {
+    indexes
+        .vars
+        .iter()
+        .position(|(existing, _)| existing == name)
+        .ok_or_else(|| PyKeyError::new_err(format!("variable {name:?} not found")))
+}
+
+fn encode_header(header: &BomHeader) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LENGTH);
+    out.extend_from_slice(&header.magic);
+    for value in [
+        header.version,
+        header.number_of_blocks,
+        header.blocks_index_offset,
+        header.blocks_index_length,
+        header.vars_index_offset,
+        header.vars_index_length,
+    ] {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    out
+}
+
+fn encode_blocks_index(blocks: &[BomBlocksEntry]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(4 + blocks.len() * 8);
+    out.extend_from_slice(&(blocks.len() as u32).to_be_bytes());
+    for entry in blocks {
+        push_blocks_entry(&mut out, entry.file_offset, entry.length);
+    }
+    out
+}
+
+fn push_blocks_entry(out: &mut Vec<u8>, file_offset: u32, length: u32) {
+    out.extend_from_slice(&file_offset.to_be_bytes());
+    out.extend_from_slice(&length.to_be_bytes());
+}
+
+/// Encode the vars index the way Apple tooling does: the stored name length
+/// excludes any NUL terminator and no terminator is written.
+fn encode_vars_index(vars: &[(String, u32)]) -> PyResult<Vec<u8>> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(vars.len() as u32).to_be_bytes());
+    for (name, block_index) in vars {
+        let name_length = u8::try_from(name.len())
+            .ok()
+            .filter(|length| *length > 0)
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "variable name {name:?} must be between 1 and 255 bytes"
+                ))
+            })?;
+        out.extend_from_slice(&block_index.to_be_bytes());
+        out.push(name_length);
+        out.extend_from_slice(name.as_bytes());
+    }
+    Ok(out)
+}
+
+fn file_offset(position: usize) -> PyResult<u32> {
+    u32::try_from(position).map_err(|_| PyValueError::new_err("BOM would exceed 4 GiB"))
+}
+
+/// Lay out a complete BOM as header, vars index, block data, then blocks index.
+///
+/// Empty blocks get a zeroed index entry. Blocks borrowing the same bytes are
+/// written once and share a file offset.
+pub(crate) fn assemble_bom(
+    version: u32,
+    vars: &[(String, u32)],
+    blocks: &[&[u8]],
+) -> PyResult<Vec<u8>> {
+    let vars_index = encode_vars_index(vars)?;
+    let mut out = vec![0u8; HEADER_LENGTH];
+    out.extend_from_slice(&vars_index);
+
+    let mut entries = Vec::with_capacity(blocks.len());
+    let mut placed: HashMap<(usize, usize), u32> = HashMap::new();
+    for block in blocks {
+        if block.is_empty() {
+            entries.push(BomBlocksEntry::default());
+            continue;
+        }
+
+        let key = (block.as_ptr() as usize, block.len());
+        let offset = match placed.get(&key) {
+            Some(offset) => *offset,
+            None => {
+                let offset = file_offset(out.len())?;
+                out.extend_from_slice(block);
+                placed.insert(key, offset);
+                offset
+            }
+        };
+        entries.push(BomBlocksEntry {
+            file_offset: offset,
+            length: block.len() as u32,
+        });
+    }
+
+    let blocks_index = encode_blocks_index(&entries);
+    let header = BomHeader {
+        magic: *b"BOMStore",
+        version,
+        number_of_blocks: entries.iter().filter(|entry| entry.length > 0).count() as u32,
+        blocks_index_offset: file_offset(out.len())?,
+        blocks_index_length: blocks_index.len() as u32,
+        vars_index_offset: HEADER_LENGTH as u32,
+        vars_index_length: vars_index.len() as u32,
+    };
+    out.extend_from_slice(&blocks_index);
+    out[..HEADER_LENGTH].copy_from_slice(&encode_header(&header));
+
+    ParsedBom::parse(&out).map_err(bom_error_to_py)?;
+
+    Ok(out)
+}
+
+/// Writes a BOM block by block, in block index order, to a seekable sink.
+///
+/// Only the blocks index is kept in memory, and with [SpillOptions] it's
+/// written out in runs of `threshold` entries as well. The header is written
+/// last, once the blocks index offset is known.
+pub(crate) struct BomStreamWriter<W> {
+    out: W,
+    target: String,
+    version: u32,
+    vars_index_length: u32,
+    position: usize,
+    spill: Option<SpillOptions>,
+    entries: Vec<BomBlocksEntry>,
+    spilled: Vec<Run<(u32, u32)>>,
+    block_count: u32,
+    non_empty_blocks: u32,
+}
+
+impl<W: Write + Seek> BomStreamWriter<W> {
+    /// Start a BOM on `out`; `target` names the sink in I/O error messages.
+    pub(crate) fn new(
+        out: W,
+        target: impl Into<String>,
+        version: u32,
+        vars: &[(String, u32)],
+        spill: Option<&SpillOptions>,
+    ) -> PyResult<Self> {
+        let vars_index = encode_vars_index(vars)?;
+        let mut writer = Self {
+            out,
+            target: target.into(),
+            version,
+            vars_index_length: vars_index.len() as u32,
+            position: 0,
+            spill: spill.cloned(),
+            entries: Vec::new(),
+            spilled: Vec::new(),
+            block_count: 0,
+            non_empty_blocks: 0,
+        };
+        writer.write(&[0; HEADER_LENGTH])?;
+        writer.write(&vars_index)?;
+
+        Ok(writer)
+    }
+
+    fn write(&mut self, data: &[u8]) -> PyResult<()> {
+        self.out.write_all(data).map_err(|err| self.io_error(err))?;
+        self.position += data.len();
+        Ok(())
+    }
+
+    fn io_error(&self, err: std::io::Error) -> PyErr {
+        PyOSError::new_err(format!("failed writing {}: {err}", self.target))
+    }
+
+    /// Append the next block and return its block index. Empty blocks get a
+    /// zeroed index entry.
+    pub(crate) fn push(&mut self, block: &[u8]) -> PyResult<u32> {
+        let index = self.block_count;
+        self.block_count += 1;
+        if block.is_empty() {
+            self.entries.push(BomBlocksEntry::default());
+        } else {
+            let file_offset = file_offset(self.position)?;
+            self.write(block)?;
+            self.entries.push(BomBlocksEntry {
+                file_offset,
+                length: block.len() as u32,
+            });
+            self.non_empty_blocks += 1;
+        }
+
+        if let Some(spill) = &self.spill {
+            if self.entries.len() >= spill.threshold {
+                let entries = self
+                    .entries
+                    .drain(..)
+                    .map(|entry| (entry.file_offset, entry.length));
+                let run = Run::create(&spill.directory, entries).map_err(spill_error)?;
+                self.spilled.push(run);
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Write the blocks index and header, returning the sink and total length.
+    pub(crate) fn finish(mut self) -> PyResult<(W, u64)> {
+        let header = BomHeader {
+            magic: *b"BOMStore",
+            version: self.version,
+            number_of_blocks: self.non_empty_blocks,
+            blocks_index_offset: file_offset(self.position)?,
+            blocks_index_length: 4 + self.block_count * 8,
+            vars_index_offset: HEADER_LENGTH as u32,
+            vars_index_length: self.vars_index_length,
+        };
+        self.write(&self.block_count.to_be_bytes())?;
+        let mut blocks_index = Vec::new();
+        for run in mem::take(&mut self.spilled) {
+            for entry in run.read().map_err(spill_error)? {
+                let (file_offset, length) = entry.map_err(spill_error)?;
+                push_blocks_entry(&mut blocks_index, file_offset, length);
+                if blocks_index.len() >= WRITE_CHUNK_LENGTH {
+                    self.write(&blocks_index)?;
+                    blocks_index.clear();
+                }
+            }
+        }
+        for entry in mem::take(&mut self.entries) {
+            push_blocks_entry(&mut blocks_index, entry.file_offset, entry.length);
+        }
+        self.write(&blocks_index)?;
+        file_offset(self.position)?;
+
+        let length = self.position as u64;
+        self.out
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| self.out.write_all(&encode_header(&header)))
+            .and_then(|_| self.out.seek(SeekFrom::End(0)))
+            .and_then(|_| self.out.flush())
+            .map_err(|err| self.io_error(err))?;
+
+        Ok((self.out, length))
+    }
+}
+
+/// Place `region` over the existing region at `offset` when it fits, otherwise
+/// append it to the end of the file. Returns the region's new offset.
+fn place_region(out: &mut Vec<u8>, offset: u32, length: u32, region: &[u8]) -> PyResult<u32> {
+    let start = offset as usize;
+    let end = start + length as usize;
+
+    if region.len() <= length as usize && end <= out.len() && start >= HEADER_LENGTH {
+        out[start..start + region.len()].copy_from_slice(region);
+        out[start + region.len()..end].fill(0);
+        Ok(offset)
+    } else {
+        let offset = file_offset(out.len())?;
+        out.extend_from_slice(region);
+        Ok(offset)
+    }
+}
+
+/// Rewrite the header and index regions of `data` to describe `indexes`.
+///
+/// Block data is left untouched. Index regions are rewritten in place when the
+/// new encoding fits and appended to the end of the file otherwise.
+pub(crate) fn write_indexes(
+    data: &[u8],
+    header: &BomHeader,
+    indexes: &BomIndexes,
+) -> PyResult<Vec<u8>> {
+    let mut out = data.to_vec();
+    let vars_index = encode_vars_index(&indexes.vars)?;
+    let blocks_index = encode_blocks_index(&indexes.blocks);
+
+    let vars_index_offset = place_region(
+        &mut out,
+        header.vars_index_offset,
+        header.vars_index_length,
+        &vars_index,
+    )?;
+    let blocks_index_offset = place_region(
+        &mut out,
+        header.blocks_index_offset,
+        header.blocks_index_length,
+        &blocks_index,
+    )?;
+
+    for (index, entry) in indexes.blocks.iter().enumerate() {
+        if entry.file_offset as usize + entry.length as usize > out.len() {
+            return Err(PyValueError::new_err(format!(
+                "block {index} ({} bytes at offset {}) extends past the end of the BOM",
+                entry.length, entry.file_offset
+            )));
+        }
+    }
+
+    let header = BomHeader {
+        version: indexes.version,
+        number_of_blocks: indexes.populated_blocks(),
+        blocks_index_offset,
+        blocks_index_length: blocks_index.len() as u32,
+        vars_index_offset,
+        vars_index_length: vars_index.len() as u32,
+        ..*header
+    };
+    out[..HEADER_LENGTH].copy_from_slice(&encode_header(&header));
+
+    ParsedBom::parse(&out).map_err(bom_error_to_py)?;
+
+    Ok(out)
+}
+
+#[pyfunction(signature = (data, *, version = None, blocks = None, variables = None))]
+pub(crate) fn rewrite_indexes(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    version: Option<u32>,
+    blocks: Option<Vec<(u32, u32)>>,
+    variables: Option<Vec<(String, u32)>>,
+) -> PyResult<PyObject> {
+    let data = BomData::new(data, true)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let mut indexes = BomIndexes::from_bom(&bom);
+
+    if let Some(version) = version {
+        indexes.version = version;
+    }
+    if let Some(blocks) = blocks {
+        indexes.blocks = blocks
+            .into_iter()
+            .map(|(file_offset, length)| BomBlocksEntry {
+                file_offset,
+                length,
+            })
+            .collect();
+    }
+    if let Some(variables) = variables {
+        indexes.vars = variables;
+    }
+
+    let out = write_indexes(&data, &bom.header, &indexes)?;
+    Ok(PyBytes::new_bound(py, &out).into_py(py))
+}
+
+#[pyfunction(signature = (data, payload, variable_name = None))]
+pub(crate) fn add_block(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    payload: &Bound<'_, PyAny>,
+    variable_name: Option<String>,
+) -> PyResult<PyObject> {
+    let data = BomData::new(data, true)?;
+    let payload = BomData::new(payload, true)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let mut indexes = BomIndexes::from_bom(&bom);
+
+    if let Some(name) = &variable_name {
+        if indexes.vars.iter().any(|(existing, _)| existing == name) {
+            return Err(PyValueError::new_err(format!(
+                "variable {name:?} already exists"
+            )));
+        }
+    }
+
+    let mut out = Vec::with_capacity(data.len() + payload.len());
+    out.extend_from_slice(&data);
+    let payload_offset = file_offset(out.len())?;
+    out.extend_from_slice(&payload);
+
+    let block_index = indexes.blocks.len() as u32;
+    indexes.blocks.push(BomBlocksEntry {
+        file_offset: payload_offset,
+        length: payload.len() as u32,
+    });
+
+    if let Some(name) = variable_name {
+        indexes.vars.push((name, block_index));
+    }
+
+    let out = write_indexes(&out, &bom.header, &indexes)?;
+    Ok(PyBytes::new_bound(py, &out).into_py(py))
+}
+
+#[pyfunction(signature = (data, name, *, gc = false))]
+pub(crate) fn remove_variable(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    name: &str,
+    gc: bool,
+) -> PyResult<PyObject> {
+    let data = BomData::new(data, true)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let mut indexes = BomIndexes::from_bom(&bom);
+    let (removed_name, removed_index) = indexes.vars.remove(variable_position(&indexes, name)?);
+
+    if gc {
+        // Only blocks owned by the removed variable are dropped; blocks that
+        // were already unreferenced are left for `compact`.
+        let owned = bom_call(|| {
+            let mut owned = Reachability::new(&bom);
+            owned.variable(&removed_name, removed_index);
+            Ok(owned.reached)
+        })?;
+        let live = live_blocks(&bom, &indexes.vars)?;
+
+        for index in owned.difference(&live) {
+            indexes.blocks[*index as usize] = BomBlocksEntry::default();
+        }
+    }
+
+    let out = write_indexes(&data, &bom.header, &indexes)?;
+    Ok(PyBytes::new_bound(py, &out).into_py(py))
+}
+
+#[pyfunction]
+pub(crate) fn rename_variable(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    old_name: &str,
+    new_name: String,
+) -> PyResult<PyObject> {
+    let data = BomData::new(data, true)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let mut indexes = BomIndexes::from_bom(&bom);
+    let position = variable_position(&indexes, old_name)?;
+
+    if old_name != new_name
+        && indexes
+            .vars
+            .iter()
+            .any(|(existing, _)| *existing == new_name)
+    {
+        return Err(PyValueError::new_err(format!(
+            "variable {new_name:?} already exists"
+        )));
+    }
+    indexes.vars[position].0 = new_name;
+
+    let out = write_indexes(&data, &bom.header, &indexes)?;
+    Ok(PyBytes::new_bound(py, &out).into_py(py))
+}
+
+#[pyfunction(signature = (data, *, drop_unreferenced = false))]
+pub(crate) fn compact(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    drop_unreferenced: bool,
+) -> PyResult<PyObject> {
+    let data = BomData::new(data, true)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let mut indexes = BomIndexes::from_bom(&bom);
+    let mut blocks_dropped = 0usize;
+
+    if drop_unreferenced {
+        let live = live_blocks(&bom, &indexes.vars)?;
+        for (index, entry) in indexes.blocks.iter_mut().enumerate().skip(1) {
+            if entry.length > 0 && !live.contains(&(index as u32)) {
+                *entry = BomBlocksEntry::default();
+                blocks_dropped += 1;
+            }
+        }
+    }
+
+    let blocks = indexes
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            if entry.length == 0 {
+                Ok(&[][..])
+            } else {
+                bom_call(|| bom.block_data(index))
+            }
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    let out = assemble_bom(indexes.version, &indexes.vars, &blocks)?;
+
+    let result = PyDict::new_bound(py);
+    result.set_item("data", PyBytes::new_bound(py, &out))?;
+    result.set_item("bytes_reclaimed", data.len() as i64 - out.len() as i64)?;
+    result.set_item("blocks_dropped", blocks_dropped)?;
+
+    Ok(result.into_py(py))
+}
@@ -0,0 +1,319 @@
+use crate::{
+    footprint::{Footprint, HeapSize},
+    json,
+    kinds::interned,
+    modes, path_type_name,
+    paths::PathStyle,
+    stats::Cell,
+    RawBlockEncoding,
+};
+use apple_bom::BomPath;
+use pyo3::{
+    exceptions::PyKeyError,
+    prelude::*,
+    types::{PyBytes, PyDict, PyString},
+};
+use serde::{
+    ser::{Error as _, SerializeMap},
+    Serialize, Serializer,
+};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+};
+
+/// The names of the cells [PathEntry::cells] gives, in order.
+pub(crate) const CELL_NAMES: [&str; 15] = [
+    "path",
+    "path_type",
+    "path_type_raw",
+    "file_mode",
+    "symbolic_mode",
+    "user_id",
+    "group_id",
+    "mtime",
+    "mtime_iso8601",
+    "size",
+    "crc32",
+    "link_name",
+    "path_id",
+    "parent_path_id",
+    "raw_record_hex",
+];
+
+/// The path and record fields of a [PathEntry], in declaration order.
+type EntryKey<'a> = (
+    &'a str,
+    u8,
+    u16,
+    u32,
+    u32,
+    i64,
+    u64,
+    Option<u32>,
+    Option<&'a str>,
+);
+
+/// A path entry as a typed object, for `typed_paths=True`.
+///
+/// Attributes mirror the keys of a path entry dictionary, except that
+/// `user_id` and `group_id` are `uid` and `gid` and `tree_position` is a
+/// `(block_index, file_index)` tuple. Indexing with a dictionary key returns
+/// the dictionary value, so code written against dictionaries keeps working.
+#[pyclass(frozen, module = "pyapplebom._native", name = "BomPathEntry")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PathEntry {
+    #[pyo3(get)]
+    path: String,
+    path_type: String,
+    #[pyo3(get)]
+    path_type_raw: u8,
+    #[pyo3(get)]
+    file_mode: u16,
+    #[pyo3(get)]
+    symbolic_mode: String,
+    #[pyo3(get)]
+    uid: u32,
+    #[pyo3(get)]
+    gid: u32,
+    #[pyo3(get)]
+    mtime: i64,
+    #[pyo3(get)]
+    mtime_iso8601: String,
+    #[pyo3(get)]
+    size: u64,
+    #[pyo3(get)]
+    crc32: Option<u32>,
+    #[pyo3(get)]
+    link_name: Option<String>,
+    #[pyo3(get)]
+    pub path_id: Option<u32>,
+    #[pyo3(get)]
+    pub parent_path_id: Option<u32>,
+    #[pyo3(get)]
+    pub tree_position: Option<(u32, u32)>,
+
+    /// The bytes of the path's `PathRecord` block, and how the entry's
+    /// dictionary carries them.
+    pub raw_record: Option<(RawBlockEncoding, Vec<u8>)>,
+}
+
+impl PathEntry {
+    /// The entry for `path`, with its full size taken from `sizes` when listed
+    /// there.
+    pub(crate) fn new(path: &BomPath, style: &PathStyle, sizes: &HashMap<String, u64>) -> Self {
+        let path_type = path.path_type();
+        Self {
+            path: style.render(path.path()),
+            path_type: path_type_name(path_type).into_owned(),
+            path_type_raw: path_type.into(),
+            file_mode: path.file_mode(),
+            symbolic_mode: modes::symbolic_mode(path_type, path.file_mode()),
+            uid: path.user_id(),
+            gid: path.group_id(),
+            mtime: path.modified_time().timestamp(),
+            mtime_iso8601: path.modified_time().to_rfc3339(),
+            size: sizes
+                .get(path.path())
+                .copied()
+                .unwrap_or(path.size() as u64),
+            crc32: path.crc32(),
+            link_name: path.link_name().map(str::to_string),
+            path_id: None,
+            parent_path_id: None,
+            tree_position: None,
+            raw_record: None,
+        }
+    }
+
+    /// The fields entries are compared and hashed by: the path and the values
+    /// read from its record, leaving out the IDs and tree position, which
+    /// differ between BOMs listing the same path.
+    fn key(&self) -> EntryKey<'_> {
+        (
+            &self.path,
+            self.path_type_raw,
+            self.file_mode,
+            self.uid,
+            self.gid,
+            self.mtime,
+            self.size,
+            self.crc32,
+            self.link_name.as_deref(),
+        )
+    }
+
+    pub(crate) fn footprint(&self) -> Footprint {
+        Footprint {
+            caches: self.path.heap_size()
+                + self.path_type.heap_size()
+                + self.symbolic_mode.heap_size()
+                + self.mtime_iso8601.heap_size()
+                + self.link_name.heap_size()
+                + self
+                    .raw_record
+                    .as_ref()
+                    .map_or(0, |(_, record)| record.heap_size()),
+            ..Default::default()
+        }
+    }
+
+    /// The fields read from the path record, as a path entry dictionary.
+    pub(crate) fn record_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let item = PyDict::new_bound(py);
+        item.set_item("path", &self.path)?;
+        item.set_item("path_type", interned(py, &self.path_type))?;
+        item.set_item("path_type_raw", self.path_type_raw)?;
+        item.set_item("file_mode", self.file_mode)?;
+        item.set_item("symbolic_mode", &self.symbolic_mode)?;
+        item.set_item("user_id", self.uid)?;
+        item.set_item("group_id", self.gid)?;
+        item.set_item("mtime", self.mtime)?;
+        item.set_item("mtime_iso8601", &self.mtime_iso8601)?;
+        item.set_item("size", self.size)?;
+        item.set_item("crc32", self.crc32)?;
+        item.set_item("link_name", &self.link_name)?;
+
+        Ok(item)
+    }
+
+    /// The scalar fields of [PathEntry::to_dict], by key, for column
+    /// statistics. `tree_position`, an object, is left out.
+    pub(crate) fn cells(&self) -> Vec<(&'static str, Cell)> {
+        let int = |value: Option<i128>| value.map_or(Cell::Null, Cell::Int);
+        let mut cells = vec![
+            ("path", Cell::Text(self.path.clone())),
+            ("path_type", Cell::Text(self.path_type.clone())),
+            ("path_type_raw", Cell::Int(self.path_type_raw.into())),
+            ("file_mode", Cell::Int(self.file_mode.into())),
+            ("symbolic_mode", Cell::Text(self.symbolic_mode.clone())),
+            ("user_id", Cell::Int(self.uid.into())),
+            ("group_id", Cell::Int(self.gid.into())),
+            ("mtime", Cell::Int(self.mtime.into())),
+            ("mtime_iso8601", Cell::Text(self.mtime_iso8601.clone())),
+            ("size", Cell::Int(self.size.into())),
+            ("crc32", int(self.crc32.map(i128::from))),
+            (
+                "link_name",
+                self.link_name.clone().map_or(Cell::Null, Cell::Text),
+            ),
+            ("path_id", int(self.path_id.map(i128::from))),
+            ("parent_path_id", int(self.parent_path_id.map(i128::from))),
+        ];
+        if let Some((RawBlockEncoding::Hex, record)) = &self.raw_record {
+            cells.push(("raw_record_hex", Cell::Text(hex::encode(record))));
+        }
+        cells
+    }
+}
+
+impl Serialize for PathEntry {
+    /// [PathEntry::to_dict], straight from the fields.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("path", &self.path)?;
+        map.serialize_entry("path_type", &self.path_type)?;
+        map.serialize_entry("path_type_raw", &self.path_type_raw)?;
+        map.serialize_entry("file_mode", &self.file_mode)?;
+        map.serialize_entry("symbolic_mode", &self.symbolic_mode)?;
+        map.serialize_entry("user_id", &self.uid)?;
+        map.serialize_entry("group_id", &self.gid)?;
+        map.serialize_entry("mtime", &self.mtime)?;
+        map.serialize_entry("mtime_iso8601", &self.mtime_iso8601)?;
+        map.serialize_entry("size", &self.size)?;
+        map.serialize_entry("crc32", &self.crc32)?;
+        map.serialize_entry("link_name", &self.link_name)?;
+        map.serialize_entry("path_id", &self.path_id)?;
+        map.serialize_entry("parent_path_id", &self.parent_path_id)?;
+        if let Some((block_index, file_index)) = self.tree_position {
+            let mut position = BTreeMap::new();
+            position.insert("block_index", block_index);
+            position.insert("file_index", file_index);
+            map.serialize_entry("tree_position", &position)?;
+        }
+        match &self.raw_record {
+            Some((RawBlockEncoding::Hex, record)) => {
+                map.serialize_entry("raw_record_hex", &hex::encode(record))?
+            }
+            Some((RawBlockEncoding::Bytes, _)) => {
+                return Err(S::Error::custom(
+                    "Object of type bytes is not JSON serializable",
+                ))
+            }
+            None => {}
+        }
+        map.end()
+    }
+}
+
+#[pymethods]
+impl PathEntry {
+    /// The entry as the dictionary `parse_bom` emits without `typed_paths`.
+    pub(crate) fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let item = self.record_dict(py)?;
+        item.set_item("path_id", self.path_id)?;
+        item.set_item("parent_path_id", self.parent_path_id)?;
+        if let Some((block_index, file_index)) = self.tree_position {
+            let position = PyDict::new_bound(py);
+            position.set_item("block_index", block_index)?;
+            position.set_item("file_index", file_index)?;
+            item.set_item("tree_position", position)?;
+        }
+        match &self.raw_record {
+            Some((RawBlockEncoding::Hex, record)) => {
+                item.set_item("raw_record_hex", hex::encode(record))?
+            }
+            Some((RawBlockEncoding::Bytes, record)) => {
+                item.set_item("raw_record", PyBytes::new_bound(py, record))?
+            }
+            None => {}
+        }
+
+        Ok(item)
+    }
+
+    /// The entry as JSON text, as `json.dumps(entry.to_dict())` writes it.
+    #[pyo3(signature = (*, indent = None))]
+    fn to_json(&self, indent: Option<usize>) -> PyResult<String> {
+        json::to_string(self, indent)
+    }
+
+    /// The bytes of the path's `PathRecord` block, or `None` unless
+    /// requested with `include_raw_records`.
+    #[getter]
+    fn raw_record<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyBytes>> {
+        let (_, record) = self.raw_record.as_ref()?;
+        Some(PyBytes::new_bound(py, record))
+    }
+
+    #[getter]
+    fn path_type<'py>(&self, py: Python<'py>) -> Bound<'py, PyString> {
+        interned(py, &self.path_type)
+    }
+
+    fn __getitem__<'py>(&self, py: Python<'py>, key: &str) -> PyResult<Bound<'py, PyAny>> {
+        self.to_dict(py)?
+            .get_item(key)?
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()))
+    }
+
+    /// Entries are equal when their paths and record fields are, wherever in
+    /// a tree they came from, so entries from two BOMs can be compared and
+    /// collected in sets.
+    fn __eq__(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.key().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "BomPathEntry(path={:?}, path_type={:?}, file_mode={:#o}, uid={}, gid={}, size={})",
+            self.path, self.path_type, self.file_mode, self.uid, self.gid, self.size
+        )
+    }
+}
@@ -0,0 +1,41 @@
+/// The optional features and whether this build has them. Cargo features
+/// leave these subsystems out to make a smaller extension; their functions
+/// are then stand-ins raising `FeatureNotAvailable`, so the module's names
+/// stay the same.
+pub(crate) const FEATURES: [(&str, bool); 4] = [
+    ("buffer", cfg!(feature = "buffer")),
+    ("bundle", cfg!(feature = "bundle")),
+    ("delta", cfg!(feature = "delta")),
+    ("size64_trailer", cfg!(feature = "size64-trailer")),
+];
+
+/// A stand-in for the function `$name` of a feature this build lacks.
+#[allow(unused_macros)]
+macro_rules! unavailable {
+    ($feature:literal: $($name:ident),*) => {
+        $(
+            #[pyo3::pyfunction(signature = (*_args, **_kwargs))]
+            pub(crate) fn $name(
+                _args: &pyo3::Bound<'_, pyo3::types::PyTuple>,
+                _kwargs: Option<&pyo3::Bound<'_, pyo3::types::PyDict>>,
+            ) -> pyo3::PyResult<()> {
+                Err(crate::FeatureNotAvailable::new_err(concat!(
+                    stringify!($name),
+                    " needs the '",
+                    $feature,
+                    "' feature, which this build of pyapplebom was compiled without"
+                )))
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "bundle")]
+pub(crate) use crate::bundle::compare_with_bundle;
+#[cfg(not(feature = "bundle"))]
+unavailable!("bundle": compare_with_bundle);
+
+#[cfg(feature = "delta")]
+pub(crate) use crate::delta::{apply_delta, make_delta};
+#[cfg(not(feature = "delta"))]
+unavailable!("delta": apply_delta, make_delta);
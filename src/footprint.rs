@@ -0,0 +1,214 @@
+use crate::{
+    blocks::BlockSequence,
+    bomfile::BomFile,
+    corpus::CorpusIndex,
+    entry::PathEntry,
+    known::{KnownFiles, KnownValues},
+    macho::MachOSlice,
+    pathiter::PathIterator,
+    store::BomStore,
+    writer::{BomBuilder, EntrySpec},
+    PathExtras,
+};
+use apple_bom::BomPath;
+use pyo3::{
+    exceptions::PyTypeError,
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    mem::size_of,
+};
+
+/// Path sections of a `parse_bom` document, which hold `BomPathEntry`
+/// objects under `typed_paths=True`.
+const PATH_SECTIONS: [&str; 4] = ["paths", "hl_index", "size64", "vindex"];
+
+/// The bytes a value owns on the heap, beyond its own size.
+///
+/// Collections count their allocated capacity but not allocator or node
+/// overhead, so the totals are estimates that err low.
+pub(crate) trait HeapSize {
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! no_heap {
+    ($($ty:ty),*) => {
+        $(impl HeapSize for $ty {
+            fn heap_size(&self) -> usize {
+                0
+            }
+        })*
+    };
+}
+
+no_heap!(u8, u16, u32, u64, i64, KnownValues, MachOSlice);
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, T::heap_size)
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize> HeapSize for BTreeSet<T> {
+    fn heap_size(&self) -> usize {
+        self.len() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for BTreeMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.len() * size_of::<(K, V)>()
+            + self
+                .iter()
+                .map(|(key, value)| key.heap_size() + value.heap_size())
+                .sum::<usize>()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for HashMap<K, V> {
+    fn heap_size(&self) -> usize {
+        // Each bucket also carries a control byte.
+        self.capacity() * (size_of::<(K, V)>() + 1)
+            + self
+                .iter()
+                .map(|(key, value)| key.heap_size() + value.heap_size())
+                .sum::<usize>()
+    }
+}
+
+impl HeapSize for BomPath {
+    fn heap_size(&self) -> usize {
+        self.path().len() + self.link_name().map_or(0, str::len)
+    }
+}
+
+impl HeapSize for PathExtras {
+    fn heap_size(&self) -> usize {
+        self.sizes.heap_size()
+            + self.ids.heap_size()
+            + self.tree_positions.heap_size()
+            + self.records.heap_size()
+    }
+}
+
+impl HeapSize for EntrySpec {
+    fn heap_size(&self) -> usize {
+        self.link_name.heap_size() + self.slices.heap_size()
+    }
+}
+
+/// Bytes an object holds on the Rust side, by what they are for.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Footprint {
+    /// Data the object owns, such as BOM bytes read from a file or staged
+    /// path entries.
+    pub buffers: usize,
+
+    /// Parsed values kept so they needn't be read again.
+    pub caches: usize,
+
+    /// Lookup structures.
+    pub indices: usize,
+
+    /// Python-owned buffers the object keeps alive without copying, which
+    /// aren't counted in the total.
+    pub shared: usize,
+}
+
+impl Footprint {
+    fn add(&mut self, other: Self) {
+        self.buffers += other.buffers;
+        self.caches += other.caches;
+        self.indices += other.indices;
+        self.shared += other.shared;
+    }
+
+    fn to_dict<'py>(self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let report = PyDict::new_bound(py);
+        report.set_item("buffers", self.buffers)?;
+        report.set_item("caches", self.caches)?;
+        report.set_item("indices", self.indices)?;
+        report.set_item("shared", self.shared)?;
+        report.set_item("total", self.buffers + self.caches + self.indices)?;
+        Ok(report)
+    }
+}
+
+/// The footprint of the typed path entries and lazy blocks in a `parse_bom`
+/// document.
+fn document_footprint(doc: &Bound<'_, PyDict>) -> PyResult<Footprint> {
+    let mut footprint = Footprint::default();
+    if let Some(blocks) = doc.get_item("blocks")? {
+        if let Ok(blocks) = blocks.downcast::<BlockSequence>() {
+            footprint.add(blocks.get().footprint());
+        }
+    }
+    for section in PATH_SECTIONS {
+        let Some(entries) = doc.get_item(section)? else {
+            continue;
+        };
+        let Ok(entries) = entries.downcast_into::<PyList>() else {
+            continue;
+        };
+        for entry in entries.iter() {
+            if let Ok(entry) = entry.downcast::<PathEntry>() {
+                footprint.add(entry.get().footprint());
+            }
+        }
+    }
+    Ok(footprint)
+}
+
+/// Report the bytes an object of this extension holds in Rust-side buffers,
+/// caches, and indices, which the Python allocator doesn't see.
+#[pyfunction]
+pub(crate) fn memory_footprint<'py>(
+    py: Python<'py>,
+    handle_or_doc: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let footprint = if let Ok(file) = handle_or_doc.downcast::<BomFile>() {
+        file.get().footprint()
+    } else if let Ok(store) = handle_or_doc.downcast::<BomStore>() {
+        store.get().footprint()
+    } else if let Ok(index) = handle_or_doc.downcast::<CorpusIndex>() {
+        index.get().footprint()
+    } else if let Ok(iterator) = handle_or_doc.downcast::<PathIterator>() {
+        iterator.borrow().footprint()
+    } else if let Ok(known) = handle_or_doc.downcast::<KnownFiles>() {
+        known.borrow().footprint()
+    } else if let Ok(builder) = handle_or_doc.downcast::<BomBuilder>() {
+        builder.borrow().footprint()
+    } else if let Ok(blocks) = handle_or_doc.downcast::<BlockSequence>() {
+        blocks.get().footprint()
+    } else if let Ok(entry) = handle_or_doc.downcast::<PathEntry>() {
+        entry.get().footprint()
+    } else if let Ok(doc) = handle_or_doc.downcast::<PyDict>() {
+        document_footprint(doc)?
+    } else {
+        return Err(PyTypeError::new_err(format!(
+            "memory_footprint expects a pyapplebom object or parse_bom document, not {}",
+            handle_or_doc.get_type().name()?
+        )));
+    };
+    footprint.to_dict(py)
+}
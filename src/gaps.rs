@@ -0,0 +1,147 @@
+use crate::RawBlockEncoding;
+use apple_bom::format::ParsedBom;
+use pyo3::{
+    prelude::*,
+    types::{PyBytes, PyDict, PyList},
+};
+
+/// Length of the header fields: the magic and six `u32`s. Writers pad the
+/// header out further, and that padding is reported as a gap.
+const HEADER_LENGTH: u64 = 32;
+
+/// A structure of the BOM that covers a range of its bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Region {
+    Header,
+    BlocksIndex,
+    VarsIndex,
+    Block(usize),
+}
+
+impl Region {
+    /// The name a gap entry gives the region.
+    pub(crate) fn name(self) -> String {
+        match self {
+            Self::Header => "header".to_string(),
+            Self::BlocksIndex => "blocks_index".to_string(),
+            Self::VarsIndex => "vars_index".to_string(),
+            Self::Block(index) => format!("block {index}"),
+        }
+    }
+}
+
+/// A run of bytes that no structure of the BOM covers.
+pub(crate) struct Gap {
+    pub offset: usize,
+    pub length: usize,
+
+    /// The region ending where the gap starts.
+    pub after: Region,
+
+    /// The region starting where the gap ends, or `None` at the end of the
+    /// data.
+    pub before: Option<Region>,
+}
+
+impl Gap {
+    /// The gap's place in the file, as a gap entry gives it.
+    pub(crate) fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let item = PyDict::new_bound(py);
+        item.set_item("offset", self.offset)?;
+        item.set_item("length", self.length)?;
+        item.set_item("after", self.after.name())?;
+        item.set_item("before", self.before.map(Region::name))?;
+        Ok(item)
+    }
+}
+
+/// The gaps between the header, the two indexes, and the blocks, in file
+/// order. Regions reaching past the end of the data are cut off there, and
+/// empty blocks cover nothing.
+pub(crate) fn gaps(bom: &ParsedBom<'_>) -> Vec<Gap> {
+    let header = &bom.header;
+    let mut regions = vec![
+        (0, HEADER_LENGTH, Region::Header),
+        (
+            u64::from(header.blocks_index_offset),
+            u64::from(header.blocks_index_length),
+            Region::BlocksIndex,
+        ),
+        (
+            u64::from(header.vars_index_offset),
+            u64::from(header.vars_index_length),
+            Region::VarsIndex,
+        ),
+    ];
+    regions.extend(
+        bom.blocks
+            .blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.length > 0)
+            .map(|(index, entry)| {
+                (
+                    u64::from(entry.file_offset),
+                    u64::from(entry.length),
+                    Region::Block(index),
+                )
+            }),
+    );
+    regions.sort_by_key(|&(offset, length, _)| (offset, length));
+
+    let end = bom.data.len() as u64;
+    let mut gaps = Vec::new();
+    let mut covered = 0;
+    let mut last = Region::Header;
+    for (offset, length, region) in regions {
+        let offset = offset.min(end);
+        if offset > covered {
+            gaps.push(Gap {
+                offset: covered as usize,
+                length: (offset - covered) as usize,
+                after: last,
+                before: Some(region),
+            });
+        }
+        // Overlapping regions leave no gap between them; the next gap
+        // follows whichever ends last.
+        let region_end = (offset + length).min(end);
+        if region_end >= covered {
+            covered = region_end;
+            last = region;
+        }
+    }
+    if covered < end {
+        gaps.push(Gap {
+            offset: covered as usize,
+            length: (end - covered) as usize,
+            after: last,
+            before: None,
+        });
+    }
+
+    gaps
+}
+
+/// The `gaps` section of a `parse_bom` document, with each gap's bytes in
+/// `raw_bytes` encoding when given.
+pub(crate) fn gaps_list<'py>(
+    py: Python<'py>,
+    bom: &ParsedBom<'_>,
+    raw_bytes: Option<RawBlockEncoding>,
+) -> PyResult<Bound<'py, PyList>> {
+    let list = PyList::empty_bound(py);
+    for gap in gaps(bom) {
+        let data = &bom.data[gap.offset..gap.offset + gap.length];
+        let item = gap.to_dict(py)?;
+        item.set_item("all_zero", data.iter().all(|&byte| byte == 0))?;
+        match raw_bytes {
+            Some(RawBlockEncoding::Hex) => item.set_item("raw_hex", hex::encode(data))?,
+            Some(RawBlockEncoding::Bytes) => item.set_item("raw", PyBytes::new_bound(py, data))?,
+            None => {}
+        }
+        list.append(item)?;
+    }
+
+    Ok(list)
+}
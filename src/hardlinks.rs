@@ -0,0 +1,63 @@
+use crate::size64::leaf_entries;
+use apple_bom::{format::ParsedBom, BomPath};
+use std::collections::{BTreeMap, HashMap};
+
+/// Paths listed in the `HLIndex` tree, and the hard link groups they form.
+#[derive(Default)]
+pub(crate) struct HardLinkSection {
+    pub paths: Vec<BomPath>,
+
+    /// Raw BOM paths of each group's members, sorted, with groups ordered by
+    /// their first member.
+    pub groups: Vec<Vec<String>>,
+}
+
+/// Resolve the `HLIndex` tree against the `Paths` tree.
+///
+/// Like `Size64`, `HLIndex` leaves reference the path info blocks of `Paths`,
+/// here for every path that is a hard link. Links to the same file share one
+/// `PathRecord` block, which is what groups them.
+pub(crate) fn resolve(bom: &ParsedBom<'_>) -> Result<HardLinkSection, apple_bom::Error> {
+    let links = leaf_entries(bom, "HLIndex")?;
+    if links.is_empty() {
+        return Ok(HardLinkSection {
+            paths: Vec::new(),
+            groups: Vec::new(),
+        });
+    }
+
+    let paths = bom.paths()?;
+    let by_id: HashMap<u32, &BomPath> = leaf_entries(bom, "Paths")?
+        .iter()
+        .map(|entry| entry.path_id)
+        .zip(&paths)
+        .collect();
+
+    let mut section = HardLinkSection {
+        paths: Vec::with_capacity(links.len()),
+        groups: Vec::new(),
+    };
+    let mut by_record: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    for entry in links {
+        let path = by_id
+            .get(&entry.path_id)
+            .ok_or(apple_bom::Error::BadIndex)?;
+        by_record
+            .entry(entry.path_record_index)
+            .or_default()
+            .push(path.path().to_string());
+        section.paths.push((*path).clone());
+    }
+
+    section.groups = by_record
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|mut members| {
+            members.sort();
+            members
+        })
+        .collect();
+    section.groups.sort();
+
+    Ok(section)
+}
@@ -0,0 +1,92 @@
+use crate::{
+    bom_error_to_py, buffer::BomData, lookup::lookup_style, options::ParseOptions, required_paths,
+    safe_bom_call, size64, SafeBomCall,
+};
+use apple_bom::{format::ParsedBom, BomPathType};
+use pyo3::{exceptions::PyValueError, prelude::*};
+use std::collections::HashMap;
+
+/// Columns of an NSRL RDS 2.x `NSRLFile.txt`, with SHA-256 appended so
+/// readers that go by position still find the standard columns.
+const RDS_HEADER: &str = "\"SHA-1\",\"MD5\",\"CRC32\",\"FileName\",\"FileSize\",\"ProductCode\",\
+                          \"OpSystemCode\",\"SpecialCode\",\"SHA-256\"";
+
+/// A CSV string field, quoted as RDS quotes every text column.
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// Export the files a BOM installs as an NSRL RDS-style hash set, in CSV.
+#[allow(clippy::too_many_arguments)]
+#[pyfunction(signature = (
+    data,
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+    sha256 = None,
+    product_code = 0,
+    os_code = "",
+    special_code = "",
+))]
+pub(crate) fn export_hash_set(
+    data: &Bound<'_, PyAny>,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+    sha256: Option<HashMap<String, String>>,
+    product_code: u32,
+    os_code: &str,
+    special_code: &str,
+) -> PyResult<String> {
+    let data = BomData::new(data, true)?;
+    // Hash sets are keyed by installed path, so paths default to absolute
+    // rather than to the default options' style.
+    let path_style = match options {
+        Some(_) => path_style,
+        None => path_style.or_else(|| Some("absolute".to_string())),
+    };
+    let style = lookup_style(options, path_style, path_root)?;
+    let sha256 = sha256.unwrap_or_default();
+    if let Some((path, digest)) = sha256
+        .iter()
+        .find(|(_, digest)| digest.len() != 64 || hex::decode(digest).is_err())
+    {
+        return Err(PyValueError::new_err(format!(
+            "sha256 for {path:?} is not a hex SHA-256 digest: {digest:?}"
+        )));
+    }
+
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let paths = required_paths(&bom)?;
+    let sizes = match safe_bom_call(|| size64::resolve(&bom)) {
+        SafeBomCall::Value(section) => section.sizes,
+        SafeBomCall::MissingVariable | SafeBomCall::Error(_) => HashMap::new(),
+    };
+
+    let mut out = format!("{RDS_HEADER}\n");
+    for path in paths
+        .iter()
+        .filter(|path| matches!(path.path_type(), BomPathType::File))
+    {
+        let name = style.render(path.path());
+        let size = sizes
+            .get(path.path())
+            .copied()
+            .unwrap_or(path.size() as u64);
+        let digest = sha256
+            .get(&name)
+            .map_or(String::new(), |digest| digest.to_uppercase());
+
+        // The BOM records a POSIX cksum, not the zlib CRC-32 RDS expects.
+        out.push_str(&format!(
+            "\"\",\"\",\"\",{},{size},{product_code},{},{},{}\n",
+            quoted(&name),
+            quoted(os_code),
+            quoted(special_code),
+            quoted(&digest),
+        ));
+    }
+
+    Ok(out)
+}
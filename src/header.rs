@@ -0,0 +1,95 @@
+use crate::json;
+use apple_bom::format::ParsedBom;
+use pyo3::{exceptions::PyKeyError, prelude::*, types::PyDict};
+use serde::Serialize;
+
+/// The header of a BOM, as parse calls report it under `header`.
+///
+/// Indexing with a key of the dictionary this replaces returns the same
+/// value, and a header compares equal to that dictionary, so code written
+/// against it keeps working.
+#[pyclass(frozen, module = "pyapplebom._native")]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct BomHeader {
+    #[pyo3(get)]
+    magic: String,
+    #[pyo3(get)]
+    version: u32,
+    #[pyo3(get)]
+    number_of_blocks: u32,
+    #[pyo3(get)]
+    blocks_index_offset: u32,
+    #[pyo3(get)]
+    blocks_index_length: u32,
+    #[pyo3(get)]
+    vars_index_offset: u32,
+    #[pyo3(get)]
+    vars_index_length: u32,
+}
+
+impl BomHeader {
+    pub(crate) fn new(bom: &ParsedBom<'_>) -> Self {
+        let header = &bom.header;
+        Self {
+            magic: String::from_utf8_lossy(&header.magic).to_string(),
+            version: header.version,
+            number_of_blocks: header.number_of_blocks,
+            blocks_index_offset: header.blocks_index_offset,
+            blocks_index_length: header.blocks_index_length,
+            vars_index_offset: header.vars_index_offset,
+            vars_index_length: header.vars_index_length,
+        }
+    }
+}
+
+#[pymethods]
+impl BomHeader {
+    /// The header as the dictionary parse calls used to emit.
+    pub(crate) fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let header = PyDict::new_bound(py);
+        header.set_item("magic", &self.magic)?;
+        header.set_item("version", self.version)?;
+        header.set_item("number_of_blocks", self.number_of_blocks)?;
+        header.set_item("blocks_index_offset", self.blocks_index_offset)?;
+        header.set_item("blocks_index_length", self.blocks_index_length)?;
+        header.set_item("vars_index_offset", self.vars_index_offset)?;
+        header.set_item("vars_index_length", self.vars_index_length)?;
+        Ok(header)
+    }
+
+    /// The header as JSON text, as `json.dumps(header.to_dict())` writes it.
+    #[pyo3(signature = (*, indent = None))]
+    fn to_json(&self, indent: Option<usize>) -> PyResult<String> {
+        json::to_string(self, indent)
+    }
+
+    fn __getitem__<'py>(&self, py: Python<'py>, key: &str) -> PyResult<Bound<'py, PyAny>> {
+        self.to_dict(py)?
+            .get_item(key)?
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()))
+    }
+
+    fn __eq__(&self, py: Python<'_>, other: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        if let Ok(other) = other.downcast::<Self>() {
+            Ok((self == other.get()).into_py(py))
+        } else if other.is_instance_of::<PyDict>() {
+            Ok(self.to_dict(py)?.eq(other)?.into_py(py))
+        } else {
+            Ok(py.NotImplemented())
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "BomHeader(magic={:?}, version={}, number_of_blocks={}, blocks_index_offset={}, \
+             blocks_index_length={}, vars_index_offset={}, vars_index_length={})",
+            self.magic,
+            self.version,
+            self.number_of_blocks,
+            self.blocks_index_offset,
+            self.blocks_index_length,
+            self.vars_index_offset,
+            self.vars_index_length,
+        )
+    }
+}
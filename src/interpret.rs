@@ -0,0 +1,122 @@
+use pyo3::{prelude::*, types::PyDict};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// What is known about a block field apple-bom leaves unnamed.
+struct FieldInterpretation {
+    kind: &'static str,
+    field: &'static str,
+    meaning: &'static str,
+    /// `"high"`, `"medium"`, or `"low"`.
+    confidence: &'static str,
+    /// The value Apple tooling writes.
+    typical: u32,
+}
+
+/// Best-known meanings of the unnamed `a`/`b`/`c` block fields. None of these
+/// are documented by Apple; keep this table in step with what the community
+/// learns, since it is the only place the knowledge lives.
+const INTERPRETATIONS: [FieldInterpretation; 6] = [
+    FieldInterpretation {
+        kind: "PathRecord",
+        field: "a",
+        meaning: "record format version",
+        confidence: "medium",
+        typical: 1,
+    },
+    FieldInterpretation {
+        kind: "PathRecord",
+        field: "b",
+        meaning: "flag, possibly marking checksum_or_type as valid",
+        confidence: "low",
+        typical: 1,
+    },
+    FieldInterpretation {
+        kind: "Tree",
+        field: "a",
+        meaning: "reserved",
+        confidence: "low",
+        typical: 0,
+    },
+    FieldInterpretation {
+        kind: "VIndex",
+        field: "a",
+        meaning: "structure version",
+        confidence: "medium",
+        typical: 1,
+    },
+    FieldInterpretation {
+        kind: "VIndex",
+        field: "b",
+        meaning: "reserved",
+        confidence: "low",
+        typical: 0,
+    },
+    FieldInterpretation {
+        kind: "VIndex",
+        field: "c",
+        meaning: "reserved",
+        confidence: "low",
+        typical: 0,
+    },
+];
+
+/// What is known about the value of one unnamed field.
+#[derive(Serialize)]
+pub(crate) struct Interpretation {
+    meaning: &'static str,
+    confidence: &'static str,
+    typical: u32,
+    is_typical: bool,
+}
+
+/// The interpretations of the unnamed fields of a block of `kind`, by field
+/// name, given the value of each field it has.
+pub(crate) fn interpretations(
+    kind: &str,
+    value: impl Fn(&str) -> Option<u32>,
+) -> BTreeMap<&'static str, Interpretation> {
+    INTERPRETATIONS
+        .iter()
+        .filter(|known| known.kind == kind)
+        .filter_map(|known| {
+            let value = value(known.field)?;
+            let interpretation = Interpretation {
+                meaning: known.meaning,
+                confidence: known.confidence,
+                typical: known.typical,
+                is_typical: value == known.typical,
+            };
+            Some((known.field, interpretation))
+        })
+        .collect()
+}
+
+/// Attach an `interpretations` dictionary to a serialized block whose kind
+/// has unnamed fields, leaving the raw values untouched.
+pub(crate) fn add_interpretations(block: &Bound<'_, PyDict>) -> PyResult<()> {
+    let py = block.py();
+    let Some(kind) = block.get_item("kind")? else {
+        return Ok(());
+    };
+    let kind: String = kind.extract()?;
+    let value = |field: &str| {
+        let value = block.get_item(field).ok().flatten()?;
+        value.extract().ok()
+    };
+
+    let found = PyDict::new_bound(py);
+    for (field, known) in interpretations(&kind, value) {
+        let item = PyDict::new_bound(py);
+        item.set_item("meaning", known.meaning)?;
+        item.set_item("confidence", known.confidence)?;
+        item.set_item("typical", known.typical)?;
+        item.set_item("is_typical", known.is_typical)?;
+        found.set_item(field, item)?;
+    }
+
+    if found.len() > 0 {
+        block.set_item("interpretations", found)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,285 @@
+use crate::{
+    buffer::BomData, document::Document, stats::TableStats, store::write_atomically, OptionChanges,
+    ParseOptions, PathEntry,
+};
+use pyo3::{
+    exceptions::{PyOSError, PyTypeError},
+    intern,
+    prelude::*,
+    types::PyBytes,
+};
+use serde::Serialize;
+use serde_json::ser::{CharEscape, Formatter, Serializer};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+/// Formats JSON as `json.dumps` does with `indent`: `", "` and `": "`
+/// between members on one line, or a member per line indented by `indent`
+/// spaces, and every character outside ASCII escaped.
+struct PythonFormatter {
+    indent: Option<usize>,
+    depth: usize,
+
+    /// Whether the innermost open object or array has members.
+    has_members: bool,
+}
+
+impl PythonFormatter {
+    fn new(indent: Option<usize>) -> Self {
+        Self {
+            indent,
+            depth: 0,
+            has_members: false,
+        }
+    }
+
+    fn newline<W: ?Sized + Write>(&self, writer: &mut W) -> io::Result<()> {
+        if let Some(indent) = self.indent {
+            writer.write_all(b"\n")?;
+            for _ in 0..indent * self.depth {
+                writer.write_all(b" ")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn open<W: ?Sized + Write>(&mut self, writer: &mut W, bracket: &[u8]) -> io::Result<()> {
+        self.depth += 1;
+        self.has_members = false;
+        writer.write_all(bracket)
+    }
+
+    fn close<W: ?Sized + Write>(&mut self, writer: &mut W, bracket: &[u8]) -> io::Result<()> {
+        self.depth -= 1;
+        if self.has_members {
+            self.newline(writer)?;
+        }
+        self.has_members = true;
+        writer.write_all(bracket)
+    }
+
+    fn member<W: ?Sized + Write>(&mut self, writer: &mut W, first: bool) -> io::Result<()> {
+        if !first {
+            writer.write_all(if self.indent.is_some() { b"," } else { b", " })?;
+        }
+        self.newline(writer)
+    }
+}
+
+impl Formatter for PythonFormatter {
+    fn begin_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.open(writer, b"[")
+    }
+
+    fn end_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.close(writer, b"]")
+    }
+
+    fn begin_array_value<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.member(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.has_members = true;
+        Ok(())
+    }
+
+    fn begin_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.open(writer, b"{")
+    }
+
+    fn end_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.close(writer, b"}")
+    }
+
+    fn begin_object_key<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.member(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(b": ")
+    }
+
+    fn end_object_value<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.has_members = true;
+        Ok(())
+    }
+
+    fn write_string_fragment<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        if fragment.is_ascii() {
+            return writer.write_all(fragment.as_bytes());
+        }
+        let mut rest = fragment;
+        while let Some(position) = rest.bytes().position(|byte| !byte.is_ascii()) {
+            writer.write_all(&rest.as_bytes()[..position])?;
+            let c = rest[position..].chars().next().unwrap_or_default();
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units) {
+                write!(writer, "\\u{unit:04x}")?;
+            }
+            rest = &rest[position + c.len_utf8()..];
+        }
+        writer.write_all(rest.as_bytes())
+    }
+
+    fn write_char_escape<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        escape: CharEscape,
+    ) -> io::Result<()> {
+        match escape {
+            CharEscape::AsciiControl(byte) => write!(writer, "\\u{byte:04x}"),
+            escape => serde_json::ser::CompactFormatter.write_char_escape(writer, escape),
+        }
+    }
+}
+
+/// Write `value` to `out` as JSON text, as `json.dumps` writes the value
+/// with `indent`.
+fn write_json(out: &mut Vec<u8>, value: &impl Serialize, indent: Option<usize>) -> PyResult<()> {
+    let mut serializer = Serializer::with_formatter(out, PythonFormatter::new(indent));
+    value
+        .serialize(&mut serializer)
+        .map_err(|err| PyTypeError::new_err(err.to_string()))
+}
+
+/// `value` as JSON text, as `json.dumps` writes the dictionary it stands in
+/// for with `indent`.
+pub(crate) fn to_string(value: &impl Serialize, indent: Option<usize>) -> PyResult<String> {
+    let mut out = Vec::new();
+    write_json(&mut out, value, indent)?;
+    // The formatter escapes everything outside ASCII.
+    Ok(String::from_utf8(out).unwrap_or_default())
+}
+
+/// Where exported lines go: a file created at a path, or a binary file-like
+/// object they are passed to with `write`.
+pub(crate) enum Destination<'py> {
+    Path(PathBuf),
+    Stream(Bound<'py, PyAny>),
+}
+
+impl<'py> FromPyObject<'py> for Destination<'py> {
+    fn extract_bound(destination: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if destination.hasattr(intern!(destination.py(), "write"))? {
+            Ok(Self::Stream(destination.clone()))
+        } else {
+            destination.extract().map(Self::Path)
+        }
+    }
+}
+
+/// An open [Destination].
+pub(crate) enum Sink<'py> {
+    File(PathBuf, BufWriter<File>),
+    Stream(Bound<'py, PyAny>),
+}
+
+impl<'py> Sink<'py> {
+    pub(crate) fn open(destination: Destination<'py>) -> PyResult<Self> {
+        match destination {
+            Destination::Path(path) => {
+                let file = File::create(&path).map_err(|err| {
+                    PyOSError::new_err(format!("failed writing {}: {err}", path.display()))
+                })?;
+                Ok(Self::File(path, BufWriter::new(file)))
+            }
+            Destination::Stream(stream) => Ok(Self::Stream(stream)),
+        }
+    }
+
+    pub(crate) fn write(&mut self, chunk: &[u8]) -> PyResult<()> {
+        match self {
+            Self::File(path, file) => file.write_all(chunk).map_err(|err| {
+                PyOSError::new_err(format!("failed writing {}: {err}", path.display()))
+            }),
+            Self::Stream(stream) => {
+                let chunk = PyBytes::new_bound(stream.py(), chunk);
+                stream.call_method1(intern!(stream.py(), "write"), (chunk,))?;
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> PyResult<()> {
+        match self {
+            Self::File(path, mut file) => file.flush().map_err(|err| {
+                PyOSError::new_err(format!("failed writing {}: {err}", path.display()))
+            }),
+            Self::Stream(_) => Ok(()),
+        }
+    }
+}
+
+/// Exported lines are handed to their destination in chunks of about this
+/// many bytes.
+pub(crate) const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Write `entries` to `destination` as NDJSON, one entry dictionary per
+/// line, adding them to `stats` when given, and return the number of lines
+/// written.
+pub(crate) fn write_ndjson(
+    destination: Destination<'_>,
+    entries: impl IntoIterator<Item = PathEntry>,
+    mut stats: Option<&mut TableStats>,
+) -> PyResult<u64> {
+    let mut sink = Sink::open(destination)?;
+    let mut out = Vec::new();
+    let mut lines = 0;
+    for entry in entries {
+        write_json(&mut out, &entry, None)?;
+        out.push(b'\n');
+        if let Some(stats) = stats.as_deref_mut() {
+            stats.add(entry.cells());
+        }
+        lines += 1;
+        if out.len() >= CHUNK_SIZE {
+            sink.write(&std::mem::take(&mut out))?;
+        }
+    }
+    if !out.is_empty() {
+        sink.write(&out)?;
+    }
+    sink.finish()?;
+    Ok(lines)
+}
+
+/// The document `parse_bom` returns for `data` with `options`, as JSON text
+/// written from the parsed BOM without building Python objects, or into the
+/// file at `path`.
+#[pyfunction(signature = (data, *, options = None, pretty = false, path = None))]
+pub(crate) fn to_json(
+    data: &Bound<'_, PyAny>,
+    options: Option<ParseOptions>,
+    pretty: bool,
+    path: Option<PathBuf>,
+) -> PyResult<Option<String>> {
+    let data = BomData::new(data, true)?;
+    let options = ParseOptions::resolve(options, OptionChanges::default())?;
+    let text = to_string(&Document::new(&data, None, &options)?, pretty.then_some(2))?;
+
+    match path {
+        Some(path) => {
+            write_atomically(&path, text.as_bytes()).map_err(|err| {
+                PyOSError::new_err(format!("failed writing {}: {err}", path.display()))
+            })?;
+            Ok(None)
+        }
+        None => Ok(Some(text)),
+    }
+}
@@ -0,0 +1,125 @@
+use pyo3::{intern, prelude::*, types::PyString};
+
+/// `name` as an interned Python string, the one object every interned copy
+/// of it shares.
+pub(crate) fn interned<'py>(py: Python<'py>, name: &str) -> Bound<'py, PyString> {
+    PyString::intern_bound(py, name)
+}
+
+/// The built-in `path_type` names of path entries.
+///
+/// Each is the very string object parsed output uses, so entries can be
+/// matched with `is` as well as `==`, and a misspelt constant fails with
+/// `AttributeError` rather than matching nothing. Registered names are
+/// interned too, so `sys.intern(name)` gives their object.
+#[pyclass(frozen, module = "pyapplebom._native")]
+pub(crate) struct PathType;
+
+#[pymethods]
+impl PathType {
+    #[classattr]
+    #[pyo3(name = "FILE")]
+    fn file(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "file").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "DIRECTORY")]
+    fn directory(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "directory").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "LINK")]
+    fn link(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "link").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "DEVICE")]
+    fn device(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "device").clone().unbind()
+    }
+
+    /// Type codes nobody has registered.
+    #[classattr]
+    #[pyo3(name = "OTHER")]
+    fn other(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "other").clone().unbind()
+    }
+}
+
+/// The `kind` names of parsed blocks, shared with parsed output as
+/// [PathType]'s are.
+#[pyclass(frozen, module = "pyapplebom._native")]
+pub(crate) struct BlockKind;
+
+#[pymethods]
+impl BlockKind {
+    #[classattr]
+    #[pyo3(name = "EMPTY")]
+    fn empty(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "Empty").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "BOM_INFO")]
+    fn bom_info(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "BomInfo").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "FILE")]
+    fn file(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "File").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "PATH_INFO_INDEX")]
+    fn path_info_index(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "PathInfoIndex").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "PATH_RECORD")]
+    fn path_record(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "PathRecord").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "PATH_RECORD_POINTER")]
+    fn path_record_pointer(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "PathRecordPointer").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "PATHS")]
+    fn paths(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "Paths").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "TREE")]
+    fn tree(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "Tree").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "TREE_POINTER")]
+    fn tree_pointer(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "TreePointer").clone().unbind()
+    }
+
+    #[classattr]
+    #[pyo3(name = "V_INDEX")]
+    fn v_index(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "VIndex").clone().unbind()
+    }
+
+    /// Blocks that failed to parse or are too small to identify.
+    #[classattr]
+    #[pyo3(name = "UNKNOWN")]
+    fn unknown(py: Python<'_>) -> Py<PyString> {
+        intern!(py, "Unknown").clone().unbind()
+    }
+}
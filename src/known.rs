@@ -0,0 +1,180 @@
+use crate::{
+    footprint::{Footprint, HeapSize},
+    writer::{extract_field, path_components},
+};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use std::collections::{BTreeSet, HashMap};
+
+/// Known-good values of a file, any of which may be unknown.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct KnownValues {
+    pub size: Option<u64>,
+    pub crc32: Option<u32>,
+}
+
+/// A way a file differs from its known-good values.
+pub(crate) struct Deviation {
+    /// `"size"` or `"crc32"`.
+    pub field: &'static str,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl KnownValues {
+    /// Compare a file's size and checksum, where known, against these
+    /// values.
+    pub(crate) fn deviations(&self, size: Option<u64>, crc32: Option<u32>) -> Vec<Deviation> {
+        [
+            ("size", self.size, size),
+            ("crc32", self.crc32.map(u64::from), crc32.map(u64::from)),
+        ]
+        .into_iter()
+        .filter_map(|(field, expected, actual)| match (expected, actual) {
+            (Some(expected), Some(actual)) if expected != actual => Some(Deviation {
+                field,
+                expected,
+                actual,
+            }),
+            _ => None,
+        })
+        .collect()
+    }
+}
+
+/// Known-good sizes and checksums of files shipped with the OS, keyed by
+/// path relative to the install root and optionally by OS build.
+#[pyclass(module = "pyapplebom._native")]
+#[derive(Default)]
+pub(crate) struct KnownFiles {
+    /// Values by path, then by OS build; `None` applies to every build.
+    files: HashMap<String, HashMap<Option<String>, KnownValues>>,
+}
+
+/// The key of a path in [KnownFiles]: its components below the install root.
+fn path_key(path: &str) -> PyResult<String> {
+    Ok(path_components(path)?.join("/"))
+}
+
+impl KnownFiles {
+    pub(crate) fn footprint(&self) -> Footprint {
+        Footprint {
+            indices: self.files.heap_size(),
+            ..Default::default()
+        }
+    }
+
+    /// The known values of `path`, given as components below the install
+    /// root, for `os_build`, falling back to values that apply to every
+    /// build.
+    pub(crate) fn get(&self, components: &[String], os_build: Option<&str>) -> Option<KnownValues> {
+        let builds = self.files.get(&components.join("/"))?;
+        os_build
+            .and_then(|build| builds.get(&Some(build.to_string())))
+            .or_else(|| builds.get(&None))
+            .copied()
+    }
+}
+
+#[pymethods]
+impl KnownFiles {
+    #[new]
+    #[pyo3(signature = (entries = None))]
+    fn new(entries: Option<&Bound<'_, PyAny>>) -> PyResult<Self> {
+        let mut known = Self::default();
+        if let Some(entries) = entries {
+            known.extend(entries)?;
+        }
+        Ok(known)
+    }
+
+    /// Record the known-good size and checksum of `path`, for `os_build` or
+    /// for every build.
+    #[pyo3(signature = (path, *, crc32 = None, size = None, os_build = None))]
+    fn add(
+        &mut self,
+        path: &str,
+        crc32: Option<u32>,
+        size: Option<u64>,
+        os_build: Option<String>,
+    ) -> PyResult<()> {
+        if crc32.is_none() && size.is_none() {
+            return Err(PyValueError::new_err(format!(
+                "known file {path:?} needs a crc32 or a size"
+            )));
+        }
+
+        self.files
+            .entry(path_key(path)?)
+            .or_default()
+            .insert(os_build, KnownValues { size, crc32 });
+        Ok(())
+    }
+
+    /// Add entries with `path` and `crc32` and/or `size`, plus an optional
+    /// `os_build`, given as mappings or objects.
+    fn extend(&mut self, entries: &Bound<'_, PyAny>) -> PyResult<()> {
+        let mut parsed = Vec::new();
+        for (index, entry) in entries.iter()?.enumerate() {
+            let entry = entry?;
+            let Some(path) = extract_field::<String>(&entry, &["path"])? else {
+                return Err(PyValueError::new_err(format!(
+                    "entry {index} has no 'path'"
+                )));
+            };
+            let values = KnownValues {
+                size: extract_field(&entry, &["size"])?,
+                crc32: extract_field(&entry, &["crc32"])?,
+            };
+            if values == KnownValues::default() {
+                return Err(PyValueError::new_err(format!(
+                    "entry {index} needs a 'crc32' or a 'size'"
+                )));
+            }
+            parsed.push((
+                path_key(&path)?,
+                extract_field(&entry, &["os_build"])?,
+                values,
+            ));
+        }
+
+        for (key, os_build, values) in parsed {
+            self.files.entry(key).or_default().insert(os_build, values);
+        }
+        Ok(())
+    }
+
+    /// The known values of `path` for `os_build`, or `None` if nothing is
+    /// known.
+    #[pyo3(signature = (path, os_build = None))]
+    fn lookup<'py>(
+        &self,
+        py: Python<'py>,
+        path: &str,
+        os_build: Option<&str>,
+    ) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let Some(values) = self.get(&path_components(path)?, os_build) else {
+            return Ok(None);
+        };
+
+        let item = PyDict::new_bound(py);
+        item.set_item("size", values.size)?;
+        item.set_item("crc32", values.crc32)?;
+        Ok(Some(item))
+    }
+
+    /// The OS builds with build-specific entries.
+    #[getter]
+    fn os_builds(&self) -> Vec<&str> {
+        let builds: BTreeSet<&str> = self
+            .files
+            .values()
+            .flat_map(|builds| builds.keys().flatten())
+            .map(String::as_str)
+            .collect();
+        builds.into_iter().collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.files.values().map(HashMap::len).sum()
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    gaps::{self, Region},
+};
+use apple_bom::format::ParsedBom;
+use pyo3::{
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use std::collections::BTreeMap;
+
+/// The largest alignment reported, a page; offsets aligned to more are
+/// counted as page-aligned.
+const MAX_ALIGNMENT: u32 = 4096;
+
+/// The largest power of two, up to [MAX_ALIGNMENT], that `offset` is a
+/// multiple of.
+fn alignment(offset: u32) -> u32 {
+    if offset == 0 {
+        MAX_ALIGNMENT
+    } else {
+        (1 << offset.trailing_zeros()).min(MAX_ALIGNMENT)
+    }
+}
+
+/// The value every byte of `data` has, or `None` when they differ.
+fn fill(data: &[u8]) -> Option<u8> {
+    let (&first, rest) = data.split_first()?;
+    rest.iter().all(|&byte| byte == first).then_some(first)
+}
+
+/// How a BOM's blocks are laid out: their alignment, the gaps between them,
+/// and the bytes the gaps are padded with, for fingerprinting the writer.
+#[pyfunction]
+pub(crate) fn block_layout<'py>(
+    py: Python<'py>,
+    data: &Bound<'_, PyAny>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let data = BomData::new(data, true)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+
+    // Empty blocks have no bytes to place, and writers leave them at 0.
+    let placed: Vec<_> = bom
+        .blocks
+        .blocks
+        .iter()
+        .filter(|entry| entry.length > 0)
+        .collect();
+    let mut alignments = BTreeMap::new();
+    for entry in &placed {
+        *alignments
+            .entry(alignment(entry.file_offset))
+            .or_insert(0usize) += 1;
+    }
+    let in_index_order = placed
+        .windows(2)
+        .all(|pair| pair[0].file_offset < pair[1].file_offset);
+    let mut by_offset: Vec<_> = placed
+        .iter()
+        .map(|entry| {
+            let start = u64::from(entry.file_offset);
+            (start, start + u64::from(entry.length))
+        })
+        .collect();
+    by_offset.sort_unstable();
+    let overlaps = by_offset
+        .windows(2)
+        .filter(|pair| pair[1].0 < pair[0].1)
+        .count();
+
+    let mut gap_lengths = BTreeMap::new();
+    let mut gap_bytes = 0;
+    let mut padding = BTreeMap::new();
+    let gap_list = PyList::empty_bound(py);
+    for gap in gaps::gaps(&bom) {
+        let bytes = &bom.data[gap.offset..gap.offset + gap.length];
+        for &byte in bytes {
+            *padding.entry(byte).or_insert(0usize) += 1;
+        }
+        let between_blocks = matches!(
+            (gap.after, gap.before),
+            (Region::Block(_), Some(Region::Block(_)))
+        );
+        if between_blocks {
+            *gap_lengths.entry(gap.length).or_insert(0usize) += 1;
+            gap_bytes += gap.length;
+        }
+        let item = gap.to_dict(py)?;
+        item.set_item("fill", fill(bytes))?;
+        gap_list.append(item)?;
+    }
+
+    let report = PyDict::new_bound(py);
+    report.set_item("blocks", placed.len())?;
+    let common = alignments.keys().next().copied().unwrap_or(MAX_ALIGNMENT);
+    report.set_item("alignment", common)?;
+    report.set_item("alignments", alignments)?;
+    report.set_item("in_index_order", in_index_order)?;
+    report.set_item("overlaps", overlaps)?;
+
+    let inter_block = PyDict::new_bound(py);
+    inter_block.set_item("count", gap_lengths.values().sum::<usize>())?;
+    inter_block.set_item("bytes", gap_bytes)?;
+    inter_block.set_item("lengths", gap_lengths)?;
+    report.set_item("inter_block_gaps", inter_block)?;
+    report.set_item("padding_bytes", padding)?;
+    report.set_item("gaps", gap_list)?;
+
+    Ok(report)
+}
@@ -1,25 +1,125 @@
 use apple_bom::{
-    format::{BomBlock, ParsedBom},
+    format::{BomBlock, BomBlockBomInfo, ParsedBom},
     BomPath, BomPathType,
 };
 use pyo3::{
     create_exception,
-    exceptions::{PyException, PyOSError, PyTypeError},
+    exceptions::{PyException, PyIndexError, PyOSError, PyTypeError, PyValueError},
+    intern,
     prelude::*,
-    types::{PyDict, PyList},
+    types::{PyBytes, PyDict, PyList},
     wrap_pyfunction, Bound,
 };
 use std::{
     any::Any,
-    panic::{catch_unwind, AssertUnwindSafe},
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    io,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
+mod access;
+mod audit;
+mod blocks;
+mod bloom;
+mod bomfile;
+mod buffer;
+mod buildinfo;
+#[cfg(feature = "bundle")]
+mod bundle;
+mod cksum;
+mod corpus;
+mod csv;
+#[cfg(feature = "delta")]
+mod delta;
+mod diff;
+mod document;
+mod duplicates;
+mod editor;
+mod entry;
+mod features;
+mod footprint;
+mod gaps;
+mod hardlinks;
+mod hashset;
+mod header;
+mod interpret;
+mod json;
+mod kinds;
+mod known;
+mod layout;
+mod lookup;
+mod lsbom;
+mod macho;
+mod mapping;
+mod modes;
+mod mtimes;
+mod mtree;
+mod options;
+mod pathiter;
+mod paths;
+mod pathtypes;
+mod quarantine;
+mod reader;
+mod size64;
+mod sizes;
+mod spill;
+mod stats;
+mod store;
+mod suppress;
+mod tree;
+mod unified;
+mod verify;
+mod walk;
+mod writer;
+
+use blocks::BlockSequence;
+use buffer::BomData;
+use entry::PathEntry;
+use header::BomHeader;
+use kinds::interned;
+use mapping::Mapping;
+use options::{OptionChanges, ParseOptions, PATH_SECTIONS};
+use paths::{PathOrder, PathStyle};
+
 create_exception!(pyapplebom, BomParseError, PyException);
+create_exception!(pyapplebom, FeatureNotAvailable, PyException);
 
 fn bom_error_to_py(err: apple_bom::Error) -> PyErr {
     BomParseError::new_err(err.to_string())
 }
 
+/// The error for using a handle after `close()`.
+fn closed_error(kind: &str) -> PyErr {
+    PyValueError::new_err(format!("operation on a closed {kind}"))
+}
+
+/// Lock a handle's state, which stays consistent even if a holder panicked.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Take a shared lock on a handle's state, tolerating a poisoned lock as
+/// [lock] does.
+fn read_lock<T>(rwlock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    rwlock
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Take an exclusive lock on a handle's state, tolerating a poisoned lock as
+/// [lock] does.
+fn write_lock<T>(rwlock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    rwlock
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
 fn panic_payload_to_string(payload: Box<dyn Any + Send>) -> String {
     if let Some(message) = payload.downcast_ref::<&str>() {
         (*message).to_string()
@@ -36,50 +136,107 @@ enum SafeBomCall<T> {
     Error(String),
 }
 
+/// What a parse does when the apple-bom parser panics, as `on_panic` names
+/// it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PanicPolicy {
+    /// Record the panic as the parse error of the section or block it hit.
+    Wrap,
+    /// Let the panic propagate, so Python sees pyo3's `PanicException` and
+    /// the panic hook's report.
+    Raise,
+    /// Record the panic as the parse error of its whole section, leaving the
+    /// section `None`.
+    AbortSection,
+}
+
+impl PanicPolicy {
+    fn from_option(on_panic: &str) -> PyResult<Self> {
+        match on_panic {
+            "wrap" => Ok(Self::Wrap),
+            "raise" => Ok(Self::Raise),
+            "abort_section" => Ok(Self::AbortSection),
+            _ => Err(PyValueError::new_err(format!(
+                "on_panic must be 'wrap', 'raise', or 'abort_section', not {on_panic:?}"
+            ))),
+        }
+    }
+
+    /// The message of a caught panic, or the panic resumed under
+    /// [PanicPolicy::Raise].
+    fn caught(self, payload: Box<dyn Any + Send>) -> String {
+        if self == Self::Raise {
+            resume_unwind(payload);
+        }
+        panic_payload_to_string(payload)
+    }
+
+    fn call<T, F>(self, func: F) -> SafeBomCall<T>
+    where
+        F: FnOnce() -> Result<T, apple_bom::Error>,
+    {
+        match catch_unwind(AssertUnwindSafe(func)) {
+            Ok(Ok(value)) => SafeBomCall::Value(value),
+            Ok(Err(apple_bom::Error::NoVar(_))) => SafeBomCall::MissingVariable,
+            Ok(Err(err)) => SafeBomCall::Error(err.to_string()),
+            Err(payload) => SafeBomCall::Error(format!(
+                "apple-bom parser panicked: {}",
+                self.caught(payload)
+            )),
+        }
+    }
+}
+
 fn safe_bom_call<T, F>(func: F) -> SafeBomCall<T>
 where
     F: FnOnce() -> Result<T, apple_bom::Error>,
 {
-    match catch_unwind(AssertUnwindSafe(func)) {
-        Ok(Ok(value)) => SafeBomCall::Value(value),
-        Ok(Err(apple_bom::Error::NoVar(_))) => SafeBomCall::MissingVariable,
-        Ok(Err(err)) => SafeBomCall::Error(err.to_string()),
-        Err(payload) => SafeBomCall::Error(format!(
-            "apple-bom parser panicked: {}",
-            panic_payload_to_string(payload)
-        )),
+    PanicPolicy::Wrap.call(func)
+}
+
+fn required_paths(bom: &ParsedBom<'_>) -> PyResult<Vec<BomPath>> {
+    match safe_bom_call(|| bom.paths()) {
+        SafeBomCall::Value(paths) => Ok(paths),
+        SafeBomCall::MissingVariable => Err(BomParseError::new_err("BOM has no Paths variable")),
+        SafeBomCall::Error(err) => Err(BomParseError::new_err(err)),
     }
 }
 
-fn path_type_name(path_type: BomPathType) -> &'static str {
-    match path_type {
-        BomPathType::File => "file",
-        BomPathType::Directory => "directory",
-        BomPathType::Link => "link",
-        BomPathType::Dev => "device",
-        BomPathType::Other(_) => "other",
+/// The `summary` of a verification or comparison report: a count per
+/// category, their total, and whether every count is zero.
+fn summary<'py, 'a>(
+    py: Python<'py>,
+    counts: impl IntoIterator<Item = (&'a str, usize)>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let by_category = PyDict::new_bound(py);
+    let mut total = 0;
+    for (category, count) in counts {
+        by_category.set_item(category, count)?;
+        total += count;
     }
+
+    let summary = PyDict::new_bound(py);
+    summary.set_item("ok", total == 0)?;
+    summary.set_item("total", total)?;
+    summary.set_item("counts", by_category)?;
+    Ok(summary)
 }
 
-fn path_to_dict<'py>(py: Python<'py>, path: &BomPath) -> PyResult<Bound<'py, PyDict>> {
-    let item = PyDict::new_bound(py);
-    let path_type = path.path_type();
-    let path_type_raw: u8 = path_type.into();
+fn path_type_name(path_type: BomPathType) -> Cow<'static, str> {
+    pathtypes::name(path_type)
+}
 
-    item.set_item("path", path.path())?;
-    item.set_item("path_type", path_type_name(path_type))?;
-    item.set_item("path_type_raw", path_type_raw)?;
-    item.set_item("file_mode", path.file_mode())?;
-    item.set_item("symbolic_mode", path.symbolic_mode())?;
-    item.set_item("user_id", path.user_id())?;
-    item.set_item("group_id", path.group_id())?;
-    item.set_item("mtime", path.modified_time().timestamp())?;
-    item.set_item("mtime_iso8601", path.modified_time().to_rfc3339())?;
-    item.set_item("size", path.size())?;
-    item.set_item("crc32", path.crc32())?;
-    item.set_item("link_name", path.link_name())?;
+fn path_type_from_name(name: &str) -> PyResult<u8> {
+    pathtypes::code(name)
+}
 
-    Ok(item)
+fn path_to_dict<'py>(
+    py: Python<'py>,
+    path: &BomPath,
+    style: &PathStyle,
+    sizes: &HashMap<String, u64>,
+) -> PyResult<Bound<'py, PyDict>> {
+    PathEntry::new(path, style, sizes).record_dict(py)
 }
 
 fn path_record_fields<'py>(
@@ -88,7 +245,7 @@ fn path_record_fields<'py>(
 ) -> PyResult<()> {
     let path_type = BomPathType::from(record.path_type);
 
-    item.set_item("path_type", path_type_name(path_type))?;
+    item.set_item("path_type", interned(item.py(), &path_type_name(path_type)))?;
     item.set_item("path_type_raw", record.path_type)?;
     item.set_item("a", record.a)?;
     item.set_item("architecture", record.architecture)?;
@@ -105,23 +262,329 @@ fn path_record_fields<'py>(
     Ok(())
 }
 
-fn serialize_path_list<'py>(py: Python<'py>, paths: &[BomPath]) -> PyResult<Bound<'py, PyList>> {
+/// Path entry values that come from outside the path records, keyed by raw
+/// BOM path.
+#[derive(Default)]
+struct PathExtras {
+    /// Full sizes of paths listed in `Size64`.
+    sizes: HashMap<String, u64>,
+
+    /// `(path_id, parent_path_id)` of each path in the `Paths` tree.
+    ids: HashMap<String, (u32, u32)>,
+
+    /// `(block_index, file_index)` of each path's entry in a `Paths` leaf, when
+    /// requested.
+    tree_positions: HashMap<String, (u32, u32)>,
+
+    /// The bytes of each path's `PathRecord` block, when requested.
+    records: HashMap<String, Vec<u8>>,
+
+    /// How entries carry `records`, or `None` when they were not requested.
+    raw_records: Option<RawBlockEncoding>,
+}
+
+impl PathExtras {
+    /// The extras of the `Paths` tree's `paths`: full sizes from `Size64`,
+    /// when it can be read, IDs, and positions and records when requested.
+    fn for_paths(bom: &ParsedBom<'_>, paths: &[BomPath], options: &PathOptions) -> Self {
+        let mut extras = Self {
+            sizes: match safe_bom_call(|| size64::resolve(bom)) {
+                SafeBomCall::Value(section) => section.sizes,
+                _ => HashMap::new(),
+            },
+            ..Default::default()
+        };
+        extras.add_tree_entries(bom, paths, options);
+        extras
+    }
+
+    /// Record the IDs, and positions and records when requested, of `paths`
+    /// as resolved through the `Paths` tree. Nothing is recorded if the
+    /// leaves can't be read.
+    fn add_tree_entries(&mut self, bom: &ParsedBom<'_>, paths: &[BomPath], options: &PathOptions) {
+        let SafeBomCall::Value(leaves) = safe_bom_call(|| lookup::path_leaves(bom)) else {
+            return;
+        };
+        self.raw_records = options.raw_records;
+        for (path, leaf) in paths.iter().zip(leaves) {
+            let path = path.path().to_string();
+            if options.tree_positions {
+                self.tree_positions
+                    .insert(path.clone(), (leaf.block_index, leaf.file_index));
+            }
+            if options.raw_records.is_some() {
+                let record = safe_bom_call(|| bom.block_data(leaf.path_record_index as _));
+                if let SafeBomCall::Value(record) = record {
+                    self.records.insert(path.clone(), record.to_vec());
+                }
+            }
+            self.ids.insert(path, (leaf.path_id, leaf.parent_path_id));
+        }
+    }
+
+    /// The entry for `path`, with its extras filled in.
+    fn entry(&self, path: &BomPath, style: &PathStyle) -> PathEntry {
+        let mut entry = PathEntry::new(path, style, &self.sizes);
+        (entry.path_id, entry.parent_path_id) = self.ids.get(path.path()).copied().unzip();
+        entry.tree_position = self.tree_positions.get(path.path()).copied();
+        entry.raw_record = self.raw_records.zip(self.records.get(path.path()).cloned());
+        entry
+    }
+}
+
+/// Serialize a path section as dictionaries, or as `BomPathEntry` objects
+/// when `typed` is set.
+fn serialize_path_list<'py>(
+    py: Python<'py>,
+    paths: &[BomPath],
+    style: &PathStyle,
+    extras: &PathExtras,
+    typed: bool,
+) -> PyResult<Bound<'py, PyList>> {
     let list = PyList::empty_bound(py);
 
     for path in paths {
-        list.append(path_to_dict(py, path)?)?;
+        let entry = extras.entry(path, style);
+        if typed {
+            list.append(Bound::new(py, entry)?)?;
+        } else {
+            list.append(entry.to_dict(py)?)?;
+        }
     }
 
     Ok(list)
 }
 
-fn append_block_entry<'py>(
+/// Whether `data` is large enough to hold the `BomInfo` entries it declares.
+///
+/// apple-bom preallocates the entry list from the declared count before
+/// reading it, and [BomBlock::try_parse] falls back to reading any block it
+/// can't otherwise place as `BomInfo`. A block of arbitrary data, such as
+/// one `add_block` appended, can then request a multi-gigabyte allocation,
+/// whose failure aborts the process rather than panicking.
+fn bom_info_fits(data: &[u8]) -> bool {
+    match data.get(8..12) {
+        Some(count) => {
+            let count = u32::from_be_bytes([count[0], count[1], count[2], count[3]]) as usize;
+            count.saturating_mul(16) <= data.len() - 12
+        }
+        None => true,
+    }
+}
+
+/// Infer the type of a block as [BomBlock::try_parse] does, trying the same
+/// types in the same order, except that `BomInfo` is skipped when the block
+/// can't hold the entries it declares (see [bom_info_fits]).
+fn detect_block<'a>(
+    bom: &'a ParsedBom<'_>,
+    index: usize,
+) -> Result<BomBlock<'a>, apple_bom::Error> {
+    if bom_info_fits(bom.block_data(index)?) {
+        return BomBlock::try_parse(bom, index);
+    }
+
+    if let Ok(tree) = bom.block_as_tree(index) {
+        if tree.paths(bom).is_ok() {
+            return Ok(BomBlock::Tree(tree));
+        }
+    }
+
+    if let Ok(paths) = bom.block_as_paths(index) {
+        if paths.iter_path_entries(bom).all(|x| x.is_ok()) {
+            return Ok(BomBlock::Paths(paths));
+        }
+    }
+
+    if let Ok(vindex) = bom.block_as_vindex(index) {
+        if vindex.tree(bom).is_ok() {
+            return Ok(BomBlock::VIndex(vindex));
+        }
+    }
+
+    if let Ok(path_info) = bom.block_as_path_info_index(index) {
+        if path_info.path_record(bom).is_ok() {
+            return Ok(BomBlock::PathInfoIndex(path_info));
+        }
+    }
+
+    if let Ok(record) = bom.block_as_path_record(index) {
+        return Ok(BomBlock::PathRecord(record));
+    }
+
+    if let Ok(file) = bom.block_as_file(index) {
+        return Ok(BomBlock::File(file));
+    }
+
+    if let Ok(pointer) = bom.block_as_path_record_pointer(index) {
+        if pointer.path_record(bom).is_ok() {
+            return Ok(BomBlock::PathRecordPointer(pointer));
+        }
+    }
+
+    if let Ok(pointer) = bom.block_as_tree_pointer(index) {
+        if pointer.tree(bom).is_ok() {
+            return Ok(BomBlock::TreePointer(pointer));
+        }
+    }
+
+    Err(apple_bom::Error::UnknownBlockType)
+}
+
+fn variables_list<'py>(py: Python<'py>, bom: &ParsedBom<'_>) -> PyResult<Bound<'py, PyList>> {
+    let variables = PyList::empty_bound(py);
+    // Each record is a u32 block index, a u8 name length, then the name bytes,
+    // following the u32 variable count.
+    let mut var_offset = 4usize;
+    for (order, var) in bom.vars.vars.iter().enumerate() {
+        let item = PyDict::new_bound(py);
+        item.set_item("name", &var.name)?;
+        item.set_item("name_length", var.name_length)?;
+        item.set_item("block_index", var.block_index)?;
+        item.set_item("order", order)?;
+        item.set_item("offset", var_offset)?;
+        variables.append(item)?;
+        var_offset += 5 + var.name_length as usize;
+    }
+
+    Ok(variables)
+}
+
+fn bom_info_dict<'py>(py: Python<'py>, info: &BomBlockBomInfo) -> PyResult<Bound<'py, PyDict>> {
+    let info_dict = PyDict::new_bound(py);
+    info_dict.set_item("version", info.version)?;
+    info_dict.set_item("number_of_paths", info.number_of_paths)?;
+    info_dict.set_item("number_of_info_entries", info.number_of_info_entries)?;
+
+    let entries = PyList::empty_bound(py);
+    for info_entry in &info.entries {
+        let item = PyDict::new_bound(py);
+        item.set_item("a", info_entry.a)?;
+        item.set_item("b", info_entry.b)?;
+        item.set_item("c", info_entry.c)?;
+        item.set_item("d", info_entry.d)?;
+        entries.append(item)?;
+    }
+    info_dict.set_item("entries", entries)?;
+
+    Ok(info_dict)
+}
+
+/// How path sections are emitted.
+#[derive(Clone, Copy)]
+struct PathOptions {
+    order: PathOrder,
+
+    /// Whether entries in `Paths` leaves carry their `tree_position`.
+    tree_positions: bool,
+
+    /// Whether entries are `BomPathEntry` objects rather than dictionaries.
+    typed: bool,
+
+    /// How entries in `Paths` leaves carry their `PathRecord` block's bytes,
+    /// or `None` when they don't.
+    raw_records: Option<RawBlockEncoding>,
+}
+
+/// How a block entry carries the block's raw bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RawBlockEncoding {
+    /// A hex string under `raw_hex`.
+    Hex,
+
+    /// A `bytes` object under `raw`.
+    Bytes,
+}
+
+impl RawBlockEncoding {
+    /// The encoding for the `raw_block_encoding` option, or `None` when raw
+    /// bytes are left out.
+    fn from_options(include_raw_block_bytes: bool, encoding: &str) -> PyResult<Option<Self>> {
+        let encoding = match encoding {
+            "hex" => Self::Hex,
+            "bytes" => Self::Bytes,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "raw_block_encoding must be 'hex' or 'bytes', not {encoding:?}"
+                )))
+            }
+        };
+        Ok(include_raw_block_bytes.then_some(encoding))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct BlockOptions {
+    raw_bytes: Option<RawBlockEncoding>,
+    interpretations: bool,
+    on_panic: PanicPolicy,
+}
+
+impl BlockOptions {
+    /// The block options for the `parse_bom` flags, or `None` when blocks are
+    /// left out.
+    fn from_flags(
+        include_blocks: bool,
+        include_raw_block_bytes: bool,
+        raw_block_encoding: &str,
+        interpret_fields: bool,
+        on_panic: PanicPolicy,
+    ) -> PyResult<Option<Self>> {
+        let raw_bytes =
+            RawBlockEncoding::from_options(include_raw_block_bytes, raw_block_encoding)?;
+        Ok(include_blocks.then_some(Self {
+            raw_bytes,
+            interpretations: interpret_fields,
+            on_panic,
+        }))
+    }
+}
+
+/// `IndexError` if `index` is past the end of the blocks index.
+fn check_block_index(bom: &ParsedBom<'_>, index: usize) -> PyResult<()> {
+    if index >= bom.blocks.blocks.len() {
+        return Err(PyIndexError::new_err(format!(
+            "block index {index} out of range for {} blocks",
+            bom.blocks.blocks.len()
+        )));
+    }
+    Ok(())
+}
+
+/// The entry of the block at `index`, or `IndexError` past the end of the
+/// blocks index.
+fn checked_block_entry<'py>(
     py: Python<'py>,
     bom: &ParsedBom<'_>,
     index: usize,
-    include_raw_block_bytes: bool,
-    blocks_list: &Bound<'py, PyList>,
+    options: BlockOptions,
+) -> PyResult<Bound<'py, PyDict>> {
+    check_block_index(bom, index)?;
+    block_entry(py, bom, index, options)
+}
+
+/// Record a panic parsing the block at `index` on its entry, or under
+/// [PanicPolicy::AbortSection] raise it as a `BomParseError` that ends the
+/// blocks section.
+fn block_panicked(
+    block_dict: &Bound<'_, PyDict>,
+    index: usize,
+    on_panic: PanicPolicy,
+    payload: Box<dyn Any + Send>,
 ) -> PyResult<()> {
+    let message = format!("block parser panicked: {}", on_panic.caught(payload));
+    if on_panic == PanicPolicy::AbortSection {
+        return Err(BomParseError::new_err(format!("block {index}: {message}")));
+    }
+    block_dict.set_item("kind", intern!(block_dict.py(), "Unknown"))?;
+    block_dict.set_item("parse_error", message)
+}
+
+fn block_entry<'py>(
+    py: Python<'py>,
+    bom: &ParsedBom<'_>,
+    index: usize,
+    options: BlockOptions,
+) -> PyResult<Bound<'py, PyDict>> {
     let entry = bom.blocks.blocks.get(index).ok_or_else(|| {
         PyTypeError::new_err(format!(
             "block index {index} out of range while serializing"
@@ -133,46 +596,46 @@ fn append_block_entry<'py>(
     block_dict.set_item("file_offset", entry.file_offset)?;
     block_dict.set_item("length", entry.length)?;
 
-    let raw_data = bom.block_data(index).map_err(bom_error_to_py)?;
+    // A blocks index entry past the end of the data panics the slicing.
+    let raw_data = match catch_unwind(AssertUnwindSafe(|| bom.block_data(index))) {
+        Ok(raw_data) => raw_data.map_err(bom_error_to_py)?,
+        Err(payload) => {
+            block_panicked(&block_dict, index, options.on_panic, payload)?;
+            return Ok(block_dict);
+        }
+    };
 
-    if include_raw_block_bytes {
-        block_dict.set_item("raw_hex", hex::encode(raw_data))?;
+    match options.raw_bytes {
+        Some(RawBlockEncoding::Hex) => block_dict.set_item("raw_hex", hex::encode(raw_data))?,
+        Some(RawBlockEncoding::Bytes) => {
+            block_dict.set_item("raw", PyBytes::new_bound(py, raw_data))?
+        }
+        None => {}
     }
 
     if raw_data.is_empty() {
-        block_dict.set_item("kind", "Empty")?;
-        blocks_list.append(block_dict)?;
-        return Ok(());
+        block_dict.set_item("kind", intern!(py, "Empty"))?;
+        return Ok(block_dict);
     }
 
     // apple-bom's block type detector assumes at least 4 bytes for tree checks.
     if raw_data.len() < 4 {
-        block_dict.set_item("kind", "Unknown")?;
+        block_dict.set_item("kind", intern!(py, "Unknown"))?;
         block_dict.set_item("parse_error", "block too small for type detection")?;
-        blocks_list.append(block_dict)?;
-        return Ok(());
+        return Ok(block_dict);
     }
 
-    match catch_unwind(AssertUnwindSafe(|| BomBlock::try_parse(bom, index))) {
-        Err(payload) => {
-            block_dict.set_item("kind", "Unknown")?;
-            block_dict.set_item(
-                "parse_error",
-                format!(
-                    "block parser panicked: {}",
-                    panic_payload_to_string(payload)
-                ),
-            )?;
-        }
+    match catch_unwind(AssertUnwindSafe(|| detect_block(bom, index))) {
+        Err(payload) => block_panicked(&block_dict, index, options.on_panic, payload)?,
         Ok(Err(err)) => {
-            block_dict.set_item("kind", "Unknown")?;
+            block_dict.set_item("kind", intern!(py, "Unknown"))?;
             block_dict.set_item("parse_error", err.to_string())?;
         }
         Ok(Ok(BomBlock::Empty)) => {
-            block_dict.set_item("kind", "Empty")?;
+            block_dict.set_item("kind", intern!(py, "Empty"))?;
         }
         Ok(Ok(BomBlock::BomInfo(info))) => {
-            block_dict.set_item("kind", "BomInfo")?;
+            block_dict.set_item("kind", intern!(py, "BomInfo"))?;
             block_dict.set_item("version", info.version)?;
             block_dict.set_item("number_of_paths", info.number_of_paths)?;
             block_dict.set_item("number_of_info_entries", info.number_of_info_entries)?;
@@ -189,25 +652,25 @@ fn append_block_entry<'py>(
             block_dict.set_item("entries", entries)?;
         }
         Ok(Ok(BomBlock::File(file))) => {
-            block_dict.set_item("kind", "File")?;
+            block_dict.set_item("kind", intern!(py, "File"))?;
             block_dict.set_item("parent_path_id", file.parent_path_id)?;
             block_dict.set_item("name", file.string_file_name())?;
         }
         Ok(Ok(BomBlock::PathInfoIndex(path_info))) => {
-            block_dict.set_item("kind", "PathInfoIndex")?;
+            block_dict.set_item("kind", intern!(py, "PathInfoIndex"))?;
             block_dict.set_item("path_id", path_info.path_id)?;
             block_dict.set_item("path_record_index", path_info.path_record_index)?;
         }
         Ok(Ok(BomBlock::PathRecord(record))) => {
-            block_dict.set_item("kind", "PathRecord")?;
+            block_dict.set_item("kind", intern!(py, "PathRecord"))?;
             path_record_fields(&block_dict, &record)?;
         }
         Ok(Ok(BomBlock::PathRecordPointer(pointer))) => {
-            block_dict.set_item("kind", "PathRecordPointer")?;
+            block_dict.set_item("kind", intern!(py, "PathRecordPointer"))?;
             block_dict.set_item("block_path_record_index", pointer.block_path_record_index)?;
         }
         Ok(Ok(BomBlock::Paths(paths))) => {
-            block_dict.set_item("kind", "Paths")?;
+            block_dict.set_item("kind", intern!(py, "Paths"))?;
             block_dict.set_item("is_path_info", paths.is_path_info)?;
             block_dict.set_item("count", paths.count)?;
             block_dict.set_item("next_paths_block_index", paths.next_paths_block_index)?;
@@ -227,7 +690,7 @@ fn append_block_entry<'py>(
             block_dict.set_item("paths", path_entries)?;
         }
         Ok(Ok(BomBlock::Tree(tree))) => {
-            block_dict.set_item("kind", "Tree")?;
+            block_dict.set_item("kind", intern!(py, "Tree"))?;
             block_dict.set_item("tree", String::from_utf8_lossy(&tree.tree).to_string())?;
             block_dict.set_item("version", tree.version)?;
             block_dict.set_item("block_paths_index", tree.block_paths_index)?;
@@ -236,11 +699,11 @@ fn append_block_entry<'py>(
             block_dict.set_item("a", tree.a)?;
         }
         Ok(Ok(BomBlock::TreePointer(pointer))) => {
-            block_dict.set_item("kind", "TreePointer")?;
+            block_dict.set_item("kind", intern!(py, "TreePointer"))?;
             block_dict.set_item("block_tree_index", pointer.block_tree_index)?;
         }
         Ok(Ok(BomBlock::VIndex(vindex))) => {
-            block_dict.set_item("kind", "VIndex")?;
+            block_dict.set_item("kind", intern!(py, "VIndex"))?;
             block_dict.set_item("a", vindex.a)?;
             block_dict.set_item("tree_block_index", vindex.tree_block_index)?;
             block_dict.set_item("b", vindex.b)?;
@@ -248,21 +711,27 @@ fn append_block_entry<'py>(
         }
     }
 
-    blocks_list.append(block_dict)?;
+    if options.interpretations {
+        interpret::add_interpretations(&block_dict)?;
+    }
 
-    Ok(())
+    Ok(block_dict)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn parse_optional_path_section<'py>(
     py: Python<'py>,
     doc: &Bound<'py, PyDict>,
     parse_errors: &Bound<'py, PyDict>,
     name: &str,
-    parser: impl FnOnce() -> Result<Vec<BomPath>, apple_bom::Error>,
+    style: &PathStyle,
+    extras: &PathExtras,
+    typed: bool,
+    section: SafeBomCall<Vec<BomPath>>,
 ) -> PyResult<()> {
-    match safe_bom_call(parser) {
+    match section {
         SafeBomCall::Value(paths) => {
-            doc.set_item(name, serialize_path_list(py, &paths)?)?;
+            doc.set_item(name, serialize_path_list(py, &paths, style, extras, typed)?)?;
         }
         SafeBomCall::MissingVariable => {
             doc.set_item(name, py.None())?;
@@ -280,10 +749,13 @@ fn parse_bom_document<'py>(
     py: Python<'py>,
     data: &[u8],
     source_path: Option<&str>,
-    include_blocks: bool,
-    include_raw_block_bytes: bool,
+    options: &ParseOptions,
 ) -> PyResult<Bound<'py, PyDict>> {
-    let bom = ParsedBom::parse(data).map_err(bom_error_to_py)?;
+    let style = &options.style()?;
+    let path_options = options.path_options()?;
+    let PathOptions { order, typed, .. } = path_options;
+    let on_panic = options.on_panic()?;
+    let mut bom = ParsedBom::parse(data).map_err(bom_error_to_py)?;
     let doc = PyDict::new_bound(py);
     let parse_errors = PyDict::new_bound(py);
 
@@ -293,19 +765,9 @@ fn parse_bom_document<'py>(
     if let Some(path) = source_path {
         doc.set_item("source_path", path)?;
     }
+    doc.set_item("path_order", order.name())?;
 
-    let header = PyDict::new_bound(py);
-    header.set_item(
-        "magic",
-        String::from_utf8_lossy(&bom.header.magic).to_string(),
-    )?;
-    header.set_item("version", bom.header.version)?;
-    header.set_item("number_of_blocks", bom.header.number_of_blocks)?;
-    header.set_item("blocks_index_offset", bom.header.blocks_index_offset)?;
-    header.set_item("blocks_index_length", bom.header.blocks_index_length)?;
-    header.set_item("vars_index_offset", bom.header.vars_index_offset)?;
-    header.set_item("vars_index_length", bom.header.vars_index_length)?;
-    doc.set_item("header", header)?;
+    doc.set_item("header", BomHeader::new(&bom).into_py(py))?;
 
     let blocks_index = PyDict::new_bound(py);
     blocks_index.set_item("count", bom.blocks.count)?;
@@ -320,35 +782,17 @@ fn parse_bom_document<'py>(
     blocks_index.set_item("entries", block_entries)?;
     doc.set_item("blocks_index", blocks_index)?;
 
-    let variables = PyList::empty_bound(py);
-    for var in &bom.vars.vars {
-        let item = PyDict::new_bound(py);
-        item.set_item("name", &var.name)?;
-        item.set_item("name_length", var.name_length)?;
-        item.set_item("block_index", var.block_index)?;
-        variables.append(item)?;
-    }
-    doc.set_item("variables", variables)?;
+    doc.set_item("variables", variables_list(py, &bom)?)?;
+    let duplicates = duplicates::resolve(&mut bom, options.on_duplicate_variable()?)?;
+    doc.set_item(
+        "duplicate_variables",
+        duplicates::duplicates_dict(py, &duplicates)?,
+    )?;
+    doc.set_item("gaps", gaps::gaps_list(py, &bom, options.raw_bytes()?)?)?;
 
-    match safe_bom_call(|| bom.bom_info()) {
+    match on_panic.call(|| bom.bom_info()) {
         SafeBomCall::Value(info) => {
-            let info_dict = PyDict::new_bound(py);
-            info_dict.set_item("version", info.version)?;
-            info_dict.set_item("number_of_paths", info.number_of_paths)?;
-            info_dict.set_item("number_of_info_entries", info.number_of_info_entries)?;
-
-            let entries = PyList::empty_bound(py);
-            for info_entry in &info.entries {
-                let item = PyDict::new_bound(py);
-                item.set_item("a", info_entry.a)?;
-                item.set_item("b", info_entry.b)?;
-                item.set_item("c", info_entry.c)?;
-                item.set_item("d", info_entry.d)?;
-                entries.append(item)?;
-            }
-            info_dict.set_item("entries", entries)?;
-
-            doc.set_item("bom_info", info_dict)?;
+            doc.set_item("bom_info", bom_info_dict(py, &info)?)?;
         }
         SafeBomCall::MissingVariable => {
             doc.set_item("bom_info", py.None())?;
@@ -359,17 +803,101 @@ fn parse_bom_document<'py>(
         }
     }
 
-    parse_optional_path_section(py, &doc, &parse_errors, "paths", || bom.paths())?;
-    parse_optional_path_section(py, &doc, &parse_errors, "hl_index", || bom.hl_index())?;
-    parse_optional_path_section(py, &doc, &parse_errors, "size64", || bom.size64())?;
-    parse_optional_path_section(py, &doc, &parse_errors, "vindex", || bom.vindex())?;
+    let sections = PATH_SECTIONS.map(|name| options.includes_section(name));
+    let [with_paths, with_hl_index, with_size64, with_vindex] = sections;
+
+    // Files over 4 GiB carry a truncated 32-bit size; the Size64 tree
+    // supplies the full value used for every section.
+    let (size64, sizes) = if sections.contains(&true) {
+        match on_panic.call(|| size64::resolve(&bom)) {
+            SafeBomCall::Value(section) => (SafeBomCall::Value(section.paths), section.sizes),
+            SafeBomCall::MissingVariable => (SafeBomCall::MissingVariable, HashMap::new()),
+            SafeBomCall::Error(err) => (SafeBomCall::Error(err), HashMap::new()),
+        }
+    } else {
+        (SafeBomCall::MissingVariable, HashMap::new())
+    };
+
+    // IDs and tree positions come from `Paths`, so it is read for any
+    // section resolved through it.
+    let paths = if with_paths || with_hl_index || with_size64 {
+        on_panic.call(|| bom.paths())
+    } else {
+        SafeBomCall::MissingVariable
+    };
+    let mut extras = PathExtras {
+        sizes,
+        ..Default::default()
+    };
+    // `VIndex` is its own tree, so only sections resolved through `Paths` get
+    // IDs and positions.
+    let vindex_extras = PathExtras {
+        sizes: extras.sizes.clone(),
+        ..Default::default()
+    };
+    if let SafeBomCall::Value(paths) = &paths {
+        extras.add_tree_entries(&bom, paths, &path_options);
+    }
+
+    let hl_index = if with_hl_index {
+        on_panic.call(|| hardlinks::resolve(&bom).map(|section| section.paths))
+    } else {
+        SafeBomCall::MissingVariable
+    };
+    let vindex = if with_vindex {
+        on_panic.call(|| bom.vindex())
+    } else {
+        SafeBomCall::MissingVariable
+    };
+    for ((name, section, extras), included) in [
+        ("paths", paths, &extras),
+        ("hl_index", hl_index, &extras),
+        ("size64", size64, &extras),
+        ("vindex", vindex, &vindex_extras),
+    ]
+    .into_iter()
+    .zip(sections)
+    {
+        if !included {
+            continue;
+        }
+        let section = match section {
+            SafeBomCall::Value(mut paths) => {
+                order.apply(&mut paths);
+                SafeBomCall::Value(paths)
+            }
+            other => other,
+        };
+        parse_optional_path_section(py, &doc, &parse_errors, name, style, extras, typed, section)?;
+    }
 
-    if include_blocks {
-        let blocks = PyList::empty_bound(py);
-        for index in 0..bom.blocks.blocks.len() {
-            append_block_entry(py, &bom, index, include_raw_block_bytes, &blocks)?;
+    if let Some(block_options) = options.blocks()? {
+        if options.lazy_blocks() {
+            let blocks = BlockSequence::new(bom.to_owned(), block_options);
+            doc.set_item("blocks", Bound::new(py, blocks)?)?;
+        } else {
+            let blocks = PyList::empty_bound(py);
+            let mut aborted = None;
+            for index in 0..bom.blocks.blocks.len() {
+                match block_entry(py, &bom, index, block_options) {
+                    Ok(entry) => blocks.append(entry)?,
+                    Err(err)
+                        if on_panic == PanicPolicy::AbortSection
+                            && err.is_instance_of::<BomParseError>(py) =>
+                    {
+                        aborted = Some(err.value_bound(py).to_string());
+                        break;
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            if let Some(err) = aborted {
+                doc.set_item("blocks", py.None())?;
+                parse_errors.set_item("blocks", err)?;
+            } else {
+                doc.set_item("blocks", blocks)?;
+            }
         }
-        doc.set_item("blocks", blocks)?;
     } else {
         doc.set_item("blocks", py.None())?;
     }
@@ -383,44 +911,230 @@ fn parse_bom_document<'py>(
     Ok(doc)
 }
 
-#[pyfunction(signature = (data, *, include_blocks = true, include_raw_block_bytes = false))]
+#[allow(clippy::too_many_arguments)]
+#[pyfunction(signature = (
+    data,
+    *,
+    options = None,
+    include_blocks = None,
+    sections = None,
+    include_raw_block_bytes = None,
+    raw_block_encoding = None,
+    path_style = None,
+    path_root = None,
+    path_order = None,
+    interpret_fields = None,
+    include_tree_positions = None,
+    include_raw_records = None,
+    typed_paths = None,
+    lazy_blocks = None,
+    on_panic = None,
+    on_duplicate_variable = None,
+))]
 fn parse_bom_bytes(
     py: Python<'_>,
-    data: &[u8],
-    include_blocks: bool,
-    include_raw_block_bytes: bool,
+    data: &Bound<'_, PyAny>,
+    options: Option<ParseOptions>,
+    include_blocks: Option<bool>,
+    sections: Option<Vec<String>>,
+    include_raw_block_bytes: Option<bool>,
+    raw_block_encoding: Option<String>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+    path_order: Option<String>,
+    interpret_fields: Option<bool>,
+    include_tree_positions: Option<bool>,
+    include_raw_records: Option<bool>,
+    typed_paths: Option<bool>,
+    lazy_blocks: Option<bool>,
+    on_panic: Option<String>,
+    on_duplicate_variable: Option<String>,
 ) -> PyResult<PyObject> {
-    let doc = parse_bom_document(py, data, None, include_blocks, include_raw_block_bytes)?;
+    let options = ParseOptions::resolve(
+        options,
+        OptionChanges {
+            include_blocks,
+            sections,
+            include_raw_block_bytes,
+            raw_block_encoding,
+            path_style,
+            path_root,
+            path_order,
+            interpret_fields,
+            include_tree_positions,
+            include_raw_records,
+            typed_paths,
+            lazy_blocks,
+            on_panic,
+            on_duplicate_variable,
+        },
+    )?;
+    let data = BomData::new(data, true)?;
+    let doc = parse_bom_document(py, &data, None, &options)?;
     Ok(doc.into_py(py))
 }
 
-#[pyfunction(signature = (path, *, include_blocks = true, include_raw_block_bytes = false))]
+#[allow(clippy::too_many_arguments)]
+#[pyfunction(signature = (
+    path,
+    *,
+    options = None,
+    include_blocks = None,
+    sections = None,
+    include_raw_block_bytes = None,
+    raw_block_encoding = None,
+    path_style = None,
+    path_root = None,
+    path_order = None,
+    interpret_fields = None,
+    include_tree_positions = None,
+    include_raw_records = None,
+    typed_paths = None,
+    lazy_blocks = None,
+    on_panic = None,
+    on_duplicate_variable = None,
+    mmap = false,
+))]
 fn parse_bom_file(
     py: Python<'_>,
-    path: &str,
-    include_blocks: bool,
-    include_raw_block_bytes: bool,
+    path: PathBuf,
+    options: Option<ParseOptions>,
+    include_blocks: Option<bool>,
+    sections: Option<Vec<String>>,
+    include_raw_block_bytes: Option<bool>,
+    raw_block_encoding: Option<String>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+    path_order: Option<String>,
+    interpret_fields: Option<bool>,
+    include_tree_positions: Option<bool>,
+    include_raw_records: Option<bool>,
+    typed_paths: Option<bool>,
+    lazy_blocks: Option<bool>,
+    on_panic: Option<String>,
+    on_duplicate_variable: Option<String>,
+    mmap: bool,
 ) -> PyResult<PyObject> {
-    let data = std::fs::read(path)
-        .map_err(|err| PyOSError::new_err(format!("failed reading {path}: {err}")))?;
-
-    let doc = parse_bom_document(
-        py,
-        &data,
-        Some(path),
-        include_blocks,
-        include_raw_block_bytes,
+    let options = ParseOptions::resolve(
+        options,
+        OptionChanges {
+            include_blocks,
+            sections,
+            include_raw_block_bytes,
+            raw_block_encoding,
+            path_style,
+            path_root,
+            path_order,
+            interpret_fields,
+            include_tree_positions,
+            include_raw_records,
+            typed_paths,
+            lazy_blocks,
+            on_panic,
+            on_duplicate_variable,
+        },
     )?;
+    let read_error =
+        |err: io::Error| PyOSError::new_err(format!("failed reading {}: {err}", path.display()));
+    let (mapped, read);
+    let data: &[u8] = if mmap {
+        let file = File::open(&path).map_err(read_error)?;
+        mapped = Mapping::new(&file).map_err(read_error)?;
+        &mapped
+    } else {
+        read = std::fs::read(&path).map_err(read_error)?;
+        &read
+    };
+
+    let doc = parse_bom_document(py, data, Some(&path.to_string_lossy()), &options)?;
 
     Ok(doc.into_py(py))
 }
 
+/// The header, block count, and variables of a BOM, as `parse_bom` reports
+/// them, without reading any block or path.
+#[pyfunction]
+fn parse_bom_header<'py>(py: Python<'py>, data: &Bound<'_, PyAny>) -> PyResult<Bound<'py, PyDict>> {
+    let data = BomData::new(data, true)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let doc = PyDict::new_bound(py);
+    doc.set_item("format", "apple-bom")?;
+    doc.set_item("byte_length", data.len())?;
+    doc.set_item("header", BomHeader::new(&bom).into_py(py))?;
+
+    let blocks_index = PyDict::new_bound(py);
+    blocks_index.set_item("count", bom.blocks.count)?;
+    doc.set_item("blocks_index", blocks_index)?;
+
+    doc.set_item("variables", variables_list(py, &bom)?)?;
+    Ok(doc)
+}
+
 #[pymodule]
 fn _native(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
     m.add("BomParseError", m.py().get_type_bound::<BomParseError>())?;
+    m.add(
+        "FeatureNotAvailable",
+        m.py().get_type_bound::<FeatureNotAvailable>(),
+    )?;
     m.add_function(wrap_pyfunction!(parse_bom_bytes, m)?)?;
     m.add_function(wrap_pyfunction!(parse_bom_file, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_bom_header, m)?)?;
+    m.add_function(wrap_pyfunction!(access::effective_access, m)?)?;
+    m.add_function(wrap_pyfunction!(audit::capabilities, m)?)?;
+    m.add_function(wrap_pyfunction!(audit::known_file_deviations, m)?)?;
+    m.add_function(wrap_pyfunction!(audit::risk_score, m)?)?;
+    m.add_function(wrap_pyfunction!(audit::shell_footprint, m)?)?;
+    m.add_function(wrap_pyfunction!(bloom::bloom_contains, m)?)?;
+    m.add_function(wrap_pyfunction!(bloom::path_bloom, m)?)?;
+    m.add_function(wrap_pyfunction!(buildinfo::build_info, m)?)?;
+    m.add_function(wrap_pyfunction!(features::compare_with_bundle, m)?)?;
+    m.add_function(wrap_pyfunction!(corpus::block_type_histogram, m)?)?;
+    m.add_function(wrap_pyfunction!(corpus::quarantine_boms, m)?)?;
+    m.add_function(wrap_pyfunction!(features::apply_delta, m)?)?;
+    m.add_function(wrap_pyfunction!(features::make_delta, m)?)?;
+    m.add_function(wrap_pyfunction!(diff::diff_boms, m)?)?;
+    m.add_function(wrap_pyfunction!(diff::diff3_boms, m)?)?;
+    m.add_function(wrap_pyfunction!(diff::summarize_diff, m)?)?;
+    m.add_function(wrap_pyfunction!(editor::add_block, m)?)?;
+    m.add_function(wrap_pyfunction!(editor::compact, m)?)?;
+    m.add_function(wrap_pyfunction!(editor::remove_variable, m)?)?;
+    m.add_function(wrap_pyfunction!(editor::rename_variable, m)?)?;
+    m.add_function(wrap_pyfunction!(editor::rewrite_indexes, m)?)?;
+    m.add_function(wrap_pyfunction!(footprint::memory_footprint, m)?)?;
+    m.add_function(wrap_pyfunction!(hashset::export_hash_set, m)?)?;
+    m.add_function(wrap_pyfunction!(json::to_json, m)?)?;
+    m.add_function(wrap_pyfunction!(pathiter::iter_paths, m)?)?;
+    m.add_function(wrap_pyfunction!(layout::block_layout, m)?)?;
+    m.add_function(wrap_pyfunction!(lookup::ancestors, m)?)?;
+    m.add_function(wrap_pyfunction!(lookup::get_block, m)?)?;
+    m.add_function(wrap_pyfunction!(lookup::get_path, m)?)?;
+    m.add_function(wrap_pyfunction!(lookup::path_by_id, m)?)?;
+    m.add_function(wrap_pyfunction!(lsbom::format_lsbom, m)?)?;
+    m.add_function(wrap_pyfunction!(mtimes::mtime_profile, m)?)?;
+    m.add_function(wrap_pyfunction!(mtree::export_mtree, m)?)?;
+    m.add_function(wrap_pyfunction!(options::get_default_options, m)?)?;
+    m.add_function(wrap_pyfunction!(options::set_default_options, m)?)?;
+    m.add_function(wrap_pyfunction!(pathtypes::register_path_type, m)?)?;
+    m.add_function(wrap_pyfunction!(pathtypes::registered_path_types, m)?)?;
+    m.add_function(wrap_pyfunction!(pathtypes::unregister_path_type, m)?)?;
+    m.add_function(wrap_pyfunction!(sizes::size_report, m)?)?;
+    m.add_function(wrap_pyfunction!(tree::paths_tree, m)?)?;
+    m.add_function(wrap_pyfunction!(unified::unified_paths, m)?)?;
+    m.add_function(wrap_pyfunction!(verify::verify_bom, m)?)?;
+    m.add_class::<blocks::BlockSequence>()?;
+    m.add_class::<bomfile::BomFile>()?;
+    m.add_class::<corpus::CorpusIndex>()?;
+    m.add_class::<entry::PathEntry>()?;
+    m.add_class::<header::BomHeader>()?;
+    m.add_class::<kinds::BlockKind>()?;
+    m.add_class::<kinds::PathType>()?;
+    m.add_class::<known::KnownFiles>()?;
+    m.add_class::<options::ParseOptions>()?;
+    m.add_class::<pathiter::PathIterator>()?;
+    m.add_class::<store::BomStore>()?;
+    m.add_class::<writer::BomBuilder>()?;
 
     Ok(())
 }
@@ -0,0 +1,415 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    checked_block_entry,
+    entry::PathEntry,
+    options::{OptionChanges, ParseOptions},
+    path_to_dict,
+    paths::PathStyle,
+    safe_bom_call, size64, BlockOptions, BomParseError, PanicPolicy, SafeBomCall,
+};
+use apple_bom::{format::ParsedBom, BomPath};
+use pyo3::{
+    exceptions::PyKeyError,
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use std::collections::HashMap;
+
+/// Where a path sits in the `Paths` tree, and the IDs that link it to its
+/// parent.
+pub(crate) struct PathLeaf {
+    pub path_id: u32,
+
+    /// Path ID of the parent directory, or 0 for the root.
+    pub parent_path_id: u32,
+
+    /// Index of the path info block.
+    pub block_index: u32,
+
+    /// Index of the `File` block naming the path.
+    pub file_index: u32,
+
+    /// Index of the path's `PathRecord` block.
+    pub path_record_index: u32,
+}
+
+/// Collect the leaf entries of the `Paths` tree, in the order `bom.paths()`
+/// returns paths.
+pub(crate) fn path_leaves(bom: &ParsedBom<'_>) -> Result<Vec<PathLeaf>, apple_bom::Error> {
+    size64::leaf_entries(bom, "Paths")?
+        .into_iter()
+        .map(|entry| {
+            Ok(PathLeaf {
+                path_id: entry.path_id,
+                parent_path_id: bom.block_as_file(entry.file_index as _)?.parent_path_id,
+                block_index: entry.block_index,
+                file_index: entry.file_index,
+                path_record_index: entry.path_record_index,
+            })
+        })
+        .collect()
+}
+
+/// A path found by [find_path], with where it sits in the `Paths` tree.
+pub(crate) struct FoundPath {
+    pub path: BomPath,
+    pub leaf: PathLeaf,
+
+    /// The full size, if the path is listed in `Size64` and its 64-bit size
+    /// agrees with the record.
+    pub size64: Option<u64>,
+}
+
+/// Find the raw path `path` by matching the names in `File` blocks against
+/// its components, so only that path's record is resolved rather than every
+/// path in the BOM.
+pub(crate) fn find_path(
+    bom: &ParsedBom<'_>,
+    path: &str,
+) -> Result<Option<FoundPath>, apple_bom::Error> {
+    let components: Vec<&str> = path.split('/').collect();
+    let leaves = size64::leaf_entries(bom, "Paths")?;
+
+    // The `(parent path ID, leaf)` of every leaf named like each component.
+    let mut candidates = vec![Vec::new(); components.len()];
+    for (position, leaf) in leaves.iter().enumerate() {
+        let file = bom.block_as_file(leaf.file_index as _)?;
+        let name = file.name.to_string_lossy();
+        for (depth, component) in components.iter().enumerate() {
+            if name == *component {
+                candidates[depth].push((file.parent_path_id, position));
+            }
+        }
+    }
+
+    let (mut parent_path_id, mut found) = (0, None);
+    for level in candidates {
+        let Some((_, position)) = level
+            .into_iter()
+            .find(|(parent, _)| *parent == parent_path_id)
+        else {
+            return Ok(None);
+        };
+        found = Some((position, parent_path_id));
+        parent_path_id = leaves[position].path_id;
+    }
+    let Some((position, parent_path_id)) = found else {
+        return Ok(None);
+    };
+
+    let leaf = &leaves[position];
+    let record = bom.block_as_path_record(leaf.path_record_index as _)?;
+    let path = BomPath::from_record(path.to_string(), &record)?;
+    let large = size64::leaf_entries(bom, "Size64").unwrap_or_default();
+    let size64 = if large
+        .iter()
+        .any(|entry| entry.block_index == leaf.block_index)
+    {
+        size64::record_size64(bom.block_data(leaf.path_record_index as _)?)
+            .filter(|size| *size as u32 as usize == path.size())
+    } else {
+        None
+    };
+
+    Ok(Some(FoundPath {
+        path,
+        leaf: PathLeaf {
+            path_id: leaf.path_id,
+            parent_path_id,
+            block_index: leaf.block_index,
+            file_index: leaf.file_index,
+            path_record_index: leaf.path_record_index,
+        },
+        size64,
+    }))
+}
+
+/// The entry for `path`, given in `style`, as `parse_bom` emits it, or `None`
+/// if the BOM has no such path.
+pub(crate) fn path_entry<'py>(
+    py: Python<'py>,
+    bom: &ParsedBom<'_>,
+    path: &str,
+    style: &PathStyle,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let Some(raw) = style.raw_path(path) else {
+        return Ok(None);
+    };
+    let found = match safe_bom_call(|| find_path(bom, &raw)) {
+        SafeBomCall::Value(found) => found,
+        SafeBomCall::MissingVariable => {
+            return Err(BomParseError::new_err("BOM has no Paths variable"))
+        }
+        SafeBomCall::Error(err) => return Err(BomParseError::new_err(err)),
+    };
+    let Some(found) = found else {
+        return Ok(None);
+    };
+
+    let sizes = found
+        .size64
+        .map(|size| (raw, size))
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    let mut entry = PathEntry::new(&found.path, style, &sizes);
+    entry.path_id = Some(found.leaf.path_id);
+    entry.parent_path_id = Some(found.leaf.parent_path_id);
+    entry.to_dict(py).map(Some)
+}
+
+/// The paths of a BOM, addressable by path ID.
+pub(crate) struct PathIndex {
+    paths: Vec<BomPath>,
+    leaves: Vec<PathLeaf>,
+    by_id: HashMap<u32, usize>,
+
+    /// Full sizes of paths listed in `Size64`.
+    sizes: HashMap<String, u64>,
+}
+
+impl PathIndex {
+    pub(crate) fn parse(data: &[u8]) -> PyResult<Self> {
+        Self::new(&ParsedBom::parse(data).map_err(bom_error_to_py)?)
+    }
+
+    pub(crate) fn new(bom: &ParsedBom<'_>) -> PyResult<Self> {
+        let index = safe_bom_call(|| {
+            let paths = bom.paths()?;
+            let leaves = path_leaves(bom)?;
+            if leaves.len() != paths.len() {
+                return Err(apple_bom::Error::BadIndex);
+            }
+            Ok((paths, leaves))
+        });
+        let (paths, leaves) = match index {
+            SafeBomCall::Value(index) => index,
+            SafeBomCall::MissingVariable => {
+                return Err(BomParseError::new_err("BOM has no Paths variable"))
+            }
+            SafeBomCall::Error(err) => return Err(BomParseError::new_err(err)),
+        };
+        let sizes = match safe_bom_call(|| size64::resolve(bom)) {
+            SafeBomCall::Value(section) => section.sizes,
+            _ => HashMap::new(),
+        };
+
+        let by_id = leaves
+            .iter()
+            .enumerate()
+            .map(|(position, leaf)| (leaf.path_id, position))
+            .collect();
+        Ok(Self {
+            paths,
+            leaves,
+            by_id,
+            sizes,
+        })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub(crate) fn path(&self, position: usize) -> &BomPath {
+        &self.paths[position]
+    }
+
+    pub(crate) fn leaf(&self, position: usize) -> &PathLeaf {
+        &self.leaves[position]
+    }
+
+    /// The position of the path with ID `path_id`.
+    pub(crate) fn find(&self, path_id: u32) -> PyResult<usize> {
+        self.by_id
+            .get(&path_id)
+            .copied()
+            .ok_or_else(|| PyKeyError::new_err(format!("no path with ID {path_id}")))
+    }
+
+    /// The position of the path `path`, given in `style`.
+    pub(crate) fn find_path(&self, path: &str, style: &PathStyle) -> PyResult<usize> {
+        self.paths
+            .iter()
+            .position(|candidate| style.render(candidate.path()) == path)
+            .ok_or_else(|| PyKeyError::new_err(format!("no path {path:?}")))
+    }
+
+    /// The positions of the directories containing the path at `position`,
+    /// from the root down to its parent.
+    pub(crate) fn ancestors(&self, position: usize) -> PyResult<Vec<usize>> {
+        let mut chain = Vec::new();
+        let mut current = position;
+        while self.leaves[current].parent_path_id != 0 {
+            let parent_id = self.leaves[current].parent_path_id;
+            current = self.by_id.get(&parent_id).copied().ok_or_else(|| {
+                BomParseError::new_err(format!(
+                    "parent path ID {parent_id} of {:?} is not in the Paths tree",
+                    self.paths[current].path()
+                ))
+            })?;
+            if current == position || chain.contains(&current) {
+                return Err(BomParseError::new_err(format!(
+                    "parent chain of {:?} loops",
+                    self.paths[position].path()
+                )));
+            }
+            chain.push(current);
+        }
+
+        chain.reverse();
+        Ok(chain)
+    }
+
+    /// The path entry at `position`, as `parse_bom` emits it.
+    pub(crate) fn entry<'py>(
+        &self,
+        py: Python<'py>,
+        position: usize,
+        style: &PathStyle,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let item = path_to_dict(py, &self.paths[position], style, &self.sizes)?;
+        item.set_item("path_id", self.leaves[position].path_id)?;
+        item.set_item("parent_path_id", self.leaves[position].parent_path_id)?;
+        Ok(item)
+    }
+}
+
+/// A path given either by its path ID or by its path.
+#[derive(FromPyObject)]
+pub(crate) enum PathOrId {
+    Id(u32),
+    Path(String),
+}
+
+impl PathOrId {
+    fn find(&self, index: &PathIndex, style: &PathStyle) -> PyResult<usize> {
+        match self {
+            Self::Id(path_id) => index.find(*path_id),
+            Self::Path(path) => index.find_path(path, style),
+        }
+    }
+}
+
+/// The path style of `options`, or of the defaults with `path_style` and
+/// `path_root` applied, for the lookups that only render paths.
+pub(crate) fn lookup_style(
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+) -> PyResult<PathStyle> {
+    ParseOptions::resolve(
+        options,
+        OptionChanges::for_paths(path_style, path_root, None, None, None, None),
+    )?
+    .style()
+}
+
+/// Look up a path entry by its path ID.
+#[pyfunction(signature = (
+    data,
+    path_id,
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+))]
+pub(crate) fn path_by_id(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    path_id: u32,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+) -> PyResult<PyObject> {
+    let data = BomData::new(data, true)?;
+    let style = lookup_style(options, path_style, path_root)?;
+    let index = PathIndex::parse(&data)?;
+    let position = index.find(path_id)?;
+    Ok(index.entry(py, position, &style)?.into_py(py))
+}
+
+/// Look up a path entry by its exact path, without resolving every path.
+#[pyfunction(signature = (data, path, *, options = None, path_style = None, path_root = None))]
+pub(crate) fn get_path(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    path: &str,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+) -> PyResult<PyObject> {
+    let data = BomData::new(data, true)?;
+    let style = lookup_style(options, path_style, path_root)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    Ok(path_entry(py, &bom, path, &style)?.into_py(py))
+}
+
+/// Parse the single block at `index`, as `parse_bom` lists it under `blocks`,
+/// without parsing any other block.
+#[pyfunction(signature = (
+    data,
+    index,
+    *,
+    options = None,
+    raw = None,
+    raw_block_encoding = None,
+    interpret_fields = None,
+))]
+pub(crate) fn get_block<'py>(
+    py: Python<'py>,
+    data: &Bound<'_, PyAny>,
+    index: usize,
+    options: Option<ParseOptions>,
+    raw: Option<bool>,
+    raw_block_encoding: Option<String>,
+    interpret_fields: Option<bool>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let data = BomData::new(data, true)?;
+    let options = ParseOptions::resolve(
+        options,
+        OptionChanges {
+            include_raw_block_bytes: raw,
+            raw_block_encoding,
+            interpret_fields,
+            ..OptionChanges::default()
+        },
+    )?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let options = BlockOptions {
+        raw_bytes: options.raw_bytes()?,
+        interpretations: options.interpret_fields(),
+        on_panic: PanicPolicy::Wrap,
+    };
+    checked_block_entry(py, &bom, index, options)
+}
+
+/// The chain of directory entries containing a path, from the root down to
+/// its parent, resolved through parent path IDs.
+#[pyfunction(signature = (
+    data,
+    path_or_id,
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+))]
+pub(crate) fn ancestors(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    path_or_id: PathOrId,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+) -> PyResult<PyObject> {
+    let data = BomData::new(data, true)?;
+    let style = lookup_style(options, path_style, path_root)?;
+    let index = PathIndex::parse(&data)?;
+    let position = path_or_id.find(&index, &style)?;
+
+    let chain = PyList::empty_bound(py);
+    for ancestor in index.ancestors(position)? {
+        chain.append(index.entry(py, ancestor, &style)?)?;
+    }
+    Ok(chain.into_py(py))
+}
@@ -0,0 +1,135 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    modes::{self, S_IFBLK, S_IFCHR, S_IFMT},
+    options::{OptionChanges, ParseOptions},
+    required_paths, safe_bom_call, size64, SafeBomCall,
+};
+use apple_bom::{format::ParsedBom, BomPath, BomPathType};
+use pyo3::{exceptions::PyValueError, prelude::*};
+use std::collections::HashMap;
+
+/// The `lsbom` type flags `include` takes.
+const KINDS: [&str; 5] = ["f", "d", "l", "b", "c"];
+
+/// The `lsbom -p` field characters `columns` takes.
+const FIELDS: &str = "fFmMug/sct";
+
+/// The `lsbom` type flag of a path: `f`, `d`, or `l` by its path type, and
+/// `b` or `c` for devices by the format bits of their mode.
+fn kind(path: &BomPath) -> Option<&'static str> {
+    match path.path_type() {
+        BomPathType::File => Some("f"),
+        BomPathType::Directory => Some("d"),
+        BomPathType::Link => Some("l"),
+        BomPathType::Dev => match path.file_mode() & S_IFMT {
+            S_IFBLK => Some("b"),
+            S_IFCHR => Some("c"),
+            _ => None,
+        },
+        BomPathType::Other(_) => None,
+    }
+}
+
+/// One `lsbom -p` field of `path`.
+fn field(field: char, path: &BomPath, name: &str, size: u64) -> String {
+    match field {
+        'f' => name.to_string(),
+        'F' => format!("\"{name}\""),
+        'm' => format!("{:o}", path.file_mode()),
+        'M' => modes::symbolic_mode(path.path_type(), path.file_mode()),
+        'u' => path.user_id().to_string(),
+        'g' => path.group_id().to_string(),
+        '/' => format!("{}/{}", path.user_id(), path.group_id()),
+        's' => size.to_string(),
+        'c' => path.crc32().unwrap_or(0).to_string(),
+        't' => path.modified_time().timestamp().to_string(),
+        _ => unreachable!("columns are checked against FIELDS"),
+    }
+}
+
+/// The fields `lsbom` prints for `path` without `-p`: the name, mode, and
+/// owner, then the size and checksum of files and links, and the target of
+/// links.
+fn default_fields(path: &BomPath) -> &'static str {
+    match path.path_type() {
+        BomPathType::File | BomPathType::Link => "fm/sc",
+        _ => "fm/",
+    }
+}
+
+/// List a BOM as `lsbom` does, a tab-separated line per path.
+#[pyfunction(signature = (
+    data,
+    *,
+    include = vec!["f".to_string(), "d".to_string(), "l".to_string()],
+    columns = None,
+    options = None,
+    path_style = None,
+    path_root = None,
+    path_order = None,
+))]
+pub(crate) fn format_lsbom(
+    data: &Bound<'_, PyAny>,
+    include: Vec<String>,
+    columns: Option<String>,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+    path_order: Option<String>,
+) -> PyResult<String> {
+    let data = BomData::new(data, true)?;
+    if let Some(flag) = include.iter().find(|flag| !KINDS.contains(&flag.as_str())) {
+        return Err(PyValueError::new_err(format!(
+            "include must hold 'f', 'd', 'l', 'b', or 'c', not {flag:?}"
+        )));
+    }
+    if let Some(c) = columns
+        .iter()
+        .flat_map(|columns| columns.chars())
+        .find(|c| !FIELDS.contains(*c))
+    {
+        return Err(PyValueError::new_err(format!(
+            "columns must use lsbom's 'f', 'F', 'm', 'M', 'u', 'g', '/', 's', 'c', or 't' \
+             fields, not {c:?}"
+        )));
+    }
+    let options = ParseOptions::resolve(
+        options,
+        OptionChanges::for_paths(path_style, path_root, path_order, None, None, None),
+    )?;
+    let style = options.style()?;
+    let order = options.path_options()?.order;
+
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let mut paths = required_paths(&bom)?;
+    let sizes = match safe_bom_call(|| size64::resolve(&bom)) {
+        SafeBomCall::Value(section) => section.sizes,
+        SafeBomCall::MissingVariable | SafeBomCall::Error(_) => HashMap::new(),
+    };
+    order.apply(&mut paths);
+
+    let mut out = String::new();
+    for path in &paths {
+        if !kind(path).is_some_and(|kind| include.iter().any(|flag| flag == kind)) {
+            continue;
+        }
+        let name = style.render(path.path());
+        let size = sizes
+            .get(path.path())
+            .copied()
+            .unwrap_or(path.size() as u64);
+        let fields = columns.as_deref().unwrap_or_else(|| default_fields(path));
+        let mut line: Vec<String> = fields
+            .chars()
+            .map(|c| field(c, path, &name, size))
+            .collect();
+        if columns.is_none() && matches!(path.path_type(), BomPathType::Link) {
+            line.extend(path.link_name().map(str::to_string));
+        }
+        out.push_str(&line.join("\t"));
+        out.push('\n');
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,136 @@
+use crate::cksum::ChecksumMode;
+use std::{fs::File, io, io::Read, path::Path};
+
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const FAT_MAGIC_64: u32 = 0xcafe_babf;
+const MH_MAGIC: u32 = 0xfeed_face;
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+const MH_CIGAM: u32 = 0xcefa_edfe;
+const MH_CIGAM_64: u32 = 0xcffa_edfe;
+
+/// Java class files share the fat magic; their version field reads as an
+/// architecture count far above anything a real universal binary carries.
+const MAX_FAT_ARCHITECTURES: u32 = 30;
+
+/// Bytes read from the start of a file to identify it.
+const HEADER_LENGTH: usize = 4096;
+
+/// One architecture slice of a Mach-O file. Thin binaries have a single
+/// slice covering the whole file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct MachOSlice {
+    pub cpu_type: u32,
+    pub cpu_subtype: u32,
+    pub offset: u64,
+    pub size: u64,
+
+    /// Checksum of the slice's bytes, filled in by [file_slices]; zero when
+    /// checksums are skipped.
+    pub checksum: u32,
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn be_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+/// Identify the architecture slices of a Mach-O file from its leading bytes.
+///
+/// Returns an empty list for anything that isn't a Mach-O or universal binary,
+/// including fat headers whose slices don't fit in `file_length`.
+pub(crate) fn parse_slices(header: &[u8], file_length: u64) -> Vec<MachOSlice> {
+    let Some(magic) = be_u32(header, 0) else {
+        return Vec::new();
+    };
+
+    let slices = match magic {
+        FAT_MAGIC | FAT_MAGIC_64 => fat_slices(header, magic == FAT_MAGIC_64),
+        MH_MAGIC | MH_MAGIC_64 => thin_slice(header, file_length, u32::from_be_bytes),
+        MH_CIGAM | MH_CIGAM_64 => thin_slice(header, file_length, u32::from_le_bytes),
+        _ => None,
+    };
+
+    slices
+        .filter(|slices| {
+            slices
+                .iter()
+                .all(|slice| slice.offset.saturating_add(slice.size) <= file_length)
+        })
+        .unwrap_or_default()
+}
+
+fn thin_slice(
+    header: &[u8],
+    file_length: u64,
+    read: fn([u8; 4]) -> u32,
+) -> Option<Vec<MachOSlice>> {
+    let field = |offset: usize| Some(read(header.get(offset..offset + 4)?.try_into().ok()?));
+
+    Some(vec![MachOSlice {
+        cpu_type: field(4)?,
+        cpu_subtype: field(8)?,
+        offset: 0,
+        size: file_length,
+        checksum: 0,
+    }])
+}
+
+fn fat_slices(header: &[u8], is_64: bool) -> Option<Vec<MachOSlice>> {
+    let count = be_u32(header, 4)?;
+    if count == 0 || count > MAX_FAT_ARCHITECTURES {
+        return None;
+    }
+
+    let entry_length = if is_64 { 32 } else { 20 };
+    (0..count as usize)
+        .map(|index| {
+            let entry = 8 + index * entry_length;
+            let (offset, size) = if is_64 {
+                (be_u64(header, entry + 8)?, be_u64(header, entry + 16)?)
+            } else {
+                (
+                    be_u32(header, entry + 8)? as u64,
+                    be_u32(header, entry + 12)? as u64,
+                )
+            };
+
+            Some(MachOSlice {
+                cpu_type: be_u32(header, entry)?,
+                cpu_subtype: be_u32(header, entry + 4)?,
+                offset,
+                size,
+                checksum: 0,
+            })
+        })
+        .collect()
+}
+
+/// Read the architecture slices of the file at `path`, with their checksums;
+/// see [parse_slices].
+pub(crate) fn file_slices(
+    path: &Path,
+    file_length: u64,
+    checksum: ChecksumMode,
+    buffer_size: usize,
+) -> io::Result<Vec<MachOSlice>> {
+    let mut header = Vec::with_capacity(HEADER_LENGTH);
+    File::open(path)?
+        .take(HEADER_LENGTH as u64)
+        .read_to_end(&mut header)?;
+
+    let mut slices = parse_slices(&header, file_length);
+    for slice in &mut slices {
+        slice.checksum = checksum
+            .range(path, slice.offset, slice.size, buffer_size)?
+            .unwrap_or(0);
+    }
+
+    Ok(slices)
+}
@@ -0,0 +1,51 @@
+use std::{fs::File, io, ops::Deref};
+
+/// Whether [Mapping] maps files rather than reading them in.
+pub(crate) const MAPS_FILES: bool = cfg!(any(unix, windows));
+
+/// A file's contents, mapped into memory read-only where the platform
+/// allows, so a large BOM can be parsed with only the pages the parser
+/// touches made resident. Elsewhere the file is read in.
+///
+/// The mapping shares the file's pages rather than copying them, so it is
+/// only as stable as the file. If another process truncates the file while
+/// it is mapped, reading a page past the new end raises `SIGBUS` on Unix,
+/// which kills the process rather than raising an error; Windows refuses to
+/// truncate a mapped file instead. Writes to the file show through too, so
+/// a BOM rewritten in place mid-parse can read as corrupt.
+pub(crate) enum Mapping {
+    #[cfg(any(unix, windows))]
+    Mapped(memmap2::Mmap),
+    #[cfg(not(any(unix, windows)))]
+    Read(Vec<u8>),
+}
+
+impl Mapping {
+    #[cfg(any(unix, windows))]
+    pub(crate) fn new(file: &File) -> io::Result<Self> {
+        // SAFETY: The map is read-only and lives no longer than `Self`. It
+        // still aliases memory other processes can change or truncate, the
+        // hazard documented on [Mapping], which the callers accept.
+        unsafe { memmap2::Mmap::map(file) }.map(Self::Mapped)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub(crate) fn new(mut file: &File) -> io::Result<Self> {
+        let mut data = Vec::new();
+        io::Read::read_to_end(&mut file, &mut data)?;
+        Ok(Self::Read(data))
+    }
+}
+
+impl Deref for Mapping {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(any(unix, windows))]
+            Self::Mapped(map) => map,
+            #[cfg(not(any(unix, windows)))]
+            Self::Read(data) => data,
+        }
+    }
+}
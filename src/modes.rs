@@ -0,0 +1,66 @@
+use apple_bom::BomPathType;
+
+pub(crate) const S_IFMT: u16 = 0o170000;
+pub(crate) const S_IFIFO: u16 = 0o010000;
+pub(crate) const S_IFCHR: u16 = 0o020000;
+pub(crate) const S_IFDIR: u16 = 0o040000;
+pub(crate) const S_IFBLK: u16 = 0o060000;
+pub(crate) const S_IFREG: u16 = 0o100000;
+pub(crate) const S_IFLNK: u16 = 0o120000;
+pub(crate) const S_IFSOCK: u16 = 0o140000;
+
+const S_ISUID: u16 = 0o4000;
+const S_ISGID: u16 = 0o2000;
+const S_ISVTX: u16 = 0o1000;
+
+/// The `ls -l` character for a path's type. Files, directories, and links
+/// go by the record's path type; devices and other types by the format bits
+/// of their mode, or `?` when those don't name one.
+fn type_char(path_type: BomPathType, file_mode: u16) -> char {
+    match path_type {
+        BomPathType::File => '-',
+        BomPathType::Directory => 'd',
+        BomPathType::Link => 'l',
+        BomPathType::Dev | BomPathType::Other(_) => match file_mode & S_IFMT {
+            S_IFIFO => 'p',
+            S_IFCHR => 'c',
+            S_IFDIR => 'd',
+            S_IFBLK => 'b',
+            S_IFREG => '-',
+            S_IFLNK => 'l',
+            S_IFSOCK => 's',
+            _ => '?',
+        },
+    }
+}
+
+/// The read, write, and execute characters for one class of permissions,
+/// given its three bits and whether its special bit (setuid, setgid, or
+/// sticky) is set, which shows as `special` over an execute bit and as its
+/// uppercase form without one.
+fn triad(bits: u16, special: bool, special_char: char) -> [char; 3] {
+    let execute = match (bits & 0o1 != 0, special) {
+        (true, true) => special_char,
+        (false, true) => special_char.to_ascii_uppercase(),
+        (true, false) => 'x',
+        (false, false) => '-',
+    };
+    [
+        if bits & 0o4 != 0 { 'r' } else { '-' },
+        if bits & 0o2 != 0 { 'w' } else { '-' },
+        execute,
+    ]
+}
+
+/// A path's mode as `ls -l` writes it, such as `drwxr-xr-x` or `-rwsr-xr-x`.
+///
+/// This is spelled out here rather than taken from the platform, so the
+/// same record gives the same string everywhere.
+pub(crate) fn symbolic_mode(path_type: BomPathType, file_mode: u16) -> String {
+    let mut mode = String::with_capacity(10);
+    mode.push(type_char(path_type, file_mode));
+    mode.extend(triad(file_mode >> 6, file_mode & S_ISUID != 0, 's'));
+    mode.extend(triad(file_mode >> 3, file_mode & S_ISGID != 0, 's'));
+    mode.extend(triad(file_mode, file_mode & S_ISVTX != 0, 't'));
+    mode
+}
@@ -0,0 +1,140 @@
+use crate::{
+    bom_error_to_py, buffer::BomData, lookup::lookup_style, options::ParseOptions,
+    paths::PathStyle, required_paths, summary,
+};
+use apple_bom::{format::ParsedBom, BomPath};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use std::collections::BTreeMap;
+
+/// A path's modification time, in seconds since the epoch and as written in
+/// its entry's `mtime_iso8601`.
+fn stamp<'py>(py: Python<'py>, path: &BomPath, style: &PathStyle) -> PyResult<Bound<'py, PyDict>> {
+    let item = PyDict::new_bound(py);
+    item.set_item("path", style.render(path.path()))?;
+    item.set_item("mtime", path.modified_time().timestamp())?;
+    item.set_item("mtime_iso8601", path.modified_time().to_rfc3339())?;
+    Ok(item)
+}
+
+/// The shortest span of the sorted `mtimes` holding `covered` of them, as
+/// positions of its first and last.
+fn densest_span(mtimes: &[i64], covered: usize) -> (usize, usize) {
+    (0..=mtimes.len() - covered)
+        .map(|first| (first, first + covered - 1))
+        .min_by_key(|&(first, last)| mtimes[last] - mtimes[first])
+        .unwrap_or((0, mtimes.len() - 1))
+}
+
+/// The distribution of a BOM's modification times, the window the build most
+/// likely stamped them in, and the entries far outside it, which point to
+/// files added or replaced after the fact.
+#[pyfunction(signature = (
+    data,
+    *,
+    coverage = 0.9,
+    tolerance = 86400,
+    options = None,
+    path_style = None,
+    path_root = None,
+))]
+pub(crate) fn mtime_profile<'py>(
+    py: Python<'py>,
+    data: &Bound<'_, PyAny>,
+    coverage: f64,
+    tolerance: i64,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let data = BomData::new(data, true)?;
+    if !(coverage > 0.0 && coverage <= 1.0) {
+        return Err(PyValueError::new_err(format!(
+            "coverage must be between 0 and 1, not {coverage}"
+        )));
+    }
+    if tolerance < 0 {
+        return Err(PyValueError::new_err("tolerance must not be negative"));
+    }
+    let style = lookup_style(options, path_style, path_root)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let paths = required_paths(&bom)?;
+
+    // A zero mtime means the writer didn't record one.
+    let mut dated: Vec<&BomPath> = paths
+        .iter()
+        .filter(|path| path.modified_time().timestamp() != 0)
+        .collect();
+    dated.sort_by_key(|path| path.modified_time().timestamp());
+    let mtimes: Vec<i64> = dated
+        .iter()
+        .map(|path| path.modified_time().timestamp())
+        .collect();
+
+    let mut by_day = BTreeMap::new();
+    for path in &dated {
+        let day = path.modified_time().format("%Y-%m-%d").to_string();
+        *by_day.entry(day).or_insert(0usize) += 1;
+    }
+
+    let report = PyDict::new_bound(py);
+    report.set_item("count", paths.len())?;
+    report.set_item("unset", paths.len() - dated.len())?;
+    let earliest = dated.first().map(|path| stamp(py, path, &style));
+    report.set_item("earliest", earliest.transpose()?)?;
+    let latest = dated.last().map(|path| stamp(py, path, &style));
+    report.set_item("latest", latest.transpose()?)?;
+    report.set_item("median", mtimes.get(mtimes.len() / 2))?;
+    report.set_item("by_day", by_day)?;
+
+    let outliers = PyList::empty_bound(py);
+    let (mut before, mut after) = (0, 0);
+    if dated.is_empty() {
+        report.set_item("build_window", py.None())?;
+    } else {
+        let covered = ((coverage * dated.len() as f64).ceil() as usize).clamp(1, dated.len());
+        let (first, last) = densest_span(&mtimes, covered);
+        let (start, end) = (mtimes[first], mtimes[last]);
+        let window = PyDict::new_bound(py);
+        window.set_item("start", start)?;
+        window.set_item("end", end)?;
+        window.set_item("start_iso8601", dated[first].modified_time().to_rfc3339())?;
+        window.set_item("end_iso8601", dated[last].modified_time().to_rfc3339())?;
+        window.set_item(
+            "count",
+            mtimes
+                .iter()
+                .filter(|&&mtime| (start..=end).contains(&mtime))
+                .count(),
+        )?;
+        report.set_item("build_window", window)?;
+
+        for path in &paths {
+            let mtime = path.modified_time().timestamp();
+            let offset = if mtime == 0 {
+                continue;
+            } else if mtime < start.saturating_sub(tolerance) {
+                before += 1;
+                mtime - start
+            } else if mtime > end.saturating_add(tolerance) {
+                after += 1;
+                mtime - end
+            } else {
+                continue;
+            };
+            let item = stamp(py, path, &style)?;
+            item.set_item("offset", offset)?;
+            outliers.append(item)?;
+        }
+    }
+    report.set_item("outliers", outliers)?;
+    report.set_item(
+        "summary",
+        summary(py, [("before", before), ("after", after)])?,
+    )?;
+
+    Ok(report)
+}
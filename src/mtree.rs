@@ -0,0 +1,141 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    modes::{S_IFBLK, S_IFCHR, S_IFDIR, S_IFIFO, S_IFLNK, S_IFMT, S_IFREG, S_IFSOCK},
+    options::{OptionChanges, ParseOptions},
+    required_paths, safe_bom_call, size64, SafeBomCall,
+};
+use apple_bom::{format::ParsedBom, BomPath, BomPathType};
+use pyo3::{exceptions::PyValueError, prelude::*};
+use std::{collections::HashMap, fmt::Write as _};
+
+/// The mtree keywords `keywords` takes, in the order entries list them.
+const KEYWORDS: [&str; 8] = [
+    "type", "mode", "uid", "gid", "size", "time", "link", "cksum",
+];
+
+/// `value` as mtree writes names: bytes other than visible ASCII, and the
+/// `#`, `=`, and `\` it gives meaning to, as `\` and three octal digits.
+fn escaped(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'#' | b'=' | b'\\' => {
+                let _ = write!(out, "\\{byte:03o}");
+            }
+            0x21..=0x7e => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\{byte:03o}");
+            }
+        }
+    }
+    out
+}
+
+/// The mtree `type` of a path: files, directories, and links by the record's
+/// path type, and devices and other types by the format bits of their mode.
+fn entry_type(path: &BomPath) -> Option<&'static str> {
+    match path.path_type() {
+        BomPathType::File => Some("file"),
+        BomPathType::Directory => Some("dir"),
+        BomPathType::Link => Some("link"),
+        BomPathType::Dev | BomPathType::Other(_) => match path.file_mode() & S_IFMT {
+            S_IFIFO => Some("fifo"),
+            S_IFCHR => Some("char"),
+            S_IFDIR => Some("dir"),
+            S_IFBLK => Some("block"),
+            S_IFREG => Some("file"),
+            S_IFLNK => Some("link"),
+            S_IFSOCK => Some("socket"),
+            _ => None,
+        },
+    }
+}
+
+/// Export a BOM as a BSD mtree(8) specification of the tree it installs,
+/// listing paths in the path order of `options`. Paths keep the `./` form
+/// mtree gives them whatever the path style.
+#[pyfunction(signature = (
+    data,
+    *,
+    keywords = KEYWORDS.map(String::from).to_vec(),
+    options = None,
+    path_order = None,
+))]
+pub(crate) fn export_mtree(
+    data: &Bound<'_, PyAny>,
+    keywords: Vec<String>,
+    options: Option<ParseOptions>,
+    path_order: Option<String>,
+) -> PyResult<String> {
+    let data = BomData::new(data, true)?;
+    if let Some(keyword) = keywords
+        .iter()
+        .find(|keyword| !KEYWORDS.contains(&keyword.as_str()))
+    {
+        return Err(PyValueError::new_err(format!(
+            "keywords must hold 'type', 'mode', 'uid', 'gid', 'size', 'time', 'link', or \
+             'cksum', not {keyword:?}"
+        )));
+    }
+    let wanted = |keyword: &str| keywords.iter().any(|wanted| wanted == keyword);
+    let options = ParseOptions::resolve(
+        options,
+        OptionChanges::for_paths(None, None, path_order, None, None, None),
+    )?;
+    let order = options.path_options()?.order;
+
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let mut paths = required_paths(&bom)?;
+    let sizes = match safe_bom_call(|| size64::resolve(&bom)) {
+        SafeBomCall::Value(section) => section.sizes,
+        SafeBomCall::MissingVariable | SafeBomCall::Error(_) => HashMap::new(),
+    };
+    order.apply(&mut paths);
+
+    let mut out = String::from("#mtree\n");
+    for path in &paths {
+        let kind = entry_type(path);
+        out.push_str(&escaped(path.path()));
+        for keyword in KEYWORDS.iter().filter(|keyword| wanted(keyword)) {
+            match (*keyword, kind) {
+                ("type", Some(kind)) => {
+                    let _ = write!(out, " type={kind}");
+                }
+                ("mode", _) => {
+                    let _ = write!(out, " mode={:04o}", path.file_mode() & 0o7777);
+                }
+                ("uid", _) => {
+                    let _ = write!(out, " uid={}", path.user_id());
+                }
+                ("gid", _) => {
+                    let _ = write!(out, " gid={}", path.group_id());
+                }
+                ("size", Some("file")) => {
+                    let size = sizes
+                        .get(path.path())
+                        .copied()
+                        .unwrap_or(path.size() as u64);
+                    let _ = write!(out, " size={size}");
+                }
+                ("time", _) => {
+                    let _ = write!(out, " time={}.000000000", path.modified_time().timestamp());
+                }
+                ("link", Some("link")) => {
+                    if let Some(target) = path.link_name() {
+                        let _ = write!(out, " link={}", escaped(target));
+                    }
+                }
+                ("cksum", Some("file")) => {
+                    if let Some(sum) = path.crc32() {
+                        let _ = write!(out, " cksum={sum}");
+                    }
+                }
+                _ => {}
+            }
+        }
+        out.push('\n');
+    }
+
+    Ok(out)
+}
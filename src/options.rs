@@ -0,0 +1,443 @@
+use crate::{
+    duplicates::DuplicatePolicy,
+    lock,
+    paths::{PathOrder, PathStyle},
+    BlockOptions, PanicPolicy, PathOptions, RawBlockEncoding,
+};
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+    types::PyTuple,
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Mutex,
+};
+
+/// The path sections `sections` can select, in document order.
+pub(crate) const PATH_SECTIONS: [&str; 4] = ["paths", "hl_index", "size64", "vindex"];
+
+/// The options `set_default_options` installed, or `None` for the built-in
+/// defaults.
+static DEFAULTS: Mutex<Option<ParseOptions>> = Mutex::new(None);
+
+fn py_bool(value: bool) -> &'static str {
+    if value {
+        "True"
+    } else {
+        "False"
+    }
+}
+
+/// `sections` as Python writes the tuple, such as `('paths', 'vindex')`.
+fn sections_repr(sections: &[String]) -> String {
+    match sections {
+        [only] => format!("('{only}',)"),
+        _ => {
+            let names: Vec<String> = sections.iter().map(|name| format!("'{name}'")).collect();
+            format!("({})", names.join(", "))
+        }
+    }
+}
+
+/// Parse options given as keyword arguments, `None` where left out.
+#[derive(Default)]
+pub(crate) struct OptionChanges {
+    pub include_blocks: Option<bool>,
+    pub sections: Option<Vec<String>>,
+    pub include_raw_block_bytes: Option<bool>,
+    pub raw_block_encoding: Option<String>,
+    pub path_style: Option<String>,
+    pub path_root: Option<String>,
+    pub path_order: Option<String>,
+    pub interpret_fields: Option<bool>,
+    pub include_tree_positions: Option<bool>,
+    pub include_raw_records: Option<bool>,
+    pub typed_paths: Option<bool>,
+    pub lazy_blocks: Option<bool>,
+    pub on_panic: Option<String>,
+    pub on_duplicate_variable: Option<String>,
+}
+
+impl OptionChanges {
+    /// The path section keywords, for the functions that only emit paths.
+    pub(crate) fn for_paths(
+        path_style: Option<String>,
+        path_root: Option<String>,
+        path_order: Option<String>,
+        include_tree_positions: Option<bool>,
+        include_raw_records: Option<bool>,
+        typed_paths: Option<bool>,
+    ) -> Self {
+        Self {
+            path_style,
+            path_root,
+            path_order,
+            include_tree_positions,
+            include_raw_records,
+            typed_paths,
+            ..Self::default()
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.include_blocks.is_none()
+            && self.sections.is_none()
+            && self.include_raw_block_bytes.is_none()
+            && self.raw_block_encoding.is_none()
+            && self.path_style.is_none()
+            && self.path_root.is_none()
+            && self.path_order.is_none()
+            && self.interpret_fields.is_none()
+            && self.include_tree_positions.is_none()
+            && self.include_raw_records.is_none()
+            && self.typed_paths.is_none()
+            && self.lazy_blocks.is_none()
+            && self.on_panic.is_none()
+            && self.on_duplicate_variable.is_none()
+    }
+}
+
+/// The keyword arguments of the parsing functions as one object, validated
+/// when it is made so a service can define its options once and pass them
+/// to every call.
+#[pyclass(frozen, module = "pyapplebom._native")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct ParseOptions {
+    #[pyo3(get)]
+    include_blocks: bool,
+    sections: Vec<String>,
+    #[pyo3(get)]
+    include_raw_block_bytes: bool,
+    #[pyo3(get)]
+    raw_block_encoding: String,
+    #[pyo3(get)]
+    path_style: String,
+    #[pyo3(get)]
+    path_root: String,
+    #[pyo3(get)]
+    path_order: String,
+    #[pyo3(get)]
+    interpret_fields: bool,
+    #[pyo3(get)]
+    include_tree_positions: bool,
+    #[pyo3(get)]
+    include_raw_records: bool,
+    #[pyo3(get)]
+    typed_paths: bool,
+    #[pyo3(get)]
+    lazy_blocks: bool,
+    #[pyo3(get)]
+    on_panic: String,
+    #[pyo3(get)]
+    on_duplicate_variable: String,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            include_blocks: true,
+            sections: PATH_SECTIONS.map(str::to_string).to_vec(),
+            include_raw_block_bytes: false,
+            raw_block_encoding: "hex".to_string(),
+            path_style: "raw".to_string(),
+            path_root: "/".to_string(),
+            path_order: "tree".to_string(),
+            interpret_fields: false,
+            include_tree_positions: false,
+            include_raw_records: false,
+            typed_paths: false,
+            lazy_blocks: false,
+            on_panic: "wrap".to_string(),
+            on_duplicate_variable: "first".to_string(),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// The options for a call: `options` if it was passed, else the defaults
+    /// with the keyword arguments given applied. Passing both is a
+    /// `TypeError`.
+    pub(crate) fn resolve(options: Option<Self>, keywords: OptionChanges) -> PyResult<Self> {
+        match options {
+            Some(_) if !keywords.is_empty() => Err(PyTypeError::new_err(
+                "pass either options or keyword arguments, not both",
+            )),
+            Some(options) => Ok(options),
+            None => get_default_options().with(keywords),
+        }
+    }
+
+    /// A copy with `changes` applied, validated.
+    pub(crate) fn with(self, changes: OptionChanges) -> PyResult<Self> {
+        Self {
+            include_blocks: changes.include_blocks.unwrap_or(self.include_blocks),
+            sections: changes.sections.unwrap_or(self.sections),
+            include_raw_block_bytes: changes
+                .include_raw_block_bytes
+                .unwrap_or(self.include_raw_block_bytes),
+            raw_block_encoding: changes
+                .raw_block_encoding
+                .unwrap_or(self.raw_block_encoding),
+            path_style: changes.path_style.unwrap_or(self.path_style),
+            path_root: changes.path_root.unwrap_or(self.path_root),
+            path_order: changes.path_order.unwrap_or(self.path_order),
+            interpret_fields: changes.interpret_fields.unwrap_or(self.interpret_fields),
+            include_tree_positions: changes
+                .include_tree_positions
+                .unwrap_or(self.include_tree_positions),
+            include_raw_records: changes
+                .include_raw_records
+                .unwrap_or(self.include_raw_records),
+            typed_paths: changes.typed_paths.unwrap_or(self.typed_paths),
+            lazy_blocks: changes.lazy_blocks.unwrap_or(self.lazy_blocks),
+            on_panic: changes.on_panic.unwrap_or(self.on_panic),
+            on_duplicate_variable: changes
+                .on_duplicate_variable
+                .unwrap_or(self.on_duplicate_variable),
+        }
+        .validated()
+    }
+
+    fn validated(mut self) -> PyResult<Self> {
+        if let Some(name) = self
+            .sections
+            .iter()
+            .find(|name| !PATH_SECTIONS.contains(&name.as_str()))
+        {
+            return Err(PyValueError::new_err(format!(
+                "sections must be 'paths', 'hl_index', 'size64', or 'vindex', not {name:?}"
+            )));
+        }
+        // Kept in document order, so equal selections compare equal.
+        self.sections = PATH_SECTIONS
+            .iter()
+            .filter(|&&section| self.sections.iter().any(|name| name == section))
+            .map(|section| section.to_string())
+            .collect();
+        self.style()?;
+        self.path_options()?;
+        self.blocks()?;
+        self.on_panic()?;
+        self.on_duplicate_variable()?;
+        Ok(self)
+    }
+
+    pub(crate) fn style(&self) -> PyResult<PathStyle> {
+        PathStyle::from_options(&self.path_style, &self.path_root)
+    }
+
+    pub(crate) fn path_options(&self) -> PyResult<PathOptions> {
+        Ok(PathOptions {
+            order: PathOrder::from_option(&self.path_order)?,
+            tree_positions: self.include_tree_positions,
+            typed: self.typed_paths,
+            raw_records: RawBlockEncoding::from_options(
+                self.include_raw_records,
+                &self.raw_block_encoding,
+            )?,
+        })
+    }
+
+    /// Whether the path section `name` is computed.
+    pub(crate) fn includes_section(&self, name: &str) -> bool {
+        self.sections.iter().any(|section| section == name)
+    }
+
+    /// Whether block entries add the best-known meanings of their fields.
+    pub(crate) fn interpret_fields(&self) -> bool {
+        self.interpret_fields
+    }
+
+    /// Whether `blocks` parses each block only when it is indexed.
+    pub(crate) fn lazy_blocks(&self) -> bool {
+        self.lazy_blocks
+    }
+
+    /// How raw bytes are included, or `None` when they are left out.
+    pub(crate) fn raw_bytes(&self) -> PyResult<Option<RawBlockEncoding>> {
+        RawBlockEncoding::from_options(self.include_raw_block_bytes, &self.raw_block_encoding)
+    }
+
+    pub(crate) fn on_panic(&self) -> PyResult<PanicPolicy> {
+        PanicPolicy::from_option(&self.on_panic)
+    }
+
+    pub(crate) fn on_duplicate_variable(&self) -> PyResult<DuplicatePolicy> {
+        DuplicatePolicy::from_option(&self.on_duplicate_variable)
+    }
+
+    /// The block options, or `None` when blocks are left out.
+    pub(crate) fn blocks(&self) -> PyResult<Option<BlockOptions>> {
+        BlockOptions::from_flags(
+            self.include_blocks,
+            self.include_raw_block_bytes,
+            &self.raw_block_encoding,
+            self.interpret_fields,
+            self.on_panic()?,
+        )
+    }
+}
+
+#[pymethods]
+impl ParseOptions {
+    #[allow(clippy::too_many_arguments)]
+    #[new]
+    #[pyo3(signature = (
+        *,
+        include_blocks = true,
+        sections = PATH_SECTIONS.map(str::to_string).to_vec(),
+        include_raw_block_bytes = false,
+        raw_block_encoding = "hex".to_string(),
+        path_style = "raw".to_string(),
+        path_root = "/".to_string(),
+        path_order = "tree".to_string(),
+        interpret_fields = false,
+        include_tree_positions = false,
+        include_raw_records = false,
+        typed_paths = false,
+        lazy_blocks = false,
+        on_panic = "wrap".to_string(),
+        on_duplicate_variable = "first".to_string(),
+    ))]
+    fn new(
+        include_blocks: bool,
+        sections: Vec<String>,
+        include_raw_block_bytes: bool,
+        raw_block_encoding: String,
+        path_style: String,
+        path_root: String,
+        path_order: String,
+        interpret_fields: bool,
+        include_tree_positions: bool,
+        include_raw_records: bool,
+        typed_paths: bool,
+        lazy_blocks: bool,
+        on_panic: String,
+        on_duplicate_variable: String,
+    ) -> PyResult<Self> {
+        Self {
+            include_blocks,
+            sections,
+            include_raw_block_bytes,
+            raw_block_encoding,
+            path_style,
+            path_root,
+            path_order,
+            interpret_fields,
+            include_tree_positions,
+            include_raw_records,
+            typed_paths,
+            lazy_blocks,
+            on_panic,
+            on_duplicate_variable,
+        }
+        .validated()
+    }
+
+    /// A copy with the given options changed.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        *,
+        include_blocks = None,
+        sections = None,
+        include_raw_block_bytes = None,
+        raw_block_encoding = None,
+        path_style = None,
+        path_root = None,
+        path_order = None,
+        interpret_fields = None,
+        include_tree_positions = None,
+        include_raw_records = None,
+        typed_paths = None,
+        lazy_blocks = None,
+        on_panic = None,
+        on_duplicate_variable = None,
+    ))]
+    fn replace(
+        &self,
+        include_blocks: Option<bool>,
+        sections: Option<Vec<String>>,
+        include_raw_block_bytes: Option<bool>,
+        raw_block_encoding: Option<String>,
+        path_style: Option<String>,
+        path_root: Option<String>,
+        path_order: Option<String>,
+        interpret_fields: Option<bool>,
+        include_tree_positions: Option<bool>,
+        include_raw_records: Option<bool>,
+        typed_paths: Option<bool>,
+        lazy_blocks: Option<bool>,
+        on_panic: Option<String>,
+        on_duplicate_variable: Option<String>,
+    ) -> PyResult<Self> {
+        self.clone().with(OptionChanges {
+            include_blocks,
+            sections,
+            include_raw_block_bytes,
+            raw_block_encoding,
+            path_style,
+            path_root,
+            path_order,
+            interpret_fields,
+            include_tree_positions,
+            include_raw_records,
+            typed_paths,
+            lazy_blocks,
+            on_panic,
+            on_duplicate_variable,
+        })
+    }
+
+    #[getter]
+    fn sections<'py>(&self, py: Python<'py>) -> Bound<'py, PyTuple> {
+        PyTuple::new_bound(py, &self.sections)
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self == other
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ParseOptions(include_blocks={}, sections={}, include_raw_block_bytes={}, \
+             raw_block_encoding={:?}, path_style={:?}, path_root={:?}, path_order={:?}, \
+             interpret_fields={}, include_tree_positions={}, include_raw_records={}, \
+             typed_paths={}, lazy_blocks={}, on_panic={:?}, on_duplicate_variable={:?})",
+            py_bool(self.include_blocks),
+            sections_repr(&self.sections),
+            py_bool(self.include_raw_block_bytes),
+            self.raw_block_encoding,
+            self.path_style,
+            self.path_root,
+            self.path_order,
+            py_bool(self.interpret_fields),
+            py_bool(self.include_tree_positions),
+            py_bool(self.include_raw_records),
+            py_bool(self.typed_paths),
+            py_bool(self.lazy_blocks),
+            self.on_panic,
+            self.on_duplicate_variable,
+        )
+    }
+}
+
+/// Make `options` what the functions taking `options=` fall back to for
+/// anything a call leaves out. `None` restores the built-in defaults.
+#[pyfunction]
+pub(crate) fn set_default_options(options: Option<ParseOptions>) {
+    *lock(&DEFAULTS) = options;
+}
+
+/// The options calls fall back to, as `set_default_options` left them.
+#[pyfunction]
+pub(crate) fn get_default_options() -> ParseOptions {
+    lock(&DEFAULTS).clone().unwrap_or_default()
+}
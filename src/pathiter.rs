@@ -0,0 +1,123 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    footprint::{Footprint, HeapSize},
+    options::{OptionChanges, ParseOptions},
+    paths::PathStyle,
+    required_paths, PathExtras, PathOptions,
+};
+use apple_bom::{format::ParsedBom, BomPath};
+use pyo3::prelude::*;
+use std::{mem::size_of_val, vec};
+
+/// A cursor over the entries of a BOM's `Paths` tree, which serializes each
+/// entry only when Python asks for it.
+///
+/// The path records are read up front, as Rust values far smaller than the
+/// dictionaries they become, so a consumer that breaks early never pays for
+/// the rest.
+#[pyclass(module = "pyapplebom._native", name = "BomPathIterator")]
+pub(crate) struct PathIterator {
+    paths: vec::IntoIter<BomPath>,
+    style: PathStyle,
+    extras: PathExtras,
+    typed: bool,
+}
+
+impl PathIterator {
+    pub(crate) fn new(
+        bom: &ParsedBom<'_>,
+        style: PathStyle,
+        path_options: PathOptions,
+    ) -> PyResult<Self> {
+        let mut paths = required_paths(bom)?;
+        let extras = PathExtras::for_paths(bom, &paths, &path_options);
+        path_options.order.apply(&mut paths);
+
+        Ok(Self {
+            paths: paths.into_iter(),
+            style,
+            extras,
+            typed: path_options.typed,
+        })
+    }
+
+    /// The records not yet yielded are cached, and the extras index them.
+    pub(crate) fn footprint(&self) -> Footprint {
+        let records = self.paths.as_slice();
+        Footprint {
+            caches: size_of_val(records) + records.iter().map(HeapSize::heap_size).sum::<usize>(),
+            indices: self.extras.heap_size(),
+            ..Default::default()
+        }
+    }
+}
+
+#[pymethods]
+impl PathIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        let Some(path) = slf.paths.next() else {
+            return Ok(None);
+        };
+        let entry = slf.extras.entry(&path, &slf.style);
+        if slf.typed {
+            Ok(Some(Bound::new(py, entry)?.into_any().unbind()))
+        } else {
+            Ok(Some(entry.to_dict(py)?.into_any().unbind()))
+        }
+    }
+
+    /// Drop the entries not yet yielded, ending the iteration.
+    fn close(&mut self) {
+        self.paths = Vec::new().into_iter();
+        self.extras = PathExtras::default();
+    }
+
+    /// The number of entries not yet yielded.
+    fn __length_hint__(&self) -> usize {
+        self.paths.len()
+    }
+}
+
+/// Iterate over the entries of a BOM's `Paths` tree one at a time.
+#[pyfunction(signature = (
+    data,
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+    path_order = None,
+    include_tree_positions = None,
+    include_raw_records = None,
+    typed_paths = None,
+))]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn iter_paths(
+    data: &Bound<'_, PyAny>,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+    path_order: Option<String>,
+    include_tree_positions: Option<bool>,
+    include_raw_records: Option<bool>,
+    typed_paths: Option<bool>,
+) -> PyResult<PathIterator> {
+    let data = BomData::new(data, true)?;
+    let options = ParseOptions::resolve(
+        options,
+        OptionChanges::for_paths(
+            path_style,
+            path_root,
+            path_order,
+            include_tree_positions,
+            include_raw_records,
+            typed_paths,
+        ),
+    )?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    PathIterator::new(&bom, options.style()?, options.path_options()?)
+}
@@ -0,0 +1,226 @@
+use apple_bom::BomPath;
+use pyo3::{exceptions::PyValueError, PyResult};
+use std::borrow::Cow;
+
+/// How BOM paths, which are stored relative to `.`, are rendered in output.
+#[derive(Clone, Debug)]
+pub(crate) enum PathStyle {
+    /// As stored in the BOM, e.g. `./usr/bin/tool`.
+    Raw,
+
+    /// Without the leading `./`, e.g. `usr/bin/tool`. The root entry becomes `""`.
+    Relative,
+
+    /// Joined onto an install root, e.g. `/usr/bin/tool`.
+    Absolute(String),
+}
+
+impl PathStyle {
+    pub(crate) fn from_options(style: &str, root: &str) -> PyResult<Self> {
+        match style {
+            "raw" => Ok(Self::Raw),
+            "relative" => Ok(Self::Relative),
+            "absolute" => {
+                let root = root.trim_end_matches('/');
+                Ok(Self::Absolute(if root.is_empty() {
+                    "/".to_string()
+                } else {
+                    root.to_string()
+                }))
+            }
+            _ => Err(PyValueError::new_err(format!(
+                "path_style must be 'raw', 'relative', or 'absolute', not {style:?}"
+            ))),
+        }
+    }
+
+    /// The style `path` is written in, telling them apart by the leading
+    /// `./` of raw paths and `/` of absolute ones (rooted at `/`).
+    pub(crate) fn of_path(path: &str) -> Self {
+        if path == "." || path.starts_with("./") {
+            Self::Raw
+        } else if path.starts_with('/') {
+            Self::Absolute("/".to_string())
+        } else {
+            Self::Relative
+        }
+    }
+
+    pub(crate) fn render(&self, path: &str) -> String {
+        let relative = if path == "." {
+            ""
+        } else {
+            path.strip_prefix("./").unwrap_or(path)
+        };
+
+        match self {
+            Self::Raw => path.to_string(),
+            Self::Relative => relative.to_string(),
+            Self::Absolute(root) if relative.is_empty() => root.clone(),
+            Self::Absolute(root) if root == "/" => format!("/{relative}"),
+            Self::Absolute(root) => format!("{root}/{relative}"),
+        }
+    }
+
+    /// The raw BOM path that [PathStyle::render] renders as `path`, or `None`
+    /// if no raw path does, such as an absolute path outside the root.
+    pub(crate) fn raw_path(&self, path: &str) -> Option<String> {
+        let relative = match self {
+            Self::Raw => return Some(path.to_string()),
+            Self::Relative => path,
+            Self::Absolute(root) if path == root => "",
+            Self::Absolute(root) if root == "/" => path.strip_prefix('/')?,
+            Self::Absolute(root) => path.strip_prefix(root.as_str())?.strip_prefix('/')?,
+        };
+
+        Some(if relative.is_empty() {
+            ".".to_string()
+        } else {
+            format!("./{relative}")
+        })
+    }
+}
+
+/// The order path sections are emitted in.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum PathOrder {
+    /// As stored: the order of the `Paths` tree leaves, which `lsbom` follows.
+    Tree,
+
+    /// Sorted by path component, so directories precede their contents.
+    Sorted,
+}
+
+impl PathOrder {
+    pub(crate) fn from_option(order: &str) -> PyResult<Self> {
+        match order {
+            "tree" => Ok(Self::Tree),
+            "sorted" => Ok(Self::Sorted),
+            _ => Err(PyValueError::new_err(format!(
+                "path_order must be 'tree' or 'sorted', not {order:?}"
+            ))),
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Tree => "tree",
+            Self::Sorted => "sorted",
+        }
+    }
+
+    pub(crate) fn apply(self, paths: &mut [BomPath]) {
+        if let Self::Sorted = self {
+            paths.sort_by(|a, b| a.path().split('/').cmp(b.path().split('/')));
+        }
+    }
+}
+
+/// Produce the key used to decide whether two paths refer to the same entry.
+///
+/// APFS and HFS+ volumes are case-insensitive by default, so comparisons
+/// against real filesystems usually want `case_sensitive = false`.
+pub(crate) fn match_key(path: &str, case_sensitive: bool) -> Cow<'_, str> {
+    if case_sensitive {
+        Cow::Borrowed(path)
+    } else {
+        Cow::Owned(path.to_lowercase())
+    }
+}
+
+/// A shell-style glob over BOM paths, e.g. `./usr/libexec/*` or `**/*.dylib`.
+///
+/// `*` and `?` never match `/`; a `**` component matches any number of
+/// components. Patterns may be written as `./a/*`, `a/*`, or `/a/*`.
+#[derive(Clone, Debug)]
+pub(crate) struct PathGlob {
+    components: Vec<Vec<char>>,
+}
+
+impl PathGlob {
+    pub(crate) fn new(pattern: &str) -> PyResult<Self> {
+        let relative = pattern
+            .strip_prefix("./")
+            .unwrap_or(pattern)
+            .trim_start_matches('/');
+        let relative = if relative == "." { "" } else { relative };
+
+        let components = relative
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .map(|component| match component {
+                "." | ".." => Err(PyValueError::new_err(format!(
+                    "invalid glob {pattern:?}: '.' and '..' components are not allowed"
+                ))),
+                _ => Ok(component.chars().collect()),
+            })
+            .collect::<PyResult<_>>()?;
+
+        Ok(Self { components })
+    }
+
+    /// Whether the path made of `components` (the root is empty) matches.
+    pub(crate) fn matches<S: AsRef<str>>(&self, components: &[S]) -> bool {
+        match_components(&self.components, components)
+    }
+
+    /// Whether the raw BOM path `path` matches.
+    pub(crate) fn matches_path(&self, path: &str) -> bool {
+        let relative = if path == "." {
+            ""
+        } else {
+            path.strip_prefix("./").unwrap_or(path)
+        };
+        let components: Vec<&str> = relative
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .collect();
+        self.matches(&components)
+    }
+}
+
+fn match_components<S: AsRef<str>>(pattern: &[Vec<char>], path: &[S]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first.as_slice() == ['*', '*'] => {
+            (0..=path.len()).any(|skip| match_components(rest, &path[skip..]))
+        }
+        Some((first, rest)) => path.split_first().is_some_and(|(component, tail)| {
+            let name: Vec<char> = component.as_ref().chars().collect();
+            match_component(first, &name) && match_components(rest, tail)
+        }),
+    }
+}
+
+/// Match a single component against `*` and `?` wildcards.
+fn match_component(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                n += 1;
+            }
+            Some(c) if *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
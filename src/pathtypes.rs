@@ -0,0 +1,104 @@
+use apple_bom::BomPathType;
+use pyo3::{
+    exceptions::{PyKeyError, PyValueError},
+    prelude::*,
+};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    sync::{Mutex, MutexGuard},
+};
+
+/// Names of the path types apple-bom knows, which can't be re-registered.
+const BUILT_IN: [(u8, &str); 4] = [(1, "file"), (2, "directory"), (3, "link"), (4, "device")];
+
+/// Name used for type codes nobody has registered.
+const UNREGISTERED: &str = "other";
+
+/// Names callers registered for vendor-specific type codes.
+static REGISTERED: Mutex<BTreeMap<u8, String>> = Mutex::new(BTreeMap::new());
+
+fn registered() -> MutexGuard<'static, BTreeMap<u8, String>> {
+    // The map stays consistent even if a holder panicked.
+    REGISTERED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The name of a path type: its built-in name, the name registered for its
+/// code, or `"other"`.
+pub(crate) fn name(path_type: BomPathType) -> Cow<'static, str> {
+    match path_type {
+        BomPathType::File => Cow::Borrowed("file"),
+        BomPathType::Directory => Cow::Borrowed("directory"),
+        BomPathType::Link => Cow::Borrowed("link"),
+        BomPathType::Dev => Cow::Borrowed("device"),
+        BomPathType::Other(code) => registered()
+            .get(&code)
+            .map_or(Cow::Borrowed(UNREGISTERED), |name| Cow::Owned(name.clone())),
+    }
+}
+
+/// The type code for a built-in or registered path type name.
+pub(crate) fn code(name: &str) -> PyResult<u8> {
+    if let Some((code, _)) = BUILT_IN.iter().find(|(_, built_in)| *built_in == name) {
+        return Ok(*code);
+    }
+    if let Some((code, _)) = registered()
+        .iter()
+        .find(|(_, registered)| *registered == name)
+    {
+        return Ok(*code);
+    }
+
+    Err(PyValueError::new_err(format!(
+        "unknown path_type {name:?}; expected 'file', 'directory', 'link', 'device', or a \
+         registered name"
+    )))
+}
+
+/// Name a vendor-specific path type code, in parsed output and for
+/// `BomBuilder`.
+#[pyfunction]
+pub(crate) fn register_path_type(code: u8, name: &str) -> PyResult<()> {
+    if let Some((_, built_in)) = BUILT_IN.iter().find(|(built_in, _)| *built_in == code) {
+        return Err(PyValueError::new_err(format!(
+            "path type {code} is built in as {built_in:?}"
+        )));
+    }
+    if name.is_empty()
+        || name == UNREGISTERED
+        || BUILT_IN.iter().any(|(_, built_in)| *built_in == name)
+    {
+        return Err(PyValueError::new_err(format!(
+            "path type name {name:?} is reserved"
+        )));
+    }
+
+    let mut registered = registered();
+    if let Some((other, _)) = registered
+        .iter()
+        .find(|(other, registered)| **other != code && *registered == name)
+    {
+        return Err(PyValueError::new_err(format!(
+            "path type name {name:?} is already registered for {other}"
+        )));
+    }
+    registered.insert(code, name.to_string());
+    Ok(())
+}
+
+/// Remove the name registered for a path type code.
+#[pyfunction]
+pub(crate) fn unregister_path_type(code: u8) -> PyResult<()> {
+    registered()
+        .remove(&code)
+        .map(drop)
+        .ok_or_else(|| PyKeyError::new_err(format!("path type {code} is not registered")))
+}
+
+/// The registered path type names by code.
+#[pyfunction]
+pub(crate) fn registered_path_types() -> BTreeMap<u8, String> {
+    registered().clone()
+}
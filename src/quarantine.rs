@@ -0,0 +1,149 @@
+use crate::{corpus::json_string, panic_payload_to_string, store::write_atomically, BomParseError};
+use pyo3::{exceptions::PyOSError, prelude::*};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+/// Why a batch operation gave up on an input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FailureKind {
+    /// The input couldn't be read, which says nothing about its contents.
+    Read,
+
+    /// The parser rejected the input as malformed.
+    Parse,
+
+    /// The parser panicked on the input.
+    Panic,
+}
+
+impl FailureKind {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Parse => "parse",
+            Self::Panic => "panic",
+        }
+    }
+}
+
+/// An input a batch operation gave up on, and why.
+#[derive(Debug)]
+pub(crate) struct Failure {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+/// An input a batch operation gave up on, and where it was quarantined, or
+/// why it couldn't be, when a quarantine was given.
+pub(crate) type Rejection = (Failure, Option<Result<PathBuf, String>>);
+
+/// Run `parse` on `data`, the input at `index` (read from `source` when
+/// given), catching panics, and set `data` aside in `quarantine` when given
+/// if it fails.
+pub(crate) fn parse_or_hold<'a, T>(
+    data: &'a [u8],
+    index: Option<usize>,
+    source: Option<&Path>,
+    quarantine: Option<&Quarantine>,
+    parse: impl FnOnce(&'a [u8]) -> Result<T, String>,
+) -> Result<T, Rejection> {
+    let failure = match catch_unwind(AssertUnwindSafe(|| parse(data))) {
+        Ok(Ok(parsed)) => return Ok(parsed),
+        Ok(Err(message)) => Failure {
+            kind: FailureKind::Parse,
+            message,
+        },
+        Err(payload) => Failure {
+            kind: FailureKind::Panic,
+            message: format!(
+                "apple-bom parser panicked: {}",
+                panic_payload_to_string(payload)
+            ),
+        },
+    };
+    let held = quarantine.and_then(|quarantine| quarantine.hold(data, index, source, &failure));
+    Err((failure, held))
+}
+
+/// The exception for an input a single-input operation gave up on, which
+/// says where the input was quarantined.
+pub(crate) fn rejection_error((failure, held): Rejection) -> PyErr {
+    let message = match held {
+        Some(Ok(copy)) => format!("{} (quarantined as {})", failure.message, copy.display()),
+        Some(Err(err)) => format!("{} (not quarantined: {err})", failure.message),
+        None => failure.message,
+    };
+    match failure.kind {
+        FailureKind::Read => PyOSError::new_err(message),
+        FailureKind::Parse | FailureKind::Panic => BomParseError::new_err(message),
+    }
+}
+
+/// A directory collecting the inputs a batch operation failed on, each next
+/// to a JSON report of the failure, so a pipeline can set them aside and
+/// keep going.
+///
+/// Inputs are stored once per SHA-256 digest as `<digest>.bom`, with the
+/// report as `<digest>.json`. The input is written first, so every report
+/// has its input even if the run is interrupted.
+pub(crate) struct Quarantine {
+    directory: PathBuf,
+
+    /// The operation named in reports.
+    operation: String,
+}
+
+impl Quarantine {
+    /// The quarantine in `directory`, creating it if needed.
+    pub(crate) fn create(directory: PathBuf, operation: &str) -> PyResult<Self> {
+        fs::create_dir_all(&directory).map_err(|err| {
+            PyOSError::new_err(format!("failed creating {}: {err}", directory.display()))
+        })?;
+        Ok(Self {
+            directory,
+            operation: operation.to_string(),
+        })
+    }
+
+    /// Set aside `data`, the input at `index` of a batch (read from `source`
+    /// when given), with a report of `failure`, returning where the input was
+    /// copied to. Read failures have no input to keep and are skipped.
+    pub(crate) fn hold(
+        &self,
+        data: &[u8],
+        index: Option<usize>,
+        source: Option<&Path>,
+        failure: &Failure,
+    ) -> Option<Result<PathBuf, String>> {
+        if failure.kind == FailureKind::Read {
+            return None;
+        }
+        let digest = hex::encode(Sha256::digest(data));
+        let copy = self.directory.join(format!("{digest}.bom"));
+        let report = format!(
+            "{{\"operation\": {}, \"index\": {}, \"source\": {}, \"sha256\": \"{digest}\", \
+             \"length\": {}, \"kind\": \"{}\", \"error\": {}}}\n",
+            json_string(&self.operation),
+            index.map_or("null".to_string(), |index| index.to_string()),
+            source.map_or("null".to_string(), |source| json_string(
+                &source.to_string_lossy()
+            )),
+            data.len(),
+            failure.kind.name(),
+            json_string(&failure.message),
+        );
+
+        let report_path = copy.with_extension("json");
+        let held = write_atomically(&copy, data)
+            .map_err(|err| format!("failed writing {}: {err}", copy.display()))
+            .and_then(|_| {
+                write_atomically(&report_path, report.as_bytes())
+                    .map_err(|err| format!("failed writing {}: {err}", report_path.display()))
+            });
+        Some(held.map(|_| copy))
+    }
+}
@@ -0,0 +1,193 @@
+use pyo3::{exceptions::PyValueError, PyResult};
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fs, panic,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+/// Default size of the buffer used to read file contents.
+pub(crate) const DEFAULT_READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// How file contents are read when building from or verifying against files
+/// on disk.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ReadOptions {
+    /// Bytes requested per read call. Network volumes usually prefer larger
+    /// reads than local SSDs.
+    pub buffer_size: usize,
+
+    /// Files read at once from each device. Files on different devices are
+    /// always read in parallel when this allows more than one reader overall.
+    pub per_device_concurrency: usize,
+}
+
+impl ReadOptions {
+    pub(crate) fn new(buffer_size: usize, per_device_concurrency: usize) -> PyResult<Self> {
+        if buffer_size == 0 {
+            return Err(PyValueError::new_err("read_buffer_size must be positive"));
+        }
+        if per_device_concurrency == 0 {
+            return Err(PyValueError::new_err(
+                "per_device_concurrency must be positive",
+            ));
+        }
+
+        Ok(Self {
+            buffer_size,
+            per_device_concurrency,
+        })
+    }
+
+    /// Apply `read` to each of `items`, returning results in input order.
+    ///
+    /// Items are grouped by the device `device` reports for them, and each
+    /// device gets up to `per_device_concurrency` worker threads.
+    pub(crate) fn map_per_device<T, R>(
+        &self,
+        items: &[T],
+        device: impl Fn(&T) -> u64,
+        read: impl Fn(&T) -> R + Sync,
+    ) -> Vec<R>
+    where
+        T: Sync,
+        R: Send,
+    {
+        let mut results = Vec::with_capacity(items.len());
+        self.for_each_per_device(items, device, read, |result| {
+            results.push(result);
+            Ok::<_, Infallible>(())
+        })
+        .unwrap_or_else(|never| match never {});
+        results
+    }
+
+    /// Like [ReadOptions::map_per_device], but hands each result to `deliver`
+    /// on the calling thread, in input order, as soon as it and every earlier
+    /// result are ready. The first error from `deliver` stops the remaining
+    /// reads and is returned.
+    pub(crate) fn for_each_per_device<T, R, E>(
+        &self,
+        items: &[T],
+        device: impl Fn(&T) -> u64,
+        read: impl Fn(&T) -> R + Sync,
+        mut deliver: impl FnMut(R) -> Result<(), E>,
+    ) -> Result<(), E>
+    where
+        T: Sync,
+        R: Send,
+    {
+        let mut queues: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+        for (index, item) in items.iter().enumerate() {
+            queues.entry(device(item)).or_default().push(index);
+        }
+
+        if queues.len() <= 1 && self.per_device_concurrency == 1 {
+            return items.iter().map(read).try_for_each(deliver);
+        }
+
+        let queues: Vec<Vec<usize>> = queues.into_values().collect();
+        let cursors: Vec<AtomicUsize> = queues.iter().map(|_| AtomicUsize::new(0)).collect();
+        let cancelled = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            let (read, cancelled) = (&read, &cancelled);
+            let mut workers = Vec::new();
+            for (queue, cursor) in queues.iter().zip(&cursors) {
+                for _ in 0..self.per_device_concurrency.min(queue.len()) {
+                    let sender = sender.clone();
+                    workers.push(scope.spawn(move || {
+                        while !cancelled.load(Ordering::Relaxed) {
+                            let Some(&index) = queue.get(cursor.fetch_add(1, Ordering::Relaxed))
+                            else {
+                                break;
+                            };
+                            if sender.send((index, read(&items[index]))).is_err() {
+                                break;
+                            }
+                        }
+                    }));
+                }
+            }
+            drop(sender);
+
+            let mut outcome = Ok(());
+            let mut pending = BTreeMap::new();
+            let mut next = 0;
+            'receive: for (index, result) in &receiver {
+                pending.insert(index, result);
+                while let Some(result) = pending.remove(&next) {
+                    next += 1;
+                    if let Err(err) = deliver(result) {
+                        cancelled.store(true, Ordering::Relaxed);
+                        outcome = Err(err);
+                        break 'receive;
+                    }
+                }
+            }
+            drop(receiver);
+
+            for worker in workers {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic));
+            }
+            outcome
+        })
+    }
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            per_device_concurrency: 1,
+        }
+    }
+}
+
+/// The (device, inode) pair identifying a file, shared by all its hard links.
+#[cfg(unix)]
+pub(crate) fn file_identity(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn file_identity(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// [file_identity] of a regular file with more than one hard link.
+#[cfg(unix)]
+pub(crate) fn hard_link_identity(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    (metadata.is_file() && metadata.nlink() > 1)
+        .then(|| file_identity(metadata))
+        .flatten()
+}
+
+#[cfg(not(unix))]
+pub(crate) fn hard_link_identity(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// The device a file lives on, for grouping reads.
+#[cfg(unix)]
+pub(crate) fn device_id(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.dev()
+}
+
+#[cfg(not(unix))]
+pub(crate) fn device_id(_metadata: &fs::Metadata) -> u64 {
+    0
+}
@@ -0,0 +1,140 @@
+use apple_bom::{format::ParsedBom, BomPath};
+use std::collections::{HashMap, HashSet};
+
+/// Offset of `link_name_length` within a `PathRecord` block.
+const LINK_NAME_LENGTH_OFFSET: usize = 27;
+
+/// Length of the fixed-size portion of a `PathRecord` block.
+const RECORD_FIXED_LENGTH: usize = 31;
+
+/// Paths listed in the `Size64` tree, with their reconciled 64-bit sizes.
+#[derive(Default)]
+pub(crate) struct Size64Section {
+    pub paths: Vec<BomPath>,
+
+    /// Full sizes keyed by raw BOM path, for records whose 64-bit size
+    /// agrees with the truncated 32-bit `size` field.
+    pub sizes: HashMap<String, u64>,
+
+    /// Raw BOM paths with no reconciled full size, whose `size` field holds
+    /// only the low 32 bits of their size. Every listed path is here unless
+    /// [SIZE64_TRAILER].
+    pub truncated: HashSet<String>,
+}
+
+/// Whether the full size of a file over 4 GiB follows its `PathRecord`, in
+/// place of the 4 zero bytes that normally end a file record.
+///
+/// Apple doesn't document where it keeps these sizes, so this layout is the
+/// writer's own guess. Only builds with the experimental `size64-trailer`
+/// feature write it or trust it; others would misread the bytes other
+/// writers leave there.
+pub(crate) const SIZE64_TRAILER: bool = cfg!(feature = "size64-trailer");
+
+/// Read the 64-bit size stored after the link name of a `PathRecord` block,
+/// or `None` unless [SIZE64_TRAILER].
+pub(crate) fn record_size64(record: &[u8]) -> Option<u64> {
+    if !SIZE64_TRAILER {
+        return None;
+    }
+    let link_name_length = record.get(LINK_NAME_LENGTH_OFFSET..RECORD_FIXED_LENGTH)?;
+    let link_name_length = u32::from_be_bytes(link_name_length.try_into().ok()?) as usize;
+    let start = RECORD_FIXED_LENGTH.checked_add(link_name_length)?;
+    let size = record.get(start..start.checked_add(8)?)?;
+
+    Some(u64::from_be_bytes(size.try_into().ok()?))
+}
+
+/// A `Paths` leaf entry and the path info block it points to.
+pub(crate) struct LeafEntry {
+    /// Index of the path info block.
+    pub block_index: u32,
+
+    /// Index of the `File` block naming the path.
+    pub file_index: u32,
+
+    pub path_id: u32,
+    pub path_record_index: u32,
+}
+
+/// Collect the path info entries of every leaf in the tree bound to `variable`.
+pub(crate) fn leaf_entries(
+    bom: &ParsedBom<'_>,
+    variable: &str,
+) -> Result<Vec<LeafEntry>, apple_bom::Error> {
+    let var = bom.find_variable(variable)?;
+    let tree = bom.block_as_tree(var.block_index as _)?;
+    let mut paths = tree.root_paths(bom)?;
+    let mut entries = Vec::with_capacity(tree.path_count as usize);
+    let mut visited = HashSet::new();
+
+    loop {
+        for entry in &paths.paths {
+            let info = bom.block_as_path_info_index(entry.block_index as _)?;
+            entries.push(LeafEntry {
+                block_index: entry.block_index,
+                file_index: entry.file_index,
+                path_id: info.path_id,
+                path_record_index: info.path_record_index,
+            });
+        }
+
+        let next = paths.next_paths_block_index;
+        if next == 0 || !visited.insert(next) {
+            break;
+        }
+        paths = bom.block_as_paths(next as _)?;
+    }
+
+    Ok(entries)
+}
+
+/// Resolve the `Size64` tree against the `Paths` tree.
+///
+/// `Size64` leaves reference the same path info blocks as `Paths`, but only
+/// for paths whose size doesn't fit in the 32-bit `size` field, so names are
+/// resolved through `Paths` rather than through `Size64` itself.
+pub(crate) fn resolve(bom: &ParsedBom<'_>) -> Result<Size64Section, apple_bom::Error> {
+    let large = leaf_entries(bom, "Size64")?;
+    if large.is_empty() {
+        return Ok(Size64Section::default());
+    }
+
+    let paths = bom.paths()?;
+    let by_id: HashMap<u32, &BomPath> = leaf_entries(bom, "Paths")?
+        .iter()
+        .map(|entry| entry.path_id)
+        .zip(&paths)
+        .collect();
+
+    let mut section = Size64Section {
+        paths: Vec::with_capacity(large.len()),
+        sizes: HashMap::with_capacity(large.len()),
+        truncated: HashSet::new(),
+    };
+
+    for entry in large {
+        let path = by_id
+            .get(&entry.path_id)
+            .ok_or(apple_bom::Error::BadIndex)?;
+        let record = bom.block_data(entry.path_record_index as _)?;
+
+        match record_size64(record) {
+            Some(size) if size as u32 as usize == path.size() => {
+                section.sizes.insert(path.path().to_string(), size);
+            }
+            _ => {
+                section.truncated.insert(path.path().to_string());
+            }
+        }
+        section.paths.push((*path).clone());
+    }
+
+    Ok(section)
+}
+
+/// Whether `actual` is a size of 4 GiB or more whose low 32 bits are the
+/// `truncated` size a record holds.
+pub(crate) fn matches_truncated(truncated: u64, actual: u64) -> bool {
+    actual > u64::from(u32::MAX) && actual as u32 as u64 == truncated
+}
@@ -0,0 +1,305 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    interned,
+    lookup::lookup_style,
+    options::ParseOptions,
+    required_paths, safe_bom_call,
+    size64::{self, Size64Section},
+    summary, BomParseError, SafeBomCall,
+};
+use apple_bom::{format::ParsedBom, BomPathType};
+use pyo3::{
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Categories of size discrepancies, in the order reports list them.
+const CHECKS: [&str; 6] = [
+    "bom_info_paths",
+    "bom_info_total",
+    "size64",
+    "payload_size",
+    "payload_missing",
+    "payload_extra",
+];
+
+/// Offset of the architecture field within a `PathRecord` block.
+const ARCHITECTURE_OFFSET: usize = 2;
+
+/// Set in the architecture field of records that carry a Mach-O slice list.
+const BINARY_INFO_FLAG: u16 = 0x2000;
+
+/// Offset of the slice count in records carrying a slice list.
+const SLICE_COUNT_OFFSET: usize = 28;
+
+/// Length of each slice: CPU type, CPU subtype, size, and checksum.
+const SLICE_LENGTH: usize = 16;
+
+/// The `(cpu_type, size)` of each Mach-O slice a `PathRecord` block lists,
+/// or `None` when it lists none or the list is cut off.
+fn record_slices(record: &[u8]) -> Option<Vec<(u32, u64)>> {
+    let architecture = record.get(ARCHITECTURE_OFFSET..ARCHITECTURE_OFFSET + 2)?;
+    if u16::from_be_bytes(architecture.try_into().ok()?) & BINARY_INFO_FLAG == 0 {
+        return None;
+    }
+    let count = record.get(SLICE_COUNT_OFFSET..SLICE_COUNT_OFFSET + 4)?;
+    let count = u32::from_be_bytes(count.try_into().ok()?) as usize;
+    let start = SLICE_COUNT_OFFSET + 4;
+    let slices = record.get(start..start.checked_add(count.checked_mul(SLICE_LENGTH)?)?)?;
+
+    slices
+        .chunks_exact(SLICE_LENGTH)
+        .map(|slice| {
+            let cpu_type = u32::from_be_bytes(slice[0..4].try_into().ok()?);
+            let size = u32::from_be_bytes(slice[8..12].try_into().ok()?);
+            Some((cpu_type, u64::from(size)))
+        })
+        .collect()
+}
+
+/// The value of a call that may fail, raising `BomParseError` on failure and
+/// giving `None` for a missing variable.
+fn optional<T>(call: SafeBomCall<T>) -> PyResult<Option<T>> {
+    match call {
+        SafeBomCall::Value(value) => Ok(Some(value)),
+        SafeBomCall::MissingVariable => Ok(None),
+        SafeBomCall::Error(err) => Err(BomParseError::new_err(err)),
+    }
+}
+
+/// Collects discrepancies and their counts per check.
+struct Discrepancies<'py> {
+    list: Bound<'py, PyList>,
+    counts: HashMap<&'static str, usize>,
+}
+
+impl<'py> Discrepancies<'py> {
+    fn add(
+        &mut self,
+        check: &'static str,
+        path: Option<&str>,
+        expected: impl ToPyObject,
+        actual: impl ToPyObject,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let py = self.list.py();
+        *self.counts.entry(check).or_default() += 1;
+        let item = PyDict::new_bound(py);
+        item.set_item("check", interned(py, check))?;
+        item.set_item("path", path)?;
+        item.set_item("expected", expected)?;
+        item.set_item("actual", actual)?;
+        self.list.append(&item)?;
+        Ok(item)
+    }
+}
+
+/// Reconcile the sizes a BOM records for its paths with its `BomInfo`
+/// totals, its `Size64` entries, and, when given, the sizes of the files in
+/// its payload.
+#[pyfunction(signature = (
+    data,
+    payload_sizes = None,
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+))]
+pub(crate) fn size_report<'py>(
+    py: Python<'py>,
+    data: &Bound<'_, PyAny>,
+    payload_sizes: Option<HashMap<String, u64>>,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let data = BomData::new(data, true)?;
+    let style = lookup_style(options, path_style, path_root)?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let paths = required_paths(&bom)?;
+    let leaves = match safe_bom_call(|| size64::leaf_entries(&bom, "Paths")) {
+        SafeBomCall::Value(leaves) => leaves,
+        SafeBomCall::MissingVariable => Vec::new(),
+        SafeBomCall::Error(err) => return Err(BomParseError::new_err(err)),
+    };
+    let section = optional(safe_bom_call(|| size64::resolve(&bom)))?;
+    let bom_info = optional(safe_bom_call(|| bom.bom_info()))?;
+    let mut discrepancies = Discrepancies {
+        list: PyList::empty_bound(py),
+        counts: HashMap::new(),
+    };
+
+    // What each path's record says, and the BomInfo totals they add up to:
+    // each Mach-O slice counts toward its CPU type, and everything else with
+    // content toward CPU type 0.
+    let mut sizes = BTreeMap::new();
+    let mut counts = (0usize, 0usize);
+    let mut record_total = 0u64;
+    let mut expected_totals: BTreeMap<u32, u64> = BTreeMap::new();
+    for (position, path) in paths.iter().enumerate() {
+        let path_type = path.path_type();
+        match path_type {
+            BomPathType::File => counts.0 += 1,
+            BomPathType::Link => counts.1 += 1,
+            _ => continue,
+        }
+        let size = section
+            .as_ref()
+            .and_then(|section| section.sizes.get(path.path()).copied())
+            .unwrap_or(path.size() as u64);
+        record_total += path.size() as u64;
+        sizes.insert(path.path(), size);
+
+        let slices = match (path_type, leaves.get(position)) {
+            (BomPathType::File, Some(leaf)) => {
+                match safe_bom_call(|| bom.block_data(leaf.path_record_index as _)) {
+                    SafeBomCall::Value(record) => record_slices(record),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match slices {
+            Some(slices) => {
+                for (cpu_type, size) in slices {
+                    *expected_totals.entry(cpu_type).or_default() += size;
+                }
+            }
+            None => *expected_totals.entry(0).or_default() += size,
+        }
+    }
+
+    let report = PyDict::new_bound(py);
+    let path_report = PyDict::new_bound(py);
+    path_report.set_item("files", counts.0)?;
+    path_report.set_item("links", counts.1)?;
+    path_report.set_item("total", sizes.values().sum::<u64>())?;
+    path_report.set_item("record_total", record_total)?;
+    report.set_item("paths", path_report)?;
+
+    match bom_info {
+        Some(info) => {
+            let expected_paths = paths.len() + 1;
+            if info.number_of_paths as usize != expected_paths {
+                discrepancies.add("bom_info_paths", None, expected_paths, info.number_of_paths)?;
+            }
+            // Totals are recorded truncated to 32 bits, like path sizes.
+            let expected: BTreeMap<u32, u32> = expected_totals
+                .iter()
+                .map(|(&cpu_type, &total)| (cpu_type, total as u32))
+                .collect();
+            let mut totals: BTreeMap<u32, u32> = BTreeMap::new();
+            for entry in &info.entries {
+                let total = totals.entry(entry.a).or_default();
+                *total = total.wrapping_add(entry.c);
+            }
+            let cpu_types: BTreeSet<u32> = expected.keys().chain(totals.keys()).copied().collect();
+            for cpu_type in cpu_types {
+                let (wanted, found) = (expected.get(&cpu_type), totals.get(&cpu_type));
+                if wanted.copied().unwrap_or(0) != found.copied().unwrap_or(0) {
+                    discrepancies
+                        .add("bom_info_total", None, wanted, found)?
+                        .set_item("cpu_type", cpu_type)?;
+                }
+            }
+
+            let info_report = PyDict::new_bound(py);
+            info_report.set_item("number_of_paths", info.number_of_paths)?;
+            info_report.set_item("total", totals.values().map(|&c| u64::from(c)).sum::<u64>())?;
+            info_report.set_item("totals", totals)?;
+            info_report.set_item("expected_totals", expected)?;
+            report.set_item("bom_info", info_report)?;
+        }
+        None => report.set_item("bom_info", py.None())?,
+    }
+
+    let size64_report = PyDict::new_bound(py);
+    let empty = Size64Section::default();
+    let section = section.as_ref().unwrap_or(&empty);
+    let (listed, reconciled) = (section.paths.as_slice(), &section.sizes);
+    // Without the size trailer no full sizes are read, so there is nothing
+    // to check the listed paths against.
+    for path in listed {
+        if size64::SIZE64_TRAILER && !reconciled.contains_key(path.path()) {
+            // The record's 64-bit size is missing or disagrees with its
+            // 32-bit size field, so the field's value is used.
+            discrepancies.add(
+                "size64",
+                Some(&style.render(path.path())),
+                path.size(),
+                py.None(),
+            )?;
+        }
+    }
+    let truncated: Vec<String> = listed
+        .iter()
+        .filter(|path| section.truncated.contains(path.path()))
+        .map(|path| style.render(path.path()))
+        .collect();
+    size64_report.set_item("count", listed.len())?;
+    size64_report.set_item("total", reconciled.values().sum::<u64>())?;
+    size64_report.set_item("truncated", truncated)?;
+    report.set_item("size64", size64_report)?;
+
+    match payload_sizes {
+        Some(payload_sizes) => {
+            for (&path, &size) in &sizes {
+                match payload_sizes.get(path) {
+                    // A truncated size can only be checked against the low
+                    // 32 bits of the payload's.
+                    Some(&actual)
+                        if actual != size
+                            && !(section.truncated.contains(path)
+                                && size64::matches_truncated(size, actual)) =>
+                    {
+                        discrepancies.add(
+                            "payload_size",
+                            Some(&style.render(path)),
+                            size,
+                            actual,
+                        )?;
+                    }
+                    Some(_) => {}
+                    None => {
+                        discrepancies.add(
+                            "payload_missing",
+                            Some(&style.render(path)),
+                            size,
+                            py.None(),
+                        )?;
+                    }
+                }
+            }
+            let extra: BTreeMap<_, _> = payload_sizes
+                .iter()
+                .filter(|(path, _)| !sizes.contains_key(path.as_str()))
+                .collect();
+            for (path, &actual) in extra {
+                discrepancies.add(
+                    "payload_extra",
+                    Some(&style.render(path)),
+                    py.None(),
+                    actual,
+                )?;
+            }
+
+            let payload_report = PyDict::new_bound(py);
+            payload_report.set_item("files", payload_sizes.len())?;
+            payload_report.set_item("total", payload_sizes.values().sum::<u64>())?;
+            report.set_item("payload", payload_report)?;
+        }
+        None => report.set_item("payload", py.None())?,
+    }
+
+    report.set_item("discrepancies", &discrepancies.list)?;
+    report.set_item(
+        "summary",
+        summary(
+            py,
+            CHECKS.map(|check| (check, discrepancies.counts.get(check).copied().unwrap_or(0))),
+        )?,
+    )?;
+
+    Ok(report)
+}
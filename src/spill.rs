@@ -0,0 +1,349 @@
+use crate::{macho::MachOSlice, writer::EntrySpec};
+use pyo3::{exceptions::PyOSError, prelude::*};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+use tempfile::NamedTempFile;
+
+/// When and where a [BomBuilder](crate::writer::BomBuilder) moves what it
+/// holds to disk.
+#[derive(Clone, Debug)]
+pub(crate) struct SpillOptions {
+    /// Number of values kept in memory before they're sorted and written out
+    /// as a run.
+    pub threshold: usize,
+
+    pub directory: PathBuf,
+}
+
+pub(crate) fn spill_error(err: io::Error) -> PyErr {
+    PyOSError::new_err(format!("failed spilling BOM entries to disk: {err}"))
+}
+
+/// A value that can be written to a run and read back.
+///
+/// Runs are only read by the process that wrote them, so the encoding is
+/// plain big-endian fields with length-prefixed strings and lists.
+pub(crate) trait Spill: Sized {
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()>;
+    fn read_from(input: &mut impl Read) -> io::Result<Self>;
+}
+
+macro_rules! spill_integer {
+    ($($ty:ty),*) => {$(
+        impl Spill for $ty {
+            fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+                out.write_all(&self.to_be_bytes())
+            }
+
+            fn read_from(input: &mut impl Read) -> io::Result<Self> {
+                let mut bytes = [0; std::mem::size_of::<$ty>()];
+                input.read_exact(&mut bytes)?;
+                Ok(<$ty>::from_be_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+spill_integer!(u8, u16, u32, u64);
+
+impl Spill for String {
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        (self.len() as u64).write_to(out)?;
+        out.write_all(self.as_bytes())
+    }
+
+    fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        let mut bytes = vec![0; u64::read_from(input)? as usize];
+        input.read_exact(&mut bytes)?;
+        String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl<T: Spill> Spill for Option<T> {
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        match self {
+            Some(value) => {
+                1u8.write_to(out)?;
+                value.write_to(out)
+            }
+            None => 0u8.write_to(out),
+        }
+    }
+
+    fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        match u8::read_from(input)? {
+            0 => Ok(None),
+            _ => T::read_from(input).map(Some),
+        }
+    }
+}
+
+impl<T: Spill> Spill for Vec<T> {
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        (self.len() as u64).write_to(out)?;
+        self.iter().try_for_each(|value| value.write_to(out))
+    }
+
+    fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        let count = u64::read_from(input)?;
+        (0..count).map(|_| T::read_from(input)).collect()
+    }
+}
+
+macro_rules! spill_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: Spill),*> Spill for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+                let ($($name,)*) = self;
+                $($name.write_to(out)?;)*
+                Ok(())
+            }
+
+            fn read_from(input: &mut impl Read) -> io::Result<Self> {
+                Ok(($($name::read_from(input)?,)*))
+            }
+        }
+    };
+}
+
+spill_tuple!(A, B);
+spill_tuple!(A, B, C);
+spill_tuple!(A, B, C, D);
+
+impl Spill for MachOSlice {
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        self.cpu_type.write_to(out)?;
+        self.cpu_subtype.write_to(out)?;
+        self.offset.write_to(out)?;
+        self.size.write_to(out)?;
+        self.checksum.write_to(out)
+    }
+
+    fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        Ok(Self {
+            cpu_type: Spill::read_from(input)?,
+            cpu_subtype: Spill::read_from(input)?,
+            offset: Spill::read_from(input)?,
+            size: Spill::read_from(input)?,
+            checksum: Spill::read_from(input)?,
+        })
+    }
+}
+
+impl Spill for EntrySpec {
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        self.path_type.write_to(out)?;
+        self.mode.write_to(out)?;
+        self.uid.write_to(out)?;
+        self.gid.write_to(out)?;
+        self.mtime.write_to(out)?;
+        self.size.write_to(out)?;
+        self.crc32.write_to(out)?;
+        self.link_name.write_to(out)?;
+        self.architecture.write_to(out)?;
+        self.slices.write_to(out)?;
+        self.hard_link.write_to(out)
+    }
+
+    fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        Ok(Self {
+            path_type: Spill::read_from(input)?,
+            mode: Spill::read_from(input)?,
+            uid: Spill::read_from(input)?,
+            gid: Spill::read_from(input)?,
+            mtime: Spill::read_from(input)?,
+            size: Spill::read_from(input)?,
+            crc32: Spill::read_from(input)?,
+            link_name: Spill::read_from(input)?,
+            architecture: Spill::read_from(input)?,
+            slices: Spill::read_from(input)?,
+            hard_link: Spill::read_from(input)?,
+        })
+    }
+}
+
+/// Sorted values written to a temporary file, which is removed on drop.
+pub(crate) struct Run<T> {
+    file: NamedTempFile,
+    len: usize,
+    values: PhantomData<T>,
+}
+
+impl<T: Spill> Run<T> {
+    /// Write `values`, which must already be sorted, to a new file in
+    /// `directory`.
+    pub(crate) fn create(
+        directory: &Path,
+        values: impl IntoIterator<Item = T>,
+    ) -> io::Result<Self> {
+        let file = NamedTempFile::with_prefix_in("pyapplebom-", directory)?;
+        let mut out = BufWriter::new(file.as_file());
+        let mut len = 0;
+        for value in values {
+            value.write_to(&mut out)?;
+            len += 1;
+        }
+        out.flush()?;
+        drop(out);
+
+        Ok(Self {
+            file,
+            len,
+            values: PhantomData,
+        })
+    }
+
+    /// Read the values back from the start. Each reader has its own file
+    /// handle, so a run can be read any number of times.
+    pub(crate) fn read(&self) -> io::Result<RunReader<T>> {
+        Ok(RunReader {
+            input: BufReader::new(self.file.reopen()?),
+            remaining: self.len,
+            values: PhantomData,
+        })
+    }
+}
+
+pub(crate) struct RunReader<T> {
+    input: BufReader<File>,
+    remaining: usize,
+    values: PhantomData<T>,
+}
+
+impl<T: Spill> Iterator for RunReader<T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(T::read_from(&mut self.input))
+    }
+}
+
+pub(crate) type Source<'a, T> = Box<dyn Iterator<Item = io::Result<T>> + 'a>;
+
+/// Merge sorted `sources` into one sorted sequence. Equal values come out in
+/// source order. A read error is yielded once and ends the merge.
+pub(crate) fn merge<T: Ord>(sources: Vec<Source<'_, T>>) -> Merge<'_, T> {
+    Merge {
+        sources,
+        heads: BinaryHeap::new(),
+        started: false,
+        failed: false,
+    }
+}
+
+pub(crate) struct Merge<'a, T> {
+    sources: Vec<Source<'a, T>>,
+    heads: BinaryHeap<Reverse<(T, usize)>>,
+    started: bool,
+    failed: bool,
+}
+
+impl<T: Ord> Merge<'_, T> {
+    fn pull(&mut self, source: usize) -> io::Result<()> {
+        if let Some(value) = self.sources[source].next() {
+            self.heads.push(Reverse((value?, source)));
+        }
+        Ok(())
+    }
+}
+
+impl<T: Ord> Iterator for Merge<'_, T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if !self.started {
+            self.started = true;
+            for source in 0..self.sources.len() {
+                if let Err(err) = self.pull(source) {
+                    self.failed = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+
+        let Reverse((value, source)) = self.heads.pop()?;
+        if let Err(err) = self.pull(source) {
+            self.failed = true;
+            return Some(Err(err));
+        }
+        Some(Ok(value))
+    }
+}
+
+/// Sorts values that may not fit in memory: once `threshold` values are
+/// buffered they're sorted and written out as a run, and the runs are merged
+/// when read.
+pub(crate) struct ExternalSort<'a, T> {
+    buffered: Vec<T>,
+    runs: Vec<Run<T>>,
+    spill: Option<&'a SpillOptions>,
+}
+
+impl<'a, T: Spill + Ord + Clone> ExternalSort<'a, T> {
+    /// Sort in memory alone when `spill` is `None`.
+    pub(crate) fn new(spill: Option<&'a SpillOptions>) -> Self {
+        Self {
+            buffered: Vec::new(),
+            runs: Vec::new(),
+            spill,
+        }
+    }
+
+    pub(crate) fn push(&mut self, value: T) -> io::Result<()> {
+        self.buffered.push(value);
+        if let Some(spill) = self
+            .spill
+            .filter(|spill| self.buffered.len() >= spill.threshold)
+        {
+            self.buffered.sort_unstable();
+            self.runs
+                .push(Run::create(&spill.directory, self.buffered.drain(..))?);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Sorted<T> {
+        self.buffered.sort_unstable();
+        Sorted {
+            len: self.buffered.len() + self.runs.iter().map(|run| run.len).sum::<usize>(),
+            buffered: self.buffered,
+            runs: self.runs,
+        }
+    }
+}
+
+/// The result of an [ExternalSort], which can be read any number of times.
+pub(crate) struct Sorted<T> {
+    buffered: Vec<T>,
+    runs: Vec<Run<T>>,
+    len: usize,
+}
+
+impl<T: Spill + Ord + Clone> Sorted<T> {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn iter(&self) -> io::Result<Merge<'_, T>> {
+        let mut sources = Vec::with_capacity(self.runs.len() + 1);
+        for run in &self.runs {
+            sources.push(Box::new(run.read()?) as Source<'_, T>);
+        }
+        sources.push(Box::new(self.buffered.iter().cloned().map(Ok)));
+        Ok(merge(sources))
+    }
+}
@@ -0,0 +1,126 @@
+use pyo3::{prelude::*, types::PyDict};
+use std::collections::BTreeSet;
+
+/// Columns with more distinct values than this have no `distinct_count`.
+const DISTINCT_LIMIT: usize = 256;
+
+/// A scalar value of an exported column.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Cell {
+    Null,
+    Int(i128),
+    Text(String),
+}
+
+impl ToPyObject for Cell {
+    fn to_object(&self, py: Python<'_>) -> PyObject {
+        match self {
+            Self::Null => py.None(),
+            Self::Int(value) => value.to_object(py),
+            Self::Text(value) => value.to_object(py),
+        }
+    }
+}
+
+/// What query engines prune by, for one column.
+#[derive(Default)]
+struct ColumnStats {
+    /// The smallest and largest values other than nulls.
+    range: Option<(Cell, Cell)>,
+    nulls: u64,
+
+    /// The distinct values seen, until there are too many to be worth
+    /// counting.
+    distinct: Option<BTreeSet<Cell>>,
+}
+
+impl ColumnStats {
+    fn new() -> Self {
+        Self {
+            distinct: Some(BTreeSet::new()),
+            ..Default::default()
+        }
+    }
+
+    fn add(&mut self, cell: Cell) {
+        if cell == Cell::Null {
+            self.nulls += 1;
+            return;
+        }
+        match &mut self.range {
+            Some((min, max)) => {
+                if cell < *min {
+                    *min = cell.clone();
+                } else if cell > *max {
+                    *max = cell.clone();
+                }
+            }
+            None => self.range = Some((cell.clone(), cell.clone())),
+        }
+        if let Some(distinct) = &mut self.distinct {
+            distinct.insert(cell);
+            if distinct.len() > DISTINCT_LIMIT {
+                self.distinct = None;
+            }
+        }
+    }
+}
+
+/// Column statistics of an exported table, gathered a row at a time as it
+/// is written.
+#[derive(Default)]
+pub(crate) struct TableStats {
+    rows: u64,
+
+    /// Each column seen, in the order it first appeared.
+    columns: Vec<(&'static str, ColumnStats)>,
+}
+
+impl TableStats {
+    /// Count a row of named cells. A column missing from some rows counts
+    /// as null there.
+    pub(crate) fn add(&mut self, row: impl IntoIterator<Item = (&'static str, Cell)>) {
+        let mut seen = vec![false; self.columns.len()];
+        for (name, cell) in row {
+            let index = match self.columns.iter().position(|(column, _)| *column == name) {
+                Some(index) => index,
+                None => {
+                    let mut stats = ColumnStats::new();
+                    for _ in 0..self.rows {
+                        stats.add(Cell::Null);
+                    }
+                    self.columns.push((name, stats));
+                    seen.push(false);
+                    self.columns.len() - 1
+                }
+            };
+            seen[index] = true;
+            self.columns[index].1.add(cell);
+        }
+        for (index, seen) in seen.into_iter().enumerate() {
+            if !seen {
+                self.columns[index].1.add(Cell::Null);
+            }
+        }
+        self.rows += 1;
+    }
+
+    /// The statistics as a dictionary of `rows` and, under `columns`, the
+    /// `min`, `max`, `null_count`, and `distinct_count` of each column.
+    pub(crate) fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let columns = PyDict::new_bound(py);
+        for (name, stats) in &self.columns {
+            let column = PyDict::new_bound(py);
+            let (min, max) = stats.range.clone().unzip();
+            column.set_item("min", min)?;
+            column.set_item("max", max)?;
+            column.set_item("null_count", stats.nulls)?;
+            column.set_item("distinct_count", stats.distinct.as_ref().map(BTreeSet::len))?;
+            columns.set_item(*name, column)?;
+        }
+        let table = PyDict::new_bound(py);
+        table.set_item("rows", self.rows)?;
+        table.set_item("columns", columns)?;
+        Ok(table)
+    }
+}
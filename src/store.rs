@@ -0,0 +1,513 @@
+use crate::{
+    bom_error_to_py,
+    bomfile::BomFile,
+    closed_error,
+    corpus::{glob_results, rendered_matches, CorpusPaths, ExportFormat, Specimen},
+    footprint::{Footprint, HeapSize},
+    paths::{PathGlob, PathStyle},
+    quarantine::{parse_or_hold, rejection_error, Quarantine},
+    read_lock, required_paths,
+    verify::{decode_field, encode_field, escape, unescape},
+    write_lock,
+};
+use apple_bom::format::ParsedBom;
+use pyo3::{
+    exceptions::{PyKeyError, PyOSError, PyValueError},
+    prelude::*,
+    types::{PyBytes, PyDict, PyList},
+};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// First line of a store's `sources` file.
+const SOURCES_MAGIC: &str = "pyapplebom-store 1";
+
+/// One ingestion of a BOM: where it came from, when, and what the caller
+/// recorded about it.
+#[derive(Clone, Debug)]
+struct SourceRecord {
+    source: Option<String>,
+
+    /// Seconds since the Unix epoch.
+    ingested_at: u64,
+
+    metadata: BTreeMap<String, String>,
+}
+
+impl SourceRecord {
+    /// The `sources` file line recording this ingestion of `digest`.
+    fn encode(&self, digest: &str) -> String {
+        let mut line = format!(
+            "bom\t{digest}\t{}\t{}",
+            self.ingested_at,
+            encode_field(&self.source)
+        );
+        for (key, value) in &self.metadata {
+            line.push_str(&format!("\t{}\t{}", escape(key), escape(value)));
+        }
+        line.push('\n');
+        line
+    }
+
+    fn decode(line: &str) -> Option<(String, Self)> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let ["bom", digest, ingested_at, source, metadata @ ..] = fields.as_slice() else {
+            return None;
+        };
+        if metadata.len() % 2 != 0 {
+            return None;
+        }
+
+        let record = Self {
+            source: decode_field(source)?,
+            ingested_at: ingested_at.parse().ok()?,
+            metadata: metadata
+                .chunks_exact(2)
+                .map(|pair| Some((unescape(pair[0])?, unescape(pair[1])?)))
+                .collect::<Option<_>>()?,
+        };
+        Some((digest.to_string(), record))
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let item = PyDict::new_bound(py);
+        item.set_item("source", &self.source)?;
+        item.set_item("ingested_at", self.ingested_at)?;
+        item.set_item("metadata", &self.metadata)?;
+        Ok(item)
+    }
+}
+
+impl HeapSize for SourceRecord {
+    fn heap_size(&self) -> usize {
+        self.source.heap_size() + self.metadata.heap_size()
+    }
+}
+
+fn write_error(path: &Path, err: io::Error) -> PyErr {
+    PyOSError::new_err(format!("failed writing {}: {err}", path.display()))
+}
+
+fn read_error(path: &Path, err: io::Error) -> PyErr {
+    PyOSError::new_err(format!("failed reading {}: {err}", path.display()))
+}
+
+/// Write `contents` to `path` through a staging file, so readers never see a
+/// partial file.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut staging = path.as_os_str().to_owned();
+    staging.push(".tmp");
+    let staging = PathBuf::from(staging);
+
+    fs::write(&staging, contents).and_then(|_| fs::rename(&staging, path))
+}
+
+fn replace_file(path: &Path, contents: &[u8]) -> PyResult<()> {
+    write_atomically(path, contents).map_err(|err| write_error(path, err))
+}
+
+/// What a [BomStore] holds in memory.
+struct StoreState {
+    sources: BTreeMap<String, Vec<SourceRecord>>,
+    index: Option<CorpusPaths>,
+}
+
+impl StoreState {
+    /// The records of `digest`, or `KeyError` if the store doesn't hold it.
+    fn records(&self, digest: &str) -> PyResult<&Vec<SourceRecord>> {
+        self.sources
+            .get(digest)
+            .ok_or_else(|| PyKeyError::new_err(format!("no BOM {digest:?} in store")))
+    }
+}
+
+/// A directory of BOMs stored once per SHA-256 digest, with a record of every
+/// source each was ingested from.
+///
+/// BOMs live under `objects/` as `<first two hex digits>/<digest>.bom`;
+/// `sources` lists one ingestion per line. Objects are written before their
+/// source line is appended, so an interrupted `add` leaves at most an unlisted
+/// object that the next `add` of the same BOM reuses.
+///
+/// Python threads can share a store: lookups and queries hold a read lock,
+/// with the GIL released for the slow ones, and writes to the store's files
+/// hold the write lock. The lock is never held while Python code runs.
+#[pyclass(frozen, module = "pyapplebom._native")]
+pub(crate) struct BomStore {
+    directory: PathBuf,
+
+    /// `None` once closed.
+    state: RwLock<Option<StoreState>>,
+}
+
+impl BomStore {
+    /// Run `f` on the state under the read lock.
+    fn with_state<T>(&self, f: impl FnOnce(&StoreState) -> PyResult<T>) -> PyResult<T> {
+        f(read_lock(&self.state)
+            .as_ref()
+            .ok_or_else(|| closed_error("BomStore"))?)
+    }
+
+    /// Run `f` on the state under the write lock.
+    fn with_state_mut<T>(&self, f: impl FnOnce(&mut StoreState) -> PyResult<T>) -> PyResult<T> {
+        f(write_lock(&self.state)
+            .as_mut()
+            .ok_or_else(|| closed_error("BomStore"))?)
+    }
+
+    /// `KeyError` unless the store holds `digest`.
+    fn check_stored(&self, digest: &str) -> PyResult<()> {
+        self.with_state(|state| state.records(digest).map(|_| ()))
+    }
+
+    fn sources_path(&self) -> PathBuf {
+        self.directory.join("sources")
+    }
+
+    fn object_path(&self, digest: &str) -> PathBuf {
+        self.directory
+            .join("objects")
+            .join(&digest[..2])
+            .join(format!("{digest}.bom"))
+    }
+
+    fn read_object(&self, digest: &str) -> PyResult<Vec<u8>> {
+        let path = self.object_path(digest);
+        fs::read(&path).map_err(|err| read_error(&path, err))
+    }
+
+    fn load_sources(path: &Path) -> PyResult<BTreeMap<String, Vec<SourceRecord>>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(err) => return Err(read_error(path, err)),
+        };
+        let invalid = || PyValueError::new_err(format!("invalid store file {}", path.display()));
+
+        let mut lines = text.lines();
+        if lines.next() != Some(SOURCES_MAGIC) {
+            return Err(invalid());
+        }
+        let mut sources: BTreeMap<String, Vec<SourceRecord>> = BTreeMap::new();
+        for line in lines {
+            let (digest, record) = SourceRecord::decode(line).ok_or_else(invalid)?;
+            sources.entry(digest).or_default().push(record);
+        }
+        Ok(sources)
+    }
+
+    pub(crate) fn footprint(&self) -> Footprint {
+        match read_lock(&self.state).as_ref() {
+            Some(state) => Footprint {
+                caches: state.sources.heap_size(),
+                indices: state
+                    .index
+                    .as_ref()
+                    .map_or(0, |index| index.footprint().indices),
+                ..Default::default()
+            },
+            None => Footprint::default(),
+        }
+    }
+
+    /// Run `f` on the index of the stored BOMs' paths, with the GIL released.
+    ///
+    /// The index is built on first use, under the write lock, and kept up to
+    /// date by `add` and `remove` from then on.
+    fn query_index<T: Send>(
+        &self,
+        py: Python<'_>,
+        f: impl FnOnce(&CorpusPaths) -> T + Send,
+    ) -> PyResult<T> {
+        py.allow_threads(|| {
+            if let Some(index) = read_lock(&self.state)
+                .as_ref()
+                .ok_or_else(|| closed_error("BomStore"))?
+                .index
+                .as_ref()
+            {
+                return Ok(f(index));
+            }
+
+            self.with_state_mut(|state| {
+                if state.index.is_none() {
+                    let mut index = CorpusPaths::default();
+                    for digest in state.sources.keys() {
+                        let data = self.read_object(digest)?;
+                        let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+                        index.insert(digest, &required_paths(&bom)?);
+                    }
+                    state.index = Some(index);
+                }
+                Ok(f(state.index.get_or_insert_with(CorpusPaths::default)))
+            })
+        })
+    }
+}
+
+#[pymethods]
+impl BomStore {
+    /// Open the store in `directory`, creating it if needed.
+    #[new]
+    fn new(directory: PathBuf) -> PyResult<Self> {
+        let objects = directory.join("objects");
+        fs::create_dir_all(&objects).map_err(|err| write_error(&objects, err))?;
+        let sources = Self::load_sources(&directory.join("sources"))?;
+        Ok(Self {
+            directory,
+            state: RwLock::new(Some(StoreState {
+                sources,
+                index: None,
+            })),
+        })
+    }
+
+    #[getter]
+    fn directory(&self) -> String {
+        self.directory.display().to_string()
+    }
+
+    /// Ingest a BOM, given as bytes or a path, and return its digest.
+    ///
+    /// `source` defaults to the path the BOM was read from. A BOM that fails
+    /// to parse is copied into `quarantine` when given.
+    #[pyo3(signature = (bom, *, source = None, metadata = None, quarantine = None))]
+    fn add(
+        &self,
+        py: Python<'_>,
+        bom: Specimen,
+        source: Option<String>,
+        metadata: Option<BTreeMap<String, String>>,
+        quarantine: Option<PathBuf>,
+    ) -> PyResult<String> {
+        let quarantine = quarantine
+            .map(|directory| Quarantine::create(directory, "BomStore.add"))
+            .transpose()?;
+        py.allow_threads(|| {
+            let data = bom.read().map_err(PyOSError::new_err)?;
+            let parsed = parse_or_hold(&data, None, bom.source(), quarantine.as_ref(), |data| {
+                ParsedBom::parse(data).map_err(|err| err.to_string())
+            })
+            .map_err(rejection_error)?;
+            let digest = hex::encode(Sha256::digest(&data));
+
+            let record = SourceRecord {
+                source: source.or_else(|| match &bom {
+                    Specimen::Path(path) => Some(path.display().to_string()),
+                    Specimen::Data(_) => None,
+                }),
+                ingested_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |age| age.as_secs()),
+                metadata: metadata.unwrap_or_default(),
+            };
+
+            self.with_state_mut(|state| {
+                let object = self.object_path(&digest);
+                if !object.is_file() {
+                    if let Some(parent) = object.parent() {
+                        fs::create_dir_all(parent).map_err(|err| write_error(parent, err))?;
+                    }
+                    replace_file(&object, &data)?;
+                }
+
+                let sources_path = self.sources_path();
+                let mut line = record.encode(&digest);
+                if !sources_path.exists() {
+                    line.insert_str(0, &format!("{SOURCES_MAGIC}\n"));
+                }
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&sources_path)
+                    .and_then(|mut file| file.write_all(line.as_bytes()))
+                    .map_err(|err| write_error(&sources_path, err))?;
+
+                if let Some(index) = &mut state.index {
+                    index.insert(&digest, &required_paths(&parsed)?);
+                }
+                state
+                    .sources
+                    .entry(digest.clone())
+                    .or_default()
+                    .push(record);
+                Ok(digest)
+            })
+        })
+    }
+
+    /// Drop a BOM and its source records from the store.
+    fn remove(&self, py: Python<'_>, digest: &str) -> PyResult<()> {
+        py.allow_threads(|| {
+            self.with_state_mut(|state| {
+                state.records(digest)?;
+                let mut remaining = state.sources.clone();
+                remaining.remove(digest);
+
+                let mut contents = format!("{SOURCES_MAGIC}\n");
+                for (digest, records) in &remaining {
+                    for record in records {
+                        contents.push_str(&record.encode(digest));
+                    }
+                }
+                replace_file(&self.sources_path(), contents.as_bytes())?;
+                state.sources = remaining;
+                if let Some(index) = &mut state.index {
+                    index.remove_key(digest);
+                }
+
+                let object = self.object_path(digest);
+                match fs::remove_file(&object) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                        Err(write_error(&object, err))
+                    }
+                    _ => Ok(()),
+                }
+            })
+        })
+    }
+
+    /// The digests of the stored BOMs, sorted.
+    fn digests(&self) -> PyResult<Vec<String>> {
+        self.with_state(|state| Ok(state.sources.keys().cloned().collect()))
+    }
+
+    /// Every ingestion of a BOM, oldest first.
+    fn sources<'py>(&self, py: Python<'py>, digest: &str) -> PyResult<Bound<'py, PyList>> {
+        let records = self.with_state(|state| state.records(digest).cloned())?;
+        let list = PyList::empty_bound(py);
+        for record in &records {
+            list.append(record.to_dict(py)?)?;
+        }
+        Ok(list)
+    }
+
+    /// The path of a BOM's object file.
+    fn path(&self, digest: &str) -> PyResult<String> {
+        self.check_stored(digest)?;
+        Ok(self.object_path(digest).display().to_string())
+    }
+
+    fn read<'py>(&self, py: Python<'py>, digest: &str) -> PyResult<Bound<'py, PyBytes>> {
+        self.check_stored(digest)?;
+        let data = py.allow_threads(|| self.read_object(digest))?;
+        Ok(PyBytes::new_bound(py, &data))
+    }
+
+    /// A stored BOM as a [BomFile].
+    fn open(&self, py: Python<'_>, digest: &str) -> PyResult<BomFile> {
+        self.check_stored(digest)?;
+        BomFile::open(py, self.object_path(digest))
+    }
+
+    /// The digests of the stored BOMs that list `path`, given in `path_style`.
+    #[pyo3(signature = (path, *, path_style = "raw", path_root = "/"))]
+    fn which_pkg(
+        &self,
+        py: Python<'_>,
+        path: &str,
+        path_style: &str,
+        path_root: &str,
+    ) -> PyResult<Vec<String>> {
+        let style = PathStyle::from_options(path_style, path_root)?;
+        let Some(raw) = style.raw_path(path) else {
+            return Ok(Vec::new());
+        };
+        self.query_index(py, |index| {
+            index
+                .holders(&raw)
+                .into_iter()
+                .map(str::to_string)
+                .collect()
+        })
+    }
+
+    /// Every path matching the glob `pattern` in the stored BOMs, as `digest`
+    /// and `path` (in `path_style`) pairs.
+    #[pyo3(signature = (pattern, *, path_style = "raw", path_root = "/"))]
+    fn glob<'py>(
+        &self,
+        py: Python<'py>,
+        pattern: &str,
+        path_style: &str,
+        path_root: &str,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let style = PathStyle::from_options(path_style, path_root)?;
+        let glob = PathGlob::new(pattern)?;
+        let rows = self.query_index(py, |index| rendered_matches(index, &glob, &style))?;
+        glob_results(py, "digest", rows)
+    }
+
+    /// Write the results of `glob(pattern)` to the file `destination` as
+    /// NDJSON or CSV, without building Python objects for them, and return
+    /// the number of rows written.
+    #[pyo3(signature = (
+        pattern,
+        destination,
+        *,
+        format = "ndjson",
+        path_style = "raw",
+        path_root = "/",
+    ))]
+    fn export_glob(
+        &self,
+        py: Python<'_>,
+        pattern: &str,
+        destination: PathBuf,
+        format: &str,
+        path_style: &str,
+        path_root: &str,
+    ) -> PyResult<u64> {
+        let style = PathStyle::from_options(path_style, path_root)?;
+        let format = ExportFormat::from_option(format)?;
+        let glob = PathGlob::new(pattern)?;
+        self.query_index(py, |index| {
+            index.export(&glob, &style, format, "digest", &destination)
+        })?
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        read_lock(&self.state).is_none()
+    }
+
+    /// Free the source records and index held in memory. The store's files
+    /// are untouched, and methods raise `ValueError` afterwards; closing
+    /// again does nothing.
+    fn close(&self, py: Python<'_>) {
+        py.allow_threads(|| *write_lock(&self.state) = None);
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &self,
+        py: Python<'_>,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc_value: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) {
+        self.close(py);
+    }
+
+    fn __len__(&self) -> PyResult<usize> {
+        self.with_state(|state| Ok(state.sources.len()))
+    }
+
+    fn __contains__(&self, digest: &str) -> PyResult<bool> {
+        self.with_state(|state| Ok(state.sources.contains_key(digest)))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("BomStore({:?})", self.directory.display().to_string())
+    }
+}
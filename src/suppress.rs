@@ -0,0 +1,114 @@
+use crate::{paths::PathGlob, writer::extract_field};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// A known-benign deviation, matched against findings so they can be reported
+/// separately from actionable ones.
+#[derive(Clone, Debug)]
+pub(crate) struct Suppression {
+    glob: PathGlob,
+
+    /// Finding kinds covered; `None` covers every kind.
+    kinds: Option<Vec<String>>,
+
+    /// The exact `actual` value expected, e.g. a locally changed mode.
+    actual: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl Suppression {
+    fn matches(&self, components: &[&str], kind: &str, actual: Option<&str>) -> bool {
+        self.kinds
+            .as_ref()
+            .is_none_or(|kinds| kinds.iter().any(|covered| covered == kind))
+            && self
+                .actual
+                .as_deref()
+                .is_none_or(|expected| Some(expected) == actual)
+            && self.glob.matches(components)
+    }
+}
+
+/// Suppression rules, checked in order; the first match wins.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Suppressions {
+    rules: Vec<Suppression>,
+}
+
+impl Suppressions {
+    /// Parse rules from Python. Each rule is a glob string, which suppresses
+    /// every kind of finding, or a mapping with `path` and optional `kinds`,
+    /// `actual`, and `reason`. `known_kinds` lists the valid `kinds`.
+    pub(crate) fn from_py(
+        rules: Option<&Bound<'_, PyAny>>,
+        known_kinds: &[&str],
+    ) -> PyResult<Self> {
+        let Some(rules) = rules else {
+            return Ok(Self::default());
+        };
+
+        let mut parsed = Vec::new();
+        for (index, rule) in rules.iter()?.enumerate() {
+            let rule = rule?;
+            if let Ok(pattern) = rule.extract::<String>() {
+                parsed.push(Suppression {
+                    glob: PathGlob::new(&pattern)?,
+                    kinds: None,
+                    actual: None,
+                    reason: None,
+                });
+                continue;
+            }
+
+            let pattern: String = extract_field(&rule, &["path"])?.ok_or_else(|| {
+                PyValueError::new_err(format!("suppression {index} has no 'path'"))
+            })?;
+            let kinds: Option<Vec<String>> = extract_field(&rule, &["kinds"])?;
+            if let Some(unknown) = kinds
+                .iter()
+                .flatten()
+                .find(|kind| !known_kinds.contains(&kind.as_str()))
+            {
+                return Err(PyValueError::new_err(format!(
+                    "suppression {index} has unknown kind {unknown:?}; expected one of {}",
+                    known_kinds.join(", ")
+                )));
+            }
+
+            parsed.push(Suppression {
+                glob: PathGlob::new(&pattern)?,
+                kinds,
+                actual: extract_field(&rule, &["actual"])?,
+                reason: extract_field(&rule, &["reason"])?,
+            });
+        }
+
+        Ok(Self { rules: parsed })
+    }
+
+    /// The first rule covering a `kind` finding with value `actual` at the
+    /// raw BOM `path`.
+    pub(crate) fn find(
+        &self,
+        path: &str,
+        kind: &str,
+        actual: Option<&str>,
+    ) -> Option<&Suppression> {
+        if self.rules.is_empty() {
+            return None;
+        }
+
+        let relative = if path == "." {
+            ""
+        } else {
+            path.strip_prefix("./").unwrap_or(path)
+        };
+        let components: Vec<&str> = relative
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .collect();
+
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(&components, kind, actual))
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    options::{OptionChanges, ParseOptions},
+    required_paths, PathExtras,
+};
+use apple_bom::format::ParsedBom;
+use pyo3::{prelude::*, types::PyDict};
+use std::collections::HashMap;
+
+/// A directory tree node: its `entry` and its `children` by name.
+struct Node<'py> {
+    node: Bound<'py, PyDict>,
+    children: Bound<'py, PyDict>,
+}
+
+/// The node for the raw path `key`, with its leading `./` dropped (`""` for
+/// the root), made along with any missing ancestors.
+fn node<'py>(
+    py: Python<'py>,
+    nodes: &mut HashMap<String, Node<'py>>,
+    key: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    if let Some(found) = nodes.get(key) {
+        return Ok(found.node.clone());
+    }
+    let made = Node {
+        node: PyDict::new_bound(py),
+        children: PyDict::new_bound(py),
+    };
+    made.node.set_item("entry", py.None())?;
+    made.node.set_item("children", &made.children)?;
+    if !key.is_empty() {
+        let (parent, name) = key.rsplit_once('/').unwrap_or(("", key));
+        node(py, nodes, parent)?;
+        nodes[parent].children.set_item(name, &made.node)?;
+    }
+    let result = made.node.clone();
+    nodes.insert(key.to_string(), made);
+    Ok(result)
+}
+
+/// The entries of the `Paths` tree as nested directory nodes, rooted at the
+/// node for `.`.
+pub(crate) fn build<'py>(
+    py: Python<'py>,
+    bom: &ParsedBom<'_>,
+    options: &ParseOptions,
+) -> PyResult<Bound<'py, PyDict>> {
+    let style = options.style()?;
+    let path_options = options.path_options()?;
+    let mut paths = required_paths(bom)?;
+    let extras = PathExtras::for_paths(bom, &paths, &path_options);
+    path_options.order.apply(&mut paths);
+
+    let mut nodes = HashMap::new();
+    let root = node(py, &mut nodes, "")?;
+    for path in &paths {
+        let raw = path.path();
+        let key = if raw == "." {
+            ""
+        } else {
+            raw.strip_prefix("./").unwrap_or(raw)
+        };
+        let entry = extras.entry(path, &style);
+        let entry = if path_options.typed {
+            Bound::new(py, entry)?.into_any()
+        } else {
+            entry.to_dict(py)?.into_any()
+        };
+        node(py, &mut nodes, key)?.set_item("entry", entry)?;
+    }
+
+    Ok(root)
+}
+
+/// The entries of a BOM's `Paths` tree as a nested directory tree.
+#[allow(clippy::too_many_arguments)]
+#[pyfunction(signature = (
+    data,
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+    path_order = None,
+    include_tree_positions = None,
+    include_raw_records = None,
+    typed_paths = None,
+))]
+pub(crate) fn paths_tree<'py>(
+    py: Python<'py>,
+    data: &Bound<'_, PyAny>,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+    path_order: Option<String>,
+    include_tree_positions: Option<bool>,
+    include_raw_records: Option<bool>,
+    typed_paths: Option<bool>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let data = BomData::new(data, true)?;
+    let options = ParseOptions::resolve(
+        options,
+        OptionChanges::for_paths(
+            path_style,
+            path_root,
+            path_order,
+            include_tree_positions,
+            include_raw_records,
+            typed_paths,
+        ),
+    )?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    build(py, &bom, &options)
+}
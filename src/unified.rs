@@ -0,0 +1,98 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    hardlinks,
+    options::{OptionChanges, ParseOptions},
+    required_paths, safe_bom_call, size64, BomParseError, PathExtras, SafeBomCall,
+};
+use apple_bom::format::ParsedBom;
+use pyo3::{prelude::*, types::PyList};
+use std::collections::{HashMap, HashSet};
+
+/// The value of an optional section, empty for a missing variable.
+fn optional<T: Default>(call: SafeBomCall<T>) -> PyResult<T> {
+    match call {
+        SafeBomCall::Value(value) => Ok(value),
+        SafeBomCall::MissingVariable => Ok(T::default()),
+        SafeBomCall::Error(err) => Err(BomParseError::new_err(err)),
+    }
+}
+
+/// The entries of the `Paths` tree, each with what the `Size64`, `HLIndex`,
+/// and `VIndex` trees say about it, so one table answers what the four path
+/// sections do.
+#[pyfunction(signature = (
+    data,
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+    path_order = None,
+    include_tree_positions = None,
+    include_raw_records = None,
+))]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn unified_paths<'py>(
+    py: Python<'py>,
+    data: &Bound<'_, PyAny>,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+    path_order: Option<String>,
+    include_tree_positions: Option<bool>,
+    include_raw_records: Option<bool>,
+) -> PyResult<Bound<'py, PyList>> {
+    let data = BomData::new(data, true)?;
+    let options = ParseOptions::resolve(
+        options,
+        OptionChanges::for_paths(
+            path_style,
+            path_root,
+            path_order,
+            include_tree_positions,
+            include_raw_records,
+            None,
+        ),
+    )?;
+    let style = options.style()?;
+    let path_options = options.path_options()?;
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let mut paths = required_paths(&bom)?;
+
+    let large: HashSet<String> = optional(safe_bom_call(|| size64::resolve(&bom)))?
+        .paths
+        .iter()
+        .map(|path| path.path().to_string())
+        .collect();
+    let links = optional(safe_bom_call(|| hardlinks::resolve(&bom)))?;
+    let linked: HashSet<String> = links
+        .paths
+        .iter()
+        .map(|path| path.path().to_string())
+        .collect();
+    let mut groups = HashMap::new();
+    for members in &links.groups {
+        let rendered = PyList::new_bound(py, members.iter().map(|member| style.render(member)));
+        for member in members {
+            groups.insert(member.as_str(), rendered.clone());
+        }
+    }
+    let versioned: HashSet<String> = optional(safe_bom_call(|| bom.vindex()))?
+        .iter()
+        .map(|path| path.path().to_string())
+        .collect();
+
+    let extras = PathExtras::for_paths(&bom, &paths, &path_options);
+    path_options.order.apply(&mut paths);
+    let rows = PyList::empty_bound(py);
+    for path in &paths {
+        let row = extras.entry(path, &style).to_dict(py)?;
+        row.set_item("in_size64", large.contains(path.path()))?;
+        row.set_item("in_hl_index", linked.contains(path.path()))?;
+        row.set_item("hard_link_group", groups.get(path.path()))?;
+        row.set_item("in_vindex", versioned.contains(path.path()))?;
+        rows.append(row)?;
+    }
+
+    Ok(rows)
+}
@@ -0,0 +1,1016 @@
+use crate::{
+    bom_error_to_py,
+    buffer::BomData,
+    cksum::{cksum, ChecksumMode},
+    hardlinks,
+    known::{KnownFiles, KnownValues},
+    lookup::lookup_style,
+    options::ParseOptions,
+    path_type_name,
+    paths::PathStyle,
+    reader::{device_id, file_identity, ReadOptions, DEFAULT_READ_BUFFER_SIZE},
+    required_paths, safe_bom_call,
+    size64::{self, Size64Section},
+    summary,
+    suppress::Suppressions,
+    walk::WalkOptions,
+    writer::path_components,
+    SafeBomCall,
+};
+use apple_bom::{format::ParsedBom, BomPath, BomPathType};
+use pyo3::{
+    exceptions::{PyOSError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// First line of a checkpoint file.
+const CHECKPOINT_MAGIC: &str = "pyapplebom-verify-checkpoint 1";
+
+/// Every finding kind, in the order they are summarized.
+const FINDING_KINDS: [&str; 12] = [
+    "missing",
+    "unreadable",
+    "invalid_path",
+    "type",
+    "mode",
+    "owner",
+    "size",
+    "checksum",
+    "link_target",
+    "hard_link",
+    "known_size",
+    "known_checksum",
+];
+
+/// A way an installed path deviates from its BOM entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Finding {
+    /// Index of the path in BOM order.
+    pub index: usize,
+
+    /// One of [FINDING_KINDS].
+    pub kind: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+
+    /// Whether a suppression rule matched, and the rule's reason.
+    pub suppressed: bool,
+    pub reason: Option<String>,
+}
+
+impl Finding {
+    fn new(index: usize, kind: &str, expected: Option<String>, actual: Option<String>) -> Self {
+        Self {
+            index,
+            kind: kind.to_string(),
+            expected,
+            actual,
+            suppressed: false,
+            reason: None,
+        }
+    }
+}
+
+/// What identifies a verification run, so a checkpoint is only resumed by the
+/// run that wrote it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RunIdentity {
+    bom_checksum: u32,
+    bom_length: usize,
+    root: String,
+    locations: LocationPolicy,
+    follow_symlinks: bool,
+    checksum: &'static str,
+    sha256: bool,
+}
+
+impl RunIdentity {
+    /// The root as a checkpoint records it, followed by the location policy
+    /// and symlink handling when they aren't the default.
+    fn root_field(&self) -> String {
+        let mut field = self.root.clone();
+        if self.locations.separator != Separator::Native {
+            field.push_str(&format!(" [separator {}]", self.locations.separator.name()));
+        }
+        if self.locations.case != CasePolicy::Sensitive {
+            field.push_str(&format!(" [case {}]", self.locations.case.name()));
+        }
+        if self.follow_symlinks {
+            field.push_str(" [follow symlinks]");
+        }
+        field
+    }
+
+    /// The checksum mode as a checkpoint records it, marked when SHA-256
+    /// digests are taken too.
+    fn checksum_field(&self) -> String {
+        if self.sha256 {
+            format!("{}+sha256", self.checksum)
+        } else {
+            self.checksum.to_string()
+        }
+    }
+}
+
+/// Progress saved by an interrupted run.
+struct Checkpoint {
+    verified: usize,
+
+    /// Unsuppressed findings so far per kind, and the number suppressed, kept
+    /// even when findings aren't collected.
+    counts: HashMap<String, usize>,
+    suppressed: usize,
+    findings: Vec<Finding>,
+
+    /// SHA-256 digests taken so far, by path index.
+    digests: BTreeMap<usize, [u8; 32]>,
+}
+
+pub(crate) fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub(crate) fn unescape(value: &str) -> Option<String> {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        out.push(match chars.next()? {
+            '\\' => '\\',
+            't' => '\t',
+            'n' => '\n',
+            'r' => '\r',
+            _ => return None,
+        });
+    }
+    Some(out)
+}
+
+/// Optional values are written as `-` or `=` followed by the escaped value.
+pub(crate) fn encode_field(value: &Option<String>) -> String {
+    match value {
+        None => "-".to_string(),
+        Some(value) => format!("={}", escape(value)),
+    }
+}
+
+pub(crate) fn decode_field(field: &str) -> Option<Option<String>> {
+    match field {
+        "-" => Some(None),
+        _ => Some(Some(unescape(field.strip_prefix('=')?)?)),
+    }
+}
+
+fn encode_checkpoint(
+    identity: &RunIdentity,
+    verified: usize,
+    counts: &HashMap<String, usize>,
+    suppressed: usize,
+    findings: &[Finding],
+    digests: &BTreeMap<usize, [u8; 32]>,
+) -> String {
+    let mut out = format!(
+        "{CHECKPOINT_MAGIC}\nbom\t{}\t{}\nroot\t{}\nchecksum\t{}\nverified\t{verified}\n",
+        identity.bom_checksum,
+        identity.bom_length,
+        escape(&identity.root_field()),
+        identity.checksum_field(),
+    );
+    for kind in FINDING_KINDS {
+        if let Some(count) = counts.get(kind) {
+            out.push_str(&format!("count\t{kind}\t{count}\n"));
+        }
+    }
+    out.push_str(&format!("suppressed\t{suppressed}\n"));
+    for finding in findings {
+        out.push_str(&format!(
+            "finding\t{}\t{}\t{}\t{}",
+            finding.index,
+            escape(&finding.kind),
+            encode_field(&finding.expected),
+            encode_field(&finding.actual),
+        ));
+        if finding.suppressed {
+            out.push_str(&format!("\t{}", encode_field(&finding.reason)));
+        }
+        out.push('\n');
+    }
+    for (index, digest) in digests {
+        out.push_str(&format!("sha256\t{index}\t{}\n", hex::encode(digest)));
+    }
+    out
+}
+
+/// Parse a checkpoint, returning `None` if it is malformed and `Some(Err)` if
+/// it was written by a different run.
+fn decode_checkpoint(text: &str, identity: &RunIdentity) -> Option<Result<Checkpoint, String>> {
+    let mut lines = text.lines();
+    if lines.next()? != CHECKPOINT_MAGIC {
+        return None;
+    }
+
+    let mut field = |name: &str| {
+        let line = lines.next()?;
+        Some(line.strip_prefix(name)?.strip_prefix('\t')?.to_string())
+    };
+    let (bom_checksum, bom_length) = field("bom")?
+        .split_once('\t')
+        .and_then(|(sum, len)| Some((sum.parse::<u32>().ok()?, len.parse::<usize>().ok()?)))?;
+    let root = unescape(&field("root")?)?;
+    let checksum = field("checksum")?;
+    let verified = field("verified")?.parse().ok()?;
+
+    if (bom_checksum, bom_length, root.as_str(), checksum)
+        != (
+            identity.bom_checksum,
+            identity.bom_length,
+            identity.root_field().as_str(),
+            identity.checksum_field(),
+        )
+    {
+        return Some(Err(
+            "it was written for a different BOM, root, or checksum mode".to_string(),
+        ));
+    }
+
+    let mut counts = HashMap::new();
+    let mut suppressed = 0;
+    let mut findings = Vec::new();
+    let mut digests = BTreeMap::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split('\t').collect();
+        match fields.as_slice() {
+            ["count", kind, count] => {
+                counts.insert(kind.to_string(), count.parse().ok()?);
+            }
+            ["suppressed", count] => suppressed = count.parse().ok()?,
+            // Suppressed findings carry their rule's reason as a fifth field.
+            ["finding", index, kind, expected, actual, reason @ ..] if reason.len() <= 1 => {
+                findings.push(Finding {
+                    index: index.parse().ok()?,
+                    kind: unescape(kind)?,
+                    expected: decode_field(expected)?,
+                    actual: decode_field(actual)?,
+                    suppressed: !reason.is_empty(),
+                    reason: match reason {
+                        [reason] => decode_field(reason)?,
+                        _ => None,
+                    },
+                })
+            }
+            ["sha256", index, digest] => {
+                digests.insert(
+                    index.parse().ok()?,
+                    hex::decode(digest).ok()?.try_into().ok()?,
+                );
+            }
+            _ => return None,
+        }
+    }
+
+    Some(Ok(Checkpoint {
+        verified,
+        counts,
+        suppressed,
+        findings,
+        digests,
+    }))
+}
+
+fn load_checkpoint(path: &Path, identity: &RunIdentity) -> PyResult<Option<Checkpoint>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(PyOSError::new_err(format!(
+                "failed reading {}: {err}",
+                path.display()
+            )))
+        }
+    };
+
+    match decode_checkpoint(&text, identity) {
+        Some(Ok(checkpoint)) => Ok(Some(checkpoint)),
+        Some(Err(reason)) => Err(PyValueError::new_err(format!(
+            "cannot resume from checkpoint {}: {reason}",
+            path.display()
+        ))),
+        None => Err(PyValueError::new_err(format!(
+            "invalid checkpoint file {}",
+            path.display()
+        ))),
+    }
+}
+
+/// Replace the checkpoint at `path` without leaving a partial file behind.
+fn save_checkpoint(path: &Path, contents: &str) -> PyResult<()> {
+    let mut staging = path.as_os_str().to_owned();
+    staging.push(".tmp");
+    let staging = PathBuf::from(staging);
+
+    fs::write(&staging, contents)
+        .and_then(|_| fs::rename(&staging, path))
+        .map_err(|err| PyOSError::new_err(format!("failed writing {}: {err}", path.display())))
+}
+
+fn disk_type_name(metadata: &fs::Metadata) -> &'static str {
+    let file_type = metadata.file_type();
+    if file_type.is_symlink() {
+        "link"
+    } else if file_type.is_dir() {
+        "directory"
+    } else if file_type.is_file() {
+        "file"
+    } else if is_device(metadata) {
+        "device"
+    } else {
+        "other"
+    }
+}
+
+#[cfg(unix)]
+fn is_device(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    let file_type = metadata.file_type();
+    file_type.is_block_device() || file_type.is_char_device()
+}
+
+#[cfg(not(unix))]
+fn is_device(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn mode_and_owner(metadata: &fs::Metadata) -> Option<(u16, u32, u32)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.mode() as u16, metadata.uid(), metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn mode_and_owner(_metadata: &fs::Metadata) -> Option<(u16, u32, u32)> {
+    None
+}
+
+/// A BOM path to check, with what was found at its install location.
+struct Target<'a> {
+    index: usize,
+    expected: &'a BomPath,
+    size: u64,
+
+    /// Whether `size` holds only the low 32 bits of a size of 4 GiB or more,
+    /// because the BOM lists the path in `Size64` without its full size.
+    size_truncated: bool,
+
+    location: Result<PathBuf, String>,
+    metadata: io::Result<fs::Metadata>,
+
+    /// How installed paths are looked up, following symlinks or not.
+    lookup: &'a WalkOptions,
+
+    /// For hard links other than the first of their group, the BOM path and
+    /// location of that first link, which carries the content check.
+    link_to: Option<(&'a str, PathBuf)>,
+
+    /// Known-good values of the file, when `known_files` has them.
+    known: Option<KnownValues>,
+}
+
+impl Target<'_> {
+    fn device(&self) -> u64 {
+        self.metadata.as_ref().map_or(0, device_id)
+    }
+
+    /// Whether this path is installed as a hard link to the first link of its
+    /// group, or `None` when that can't be told on this platform.
+    fn is_linked(&self, metadata: &fs::Metadata) -> Option<bool> {
+        let (_, leader) = self.link_to.as_ref()?;
+        let identity = file_identity(metadata)?;
+        Some(
+            self.lookup
+                .metadata(leader)
+                .is_ok_and(|leader| file_identity(&leader) == Some(identity)),
+        )
+    }
+
+    /// Findings for an installed file whose size or checksum differs from its
+    /// known-good values.
+    /// Whether an installed file of `size` bytes has the size the BOM records.
+    fn size_matches(&self, size: u64) -> bool {
+        if self.size_truncated {
+            size64::matches_truncated(self.size, size)
+        } else {
+            size == self.size
+        }
+    }
+
+    /// The recorded size as a finding's expected value.
+    fn expected_size(&self) -> String {
+        if self.size_truncated {
+            format!("{} (truncated to 32 bits)", self.size)
+        } else {
+            self.size.to_string()
+        }
+    }
+
+    fn known_findings(&self, size: Option<u64>, crc32: Option<u32>) -> Vec<Finding> {
+        let Some(known) = self.known else {
+            return Vec::new();
+        };
+        known
+            .deviations(size, crc32)
+            .into_iter()
+            .map(|deviation| {
+                let kind = match deviation.field {
+                    "size" => "known_size",
+                    _ => "known_checksum",
+                };
+                Finding::new(
+                    self.index,
+                    kind,
+                    Some(deviation.expected.to_string()),
+                    Some(deviation.actual.to_string()),
+                )
+            })
+            .collect()
+    }
+
+    /// Compare the installed path against its BOM entry, also returning the
+    /// SHA-256 of a file whose contents were read in full when `sha256` is
+    /// set.
+    fn check(
+        &self,
+        mode: ChecksumMode,
+        buffer_size: usize,
+        sha256: bool,
+    ) -> (Vec<Finding>, Option<[u8; 32]>) {
+        let index = self.index;
+        let expected = self.expected;
+        let expected_type = path_type_name(expected.path_type());
+
+        let location = match &self.location {
+            Ok(location) => location,
+            Err(reason) => {
+                return (
+                    vec![Finding::new(
+                        index,
+                        "invalid_path",
+                        None,
+                        Some(reason.clone()),
+                    )],
+                    None,
+                )
+            }
+        };
+        let metadata = match &self.metadata {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return (
+                    vec![Finding::new(
+                        index,
+                        "missing",
+                        Some(expected_type.to_string()),
+                        None,
+                    )],
+                    None,
+                )
+            }
+            Err(err) => {
+                return (
+                    vec![Finding::new(
+                        index,
+                        "unreadable",
+                        None,
+                        Some(err.to_string()),
+                    )],
+                    None,
+                )
+            }
+        };
+
+        let actual_type = disk_type_name(metadata);
+        if actual_type != expected_type {
+            return (
+                vec![Finding::new(
+                    index,
+                    "type",
+                    Some(expected_type.to_string()),
+                    Some(actual_type.to_string()),
+                )],
+                None,
+            );
+        }
+
+        let mut findings = Vec::new();
+        let mut digest = None;
+        let is_link = matches!(expected.path_type(), BomPathType::Link);
+        if let Some((actual_mode, uid, gid)) = mode_and_owner(metadata) {
+            // Symlink permissions aren't meaningful and differ between systems.
+            if !is_link && actual_mode != expected.file_mode() {
+                findings.push(Finding::new(
+                    index,
+                    "mode",
+                    Some(format!("{:o}", expected.file_mode())),
+                    Some(format!("{actual_mode:o}")),
+                ));
+            }
+            if (uid, gid) != (expected.user_id(), expected.group_id()) {
+                findings.push(Finding::new(
+                    index,
+                    "owner",
+                    Some(format!("{}/{}", expected.user_id(), expected.group_id())),
+                    Some(format!("{uid}/{gid}")),
+                ));
+            }
+        }
+
+        match expected.path_type() {
+            BomPathType::File => {
+                let linked = self.is_linked(metadata);
+                if let (Some(false), Some((leader, _))) = (linked, &self.link_to) {
+                    findings.push(Finding::new(
+                        index,
+                        "hard_link",
+                        Some(leader.to_string()),
+                        None,
+                    ));
+                }
+
+                findings.extend(self.known_findings(Some(metadata.len()), None));
+                if !self.size_matches(metadata.len()) {
+                    findings.push(Finding::new(
+                        index,
+                        "size",
+                        Some(self.expected_size()),
+                        Some(metadata.len().to_string()),
+                    ));
+                } else if linked != Some(true) {
+                    match mode.file_with_sha256(location, metadata.len(), buffer_size, sha256) {
+                        Ok((Some(actual), sha256)) => {
+                            digest = sha256;
+                            if Some(actual) != expected.crc32() {
+                                findings.push(Finding::new(
+                                    index,
+                                    "checksum",
+                                    expected.crc32().map(|sum| sum.to_string()),
+                                    Some(actual.to_string()),
+                                ));
+                            }
+                            findings.extend(self.known_findings(None, Some(actual)));
+                        }
+                        Ok((None, _)) => {}
+                        Err(err) => findings.push(Finding::new(
+                            index,
+                            "unreadable",
+                            None,
+                            Some(err.to_string()),
+                        )),
+                    }
+                }
+            }
+            BomPathType::Link => match fs::read_link(location) {
+                Ok(target) => {
+                    let target = target.to_string_lossy();
+                    let differs = match expected.link_name() {
+                        Some(link_name) => link_name != target,
+                        None => expected.crc32() != Some(cksum(target.as_bytes())),
+                    };
+                    if differs {
+                        findings.push(Finding::new(
+                            index,
+                            "link_target",
+                            expected.link_name().map(str::to_string),
+                            Some(target.to_string()),
+                        ));
+                    }
+                }
+                Err(err) => findings.push(Finding::new(
+                    index,
+                    "unreadable",
+                    None,
+                    Some(err.to_string()),
+                )),
+            },
+            _ => {}
+        }
+
+        (findings, digest)
+    }
+}
+
+fn finding_to_dict<'py>(
+    py: Python<'py>,
+    finding: &Finding,
+    paths: &[BomPath],
+    style: &PathStyle,
+) -> PyResult<Bound<'py, PyDict>> {
+    let item = PyDict::new_bound(py);
+    item.set_item("path", style.render(paths[finding.index].path()))?;
+    item.set_item("kind", &finding.kind)?;
+    item.set_item("expected", &finding.expected)?;
+    item.set_item("actual", &finding.actual)?;
+    if finding.suppressed {
+        item.set_item("reason", &finding.reason)?;
+    }
+    Ok(item)
+}
+
+/// The separator BOM path components are joined with under the root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Separator {
+    /// The platform's, as `Path::join` writes it.
+    Native,
+    Posix,
+    Windows,
+}
+
+impl Separator {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Native => "native",
+            Self::Posix => "posix",
+            Self::Windows => "windows",
+        }
+    }
+
+    /// Whether `c` separates components of a location joined this way.
+    fn is_separator(self, c: char) -> bool {
+        match self {
+            Self::Native => std::path::is_separator(c),
+            Self::Posix => c == '/',
+            Self::Windows => c == '\\' || c == '/',
+        }
+    }
+
+    /// `location` with `component` appended.
+    fn join(self, location: &Path, component: &str) -> PathBuf {
+        let separator = match self {
+            Self::Native => return location.join(component),
+            Self::Posix => '/',
+            Self::Windows => '\\',
+        };
+        let mut joined = location.as_os_str().to_owned();
+        if !joined.to_string_lossy().ends_with(|c| self.is_separator(c)) {
+            joined.push(separator.to_string());
+        }
+        joined.push(component);
+        PathBuf::from(joined)
+    }
+}
+
+/// Whether BOM path components must match installed names exactly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CasePolicy {
+    Sensitive,
+
+    /// Names are matched ignoring case, as on macOS's default file systems,
+    /// when no name matches exactly.
+    Insensitive,
+}
+
+impl CasePolicy {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Sensitive => "sensitive",
+            Self::Insensitive => "insensitive",
+        }
+    }
+}
+
+/// How BOM paths are found under the verified root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct LocationPolicy {
+    separator: Separator,
+    case: CasePolicy,
+}
+
+impl LocationPolicy {
+    fn from_options(separator: &str, case: &str) -> PyResult<Self> {
+        let separator = match separator {
+            "native" => Separator::Native,
+            "posix" => Separator::Posix,
+            "windows" => Separator::Windows,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "separator must be 'native', 'posix', or 'windows', not {separator:?}"
+                )))
+            }
+        };
+        let case = match case {
+            "sensitive" => CasePolicy::Sensitive,
+            "insensitive" => CasePolicy::Insensitive,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "case must be 'sensitive' or 'insensitive', not {case:?}"
+                )))
+            }
+        };
+        Ok(Self { separator, case })
+    }
+
+    /// The installed name in `directory` matching `component` ignoring case,
+    /// the first in sorted order when several do.
+    fn find_ignoring_case(directory: &Path, component: &str) -> Option<String> {
+        let wanted = component.to_lowercase();
+        let mut names: Vec<String> = fs::read_dir(directory)
+            .ok()?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.to_lowercase() == wanted)
+            .collect();
+        names.sort();
+        names.into_iter().next()
+    }
+
+    /// Where BOM path `path` is installed under `root`, or why it can't be
+    /// placed there.
+    fn resolve(&self, root: &Path, path: &str) -> Result<PathBuf, String> {
+        let components = path_components(path).map_err(|err| err.to_string())?;
+        let mut location = root.to_path_buf();
+        for component in &components {
+            if component.contains(|c| self.separator.is_separator(c)) {
+                return Err(format!(
+                    "invalid BOM path {path:?}: component {component:?} contains a {} path \
+                     separator",
+                    self.separator.name()
+                ));
+            }
+            let mut next = self.separator.join(&location, component);
+            if self.case == CasePolicy::Insensitive
+                && matches!(fs::symlink_metadata(&next), Err(err) if err.kind() == io::ErrorKind::NotFound)
+            {
+                if let Some(name) = Self::find_ignoring_case(&location, component) {
+                    next = self.separator.join(&location, &name);
+                }
+            }
+            location = next;
+        }
+        Ok(location)
+    }
+}
+
+/// Verify every path in a BOM against an installed tree, like
+/// `pkgutil --verify`.
+#[allow(clippy::too_many_arguments)]
+#[pyfunction(signature = (
+    data,
+    root = PathBuf::from("/"),
+    *,
+    options = None,
+    path_style = None,
+    path_root = None,
+    separator = "native",
+    case = "sensitive",
+    follow_symlinks = false,
+    checksum = "full",
+    read_buffer_size = DEFAULT_READ_BUFFER_SIZE,
+    per_device_concurrency = 1,
+    checkpoint = None,
+    checkpoint_interval = 1000,
+    on_finding = None,
+    collect_findings = true,
+    suppressions = None,
+    known_files = None,
+    os_build = None,
+    sha256 = false,
+))]
+pub(crate) fn verify_bom(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    root: PathBuf,
+    options: Option<ParseOptions>,
+    path_style: Option<String>,
+    path_root: Option<String>,
+    separator: &str,
+    case: &str,
+    follow_symlinks: bool,
+    checksum: &str,
+    read_buffer_size: usize,
+    per_device_concurrency: usize,
+    checkpoint: Option<PathBuf>,
+    checkpoint_interval: usize,
+    on_finding: Option<Bound<'_, PyAny>>,
+    collect_findings: bool,
+    suppressions: Option<Bound<'_, PyAny>>,
+    known_files: Option<PyRef<'_, KnownFiles>>,
+    os_build: Option<&str>,
+    sha256: bool,
+) -> PyResult<PyObject> {
+    // `on_finding` can run Python code mid-read, so writable buffers are copied.
+    let data = BomData::new(data, false)?;
+    let style = lookup_style(options, path_style, path_root)?;
+    let locations = LocationPolicy::from_options(separator, case)?;
+    let lookup = WalkOptions {
+        follow_symlinks,
+        ..Default::default()
+    };
+    let mode = ChecksumMode::from_name(checksum)?;
+    let reads = ReadOptions::new(read_buffer_size, per_device_concurrency)?;
+    let suppressions = Suppressions::from_py(suppressions.as_ref(), &FINDING_KINDS)?;
+    if checkpoint_interval == 0 {
+        return Err(PyValueError::new_err(
+            "checkpoint_interval must be positive",
+        ));
+    }
+
+    let bom = ParsedBom::parse(&data).map_err(bom_error_to_py)?;
+    let paths = required_paths(&bom)?;
+    let section = match safe_bom_call(|| size64::resolve(&bom)) {
+        SafeBomCall::Value(section) => section,
+        SafeBomCall::MissingVariable | SafeBomCall::Error(_) => Size64Section::default(),
+    };
+    let links = match safe_bom_call(|| hardlinks::resolve(&bom)) {
+        SafeBomCall::Value(section) => section.groups,
+        SafeBomCall::MissingVariable | SafeBomCall::Error(_) => Vec::new(),
+    };
+    let link_leaders: HashMap<&str, &str> = links
+        .iter()
+        .flat_map(|members| {
+            members[1..]
+                .iter()
+                .map(|member| (member.as_str(), members[0].as_str()))
+        })
+        .collect();
+
+    let identity = RunIdentity {
+        bom_checksum: cksum(&data),
+        bom_length: data.len(),
+        root: root.display().to_string(),
+        locations,
+        follow_symlinks,
+        checksum: mode.name(),
+        sha256,
+    };
+    let resumed = match &checkpoint {
+        Some(path) => load_checkpoint(path, &identity)?,
+        None => None,
+    };
+    let resumed_count = resumed.as_ref().map_or(0, |resumed| resumed.verified);
+    if resumed_count > paths.len() {
+        return Err(PyValueError::new_err(format!(
+            "cannot resume from checkpoint: it records {resumed_count} verified paths but the BOM has {}",
+            paths.len()
+        )));
+    }
+    let (mut counts, mut suppressed, mut findings, mut digests) = resumed
+        .map(|resumed| {
+            (
+                resumed.counts,
+                resumed.suppressed,
+                resumed.findings,
+                resumed.digests,
+            )
+        })
+        .unwrap_or_default();
+
+    let mut start = resumed_count;
+    while start < paths.len() {
+        let end = (start + checkpoint_interval).min(paths.len());
+        let targets: Vec<Target<'_>> = (start..end)
+            .map(|index| {
+                let expected = &paths[index];
+                let location = locations.resolve(&root, expected.path());
+                let metadata = match &location {
+                    Ok(location) => lookup.metadata(location),
+                    Err(_) => Err(io::ErrorKind::InvalidInput.into()),
+                };
+                Target {
+                    index,
+                    expected,
+                    size: section
+                        .sizes
+                        .get(expected.path())
+                        .copied()
+                        .unwrap_or(expected.size() as u64),
+                    size_truncated: section.truncated.contains(expected.path()),
+                    location,
+                    metadata,
+                    lookup: &lookup,
+                    link_to: link_leaders
+                        .get(expected.path())
+                        .and_then(|leader| Some((*leader, locations.resolve(&root, leader).ok()?))),
+                    known: known_files.as_ref().and_then(|known| {
+                        known.get(&path_components(expected.path()).ok()?, os_build)
+                    }),
+                }
+            })
+            .collect();
+
+        reads.for_each_per_device(
+            &targets,
+            Target::device,
+            |target| (target.index, target.check(mode, reads.buffer_size, sha256)),
+            |(index, (found, digest))| {
+                if let Some(digest) = digest {
+                    digests.insert(index, digest);
+                }
+                for mut finding in found {
+                    let path = paths[finding.index].path();
+                    if let Some(rule) =
+                        suppressions.find(path, &finding.kind, finding.actual.as_deref())
+                    {
+                        finding.suppressed = true;
+                        finding.reason = rule.reason.clone();
+                        suppressed += 1;
+                    } else {
+                        *counts.entry(finding.kind.clone()).or_default() += 1;
+                        if let Some(on_finding) = &on_finding {
+                            on_finding.call1((finding_to_dict(py, &finding, &paths, &style)?,))?;
+                        }
+                    }
+                    if collect_findings {
+                        findings.push(finding);
+                    }
+                }
+                py.check_signals()
+            },
+        )?;
+
+        start = end;
+        if let Some(path) = &checkpoint {
+            save_checkpoint(
+                path,
+                &encode_checkpoint(&identity, start, &counts, suppressed, &findings, &digests),
+            )?;
+        }
+        py.check_signals()?;
+    }
+
+    if let Some(path) = &checkpoint {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(PyOSError::new_err(format!(
+                    "failed writing {}: {err}",
+                    path.display()
+                )))
+            }
+            _ => {}
+        }
+    }
+
+    let report = PyDict::new_bound(py);
+    report.set_item("root", root.display().to_string())?;
+    report.set_item("separator", locations.separator.name())?;
+    report.set_item("case", locations.case.name())?;
+    report.set_item("follow_symlinks", follow_symlinks)?;
+    report.set_item("checksum", mode.name())?;
+    report.set_item("checked", paths.len())?;
+    report.set_item("resumed", resumed_count)?;
+    let report_summary = summary(
+        py,
+        FINDING_KINDS
+            .iter()
+            .map(|kind| (*kind, counts.get(*kind).copied().unwrap_or(0))),
+    )?;
+    report_summary.set_item("suppressed", suppressed)?;
+    report.set_item("summary", report_summary)?;
+
+    if collect_findings {
+        let actionable = PyList::empty_bound(py);
+        let benign = PyList::empty_bound(py);
+        for finding in &findings {
+            let list = if finding.suppressed {
+                &benign
+            } else {
+                &actionable
+            };
+            list.append(finding_to_dict(py, finding, &paths, &style)?)?;
+        }
+        report.set_item("findings", actionable)?;
+        report.set_item("suppressed", benign)?;
+    } else {
+        report.set_item("findings", py.None())?;
+        report.set_item("suppressed", py.None())?;
+    }
+
+    if sha256 {
+        let by_path = PyDict::new_bound(py);
+        for (index, digest) in &digests {
+            by_path.set_item(style.render(paths[*index].path()), hex::encode(digest))?;
+        }
+        report.set_item("sha256", by_path)?;
+    } else {
+        report.set_item("sha256", py.None())?;
+    }
+
+    Ok(report.into_py(py))
+}
@@ -0,0 +1,158 @@
+use crate::paths::PathGlob;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Filters applied while walking a directory tree.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct WalkOptions {
+    /// Entries matching any of these globs are skipped, along with everything
+    /// beneath them. Patterns without a `/` match the entry name at any depth,
+    /// like `.gitignore` patterns.
+    pub exclude: Vec<(PathGlob, bool)>,
+
+    /// Skip sockets and FIFOs.
+    pub skip_special: bool,
+
+    /// Describe (and descend into) symlink targets instead of the links
+    /// themselves. Dangling links are still recorded as links.
+    pub follow_symlinks: bool,
+}
+
+impl WalkOptions {
+    pub(crate) fn new(
+        exclude: &[String],
+        skip_special: bool,
+        follow_symlinks: bool,
+    ) -> pyo3::PyResult<Self> {
+        let exclude = exclude
+            .iter()
+            .map(|pattern| {
+                let anchored = pattern.trim_end_matches('/').contains('/');
+                Ok((PathGlob::new(pattern)?, anchored))
+            })
+            .collect::<pyo3::PyResult<_>>()?;
+
+        Ok(Self {
+            exclude,
+            skip_special,
+            follow_symlinks,
+        })
+    }
+
+    fn excludes(&self, components: &[String], metadata: &fs::Metadata) -> bool {
+        let name = &components[components.len() - 1..];
+        let excluded = self.exclude.iter().any(|(glob, anchored)| {
+            if *anchored {
+                glob.matches(components)
+            } else {
+                glob.matches(name)
+            }
+        });
+
+        excluded || (self.skip_special && is_special(metadata))
+    }
+
+    /// The metadata of `path`, or of its target when following symlinks and
+    /// the link isn't dangling.
+    pub(crate) fn metadata(&self, path: &Path) -> io::Result<fs::Metadata> {
+        let metadata = fs::symlink_metadata(path)?;
+        if self.follow_symlinks && metadata.file_type().is_symlink() {
+            return Ok(fs::metadata(path).unwrap_or(metadata));
+        }
+
+        Ok(metadata)
+    }
+}
+
+#[cfg(unix)]
+fn is_special(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    let file_type = metadata.file_type();
+    file_type.is_socket() || file_type.is_fifo()
+}
+
+#[cfg(not(unix))]
+fn is_special(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// An entry found by [walk_entries].
+pub(crate) struct WalkEntry {
+    /// Path components relative to the walk root.
+    pub components: Vec<String>,
+    pub path: PathBuf,
+    pub metadata: fs::Metadata,
+}
+
+impl WalkEntry {
+    /// The relative path with `/` as the separator.
+    #[cfg_attr(not(feature = "bundle"), allow(dead_code))]
+    pub(crate) fn relative(&self) -> String {
+        self.components.join("/")
+    }
+}
+
+/// Recursively collect every entry beneath `root`, excluding `root` itself,
+/// with its metadata.
+///
+/// Paths are relative to `root`. Symlinks are recorded rather than followed
+/// unless `options` says otherwise. Entries are produced in sorted order within
+/// each directory so results are deterministic across platforms.
+pub(crate) fn walk_entries(root: &Path, options: &WalkOptions) -> io::Result<Vec<WalkEntry>> {
+    let mut entries = Vec::new();
+    let mut ancestors = Vec::new();
+    walk_dir(root, &[], options, &mut ancestors, &mut entries)?;
+    Ok(entries)
+}
+
+/// `ancestors` holds the canonical paths of the directories being walked when
+/// following symlinks, so a link back up the tree is recorded but not entered.
+fn walk_dir(
+    dir: &Path,
+    prefix: &[String],
+    options: &WalkOptions,
+    ancestors: &mut Vec<PathBuf>,
+    entries: &mut Vec<WalkEntry>,
+) -> io::Result<()> {
+    if options.follow_symlinks {
+        let canonical = fs::canonicalize(dir)?;
+        if ancestors.contains(&canonical) {
+            return Ok(());
+        }
+        ancestors.push(canonical);
+    }
+
+    let mut children = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    children.sort_by_key(|child| child.file_name());
+
+    for child in children {
+        let mut components = prefix.to_vec();
+        components.push(child.file_name().to_string_lossy().to_string());
+        let path = child.path();
+        let metadata = options.metadata(&path)?;
+
+        if options.excludes(&components, &metadata) {
+            continue;
+        }
+
+        let is_dir = metadata.is_dir();
+        entries.push(WalkEntry {
+            components: components.clone(),
+            path: path.clone(),
+            metadata,
+        });
+
+        if is_dir {
+            walk_dir(&path, &components, options, ancestors, entries)?;
+        }
+    }
+
+    if options.follow_symlinks {
+        ancestors.pop();
+    }
+
+    Ok(())
+}
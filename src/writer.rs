@@ -0,0 +1,1390 @@
+use crate::{
+    cksum::{cksum, ChecksumMode},
+    editor::BomStreamWriter,
+    footprint::{Footprint, HeapSize},
+    macho::{file_slices, MachOSlice},
+    modes::{S_IFDIR, S_IFLNK, S_IFMT, S_IFREG},
+    path_type_from_name,
+    paths::PathGlob,
+    reader::{device_id, hard_link_identity, ReadOptions, DEFAULT_READ_BUFFER_SIZE},
+    size64,
+    spill::{merge, spill_error, ExternalSort, Run, Sorted, Source, Spill, SpillOptions},
+    walk::{walk_entries, WalkOptions},
+};
+use apple_bom::{BomPath, BomPathType};
+use pyo3::{
+    exceptions::{PyOSError, PyValueError},
+    prelude::*,
+    types::{PyBytes, PyMapping},
+};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, VecDeque},
+    env,
+    fs::{self, File},
+    io::{self, BufWriter, Cursor, Read, Seek, Write},
+    mem,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Size in bytes of a `Paths` block header: is_path_info, count, next, previous.
+const PATHS_HEADER_LENGTH: u32 = 12;
+
+/// Size in bytes of each entry in a `Paths` block.
+const PATHS_ENTRY_LENGTH: u32 = 8;
+
+/// Block index of the first path record; blocks 0 through 10 are reserved.
+const FIRST_PATH_BLOCK: u32 = 11;
+
+/// Tree block size Apple tooling uses for the `VIndex` tree.
+const VINDEX_BLOCK_SIZE: u32 = 128;
+
+/// Tree block size Apple tooling uses for the empty per-path trees.
+const AUXILIARY_BLOCK_SIZE: u32 = 64;
+
+/// Architecture value Apple tooling records for paths that aren't Mach-O binaries.
+pub(crate) const DEFAULT_ARCHITECTURE: u16 = 15;
+
+/// Set in the architecture field of records that carry a Mach-O slice list.
+const BINARY_INFO_FLAG: u16 = 0x2000;
+
+const PERMISSION_BITS: u16 = 0o7777;
+
+/// Layout knobs for the trees the writer emits.
+#[derive(Clone, Debug)]
+pub(crate) struct WriterOptions {
+    /// `block_size` recorded in the `Paths`, `HLIndex`, and `Size64` trees.
+    /// Their `Paths` blocks are padded to this size.
+    pub block_size: u32,
+
+    /// Maximum number of paths stored in a single leaf `Paths` block.
+    pub max_paths_per_leaf: usize,
+}
+
+impl WriterOptions {
+    pub(crate) fn new(block_size: u32, max_paths_per_leaf: Option<usize>) -> PyResult<Self> {
+        let capacity = paths_capacity(block_size);
+        if capacity < 2 {
+            return Err(PyValueError::new_err(format!(
+                "block_size {block_size} is too small to hold a tree node"
+            )));
+        }
+
+        let max_paths_per_leaf = max_paths_per_leaf.unwrap_or(capacity);
+        if max_paths_per_leaf == 0 || max_paths_per_leaf > capacity {
+            return Err(PyValueError::new_err(format!(
+                "max_paths_per_leaf must be between 1 and {capacity} for block_size {block_size}"
+            )));
+        }
+
+        Ok(Self {
+            block_size,
+            max_paths_per_leaf,
+        })
+    }
+}
+
+/// How files on disk are read when describing them.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ScanOptions {
+    /// Check regular files for Mach-O headers and record their slices.
+    pub inspect_binaries: bool,
+
+    pub checksum: ChecksumMode,
+    pub reads: ReadOptions,
+}
+
+/// Number of entries a `Paths` block of `block_size` bytes can hold.
+fn paths_capacity(block_size: u32) -> usize {
+    let entries = block_size.saturating_sub(PATHS_HEADER_LENGTH) / PATHS_ENTRY_LENGTH;
+    (entries as usize).min(u16::MAX as usize)
+}
+
+/// A per-glob ownership or permission override, like a targeted `chmod`/`chown`.
+#[derive(Clone, Debug)]
+struct PathOverride {
+    glob: PathGlob,
+    permissions: Option<u16>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+}
+
+/// Ownership and permissions applied to every path as it is written,
+/// mirroring the `chown -R` / `chmod` passes packaging scripts run before
+/// `mkbom`.
+///
+/// Precedence, lowest first: the entry's own owner (or the default owner),
+/// `force_owner`, `umask`, then overrides in the order they were added.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OwnershipPolicy {
+    pub default_owner: (u32, u32),
+    pub force_owner: Option<(u32, u32)>,
+    pub umask: u16,
+    overrides: Vec<PathOverride>,
+}
+
+impl OwnershipPolicy {
+    fn apply(&self, key: &[String], entry: &EntrySpec) -> EntrySpec {
+        let mut entry = entry.clone();
+        let (mut uid, mut gid) = self.force_owner.unwrap_or((
+            entry.uid.unwrap_or(self.default_owner.0),
+            entry.gid.unwrap_or(self.default_owner.1),
+        ));
+        entry.mode &= !(self.umask & PERMISSION_BITS);
+
+        for path_override in self.overrides.iter().filter(|o| o.glob.matches(key)) {
+            if let Some(permissions) = path_override.permissions {
+                entry.mode = (entry.mode & !PERMISSION_BITS) | permissions;
+            }
+            uid = path_override.uid.unwrap_or(uid);
+            gid = path_override.gid.unwrap_or(gid);
+        }
+
+        entry.uid = Some(uid);
+        entry.gid = Some(gid);
+        entry
+    }
+}
+
+/// Metadata for a single path to be written.
+#[derive(Clone, Debug)]
+pub(crate) struct EntrySpec {
+    pub path_type: u8,
+    pub mode: u16,
+
+    /// Owner; `None` takes the policy's default.
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub mtime: u32,
+    pub size: u64,
+    pub crc32: Option<u32>,
+    pub link_name: Option<String>,
+    pub architecture: u16,
+
+    /// Mach-O slices, used for the per-architecture totals in `BomInfo` and
+    /// listed in the path record.
+    pub slices: Vec<MachOSlice>,
+
+    /// Identity shared by hard links to the same file; paths with the same
+    /// value are written as one `HLIndex` group.
+    pub hard_link: Option<(u64, u64)>,
+}
+
+impl EntrySpec {
+    fn directory() -> Self {
+        Self {
+            path_type: BomPathType::Directory.into(),
+            mode: S_IFDIR | 0o755,
+            uid: None,
+            gid: None,
+            mtime: 0,
+            size: 0,
+            crc32: None,
+            link_name: None,
+            architecture: DEFAULT_ARCHITECTURE,
+            slices: Vec::new(),
+            hard_link: None,
+        }
+    }
+
+    /// The entry a parsed path record describes, with its full `size`. Mach-O
+    /// slices and hard link identity aren't recovered.
+    #[cfg_attr(not(feature = "delta"), allow(dead_code))]
+    pub(crate) fn from_record(path: &BomPath, size: u64) -> Self {
+        Self {
+            path_type: path.path_type().into(),
+            mode: path.file_mode(),
+            uid: Some(path.user_id()),
+            gid: Some(path.group_id()),
+            mtime: path.modified_time().timestamp().clamp(0, u32::MAX as i64) as u32,
+            size,
+            crc32: path.crc32(),
+            link_name: path.link_name().map(str::to_string),
+            architecture: DEFAULT_ARCHITECTURE,
+            slices: Vec::new(),
+            hard_link: None,
+        }
+    }
+
+    /// Describe a path on disk. Symlinks are recorded rather than followed.
+    ///
+    /// Sockets and FIFOs, which BOMs have no type for, are recorded as files
+    /// whose mode keeps their real type bits.
+    pub(crate) fn from_disk(
+        path: &Path,
+        metadata: &fs::Metadata,
+        scan: ScanOptions,
+    ) -> io::Result<Self> {
+        let file_type = metadata.file_type();
+        let mut slices = Vec::new();
+        let (path_type, crc32, link_name) = if file_type.is_symlink() {
+            let target = fs::read_link(path)?.to_string_lossy().to_string();
+            (
+                BomPathType::Link,
+                Some(cksum(target.as_bytes())),
+                Some(target),
+            )
+        } else if file_type.is_dir() {
+            (BomPathType::Directory, None, None)
+        } else if file_type.is_file() {
+            if scan.inspect_binaries {
+                slices = file_slices(path, metadata.len(), scan.checksum, scan.reads.buffer_size)?;
+            }
+            (
+                BomPathType::File,
+                scan.checksum
+                    .file(path, metadata.len(), scan.reads.buffer_size)?,
+                None,
+            )
+        } else if let Some(device) = device_number(metadata) {
+            (BomPathType::Dev, Some(device), None)
+        } else {
+            (BomPathType::File, None, None)
+        };
+        let path_type: u8 = path_type.into();
+        let (mode, uid, gid) = ownership(path_type, metadata);
+
+        Ok(Self {
+            path_type,
+            mode,
+            uid,
+            gid,
+            mtime: metadata
+                .modified()
+                .ok()
+                .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |age| age.as_secs().min(u32::MAX as u64) as u32),
+            size: if file_type.is_dir() {
+                0
+            } else {
+                metadata.len()
+            },
+            crc32,
+            link_name,
+            architecture: DEFAULT_ARCHITECTURE,
+            slices,
+            hard_link: hard_link_identity(metadata),
+        })
+    }
+
+    /// Whether this path's size overflows the 32-bit record field.
+    fn needs_size64(&self) -> bool {
+        matches!(BomPathType::from(self.path_type), BomPathType::File)
+            && self.size > u32::MAX as u64
+    }
+
+    /// Whether the record ends with the full size (see
+    /// [size64::SIZE64_TRAILER]).
+    fn has_size64_trailer(&self) -> bool {
+        size64::SIZE64_TRAILER && self.needs_size64()
+    }
+
+    /// Whether the record lists Mach-O slices. Files with a size trailer
+    /// keep the plain layout, since the full size takes the slice list's
+    /// place.
+    fn has_binary_info(&self) -> bool {
+        matches!(BomPathType::from(self.path_type), BomPathType::File)
+            && !self.slices.is_empty()
+            && !self.has_size64_trailer()
+    }
+}
+
+/// Apply the file type bits for `path_type` when `mode` only carries permissions.
+fn mode_with_type(path_type: u8, mode: Option<u16>) -> u16 {
+    let (type_bits, default_permissions) = match BomPathType::from(path_type) {
+        BomPathType::File => (S_IFREG, 0o644),
+        BomPathType::Directory => (S_IFDIR, 0o755),
+        BomPathType::Link => (S_IFLNK, 0o755),
+        BomPathType::Dev | BomPathType::Other(_) => (0, 0o644),
+    };
+
+    match mode {
+        Some(mode) if mode & S_IFMT != 0 => mode,
+        Some(mode) => type_bits | mode,
+        None => type_bits | default_permissions,
+    }
+}
+
+#[cfg(unix)]
+fn ownership(_path_type: u8, metadata: &fs::Metadata) -> (u16, Option<u32>, Option<u32>) {
+    use std::os::unix::fs::MetadataExt;
+
+    (
+        metadata.mode() as u16,
+        Some(metadata.uid()),
+        Some(metadata.gid()),
+    )
+}
+
+#[cfg(not(unix))]
+fn ownership(path_type: u8, _metadata: &fs::Metadata) -> (u16, Option<u32>, Option<u32>) {
+    (mode_with_type(path_type, None), None, None)
+}
+
+/// The device number of a block or character device, stored where files keep
+/// their checksum.
+#[cfg(unix)]
+fn device_number(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+    let file_type = metadata.file_type();
+    (file_type.is_block_device() || file_type.is_char_device()).then(|| metadata.rdev() as u32)
+}
+
+#[cfg(not(unix))]
+fn device_number(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Split a BOM path into components, accepting `./a/b`, `a/b`, and `/a/b`.
+///
+/// The root is an empty list.
+pub(crate) fn path_components(path: &str) -> PyResult<Vec<String>> {
+    let trimmed = path
+        .strip_prefix("./")
+        .unwrap_or(path)
+        .trim_start_matches('/');
+    if trimmed.is_empty() || trimmed == "." {
+        return Ok(Vec::new());
+    }
+
+    trimmed
+        .trim_end_matches('/')
+        .split('/')
+        .map(|component| match component {
+            "" | "." | ".." => Err(PyValueError::new_err(format!(
+                "invalid BOM path {path:?}: empty, '.', or '..' component"
+            ))),
+            _ => Ok(component.to_string()),
+        })
+        .collect()
+}
+
+fn encode_path_record(entry: &EntrySpec) -> Vec<u8> {
+    let path_type = BomPathType::from(entry.path_type);
+    let mut out = Vec::with_capacity(35);
+
+    out.push(entry.path_type);
+    out.push(1);
+    let architecture = if entry.has_binary_info() {
+        entry.architecture | BINARY_INFO_FLAG
+    } else {
+        entry.architecture
+    };
+    out.extend_from_slice(&architecture.to_be_bytes());
+    out.extend_from_slice(&entry.mode.to_be_bytes());
+    out.extend_from_slice(&entry.uid.unwrap_or(0).to_be_bytes());
+    out.extend_from_slice(&entry.gid.unwrap_or(0).to_be_bytes());
+    out.extend_from_slice(&entry.mtime.to_be_bytes());
+    out.extend_from_slice(&(entry.size as u32).to_be_bytes());
+    out.push(1);
+    out.extend_from_slice(&entry.crc32.unwrap_or(0).to_be_bytes());
+
+    match (path_type, &entry.link_name) {
+        (BomPathType::Link, Some(link_name)) => {
+            out.extend_from_slice(&(link_name.len() as u32 + 1).to_be_bytes());
+            out.extend_from_slice(link_name.as_bytes());
+            out.push(0);
+            // Apple tooling follows link records with 8 zero bytes.
+            out.extend_from_slice(&[0; 8]);
+        }
+        (BomPathType::File, _) if entry.has_size64_trailer() => {
+            out.extend_from_slice(&0u32.to_be_bytes());
+            // The size field above holds the low 32 bits; the full size
+            // follows the record.
+            out.extend_from_slice(&entry.size.to_be_bytes());
+        }
+        (BomPathType::File, _) if entry.has_binary_info() => {
+            // Apple tooling lists each slice's CPU type, subtype, size, and
+            // checksum where other records store the link name length.
+            out.push(1);
+            out.extend_from_slice(&(entry.slices.len() as u32).to_be_bytes());
+            for slice in &entry.slices {
+                out.extend_from_slice(&slice.cpu_type.to_be_bytes());
+                out.extend_from_slice(&slice.cpu_subtype.to_be_bytes());
+                out.extend_from_slice(&(slice.size as u32).to_be_bytes());
+                out.extend_from_slice(&slice.checksum.to_be_bytes());
+            }
+            out.extend_from_slice(&[0; 8]);
+        }
+        (BomPathType::File, _) => {
+            out.extend_from_slice(&0u32.to_be_bytes());
+            // Apple tooling follows file records with 4 zero bytes.
+            out.extend_from_slice(&[0; 4]);
+        }
+        _ => out.extend_from_slice(&0u32.to_be_bytes()),
+    }
+
+    out
+}
+
+fn encode_file(parent_path_id: u32, name: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(name.len() + 5);
+    out.extend_from_slice(&parent_path_id.to_be_bytes());
+    out.extend_from_slice(name.as_bytes());
+    out.push(0);
+    out
+}
+
+fn encode_pair(first: u32, second: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(8);
+    out.extend_from_slice(&first.to_be_bytes());
+    out.extend_from_slice(&second.to_be_bytes());
+    out
+}
+
+fn encode_tree(block_paths_index: u32, block_size: u32, path_count: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(21);
+    out.extend_from_slice(b"tree");
+    out.extend_from_slice(&1u32.to_be_bytes());
+    out.extend_from_slice(&block_paths_index.to_be_bytes());
+    out.extend_from_slice(&block_size.to_be_bytes());
+    out.extend_from_slice(&path_count.to_be_bytes());
+    out.push(0);
+    out
+}
+
+/// Encode a `Paths` block, padded to the owning tree's block size.
+fn encode_paths(
+    is_path_info: bool,
+    entries: &[(u32, u32)],
+    next: u32,
+    previous: u32,
+    block_size: u32,
+) -> Vec<u8> {
+    let mut out = Vec::with_capacity(block_size as usize);
+    out.extend_from_slice(&u16::from(is_path_info).to_be_bytes());
+    out.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+    out.extend_from_slice(&next.to_be_bytes());
+    out.extend_from_slice(&previous.to_be_bytes());
+    for (block_index, file_index) in entries {
+        out.extend_from_slice(&block_index.to_be_bytes());
+        out.extend_from_slice(&file_index.to_be_bytes());
+    }
+    out.resize(out.len().max(block_size as usize), 0);
+    out
+}
+
+fn encode_vindex(tree_block_index: u32) -> Vec<u8> {
+    let mut out = encode_pair(1, tree_block_index);
+    out.extend_from_slice(&0u32.to_be_bytes());
+    out.push(0);
+    out
+}
+
+/// Add `entry` to the bytes per CPU type recorded in `BomInfo`.
+///
+/// Each Mach-O slice counts toward its CPU type. Everything else that has
+/// content (other files and symlinks) counts toward CPU type 0.
+fn add_architecture_totals(totals: &mut BTreeMap<u32, u64>, entry: &EntrySpec) {
+    match BomPathType::from(entry.path_type) {
+        BomPathType::File | BomPathType::Link if entry.slices.is_empty() => {
+            *totals.entry(0).or_default() += entry.size;
+        }
+        BomPathType::File => {
+            for slice in &entry.slices {
+                *totals.entry(slice.cpu_type).or_default() += slice.size;
+            }
+        }
+        _ => {}
+    }
+}
+
+fn encode_bom_info(number_of_paths: u32, totals: &BTreeMap<u32, u64>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(12 + totals.len() * 16);
+    out.extend_from_slice(&1u32.to_be_bytes());
+    out.extend_from_slice(&number_of_paths.to_be_bytes());
+    out.extend_from_slice(&(totals.len() as u32).to_be_bytes());
+    for (cpu_type, total) in totals {
+        out.extend_from_slice(&cpu_type.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        // Like path sizes, totals are truncated to 32 bits.
+        out.extend_from_slice(&(*total as u32).to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+    }
+    out
+}
+
+/// Block index of the record for `path_id`, which its file and path info
+/// blocks follow.
+fn record_index(path_id: u32) -> u32 {
+    FIRST_PATH_BLOCK + (path_id - 1) * 3
+}
+
+/// Sort key of a path in the `Paths`, `Size64`, and `HLIndex` trees, which
+/// are keyed on (parent path ID, file name), followed by its path ID.
+type TreeKey = (u32, String, u32);
+
+/// The `(path info, file)` entry a tree leaf holds for `key`.
+fn leaf_entry(key: &TreeKey) -> (u32, u32) {
+    let record = record_index(key.2);
+    (record + 2, record + 1)
+}
+
+/// Block layout of a `Paths` tree whose root node lives in a reserved block.
+///
+/// Leaves and lower index nodes are numbered consecutively from the first
+/// block after the reserved ones. Only the last file of each leaf is kept
+/// while planning; leaf blocks are encoded from `keys` when written.
+struct PathsTreeLayout<'a> {
+    root: Vec<u8>,
+    first_block: u32,
+    keys: &'a Sorted<TreeKey>,
+    leaf_count: u32,
+    nodes: Vec<Vec<(u32, u32)>>,
+}
+
+impl<'a> PathsTreeLayout<'a> {
+    /// Plan the tree for sorted `keys`.
+    ///
+    /// A single leaf is the root itself; otherwise index nodes pointing at each
+    /// child and its last file are stacked until one node remains.
+    fn plan(
+        keys: &'a Sorted<TreeKey>,
+        first_block: u32,
+        options: &WriterOptions,
+    ) -> PyResult<Self> {
+        let mut first_leaf = Vec::new();
+        let mut last_files = Vec::new();
+        for (position, key) in keys.iter().map_err(spill_error)?.enumerate() {
+            let (path_info, file) = leaf_entry(&key.map_err(spill_error)?);
+            if position < options.max_paths_per_leaf {
+                first_leaf.push((path_info, file));
+            }
+            match last_files.last_mut() {
+                Some(last_file) if position % options.max_paths_per_leaf != 0 => *last_file = file,
+                _ => last_files.push(file),
+            }
+        }
+
+        if last_files.len() <= 1 {
+            return Ok(Self {
+                root: encode_paths(true, &first_leaf, 0, 0, options.block_size),
+                first_block,
+                keys,
+                leaf_count: 0,
+                nodes: Vec::new(),
+            });
+        }
+
+        let last_file = |entries: &[(u32, u32)]| entries.last().map_or(0, |(_, file)| *file);
+        let mut next_block = first_block + last_files.len() as u32;
+        let mut level: Vec<(u32, u32)> = last_files
+            .iter()
+            .enumerate()
+            .map(|(position, file)| (first_block + position as u32, *file))
+            .collect();
+        let mut nodes = Vec::new();
+
+        let fan_out = paths_capacity(options.block_size);
+        while level.len() > fan_out {
+            let mut parents = Vec::with_capacity(level.len().div_ceil(fan_out));
+            for children in level.chunks(fan_out) {
+                parents.push((next_block, last_file(children)));
+                nodes.push(children.to_vec());
+                next_block += 1;
+            }
+            level = parents;
+        }
+
+        Ok(Self {
+            root: encode_paths(false, &level, 0, 0, options.block_size),
+            first_block,
+            keys,
+            leaf_count: last_files.len() as u32,
+            nodes,
+        })
+    }
+
+    /// Number of blocks written after the reserved root block.
+    fn block_count(&self) -> u32 {
+        self.leaf_count + self.nodes.len() as u32
+    }
+
+    fn write<W: Write + Seek>(
+        &self,
+        out: &mut BomStreamWriter<W>,
+        options: &WriterOptions,
+    ) -> PyResult<()> {
+        if self.leaf_count > 0 {
+            let mut leaf = Vec::with_capacity(options.max_paths_per_leaf);
+            let mut position = 0;
+            for key in self.keys.iter().map_err(spill_error)? {
+                leaf.push(leaf_entry(&key.map_err(spill_error)?));
+                if leaf.len() == options.max_paths_per_leaf {
+                    self.write_leaf(out, position, &leaf, options)?;
+                    leaf.clear();
+                    position += 1;
+                }
+            }
+            if !leaf.is_empty() {
+                self.write_leaf(out, position, &leaf, options)?;
+            }
+        }
+        for node in &self.nodes {
+            out.push(&encode_paths(false, node, 0, 0, options.block_size))?;
+        }
+
+        Ok(())
+    }
+
+    fn write_leaf<W: Write + Seek>(
+        &self,
+        out: &mut BomStreamWriter<W>,
+        position: u32,
+        leaf: &[(u32, u32)],
+        options: &WriterOptions,
+    ) -> PyResult<()> {
+        let index = self.first_block + position;
+        let next = if position + 1 < self.leaf_count {
+            index + 1
+        } else {
+            0
+        };
+        let previous = if position > 0 { index - 1 } else { 0 };
+        out.push(&encode_paths(
+            true,
+            leaf,
+            next,
+            previous,
+            options.block_size,
+        ))?;
+        Ok(())
+    }
+}
+
+/// Entries [write_bom] reads, once to plan the BOM and once to write it.
+pub(crate) trait EntrySource {
+    /// The entries keyed by path components, in order and without
+    /// duplicates.
+    fn sorted(&self) -> io::Result<Source<'_, (Vec<String>, EntrySpec)>>;
+}
+
+impl EntrySource for BTreeMap<Vec<String>, EntrySpec> {
+    fn sorted(&self) -> io::Result<Source<'_, (Vec<String>, EntrySpec)>> {
+        Ok(Box::new(
+            self.iter()
+                .map(|(key, entry)| Ok((key.clone(), entry.clone()))),
+        ))
+    }
+}
+
+/// An entry in a run of staged entries. Runs written later replace earlier
+/// ones, so among equal paths the latest generation sorts first.
+struct StagedEntry {
+    key: Vec<String>,
+    generation: u64,
+    entry: EntrySpec,
+}
+
+impl PartialEq for StagedEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.generation == other.generation
+    }
+}
+
+impl Eq for StagedEntry {}
+
+impl PartialOrd for StagedEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for StagedEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then(other.generation.cmp(&self.generation))
+    }
+}
+
+impl Spill for StagedEntry {
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        self.key.write_to(out)?;
+        self.generation.write_to(out)?;
+        self.entry.write_to(out)
+    }
+
+    fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        Ok(Self {
+            key: Spill::read_from(input)?,
+            generation: Spill::read_from(input)?,
+            entry: Spill::read_from(input)?,
+        })
+    }
+}
+
+/// The entries a [BomBuilder] has staged. With [SpillOptions], every
+/// `threshold` distinct paths are written out as a sorted run and merged back
+/// when the BOM is written.
+#[derive(Default)]
+struct StagedEntries {
+    entries: BTreeMap<Vec<String>, EntrySpec>,
+    runs: Vec<Run<StagedEntry>>,
+    spill: Option<SpillOptions>,
+}
+
+impl StagedEntries {
+    /// Add or replace the entry for `key`.
+    fn insert(&mut self, key: Vec<String>, entry: EntrySpec) -> PyResult<()> {
+        self.entries.insert(key, entry);
+
+        match &self.spill {
+            Some(spill) if self.entries.len() >= spill.threshold => {
+                let generation = self.runs.len() as u64;
+                let staged = mem::take(&mut self.entries)
+                    .into_iter()
+                    .map(|(key, entry)| StagedEntry {
+                        key,
+                        generation,
+                        entry,
+                    });
+                self.runs
+                    .push(Run::create(&spill.directory, staged).map_err(spill_error)?);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn extend(
+        &mut self,
+        entries: impl IntoIterator<Item = (Vec<String>, EntrySpec)>,
+    ) -> PyResult<()> {
+        entries
+            .into_iter()
+            .try_for_each(|(key, entry)| self.insert(key, entry))
+    }
+
+    /// Number of distinct paths, which takes a pass over the runs once any
+    /// have been written.
+    fn len(&self) -> PyResult<usize> {
+        if self.runs.is_empty() {
+            return Ok(self.entries.len());
+        }
+
+        let mut count = 0;
+        for entry in self.sorted().map_err(spill_error)? {
+            entry.map_err(spill_error)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl EntrySource for StagedEntries {
+    fn sorted(&self) -> io::Result<Source<'_, (Vec<String>, EntrySpec)>> {
+        if self.runs.is_empty() {
+            return self.entries.sorted();
+        }
+
+        let generation = self.runs.len() as u64;
+        let mut sources = Vec::with_capacity(self.runs.len() + 1);
+        for run in &self.runs {
+            sources.push(Box::new(run.read()?) as Source<'_, StagedEntry>);
+        }
+        sources.push(Box::new(self.entries.iter().map(move |(key, entry)| {
+            Ok(StagedEntry {
+                key: key.clone(),
+                generation,
+                entry: entry.clone(),
+            })
+        })));
+
+        let mut previous: Option<Vec<String>> = None;
+        Ok(Box::new(merge(sources).filter_map(
+            move |staged| match staged {
+                Ok(staged) if previous.as_ref() == Some(&staged.key) => None,
+                Ok(staged) => {
+                    previous = Some(staged.key.clone());
+                    Some(Ok((staged.key, staged.entry)))
+                }
+                Err(err) => Some(Err(err)),
+            },
+        )))
+    }
+}
+
+/// An entry numbered by [TreeWalk].
+struct WalkedEntry {
+    key: Vec<String>,
+    entry: EntrySpec,
+    path_id: u32,
+    parent_path_id: u32,
+}
+
+impl WalkedEntry {
+    fn name(&self) -> &str {
+        self.key.last().map_or(".", String::as_str)
+    }
+
+    fn tree_key(&self) -> TreeKey {
+        (self.parent_path_id, self.name().to_string(), self.path_id)
+    }
+}
+
+/// Walks sorted entries, synthesizing missing parent directories (including
+/// the root) and handing out path IDs.
+///
+/// Component-wise ordering is a depth-first pre-order walk, which is how
+/// Apple tooling hands out path IDs, so only the path IDs of the last path's
+/// ancestors are kept.
+struct TreeWalk<'a> {
+    entries: Source<'a, (Vec<String>, EntrySpec)>,
+    last_key: Vec<String>,
+
+    /// Path IDs of `last_key` and its ancestors, by depth.
+    ancestors: Vec<u32>,
+
+    path_count: u32,
+    pending: VecDeque<WalkedEntry>,
+    done: bool,
+}
+
+impl<'a> TreeWalk<'a> {
+    fn new(entries: Source<'a, (Vec<String>, EntrySpec)>) -> Self {
+        Self {
+            entries,
+            last_key: Vec::new(),
+            ancestors: Vec::new(),
+            path_count: 0,
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn visit(&mut self, key: Vec<String>, entry: EntrySpec) {
+        let known_depth = match self.path_count {
+            0 => 0,
+            _ => {
+                let shared = self.last_key.iter().zip(&key).take_while(|(a, b)| a == b);
+                shared.count() + 1
+            }
+        };
+        self.ancestors.truncate(known_depth);
+        for depth in known_depth..key.len() {
+            self.number(key[..depth].to_vec(), EntrySpec::directory());
+        }
+
+        self.last_key.clone_from(&key);
+        self.number(key, entry);
+    }
+
+    fn number(&mut self, key: Vec<String>, entry: EntrySpec) {
+        self.path_count += 1;
+        let parent_path_id = match key.len() {
+            0 => 0,
+            depth => self.ancestors[depth - 1],
+        };
+        self.ancestors.push(self.path_count);
+        self.pending.push_back(WalkedEntry {
+            key,
+            entry,
+            path_id: self.path_count,
+            parent_path_id,
+        });
+    }
+}
+
+impl Iterator for TreeWalk<'_> {
+    type Item = io::Result<WalkedEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.done {
+            match self.entries.next() {
+                Some(Ok((key, entry))) => self.visit(key, entry),
+                Some(Err(err)) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+                None => {
+                    self.done = true;
+                    if self.path_count == 0 {
+                        self.number(Vec::new(), EntrySpec::directory());
+                    }
+                }
+            }
+        }
+
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// Serialize `entries` as a complete BOM on `out`.
+///
+/// Missing parent directories (including the root) are synthesized. Block
+/// numbering follows Apple tooling: `BomInfo` at 1, the variable trees at 2
+/// through 10, then a record, file, and path info block for every path. Every
+/// block index is computed up front, so blocks are encoded and written one at
+/// a time rather than the BOM being built in memory.
+///
+/// The entries are read twice. The sort keys of the trees and the hard link
+/// groups are sorted with [ExternalSort], so with `spill` nothing held grows
+/// with the number of paths but the last file of each leaf and the index
+/// nodes above them.
+pub(crate) fn write_bom<W: Write + Seek>(
+    entries: &impl EntrySource,
+    options: &WriterOptions,
+    policy: &OwnershipPolicy,
+    spill: Option<&SpillOptions>,
+    out: W,
+    target: &str,
+) -> PyResult<(W, u64)> {
+    let mut path_count = 0;
+    let mut totals = BTreeMap::new();
+    let mut leaf_keys = ExternalSort::new(spill);
+    let mut large_keys = ExternalSort::new(spill);
+    let mut link_members = ExternalSort::new(spill);
+    for walked in TreeWalk::new(entries.sorted().map_err(spill_error)?) {
+        let walked = walked.map_err(spill_error)?;
+        path_count = walked.path_id;
+        add_architecture_totals(&mut totals, &walked.entry);
+
+        let tree_key = walked.tree_key();
+        if walked.entry.needs_size64() {
+            large_keys.push(tree_key.clone()).map_err(spill_error)?;
+        }
+        if let Some(identity) = walked.entry.hard_link {
+            link_members
+                .push((identity, walked.path_id, tree_key.clone()))
+                .map_err(spill_error)?;
+        }
+        leaf_keys.push(tree_key).map_err(spill_error)?;
+    }
+
+    // Hard links share the record of their group's first path, and every
+    // member is listed in the HLIndex tree.
+    let mut record_owners = ExternalSort::new(spill);
+    let mut link_keys = ExternalSort::new(spill);
+    let link_members = link_members.finish();
+    let mut members = link_members.iter().map_err(spill_error)?;
+    let mut group: Vec<(u32, TreeKey)> = Vec::new();
+    let mut group_identity = None;
+    loop {
+        let member = members.next().transpose().map_err(spill_error)?;
+        if member.as_ref().map(|(identity, ..)| *identity) != group_identity {
+            if group.len() > 1 {
+                let owner = group[0].0;
+                for (path_id, tree_key) in group.drain(..) {
+                    record_owners.push((path_id, owner)).map_err(spill_error)?;
+                    link_keys.push(tree_key).map_err(spill_error)?;
+                }
+            }
+            group.clear();
+        }
+
+        let Some((identity, path_id, tree_key)) = member else {
+            break;
+        };
+        group_identity = Some(identity);
+        group.push((path_id, tree_key));
+    }
+
+    let [leaf_keys, large_keys, link_keys] =
+        [leaf_keys, large_keys, link_keys].map(ExternalSort::finish);
+    let record_owners = record_owners.finish();
+
+    let paths_tree = PathsTreeLayout::plan(&leaf_keys, record_index(path_count + 1), options)?;
+    let size64_tree = PathsTreeLayout::plan(
+        &large_keys,
+        paths_tree.first_block + paths_tree.block_count(),
+        options,
+    )?;
+    let link_tree = PathsTreeLayout::plan(
+        &link_keys,
+        size64_tree.first_block + size64_tree.block_count(),
+        options,
+    )?;
+    let first_auxiliary_block = link_tree.first_block + link_tree.block_count();
+
+    let vars = [
+        ("BomInfo", 1),
+        ("Paths", 2),
+        ("HLIndex", 4),
+        ("VIndex", 6),
+        ("Size64", 9),
+    ]
+    .map(|(name, index)| (name.to_string(), index));
+    let mut out = BomStreamWriter::new(out, target, 1, &vars, spill)?;
+
+    out.push(&[])?;
+    out.push(&encode_bom_info(path_count + 1, &totals))?;
+    out.push(&encode_tree(3, options.block_size, path_count))?;
+    out.push(&paths_tree.root)?;
+    out.push(&encode_tree(5, options.block_size, link_keys.len() as u32))?;
+    out.push(&link_tree.root)?;
+    out.push(&encode_vindex(7))?;
+    out.push(&encode_tree(8, VINDEX_BLOCK_SIZE, 0))?;
+    out.push(&encode_paths(true, &[], 0, 0, VINDEX_BLOCK_SIZE))?;
+    out.push(&encode_tree(
+        10,
+        options.block_size,
+        large_keys.len() as u32,
+    ))?;
+    out.push(&size64_tree.root)?;
+
+    // Record owners are sorted by path ID, the order records are written in.
+    let mut record_owners = record_owners.iter().map_err(spill_error)?.peekable();
+    for walked in TreeWalk::new(entries.sorted().map_err(spill_error)?) {
+        let walked = walked.map_err(spill_error)?;
+        out.push(&encode_path_record(
+            &policy.apply(&walked.key, &walked.entry),
+        ))?;
+        out.push(&encode_file(walked.parent_path_id, walked.name()))?;
+
+        let owned = |owner: &io::Result<(u32, u32)>| {
+            owner
+                .as_ref()
+                .map_or(true, |(path_id, _)| *path_id == walked.path_id)
+        };
+        let record_owner = match record_owners.next_if(owned) {
+            Some(owner) => owner.map_err(spill_error)?.1,
+            None => walked.path_id,
+        };
+        out.push(&encode_pair(walked.path_id, record_index(record_owner)))?;
+    }
+
+    paths_tree.write(&mut out, options)?;
+    size64_tree.write(&mut out, options)?;
+    link_tree.write(&mut out, options)?;
+
+    // Apple tooling also writes an empty tree plus record and tree pointers
+    // for every path. Their purpose is unknown; they're kept for parity.
+    for path_id in 1..=path_count {
+        let tree_index = first_auxiliary_block + (path_id - 1) * 4;
+        out.push(&encode_tree(tree_index + 1, AUXILIARY_BLOCK_SIZE, 0))?;
+        out.push(&encode_paths(true, &[], 0, 0, AUXILIARY_BLOCK_SIZE))?;
+        out.push(&record_index(path_id).to_be_bytes())?;
+        out.push(&tree_index.to_be_bytes())?;
+    }
+
+    out.finish()
+}
+
+/// Look up the first of `names` set on a mapping or object, treating `None` as unset.
+fn entry_field<'py>(
+    entry: &Bound<'py, PyAny>,
+    names: &[&str],
+) -> PyResult<Option<Bound<'py, PyAny>>> {
+    for name in names {
+        let value = match entry.downcast::<PyMapping>() {
+            Ok(mapping) if mapping.contains(*name)? => mapping.get_item(*name)?,
+            Ok(_) => continue,
+            Err(_) if entry.hasattr(*name)? => entry.getattr(*name)?,
+            Err(_) => continue,
+        };
+
+        if !value.is_none() {
+            return Ok(Some(value));
+        }
+    }
+
+    Ok(None)
+}
+
+pub(crate) fn extract_field<'py, T: FromPyObject<'py>>(
+    entry: &Bound<'py, PyAny>,
+    names: &[&str],
+) -> PyResult<Option<T>> {
+    entry_field(entry, names)?
+        .map(|value| value.extract())
+        .transpose()
+}
+
+#[pyclass(module = "pyapplebom._native")]
+pub(crate) struct BomBuilder {
+    options: WriterOptions,
+    policy: OwnershipPolicy,
+    entries: StagedEntries,
+}
+
+impl BomBuilder {
+    /// The staged entries still in memory are the builder's buffer.
+    pub(crate) fn footprint(&self) -> Footprint {
+        Footprint {
+            buffers: self.entries.entries.heap_size(),
+            ..Default::default()
+        }
+    }
+}
+
+#[pymethods]
+impl BomBuilder {
+    #[new]
+    #[pyo3(signature = (
+        *,
+        block_size = 4096,
+        max_paths_per_leaf = None,
+        default_uid = 0,
+        default_gid = 0,
+        force_owner = None,
+        umask = 0,
+        spill_threshold = None,
+        spill_directory = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        block_size: u32,
+        max_paths_per_leaf: Option<usize>,
+        default_uid: u32,
+        default_gid: u32,
+        force_owner: Option<(u32, u32)>,
+        umask: u16,
+        spill_threshold: Option<usize>,
+        spill_directory: Option<PathBuf>,
+    ) -> PyResult<Self> {
+        if umask > PERMISSION_BITS {
+            return Err(PyValueError::new_err(format!(
+                "umask must be between 0 and 0o7777, not {umask:#o}"
+            )));
+        }
+        let spill = match (spill_threshold, spill_directory) {
+            (Some(0), _) => {
+                return Err(PyValueError::new_err("spill_threshold must be at least 1"));
+            }
+            (Some(threshold), directory) => Some(SpillOptions {
+                threshold,
+                directory: directory.unwrap_or_else(env::temp_dir),
+            }),
+            (None, Some(_)) => {
+                return Err(PyValueError::new_err(
+                    "spill_directory needs spill_threshold",
+                ));
+            }
+            (None, None) => None,
+        };
+
+        Ok(Self {
+            options: WriterOptions::new(block_size, max_paths_per_leaf)?,
+            policy: OwnershipPolicy {
+                default_owner: (default_uid, default_gid),
+                force_owner,
+                umask,
+                overrides: Vec::new(),
+            },
+            entries: StagedEntries {
+                spill,
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Override permissions and/or owner for every path matching `pattern`.
+    #[pyo3(signature = (pattern, *, mode = None, uid = None, gid = None))]
+    fn add_override(
+        &mut self,
+        pattern: &str,
+        mode: Option<u16>,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> PyResult<()> {
+        if mode.is_none() && uid.is_none() && gid.is_none() {
+            return Err(PyValueError::new_err(
+                "add_override needs at least one of mode, uid, or gid",
+            ));
+        }
+        if mode.is_some_and(|mode| mode > PERMISSION_BITS) {
+            return Err(PyValueError::new_err(
+                "override mode must be permission bits between 0 and 0o7777",
+            ));
+        }
+
+        self.policy.overrides.push(PathOverride {
+            glob: PathGlob::new(pattern)?,
+            permissions: mode,
+            uid,
+            gid,
+        });
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        path,
+        path_type = "file",
+        *,
+        mode = None,
+        uid = None,
+        gid = None,
+        mtime = 0,
+        size = 0,
+        crc32 = None,
+        link_name = None,
+    ))]
+    fn add_path(
+        &mut self,
+        path: &str,
+        path_type: &str,
+        mode: Option<u16>,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        mtime: u32,
+        size: u64,
+        crc32: Option<u32>,
+        link_name: Option<String>,
+    ) -> PyResult<()> {
+        let path_type = path_type_from_name(path_type)?;
+
+        self.entries.insert(
+            path_components(path)?,
+            EntrySpec {
+                path_type,
+                mode: mode_with_type(path_type, mode),
+                uid,
+                gid,
+                mtime,
+                size,
+                crc32,
+                link_name,
+                architecture: DEFAULT_ARCHITECTURE,
+                slices: Vec::new(),
+                hard_link: None,
+            },
+        )
+    }
+
+    /// Add every entry from an iterable of mappings or objects.
+    ///
+    /// Entries use the `add_path` argument names; the field names of parsed
+    /// path entries (`file_mode`, `user_id`, `group_id`) and `type` /
+    /// `link_target` are accepted as well. Nothing is added if any entry is
+    /// invalid.
+    fn extend(&mut self, entries: &Bound<'_, PyAny>) -> PyResult<()> {
+        let mut parsed = Vec::new();
+
+        for (index, entry) in entries.iter()?.enumerate() {
+            let entry = entry?;
+            let Some(path) = entry_field(&entry, &["path"])? else {
+                return Err(PyValueError::new_err(format!(
+                    "entry {index} has no 'path'"
+                )));
+            };
+            let path_type = match entry_field(&entry, &["path_type", "type"])? {
+                Some(value) => path_type_from_name(&value.extract::<String>()?)?,
+                None => BomPathType::File.into(),
+            };
+            let mode = extract_field::<u16>(&entry, &["mode", "file_mode"])?;
+
+            parsed.push((
+                path_components(&path.extract::<String>()?)?,
+                EntrySpec {
+                    path_type,
+                    mode: mode_with_type(path_type, mode),
+                    uid: extract_field(&entry, &["uid", "user_id"])?,
+                    gid: extract_field(&entry, &["gid", "group_id"])?,
+                    mtime: extract_field(&entry, &["mtime"])?.unwrap_or(0),
+                    size: extract_field(&entry, &["size"])?.unwrap_or(0),
+                    crc32: extract_field(&entry, &["crc32"])?,
+                    link_name: extract_field(&entry, &["link_name", "link_target"])?,
+                    architecture: DEFAULT_ARCHITECTURE,
+                    slices: Vec::new(),
+                    hard_link: None,
+                },
+            ));
+        }
+
+        self.entries.extend(parsed)
+    }
+
+    /// Add `root` as `.` plus everything beneath it, returning the number of
+    /// paths added.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        root,
+        *,
+        exclude = Vec::new(),
+        skip_special = false,
+        follow_symlinks = false,
+        inspect_binaries = true,
+        checksum = "full",
+        read_buffer_size = DEFAULT_READ_BUFFER_SIZE,
+        per_device_concurrency = 1,
+    ))]
+    fn add_directory(
+        &mut self,
+        root: PathBuf,
+        exclude: Vec<String>,
+        skip_special: bool,
+        follow_symlinks: bool,
+        inspect_binaries: bool,
+        checksum: &str,
+        read_buffer_size: usize,
+        per_device_concurrency: usize,
+    ) -> PyResult<usize> {
+        let scan = ScanOptions {
+            inspect_binaries,
+            checksum: ChecksumMode::from_name(checksum)?,
+            reads: ReadOptions::new(read_buffer_size, per_device_concurrency)?,
+        };
+        let options = WalkOptions::new(&exclude, skip_special, follow_symlinks)?;
+        let read_error = |path: &Path, err: io::Error| {
+            PyOSError::new_err(format!("failed reading {}: {err}", path.display()))
+        };
+
+        let metadata = fs::metadata(&root).map_err(|err| read_error(&root, err))?;
+        if !metadata.is_dir() {
+            return Err(PyValueError::new_err(format!(
+                "{} is not a directory",
+                root.display()
+            )));
+        }
+
+        let mut added = vec![(
+            Vec::new(),
+            EntrySpec::from_disk(&root, &metadata, scan).map_err(|err| read_error(&root, err))?,
+        )];
+        let walked = walk_entries(&root, &options).map_err(|err| read_error(&root, err))?;
+        let specs = scan.reads.map_per_device(
+            &walked,
+            |entry| device_id(&entry.metadata),
+            |entry| {
+                EntrySpec::from_disk(&entry.path, &entry.metadata, scan)
+                    .map_err(|err| read_error(&entry.path, err))
+            },
+        );
+        for (entry, spec) in walked.into_iter().zip(specs) {
+            added.push((entry.components, spec?));
+        }
+
+        let count = added.len();
+        self.entries.extend(added)?;
+
+        Ok(count)
+    }
+
+    fn __len__(&self) -> PyResult<usize> {
+        self.entries.len()
+    }
+
+    fn build(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let (data, _) = write_bom(
+            &self.entries,
+            &self.options,
+            &self.policy,
+            self.entries.spill.as_ref(),
+            Cursor::new(Vec::new()),
+            "BOM",
+        )?;
+        Ok(PyBytes::new_bound(py, data.get_ref()).into_py(py))
+    }
+
+    /// Stream the BOM to `path`, returning the number of bytes written.
+    fn write(&self, path: PathBuf) -> PyResult<u64> {
+        let target = path.display().to_string();
+        let file = File::create(&path)
+            .map_err(|err| PyOSError::new_err(format!("failed writing {target}: {err}")))?;
+        let (_, length) = write_bom(
+            &self.entries,
+            &self.options,
+            &self.policy,
+            self.entries.spill.as_ref(),
+            BufWriter::new(file),
+            &target,
+        )?;
+
+        Ok(length)
+    }
+}
@@ -1,5 +1,19 @@
 from __future__ import annotations
 
+import array
+import csv
+import gzip
+import hashlib
+import io
+import json
+import mmap
+import operator
+import os
+import subprocess
+import sys
+import tarfile
+import zipfile
+from concurrent.futures import ThreadPoolExecutor
 from pathlib import Path
 
 import pytest
@@ -53,6 +67,1501 @@ def test_include_raw_block_bytes_exposes_raw_hex() -> None:
     assert all("raw_hex" in block for block in doc["blocks"])
 
 
+def test_raw_block_encoding_bytes_gives_raw_bytes() -> None:
+    hexed = pyapplebom.parse_bom_file(FIXTURE, include_raw_block_bytes=True)
+    doc = pyapplebom.parse_bom_file(
+        FIXTURE, include_raw_block_bytes=True, raw_block_encoding="bytes"
+    )
+
+    assert [block["raw"] for block in doc["blocks"]] == [
+        bytes.fromhex(block["raw_hex"]) for block in hexed["blocks"]
+    ]
+    assert all("raw_hex" not in block for block in doc["blocks"])
+    data = FIXTURE.read_bytes()
+    assert pyapplebom.get_block(data, 1, raw=True, raw_block_encoding="bytes") == doc["blocks"][1]
+    bom = pyapplebom.BomFile(data)
+    block = bom.block(1, include_raw_block_bytes=True, raw_block_encoding="bytes")
+    assert block == doc["blocks"][1]
+    with pytest.raises(ValueError, match="raw_block_encoding must be 'hex' or 'bytes'"):
+        pyapplebom.parse_bom(data, raw_block_encoding="base64")
+
+
+def test_parse_bom_accepts_any_buffer(tmp_path: Path) -> None:
+    data = FIXTURE.read_bytes()
+    expected = pyapplebom.parse_bom_bytes(data)
+
+    assert pyapplebom.parse_bom_bytes(array.array("B", data)) == expected
+    assert pyapplebom.parse_bom(memoryview(data)[::1]) == expected
+    with open(FIXTURE, "rb") as f, mmap.mmap(f.fileno(), 0, access=mmap.ACCESS_READ) as mapped:
+        assert pyapplebom.parse_bom_bytes(mapped) == expected
+        assert pyapplebom.BomFile(mapped).header == expected["header"]
+        assert pyapplebom.get_path(mapped, "./Python 3.9/ReadMe.rtf") is not None
+    # The native functions take buffers themselves, not copies made in Python.
+    native = pyapplebom._native
+    assert native.parse_bom_header(bytearray(data))["header"] == expected["header"]
+    assert native.size_report(memoryview(data)) == pyapplebom.size_report(data)
+    with pytest.raises(TypeError, match="buffer protocol, not str"):
+        pyapplebom.parse_bom_bytes("not bytes")  # type: ignore[arg-type]
+    with pytest.raises(TypeError, match="buffer protocol, not str"):
+        pyapplebom.diff_boms("not bytes", data)  # type: ignore[arg-type]
+
+
+def test_bom_file_borrows_read_only_buffers_with_the_buffer_feature() -> None:
+    data = FIXTURE.read_bytes()
+    in_place = pyapplebom.build_info()["features"]["buffer"]
+
+    view = pyapplebom.memory_footprint(pyapplebom.BomFile(memoryview(data)))
+    assert view["shared" if in_place else "buffers"] == len(data)
+    writable = pyapplebom.memory_footprint(pyapplebom.BomFile(bytearray(data)))
+    assert writable["buffers"] == len(data) and writable["shared"] == 0
+    with open(FIXTURE, "rb") as f, mmap.mmap(f.fileno(), 0, access=mmap.ACCESS_READ) as mapped:
+        bom = pyapplebom.BomFile(mapped)
+        assert bytes(bom.block_view(1)) == bytes(pyapplebom.BomFile(data).block_view(1))
+        bom.close()
+
+
+def test_parse_bom_file_accepts_path_like_and_bytes_paths() -> None:
+    expected = pyapplebom.parse_bom_file(str(FIXTURE))
+
+    assert pyapplebom.parse_bom_file(FIXTURE) == expected
+    assert pyapplebom.parse_bom_file(os.fsencode(FIXTURE)) == expected
+    assert pyapplebom._native.parse_bom_file(FIXTURE) == expected
+    with pytest.raises(OSError, match="failed reading"):
+        pyapplebom.parse_bom_file(FIXTURE.with_name("missing.bom"))
+
+
+def test_parse_bom_stream_reads_file_like_objects(tmp_path: Path) -> None:
+    data = FIXTURE.read_bytes()
+    expected = pyapplebom.parse_bom(data)
+
+    with zipfile.ZipFile(tmp_path / "boms.zip", "w") as archive:
+        archive.writestr("receipt.bom", data)
+    with zipfile.ZipFile(tmp_path / "boms.zip") as archive, archive.open("receipt.bom") as member:
+        assert pyapplebom.parse_bom_stream(member) == expected
+
+    with tarfile.open(tmp_path / "boms.tar", "w") as archive:
+        archive.add(FIXTURE, arcname="receipt.bom")
+    with tarfile.open(tmp_path / "boms.tar") as archive:
+        member = archive.extractfile("receipt.bom")
+        assert member is not None
+        assert pyapplebom.parse_bom_stream(member) == expected
+
+    class ShortReads:
+        def __init__(self) -> None:
+            self.stream = io.BytesIO(data)
+
+        def read(self, size: int) -> bytes:
+            return self.stream.read(min(size, 7))
+
+    assert pyapplebom.parse_bom_stream(ShortReads()) == expected
+
+    with pytest.raises(TypeError, match="binary mode"):
+        pyapplebom.parse_bom_stream(io.StringIO("not a bom"))
+
+
+def test_sections_select_the_path_sections_to_compute() -> None:
+    data = FIXTURE.read_bytes()
+    full = pyapplebom.parse_bom(data, include_blocks=False)
+
+    only_paths = pyapplebom.parse_bom(data, include_blocks=False, sections=["paths"])
+    assert only_paths["paths"] == full["paths"]
+    assert not {"hl_index", "size64", "vindex"} & only_paths.keys()
+
+    selected = pyapplebom.parse_bom_file(
+        FIXTURE, include_blocks=False, sections=("vindex", "hl_index")
+    )
+    assert (selected["hl_index"], selected["vindex"]) == (full["hl_index"], full["vindex"])
+    assert not {"paths", "size64"} & selected.keys()
+    assert pyapplebom.parse_bom(data, include_blocks=False, sections=())["bom_info"] == (
+        full["bom_info"]
+    )
+
+    options = pyapplebom.ParseOptions(sections=["vindex", "paths", "paths"])
+    assert options.sections == ("paths", "vindex")
+    assert options == pyapplebom.ParseOptions(sections=("paths", "vindex"))
+    assert pyapplebom.ParseOptions().sections == ("paths", "hl_index", "size64", "vindex")
+    assert "sections=('paths', 'vindex')" in repr(options)
+    assert "sections=('vindex',)" in repr(options.replace(sections=["vindex"]))
+    with pytest.raises(ValueError, match="sections must be"):
+        pyapplebom.ParseOptions(sections=["blocks"])
+
+
+def test_parse_options_bundle_keyword_arguments() -> None:
+    data = FIXTURE.read_bytes()
+    options = pyapplebom.ParseOptions(
+        include_blocks=False, path_style="absolute", path_order="sorted", typed_paths=True
+    )
+    assert options.path_style == "absolute" and not options.include_blocks
+    assert repr(options).startswith("ParseOptions(include_blocks=False, ")
+
+    expected = pyapplebom.parse_bom(
+        data, include_blocks=False, path_style="absolute", path_order="sorted", typed_paths=True
+    )
+    assert pyapplebom.parse_bom(data, options=options) == expected
+    assert pyapplebom.parse_bom_file(FIXTURE, options=options)["paths"] == expected["paths"]
+    assert pyapplebom.parse_bom_stream(io.BytesIO(data), options=options) == expected
+    assert list(pyapplebom.iter_paths(data, options=options)) == expected["paths"]
+    assert pyapplebom.BomFile(data).paths(options=options) == expected["paths"]
+    assert list(pyapplebom.BomFile(data).iter_paths(options=options)) == expected["paths"]
+
+    relative = options.replace(path_style="relative")
+    assert relative.path_style == "relative" and options.path_style == "absolute"
+    assert relative == pyapplebom.ParseOptions(
+        include_blocks=False, path_style="relative", path_order="sorted", typed_paths=True
+    )
+    assert hash(relative) == hash(options.replace(path_style="relative"))
+    assert len({options, relative, options.replace()}) == 2
+
+    with pytest.raises(ValueError, match="path_order"):
+        pyapplebom.ParseOptions(path_order="random")
+    with pytest.raises(ValueError, match="raw_block_encoding"):
+        options.replace(raw_block_encoding="base64")
+    with pytest.raises(TypeError, match="not both"):
+        pyapplebom.parse_bom(data, options=options, path_style="relative")
+
+
+def test_default_options_apply_to_calls_leaving_them_out() -> None:
+    data = FIXTURE.read_bytes()
+    defaults = pyapplebom.ParseOptions(include_blocks=False, path_style="absolute")
+    pyapplebom.set_default_options(defaults)
+    try:
+        assert pyapplebom.get_default_options() == defaults
+        doc = pyapplebom.parse_bom(data)
+        assert doc["blocks"] is None
+        assert doc["paths"][0]["path"].startswith("/")
+        assert list(pyapplebom.iter_paths(data)) == doc["paths"]
+
+        overridden = pyapplebom.parse_bom(data, path_style="raw")
+        assert overridden["blocks"] is None
+        assert overridden["paths"][0]["path"].startswith(".")
+
+        explicit = pyapplebom.parse_bom(data, options=pyapplebom.ParseOptions())
+        assert explicit == pyapplebom.parse_bom(data, include_blocks=True, path_style="raw")
+    finally:
+        pyapplebom.set_default_options(None)
+    assert pyapplebom.get_default_options() == pyapplebom.ParseOptions()
+
+
+def test_lookups_and_listings_take_options() -> None:
+    data = FIXTURE.read_bytes()
+    path = "/Python 3.9/ReadMe.rtf"
+    options = pyapplebom.ParseOptions(
+        path_style="absolute", path_order="sorted", include_raw_block_bytes=True
+    )
+    pyapplebom.set_default_options(options)
+    try:
+        entry = pyapplebom.get_path(data, path)
+        assert entry is not None and entry["path"] == path
+        assert pyapplebom.path_by_id(data, entry["path_id"]) == entry
+        assert pyapplebom.BomFile(data).get_path(path) == entry
+        assert pyapplebom.ancestors(data, path)[-1]["path"] == "/Python 3.9"
+        assert "raw_hex" in pyapplebom.get_block(data, 1)
+        assert pyapplebom.format_lsbom(data).startswith("/\t")
+    finally:
+        pyapplebom.set_default_options(None)
+
+    assert pyapplebom.get_path(data, path) is None
+    assert pyapplebom.get_path(data, path, options=options) == entry
+    assert pyapplebom.format_lsbom(data, options=options) == pyapplebom.format_lsbom(
+        data, path_style="absolute", path_order="sorted"
+    )
+    mtree = pyapplebom.export_mtree(data, options=options).splitlines()[1:]
+    sorted_paths = pyapplebom.iter_paths(data, path_order="sorted")
+    escaped = [entry["path"].replace(" ", "\\040").replace("\r", "\\015") for entry in sorted_paths]
+    assert [line.split(" ")[0] for line in mtree] == escaped
+    with pytest.raises(TypeError, match="not both"):
+        pyapplebom.get_block(data, 1, options=options, raw=True)
+
+
+def test_diffs_checks_and_reports_take_options(tmp_path) -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./Applications/Tool.app/Contents/Info.plist", size=12, mtime=1700000000)
+    builder.add_path("./usr/bin/tool", mode=0o777, size=5, mtime=1700000000)
+    old = builder.build()
+    builder.add_path("./usr/bin/helper", size=7, mtime=1700000000)
+    new = builder.build()
+    options = pyapplebom.ParseOptions(path_style="absolute")
+    app_path = tmp_path / "Tool.app"
+    (app_path / "Contents").mkdir(parents=True)
+
+    calls = [
+        lambda **kwargs: pyapplebom.diff_boms(old, new, **kwargs),
+        lambda **kwargs: pyapplebom.diff3_boms(old, new, old, **kwargs),
+        lambda **kwargs: pyapplebom.verify_bom(new, tmp_path, **kwargs)["findings"],
+        lambda **kwargs: pyapplebom.compare_with_bundle(new, app_path, **kwargs),
+        lambda **kwargs: pyapplebom.export_hash_set(new, **kwargs),
+        lambda **kwargs: pyapplebom.size_report(new, **kwargs),
+        lambda **kwargs: pyapplebom.mtime_profile(new, **kwargs),
+        lambda **kwargs: pyapplebom.effective_access(new, **kwargs),
+        lambda **kwargs: pyapplebom.path_bloom(new, **kwargs),
+    ]
+    for call in calls:
+        assert call(options=options) == call(path_style="absolute")
+        with pytest.raises(TypeError, match="not both"):
+            call(options=options, path_style="raw")
+
+    assert pyapplebom.diff_boms(old, new, options=options)["added"][0]["path"] == "/usr/bin/helper"
+    # Hash sets stay keyed by installed path unless asked otherwise.
+    pyapplebom.set_default_options(pyapplebom.ParseOptions(path_style="raw"))
+    try:
+        assert pyapplebom.export_hash_set(new) == pyapplebom.export_hash_set(new, options=options)
+    finally:
+        pyapplebom.set_default_options(None)
+    assert '"./usr/bin/tool"' in pyapplebom.export_hash_set(
+        new, options=pyapplebom.ParseOptions(path_style="raw")
+    )
+
+
+def test_kind_and_type_constants_are_the_emitted_objects() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom(data)
+    assert pyapplebom.PathType.FILE == "file"
+    assert {entry["path_type"] for entry in doc["paths"]} <= {
+        pyapplebom.PathType.FILE,
+        pyapplebom.PathType.DIRECTORY,
+        pyapplebom.PathType.LINK,
+    }
+    assert any(entry["path_type"] is pyapplebom.PathType.FILE for entry in doc["paths"])
+    assert any(entry["path_type"] is pyapplebom.PathType.DIRECTORY for entry in doc["paths"])
+    assert any(block["kind"] is pyapplebom.BlockKind.TREE for block in doc["blocks"])
+    assert any(block["kind"] is pyapplebom.BlockKind.PATH_RECORD for block in doc["blocks"])
+
+    typed = pyapplebom.parse_bom(data, typed_paths=True, include_blocks=False)
+    assert typed["paths"][0].path_type is doc["paths"][0]["path_type"]
+    assert typed["paths"][0]["path_type"] is doc["paths"][0]["path_type"]
+    assert not hasattr(pyapplebom.BlockKind, "PATHRECORD")
+
+
+def test_parse_bom_header_reads_only_the_indexes() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom(data)
+    header = pyapplebom.parse_bom_header(bytearray(data))
+
+    assert set(header) == {"format", "byte_length", "header", "blocks_index", "variables"}
+    for key in ("format", "byte_length", "header", "variables"):
+        assert header[key] == doc[key]
+    assert header["blocks_index"] == {"count": doc["blocks_index"]["count"]}
+    with pytest.raises(pyapplebom.BomParseError):
+        pyapplebom.parse_bom_header(b"this is not a bom file")
+
+
+def test_lazy_blocks_parse_each_block_on_access() -> None:
+    data = FIXTURE.read_bytes()
+    eager = pyapplebom.parse_bom(data, interpret_fields=True)["blocks"]
+    lazy = pyapplebom.parse_bom(bytearray(data), interpret_fields=True, lazy_blocks=True)["blocks"]
+
+    assert isinstance(lazy, pyapplebom.BomBlocks)
+    assert len(lazy) == len(eager) and lazy.parsed_count == 0
+    assert lazy[-1] == eager[-1] and lazy[-1] is lazy[len(lazy) - 1]
+    assert lazy.parsed_count == 1
+    assert lazy[1:6:2] == eager[1:6:2]
+    assert list(lazy) == eager and lazy.parsed_count == len(eager)
+    with pytest.raises(IndexError, match="out of range"):
+        lazy[len(eager)]
+
+    from_file = pyapplebom.parse_bom_file(FIXTURE, lazy_blocks=True, include_blocks=False)
+    assert from_file["blocks"] is None
+    options = pyapplebom.ParseOptions(lazy_blocks=True)
+    doc = pyapplebom.parse_bom_file(FIXTURE, options=options)
+    assert doc["blocks"][1] == pyapplebom.get_block(data, 1)
+    assert pyapplebom.memory_footprint(doc)["buffers"] == len(data)
+
+
+def test_duplicate_variables_resolve_as_chosen() -> None:
+    data = FIXTURE.read_bytes()
+    info = bytearray(pyapplebom.BomFile(data).get_variable("BomInfo"))
+    info[4:8] = (7).to_bytes(4, "big")  # number_of_paths
+    # add_block refuses a name already declared, so rename the new one after.
+    patched = pyapplebom.add_block(data, bytes(info), "BomInfX").replace(b"BomInfX", b"BomInfo")
+
+    first = pyapplebom.parse_bom(patched, include_blocks=False)
+    assert [var["name"] for var in first["variables"]].count("BomInfo") == 2
+    original = next(var for var in first["variables"] if var["name"] == "BomInfo")
+    added = first["variables"][-1]
+    assert first["duplicate_variables"] == {
+        "BomInfo": [original["block_index"], added["block_index"]]
+    }
+    assert first["bom_info"] == pyapplebom.parse_bom(data)["bom_info"]
+    assert pyapplebom.parse_bom(data)["duplicate_variables"] is None
+
+    last = pyapplebom.parse_bom(patched, on_duplicate_variable="last")
+    assert last["bom_info"]["number_of_paths"] == 7
+    assert last["variables"] == first["variables"]
+    assert last["paths"] == first["paths"]
+    with pytest.raises(pyapplebom.BomParseError, match='"BomInfo" is declared 2 times'):
+        pyapplebom.parse_bom(patched, on_duplicate_variable="error")
+    with pytest.raises(ValueError, match="on_duplicate_variable must be"):
+        pyapplebom.ParseOptions(on_duplicate_variable="middle")
+
+
+def test_gaps_report_bytes_no_structure_covers() -> None:
+    data = bytearray(FIXTURE.read_bytes())
+    gaps = pyapplebom.parse_bom(data)["gaps"]
+
+    slack = gaps[0]
+    assert (slack["offset"], slack["length"]) == (32, 480)
+    assert slack["after"] == "header" and slack["before"].startswith("block ")
+    assert slack["all_zero"] and "raw_hex" not in slack
+    assert [gap["offset"] for gap in gaps] == sorted(gap["offset"] for gap in gaps)
+
+    data[100:108] = b"ARTIFACT"
+    doc = pyapplebom.parse_bom(
+        data, include_blocks=False, include_raw_block_bytes=True, raw_block_encoding="bytes"
+    )
+    marked = doc["gaps"][0]
+    assert not marked["all_zero"] and marked["raw"][68:76] == b"ARTIFACT"
+    assert all(
+        gap["raw"] == data[gap["offset"] : gap["offset"] + gap["length"]] for gap in doc["gaps"]
+    )
+    hex_gaps = pyapplebom.parse_bom(data, include_raw_block_bytes=True)["gaps"]
+    assert hex_gaps[0]["raw_hex"] == marked["raw"].hex()
+
+
+def test_on_panic_chooses_how_parser_panics_surface() -> None:
+    data = bytearray(FIXTURE.read_bytes())
+    header = pyapplebom.parse_bom_header(data)["header"]
+    last = header.number_of_blocks - 1
+    # Stretch the last block past the end of the data, which panics apple-bom.
+    length_offset = header.blocks_index_offset + 4 + 8 * last + 4
+    data[length_offset : length_offset + 4] = (0x7FFFFFFF).to_bytes(4, "big")
+
+    wrapped = pyapplebom.parse_bom(data)["blocks"][last]
+    assert wrapped["kind"] == "Unknown"
+    assert wrapped["parse_error"].startswith("block parser panicked: range end index")
+
+    aborted = pyapplebom.parse_bom(data, on_panic="abort_section")
+    assert aborted["blocks"] is None
+    assert aborted["parse_errors"]["blocks"].startswith(f"block {last}: block parser panicked")
+    lazy = pyapplebom.parse_bom(data, on_panic="abort_section", lazy_blocks=True)["blocks"]
+    assert lazy[0] == pyapplebom.parse_bom(data)["blocks"][0]
+    with pytest.raises(pyapplebom.BomParseError, match="panicked"):
+        lazy[last]
+
+    with pytest.raises(BaseException, match="range end index") as raised:
+        pyapplebom.parse_bom(data, on_panic="raise")
+    assert type(raised.value).__name__ == "PanicException"
+    options = pyapplebom.ParseOptions(on_panic="raise")
+    assert options.on_panic == "raise" and "on_panic=\"raise\"" in repr(options)
+    with pytest.raises(ValueError, match="on_panic must be"):
+        pyapplebom.parse_bom(data, on_panic="ignore")
+
+
+def test_parse_bom_file_can_memory_map(tmp_path: Path) -> None:
+    for include_blocks in (True, False):
+        assert pyapplebom.parse_bom_file(
+            FIXTURE, include_blocks=include_blocks, mmap=True
+        ) == pyapplebom.parse_bom_file(FIXTURE, include_blocks=include_blocks)
+
+    (tmp_path / "empty.bom").write_bytes(b"")
+    with pytest.raises(pyapplebom.BomParseError):
+        pyapplebom.parse_bom_file(tmp_path / "empty.bom", mmap=True)
+    with pytest.raises(OSError, match="failed reading"):
+        pyapplebom.parse_bom_file(tmp_path / "missing.bom", mmap=True)
+
+
 def test_parse_invalid_data_raises() -> None:
     with pytest.raises(pyapplebom.BomParseError):
         pyapplebom.parse_bom_bytes(b"this is not a bom file")
+
+
+def test_path_style_controls_emitted_paths() -> None:
+    relative = pyapplebom.parse_bom_file(FIXTURE, include_blocks=False, path_style="relative")
+    absolute = pyapplebom.parse_bom_file(
+        FIXTURE,
+        include_blocks=False,
+        path_style="absolute",
+        path_root="/Applications/",
+    )
+
+    assert relative["paths"][0]["path"] == ""
+    assert "Python 3.9/ReadMe.rtf" in {path["path"] for path in relative["paths"]}
+    assert absolute["paths"][0]["path"] == "/Applications"
+    assert "/Applications/Python 3.9/ReadMe.rtf" in {path["path"] for path in absolute["paths"]}
+
+    with pytest.raises(ValueError):
+        pyapplebom.parse_bom_file(FIXTURE, path_style="posix")
+
+
+def test_variables_expose_declaration_order_and_offsets() -> None:
+    doc = pyapplebom.parse_bom_file(FIXTURE, include_blocks=False)
+    variables = doc["variables"]
+
+    assert [variable["order"] for variable in variables] == list(range(len(variables)))
+    assert variables[0]["offset"] == 4
+    for previous, current in zip(variables, variables[1:]):
+        assert current["offset"] == previous["offset"] + 5 + previous["name_length"]
+    last = variables[-1]
+    assert last["offset"] + 5 + last["name_length"] == doc["header"]["vars_index_length"]
+
+
+def test_block_layout_reports_alignment_and_padding() -> None:
+    data = FIXTURE.read_bytes()
+    layout = pyapplebom.block_layout(data)
+    doc = pyapplebom.parse_bom(data)
+    gaps = doc["gaps"]
+
+    assert layout["blocks"] == sum(1 for block in doc["blocks"] if block["length"])
+    assert sum(layout["alignments"].values()) == layout["blocks"]
+    assert layout["alignment"] == min(layout["alignments"])
+    assert layout["overlaps"] == 0
+    assert [(gap["offset"], gap["length"]) for gap in layout["gaps"]] == [
+        (gap["offset"], gap["length"]) for gap in gaps
+    ]
+    assert layout["gaps"][0]["fill"] == 0
+    between = [
+        gap
+        for gap in layout["gaps"]
+        if gap["after"].startswith("block ") and (gap["before"] or "").startswith("block ")
+    ]
+    assert layout["inter_block_gaps"]["count"] == len(between)
+    assert layout["inter_block_gaps"]["bytes"] == sum(gap["length"] for gap in between)
+    assert sum(layout["padding_bytes"].values()) == sum(gap["length"] for gap in gaps)
+
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./usr/local/bin/tool", mode=0o755)
+    built = pyapplebom.block_layout(builder.build())
+    assert built["in_index_order"] and built["inter_block_gaps"]["count"] == 0
+
+
+def test_size_report_reconciles_bom_info_and_payload_sizes() -> None:
+    data = FIXTURE.read_bytes()
+    report = pyapplebom.size_report(data)
+    assert report["summary"]["ok"] and report["payload"] is None
+    assert report["paths"]["total"] == 614772
+    assert report["bom_info"]["totals"] == report["bom_info"]["expected_totals"]
+
+    info = next(block for block in pyapplebom.parse_bom(data)["blocks"] if block["index"] == 1)
+    offset = info["file_offset"] + 4
+    corrupted = data[:offset] + (56).to_bytes(4, "big") + data[offset + 4 :]
+    [discrepancy] = pyapplebom.size_report(corrupted)["discrepancies"]
+    assert discrepancy == {
+        "check": "bom_info_paths",
+        "path": None,
+        "expected": 55,
+        "actual": 56,
+    }
+
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./a", size=3)
+    builder.add_path("./b", size=5)
+    builder.add_path("./l", "link", size=1, link_name="a")
+    archive = b""
+    for name, mode, body in [
+        (".", 0o40755, b""),
+        ("./a", 0o100644, b"abc"),
+        ("./b", 0o100644, b"abcd"),
+        ("./c", 0o100644, b"ab"),
+        ("TRAILER!!!", 0, b""),
+    ]:
+        fields = [0, 0, mode, 0, 0, 1, 0]
+        archive += b"070707" + b"".join(b"%06o" % field for field in fields)
+        archive += b"%011o%06o%011o" % (0, len(name) + 1, len(body))
+        archive += name.encode() + b"\x00" + body
+
+    report = pyapplebom.size_report(builder.build(), gzip.compress(archive), path_style="absolute")
+    assert report["payload"] == {"files": 3, "total": 9}
+    assert report["summary"]["counts"]["bom_info_total"] == 0
+    assert [
+        (item["check"], item["path"], item["expected"], item["actual"])
+        for item in report["discrepancies"]
+    ] == [
+        ("payload_size", "/b", 5, 4),
+        ("payload_missing", "/l", 1, None),
+        ("payload_extra", "/c", None, 2),
+    ]
+
+
+def test_mtime_profile_flags_entries_outside_the_build_window() -> None:
+    profile = pyapplebom.mtime_profile(FIXTURE.read_bytes())
+    assert (profile["count"], profile["unset"]) == (54, 2)
+    assert profile["by_day"] == {"2021-05-03": 52}
+    assert profile["earliest"]["path"] == "."
+    assert profile["build_window"]["end"] == profile["latest"]["mtime"] == 1620061904
+    assert profile["outliers"] == [] and profile["summary"]["ok"]
+
+    built = 1_700_000_000
+    builder = pyapplebom.BomBuilder()
+    for index in range(20):
+        builder.add_path(f"./lib/{index}", mtime=built + index)
+    builder.add_path("./bin/tool", mtime=built + 30 * 86400)
+    builder.add_path("./bin/old", mtime=built - 400 * 86400)
+    profile = pyapplebom.mtime_profile(builder.build(), path_style="absolute")
+
+    assert profile["build_window"]["start"] == built
+    assert profile["build_window"]["count"] == 20
+    assert profile["unset"] == 3
+    assert [(entry["path"], entry["offset"]) for entry in profile["outliers"]] == [
+        ("/bin/old", -400 * 86400),
+        ("/bin/tool", 30 * 86400 - 19),
+    ]
+    assert profile["summary"]["counts"] == {"before": 1, "after": 1}
+    loose = pyapplebom.mtime_profile(builder.build(), tolerance=365 * 86400)
+    assert [entry["path"] for entry in loose["outliers"]] == ["./bin/old"]
+
+    with pytest.raises(ValueError, match="coverage"):
+        pyapplebom.mtime_profile(builder.build(), coverage=0)
+
+
+def test_block_type_histogram_aggregates_a_corpus() -> None:
+    data = FIXTURE.read_bytes()
+    specimen = pyapplebom.add_block(data, b"\xde\xad\xbe\xef" * 3)
+
+    histogram = pyapplebom.block_type_histogram(
+        [FIXTURE, data, b"not a bom", specimen], workers=2
+    )
+
+    blocks = pyapplebom.parse_bom(data)["blocks"]
+    expected = {}
+    for block in blocks:
+        expected[block["kind"]] = expected.get(block["kind"], 0) + 1
+    assert histogram["files"] == 3
+    assert histogram["blocks"] == 3 * len(blocks) + 1
+    for kind, count in expected.items():
+        if kind != "Unknown":
+            assert histogram["kinds"][kind]["blocks"] == 3 * count
+            assert histogram["kinds"][kind]["files"] == 3
+    assert histogram["kinds"]["Unknown"]["blocks"] == 3 * expected.get("Unknown", 0) + 1
+    assert histogram["kinds"]["Unknown"]["max_length"] >= 12
+    assert histogram["kinds"]["Paths"]["bytes"] > 0
+    assert histogram["unknown"]["specimens"][-1] == 3
+    assert [error["index"] for error in histogram["errors"]] == [2]
+
+
+def test_block_type_histogram_reports_unreadable_paths(tmp_path: Path) -> None:
+    histogram = pyapplebom.block_type_histogram([tmp_path / "missing.bom"])
+
+    assert histogram["files"] == 0
+    assert histogram["unknown"] == {"files": 0, "fraction": 0.0, "specimens": []}
+    assert histogram["kinds"]["Tree"] == {
+        "blocks": 0,
+        "bytes": 0,
+        "min_length": None,
+        "max_length": None,
+        "files": 0,
+    }
+    assert "failed reading" in histogram["errors"][0]["error"]
+    with pytest.raises(ValueError, match="workers must be positive"):
+        pyapplebom.block_type_histogram([], workers=0)
+
+
+def test_block_type_histogram_quarantines_bad_inputs(tmp_path: Path) -> None:
+    # Cut off inside the blocks index, which panics apple-bom.
+    bad = tmp_path / "bad.bom"
+    bad.write_bytes(FIXTURE.read_bytes()[:64])
+    quarantine = tmp_path / "quarantine"
+
+    histogram = pyapplebom.block_type_histogram(
+        [FIXTURE, b"not a bom", tmp_path / "missing.bom", bad], quarantine=quarantine
+    )
+
+    assert histogram["files"] == 1
+    by_index = {error["index"]: error for error in histogram["errors"]}
+    assert sorted(by_index) == [1, 2, 3]
+    assert by_index[2]["kind"] == "read" and by_index[2]["quarantined"] is None
+    for index, data, source, kind in (
+        (1, b"not a bom", None, "parse"),
+        (3, bad.read_bytes(), str(bad), "panic"),
+    ):
+        error = by_index[index]
+        assert error["kind"] == kind
+        copy = Path(error["quarantined"])
+        assert copy.parent == quarantine and copy.read_bytes() == data
+        report = json.loads(copy.with_suffix(".json").read_text())
+        assert report == {
+            "operation": "block_type_histogram",
+            "index": index,
+            "source": source,
+            "sha256": hashlib.sha256(data).hexdigest(),
+            "length": len(data),
+            "kind": kind,
+            "error": error["error"],
+        }
+    assert len(list(quarantine.iterdir())) == 4
+
+    plain = pyapplebom.block_type_histogram([b"not a bom"])
+    assert plain["errors"][0]["quarantined"] is None
+
+
+def test_store_and_index_adds_quarantine_bad_inputs(tmp_path: Path) -> None:
+    bad = tmp_path / "bad.bom"
+    bad.write_bytes(FIXTURE.read_bytes()[:64])
+    quarantine = tmp_path / "quarantine"
+    store = pyapplebom.BomStore(tmp_path / "store")
+    index = pyapplebom.CorpusIndex()
+
+    for operation, add in (
+        ("BomStore.add", lambda bom: store.add(bom, quarantine=quarantine)),
+        ("CorpusIndex.add", lambda bom: index.add("key", bom, quarantine=quarantine)),
+    ):
+        with pytest.raises(pyapplebom.BomParseError, match="panicked.*quarantined as") as err:
+            add(bad)
+        copy = quarantine / (hashlib.sha256(bad.read_bytes()).hexdigest() + ".bom")
+        assert str(copy) in str(err.value) and copy.read_bytes() == bad.read_bytes()
+        report = json.loads(copy.with_suffix(".json").read_text())
+        assert report["operation"] == operation and report["index"] is None
+        assert report["source"] == str(bad) and report["kind"] == "panic"
+        with pytest.raises(OSError):
+            add(tmp_path / "missing.bom")
+        add(FIXTURE)
+
+    assert len(list(quarantine.iterdir())) == 2
+    assert len(store) == 1 and index.keys() == ["key"]
+
+
+def test_path_order_selects_tree_or_sorted_order() -> None:
+    builder = pyapplebom.BomBuilder()
+    for path in ("./a-b", "./a/b", "./a", "./B"):
+        builder.add_path(path)
+    data = builder.build()
+
+    tree = pyapplebom.parse_bom(data)
+    ordered = pyapplebom.parse_bom(data, path_order="sorted")
+
+    assert tree["path_order"] == "tree"
+    assert ordered["path_order"] == "sorted"
+    assert [entry["path"] for entry in ordered["paths"]] == [".", "./B", "./a", "./a/b", "./a-b"]
+    assert sorted(entry["path"] for entry in tree["paths"]) == sorted(
+        entry["path"] for entry in ordered["paths"]
+    )
+
+
+def test_path_order_keeps_stored_order_by_default() -> None:
+    doc = pyapplebom.parse_bom_file(FIXTURE, include_raw_block_bytes=True)
+    blocks = {block["index"]: block for block in doc["blocks"]}
+    paths_root = next(v for v in doc["variables"] if v["name"] == "Paths")["block_index"]
+    leaf = blocks[blocks[paths_root]["block_paths_index"]]
+    while not leaf["is_path_info"]:
+        leaf = blocks[leaf["paths"][0]["block_index"]]
+
+    names = []
+    while True:
+        # File blocks are a parent path ID followed by the NUL-terminated name.
+        for entry in leaf["paths"]:
+            name = bytes.fromhex(blocks[entry["file_index"]]["raw_hex"])[4:].split(b"\0")[0]
+            names.append(name.decode())
+        if not leaf["next_paths_block_index"]:
+            break
+        leaf = blocks[leaf["next_paths_block_index"]]
+
+    assert doc["path_order"] == "tree"
+    assert [entry["path"].rsplit("/", 1)[-1] for entry in doc["paths"]] == names
+    with pytest.raises(ValueError, match="path_order must be 'tree' or 'sorted'"):
+        pyapplebom.parse_bom_file(FIXTURE, path_order="lsbom")
+
+
+def test_interpret_fields_annotates_unnamed_block_fields() -> None:
+    plain = pyapplebom.parse_bom_file(FIXTURE)
+    doc = pyapplebom.parse_bom_file(FIXTURE, interpret_fields=True)
+    blocks = {block["kind"]: block for block in doc["blocks"]}
+
+    assert all("interpretations" not in block for block in plain["blocks"])
+    assert set(blocks["PathRecord"]["interpretations"]) == {"a", "b"}
+    assert set(blocks["Tree"]["interpretations"]) == {"a"}
+    assert set(blocks["VIndex"]["interpretations"]) == {"a", "b", "c"}
+    assert "interpretations" not in blocks["Paths"]
+
+    version = blocks["VIndex"]["interpretations"]["a"]
+    assert version["meaning"] == "structure version"
+    assert version["confidence"] == "medium"
+    assert version["is_typical"] == (blocks["VIndex"]["a"] == version["typical"])
+    # Raw values stay as they were.
+    assert [block.get("a") for block in doc["blocks"]] == [
+        block.get("a") for block in plain["blocks"]
+    ]
+
+
+def test_include_tree_positions_points_at_paths_leaf_entries() -> None:
+    doc = pyapplebom.parse_bom_file(
+        FIXTURE, include_raw_block_bytes=True, include_tree_positions=True
+    )
+    blocks = {block["index"]: block for block in doc["blocks"]}
+    leaf_entries = [
+        (entry["block_index"], entry["file_index"])
+        for block in doc["blocks"]
+        if block["kind"] == "Paths" and block["is_path_info"]
+        for entry in block["paths"]
+    ]
+
+    for entry in doc["paths"]:
+        position = entry["tree_position"]
+        assert (position["block_index"], position["file_index"]) in leaf_entries
+        # File blocks are a parent path ID followed by the NUL-terminated name.
+        name = bytes.fromhex(blocks[position["file_index"]]["raw_hex"])[4:].split(b"\0")[0]
+        assert entry["path"].rsplit("/", 1)[-1] == name.decode()
+
+    plain = pyapplebom.parse_bom_file(FIXTURE, include_blocks=False)
+    assert all("tree_position" not in entry for entry in plain["paths"])
+
+
+def test_include_raw_records_carries_path_record_bytes() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom(
+        data, include_tree_positions=True, include_raw_records=True
+    )
+
+    for entry in doc["paths"]:
+        # Path info blocks are the path ID followed by the record's block index.
+        info = pyapplebom.get_block(data, entry["tree_position"]["block_index"], raw=True)
+        record_index = int.from_bytes(bytes.fromhex(info["raw_hex"])[4:8], "big")
+        record = pyapplebom.get_block(data, record_index, raw=True)
+        assert entry["raw_record_hex"] == record["raw_hex"]
+
+    raw = pyapplebom.parse_bom(data, include_raw_records=True, raw_block_encoding="bytes")
+    assert [entry["raw_record"] for entry in raw["paths"]] == [
+        bytes.fromhex(entry["raw_record_hex"]) for entry in doc["paths"]
+    ]
+    typed = pyapplebom.parse_bom(data, include_raw_records=True, typed_paths=True)
+    assert [entry.raw_record for entry in typed["paths"]] == [
+        entry["raw_record"] for entry in raw["paths"]
+    ]
+
+    plain = pyapplebom.parse_bom(data, typed_paths=True)
+    assert all(entry.raw_record is None for entry in plain["paths"])
+    assert all("raw_record_hex" not in entry.to_dict() for entry in plain["paths"])
+
+
+def test_path_entries_expose_path_ids() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom(data)
+    by_id = {entry["path_id"]: entry for entry in doc["paths"]}
+
+    assert len(by_id) == len(doc["paths"])
+    for entry in doc["paths"]:
+        if entry["path"] == ".":
+            assert entry["parent_path_id"] == 0
+        else:
+            parent = by_id[entry["parent_path_id"]]
+            assert entry["path"].rsplit("/", 1)[0] == parent["path"]
+
+    entry = doc["paths"][5]
+    assert pyapplebom.path_by_id(data, entry["path_id"]) == entry
+    relative = pyapplebom.path_by_id(data, entry["path_id"], path_style="relative")
+    assert relative["path"] == entry["path"][2:]
+    with pytest.raises(KeyError, match="no path with ID"):
+        pyapplebom.path_by_id(data, max(by_id) + 1)
+
+    bom = pyapplebom.BomFile(data)
+    assert bom.get_path_by_id(entry["path_id"]) == entry
+    parent = bom.get_path_by_id(entry["parent_path_id"], path_style="absolute")
+    assert parent["path"] == "/" + by_id[entry["parent_path_id"]]["path"][2:]
+    with pytest.raises(KeyError, match="no path with ID"):
+        bom.get_path_by_id(max(by_id) + 1)
+
+
+def test_ancestors_resolve_parent_chain() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./usr", "directory", mode=0o755)
+    builder.add_path("./usr/local", "directory", mode=0o775, gid=80)
+    builder.add_path("./usr/local/bin/tool", mode=0o755)
+    data = builder.build()
+
+    chain = pyapplebom.ancestors(data, "./usr/local/bin/tool")
+
+    assert [entry["path"] for entry in chain] == [".", "./usr", "./usr/local", "./usr/local/bin"]
+    assert (chain[2]["file_mode"], chain[2]["group_id"]) == (0o40775, 80)
+    tool = next(e for e in pyapplebom.parse_bom(data)["paths"] if e["path"].endswith("tool"))
+    assert pyapplebom.ancestors(data, tool["path_id"]) == chain
+    absolute = pyapplebom.ancestors(data, "/usr/local", path_style="absolute")
+    assert [entry["path"] for entry in absolute] == ["/", "/usr"]
+    assert pyapplebom.ancestors(data, ".") == []
+    with pytest.raises(KeyError, match="no path"):
+        pyapplebom.ancestors(data, "./usr/bin")
+
+
+def test_bom_file_serializes_sections_on_demand() -> None:
+    doc = pyapplebom.parse_bom_file(FIXTURE, include_tree_positions=True)
+    bom = pyapplebom.BomFile.open(FIXTURE)
+
+    assert bom.source_path == doc["source_path"]
+    assert bom.byte_length == doc["byte_length"]
+    assert bom.header == doc["header"]
+    assert bom.variables == doc["variables"]
+    assert bom.bom_info == doc["bom_info"]
+    assert bom.block_count == len(doc["blocks"])
+    assert bom.block(1) == doc["blocks"][1]
+    assert bom.paths(include_tree_positions=True) == doc["paths"]
+    sorted_doc = pyapplebom.parse_bom_file(FIXTURE, path_style="relative", path_order="sorted")
+    assert bom.paths(path_style="relative", path_order="sorted") == sorted_doc["paths"]
+
+    from_bytes = pyapplebom.BomFile(FIXTURE.read_bytes())
+    assert from_bytes.source_path is None
+    assert from_bytes.block(0, include_raw_block_bytes=True)["raw_hex"] == ""
+    with pytest.raises(IndexError, match="out of range"):
+        from_bytes.block(bom.block_count)
+    with pytest.raises(pyapplebom.BomParseError):
+        pyapplebom.BomFile(b"this is not a bom file")
+
+
+def test_typed_objects_convert_to_the_dictionary_schema() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom_bytes(data)
+    bom = pyapplebom.BomFile(data)
+
+    assert bom.to_dict() == doc
+    assert pyapplebom.BomFile.open(FIXTURE).to_dict() == pyapplebom.parse_bom_file(FIXTURE)
+    assert bom.to_dict(options=pyapplebom.ParseOptions(include_blocks=False))["blocks"] is None
+
+    plain = json.loads(json.dumps({**doc, "header": doc["header"].to_dict()}))
+    typed = pyapplebom.ParseOptions(typed_paths=True, lazy_blocks=True)
+    assert json.loads(json.dumps(bom.to_dict())) == plain
+    assert json.dumps(bom.to_dict(options=typed)) == bom.to_json(options=typed)
+    assert type(bom.to_dict(options=typed)["header"]) is dict
+    assert json.loads(bom.to_json(options=typed)) == plain
+    assert bom.to_json(indent=2).startswith('{\n  "format": "apple-bom"')
+
+    assert json.loads(bom.header.to_json()) == doc["header"].to_dict()
+    entry = bom.paths(typed_paths=True)[1]
+    assert json.loads(entry.to_json()) == doc["paths"][1]
+    raw = pyapplebom.ParseOptions(include_raw_block_bytes=True, raw_block_encoding="bytes")
+    with pytest.raises(TypeError):
+        bom.to_json(options=raw)
+
+
+def test_to_json_writes_what_json_dumps_writes(tmp_path: Path) -> None:
+    data = FIXTURE.read_bytes()
+    options = pyapplebom.ParseOptions(interpret_fields=True, include_tree_positions=True)
+    doc = pyapplebom.parse_bom(data, options=options)
+    plain = {**doc, "header": doc["header"].to_dict()}
+
+    assert pyapplebom.to_json(data, options=options) == json.dumps(plain)
+    assert pyapplebom.to_json(data, options=options, pretty=True) == json.dumps(plain, indent=2)
+    typed = pyapplebom.ParseOptions(
+        interpret_fields=True, include_tree_positions=True, typed_paths=True, lazy_blocks=True
+    )
+    assert pyapplebom.to_json(data, options=typed) == json.dumps(plain)
+
+    target = tmp_path / "doc.json"
+    assert pyapplebom.to_json(data, path=target) is None
+    assert json.loads(target.read_text()) == json.loads(pyapplebom.to_json(data))
+
+    staged = tmp_path / "staged"
+    staged.mkdir()
+    (staged / "caf\u00e9 \U0001f600\t\"x\"").write_bytes(b"x")
+    staged_data = pyapplebom.build_bom_from_directory(staged)
+    staged_doc = pyapplebom.parse_bom(staged_data)
+    staged_doc["header"] = staged_doc["header"].to_dict()
+    assert pyapplebom.to_json(staged_data) == json.dumps(staged_doc)
+
+    header = pyapplebom.BomFile(data).header
+    assert header.to_json(indent=0) == json.dumps(header.to_dict(), indent=0)
+
+
+def test_bom_file_exports_paths_as_ndjson(tmp_path: Path) -> None:
+    bom = pyapplebom.BomFile(FIXTURE.read_bytes())
+    expected = "".join(
+        json.dumps(entry) + "\n"
+        for entry in bom.paths(path_style="absolute", include_tree_positions=True)
+    )
+
+    target = tmp_path / "paths.ndjson"
+    count = bom.export_paths_ndjson(target, path_style="absolute", include_tree_positions=True)
+    assert count == 54
+    assert target.read_text() == expected
+
+    stream = io.BytesIO()
+    assert bom.export_paths_ndjson(stream, path_order="sorted") == 54
+    lines = stream.getvalue().decode().splitlines()
+    assert [json.loads(line) for line in lines] == bom.paths(path_order="sorted")
+
+    stats = bom.export_paths_ndjson(io.BytesIO(), statistics=True)
+    entries = bom.paths()
+    assert stats["rows"] == len(entries) == 54
+    assert list(stats["columns"]) == [key for key in entries[0] if key != "tree_position"]
+    for name, column in stats["columns"].items():
+        values = [entry[name] for entry in entries if entry[name] is not None]
+        assert column["null_count"] == len(entries) - len(values)
+        bounds = (min(values), max(values)) if values else (None, None)
+        assert (column["min"], column["max"]) == bounds
+        distinct = len(set(values))
+        assert column["distinct_count"] == (distinct if distinct <= 256 else None)
+    assert stats["columns"]["path_type"]["distinct_count"] == 3
+
+    with pytest.raises(TypeError):
+        bom.export_paths_ndjson(io.StringIO())
+    with pytest.raises(OSError, match="failed writing"):
+        bom.export_paths_ndjson(tmp_path / "missing" / "paths.ndjson")
+
+
+def test_bom_file_exports_paths_as_csv(tmp_path: Path) -> None:
+    bom = pyapplebom.BomFile(FIXTURE.read_bytes())
+    entries = bom.paths(path_style="absolute")
+    expected = [list(entries[0])] + [
+        ["" if value is None else str(value) for value in entry.values()] for entry in entries
+    ]
+
+    target = tmp_path / "paths.csv"
+    assert bom.export_paths_csv(target, path_style="absolute") == 54
+    with open(target, newline="") as handle:
+        assert list(csv.reader(handle)) == expected
+    assert '"/Python 3.9/._Icon\r",' in target.read_bytes().decode()
+
+    stream = io.BytesIO()
+    columns = ["path", "size", "link_name"]
+    assert bom.export_paths_csv(stream, delimiter="\t", columns=columns) == 54
+    text = io.StringIO(stream.getvalue().decode(), newline="")
+    rows = list(csv.reader(text, delimiter="\t"))
+    assert rows[0] == columns
+    assert rows[1:] == [
+        [entry["path"], str(entry["size"]), entry["link_name"] or ""] for entry in bom.paths()
+    ]
+
+    stream = io.BytesIO()
+    bom.export_paths_csv(stream, columns=["path", "raw_record_hex"], include_raw_records=True)
+    raw = bom.paths(include_raw_records=True)
+    rows = list(csv.reader(io.StringIO(stream.getvalue().decode(), newline="")))
+    assert rows == [["path", "raw_record_hex"]] + [
+        [entry["path"], entry["raw_record_hex"]] for entry in raw
+    ]
+
+    stats = bom.export_paths_csv(io.BytesIO(), columns=("size", "path_type"), statistics=True)
+    assert stats["rows"] == 54
+    assert list(stats["columns"]) == ["size", "path_type"]
+    assert stats["columns"]["path_type"]["distinct_count"] == 3
+
+    with pytest.raises(ValueError, match="columns must name"):
+        bom.export_paths_csv(io.BytesIO(), columns=["path", "tree_position"])
+    with pytest.raises(ValueError, match="delimiter must be one character"):
+        bom.export_paths_csv(io.BytesIO(), delimiter=", ")
+    with pytest.raises(OSError, match="failed writing"):
+        bom.export_paths_csv(tmp_path / "missing" / "paths.csv")
+
+
+def test_bom_file_pages_through_paths() -> None:
+    data = FIXTURE.read_bytes()
+    bom = pyapplebom.BomFile(data)
+    everything = bom.paths(path_order="sorted")
+
+    assert bom.count_paths() == len(everything) == 54
+    pages = [
+        bom.paths(offset=offset, limit=10, path_order="sorted")
+        for offset in range(0, bom.count_paths(), 10)
+    ]
+    assert [len(page) for page in pages] == [10, 10, 10, 10, 10, 4]
+    assert [entry for page in pages for entry in page] == everything
+    assert bom.paths(offset=50) == bom.paths()[50:]
+    assert bom.paths(limit=0) == [] and bom.paths(offset=100, limit=10) == []
+    typed = bom.paths(offset=5, limit=2, typed_paths=True)
+    assert [entry.to_dict() for entry in typed] == bom.paths()[5:7]
+
+    no_paths = pyapplebom.BomFile(pyapplebom.remove_variable(data, "Paths"))
+    assert no_paths.count_paths() is None
+    assert no_paths.paths(offset=10, limit=10) is None
+
+
+def test_bom_file_is_a_mapping_of_paths() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom_bytes(data, include_blocks=False)
+    bom = pyapplebom.BomFile(data)
+
+    assert len(bom) == len(doc["paths"])
+    assert list(bom) == [entry["path"] for entry in doc["paths"]]
+    readme = bom.get_path("./Python 3.9/ReadMe.rtf")
+    assert bom["./Python 3.9/ReadMe.rtf"] == readme
+    assert bom["Python 3.9/ReadMe.rtf"]["path"] == "Python 3.9/ReadMe.rtf"
+    assert bom["/Python 3.9/ReadMe.rtf"]["path"] == "/Python 3.9/ReadMe.rtf"
+    assert bom["/"]["path_type"] == "directory"
+    assert "/Python 3.9/ReadMe.rtf" in bom and "Python 3.9" in bom
+    assert "/Python 3.9/Missing.rtf" not in bom and 42 not in bom
+    with pytest.raises(KeyError):
+        bom["./Python 3.9/Missing.rtf"]
+
+    no_paths = pyapplebom.BomFile(pyapplebom.remove_variable(data, "Paths"))
+    assert len(no_paths) == 0 and list(no_paths) == [] and "." not in no_paths
+    bom.close()
+    with pytest.raises(ValueError, match="closed"):
+        len(bom)
+
+
+def test_header_is_a_typed_object() -> None:
+    data = FIXTURE.read_bytes()
+    header = pyapplebom.parse_bom(data)["header"]
+
+    assert isinstance(header, pyapplebom.BomHeader)
+    assert header.magic == header["magic"] == "BOMStore"
+    assert isinstance(header.number_of_blocks, int)
+    assert header.vars_index_length == header.to_dict()["vars_index_length"]
+    assert header == header.to_dict() and header.to_dict() == header
+    assert header == pyapplebom.BomFile(data).header == pyapplebom.parse_bom_header(data)["header"]
+    assert header != {**header.to_dict(), "version": header.version + 1}
+    assert json.loads(json.dumps(header.to_dict()))["version"] == header.version
+    assert repr(header).startswith('BomHeader(magic="BOMStore", version=')
+    with pytest.raises(KeyError):
+        header["missing"]
+
+
+def test_block_view_shares_the_bom_bytes() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom_bytes(data, include_raw_block_bytes=True, raw_block_encoding="bytes")
+
+    bom = pyapplebom.BomFile(data)
+    views = [bom.block_view(block["index"]) for block in doc["blocks"]]
+    assert [bytes(view) for view in views] == [block["raw"] for block in doc["blocks"]]
+    view = max(views, key=len)
+    assert view.readonly and view.obj is data
+    opened = pyapplebom.BomFile.open(FIXTURE).block_view(1)
+    assert opened.readonly and bytes(opened) == doc["blocks"][1]["raw"]
+    copied = pyapplebom.BomFile(bytearray(data)).block_view(1)
+    assert copied.obj is not data and bytes(copied) == doc["blocks"][1]["raw"]
+    with pytest.raises(IndexError, match="out of range"):
+        bom.block_view(len(doc["blocks"]))
+
+
+def test_get_block_parses_one_block() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom_bytes(data, include_raw_block_bytes=True, interpret_fields=True)
+    plain = pyapplebom.parse_bom_bytes(data)
+
+    assert pyapplebom.get_block(data, 1) == plain["blocks"][1]
+    last = len(doc["blocks"]) - 1
+    assert pyapplebom.get_block(data, last, raw=True, interpret_fields=True) == doc["blocks"][last]
+    assert pyapplebom.get_block(bytearray(data), 0, raw=True)["raw_hex"] == ""
+    with pytest.raises(IndexError, match="out of range"):
+        pyapplebom.get_block(data, len(doc["blocks"]))
+    with pytest.raises(pyapplebom.BomParseError):
+        pyapplebom.get_block(b"this is not a bom file", 0)
+
+
+def test_typed_paths_emit_bom_path_entries() -> None:
+    doc = pyapplebom.parse_bom_file(FIXTURE, include_tree_positions=True)
+    typed = pyapplebom.parse_bom_file(FIXTURE, include_tree_positions=True, typed_paths=True)
+
+    assert [entry.to_dict() for entry in typed["paths"]] == doc["paths"]
+    readme = next(entry for entry in typed["paths"] if entry.path == "./Python 3.9/ReadMe.rtf")
+    assert isinstance(readme, pyapplebom.BomPathEntry)
+    assert (readme.path_type, readme.symbolic_mode) == ("file", "-rw-r--r--")
+    assert readme.uid == readme["user_id"]
+    assert readme.tree_position == (
+        readme["tree_position"]["block_index"],
+        readme["tree_position"]["file_index"],
+    )
+    assert repr(readme).startswith('BomPathEntry(path="./Python 3.9/ReadMe.rtf"')
+    with pytest.raises(KeyError):
+        readme["uid"]
+
+    bom = pyapplebom.BomFile.open(FIXTURE)
+    assert bom.paths(typed_paths=True) == pyapplebom.parse_bom_file(FIXTURE, typed_paths=True)[
+        "paths"
+    ]
+    assert pyapplebom.risk_score(typed) == pyapplebom.risk_score(doc)
+
+
+def test_path_entries_compare_and_hash_by_path_and_record() -> None:
+    data = FIXTURE.read_bytes()
+    entries = pyapplebom.parse_bom(data, typed_paths=True)["paths"]
+    positioned = pyapplebom.parse_bom(data, typed_paths=True, include_tree_positions=True)["paths"]
+
+    assert positioned == entries and positioned[1].tree_position != entries[1].tree_position
+    assert set(positioned) == set(entries) and len(set(entries)) == len(entries)
+    assert {entry: entry.path for entry in entries}[positioned[1]] == entries[1].path
+
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./a", size=3, crc32=1)
+    builder.add_path("./b", size=3, crc32=1)
+    old = builder.build()
+    builder.add_path("./0")
+    new = builder.build()
+    old_entries = set(pyapplebom.parse_bom(old, typed_paths=True)["paths"])
+    new_entries = set(pyapplebom.parse_bom(new, typed_paths=True)["paths"])
+    assert [entry.path for entry in new_entries - old_entries] == ["./0"]
+    assert not old_entries - new_entries
+    [a] = [entry for entry in old_entries if entry.path == "./a"]
+    [b] = [entry for entry in old_entries if entry.path == "./b"]
+    assert a != b and a.to_dict() != b.to_dict()
+
+
+def test_path_bloom_answers_membership_queries() -> None:
+    data = FIXTURE.read_bytes()
+    paths = [entry["path"] for entry in pyapplebom.parse_bom(data, path_style="absolute")["paths"]]
+    bloom = pyapplebom.path_bloom(data, path_style="absolute")
+
+    assert all(pyapplebom.bloom_contains(bloom, path) for path in paths)
+    absent = [f"/Applications/Missing {index}.app" for index in range(2000)]
+    assert sum(pyapplebom.bloom_contains(bloom, path) for path in absent) < 20
+    assert len(bloom) < 4 * len(paths)
+    assert pyapplebom.path_bloom(data, path_style="absolute") == bloom
+    loose = pyapplebom.path_bloom(data, 0.1)
+    assert len(loose) < len(bloom)
+    assert pyapplebom.bloom_contains(loose, paths[1].replace("/", "./", 1))
+
+    with pytest.raises(ValueError, match="fp_rate"):
+        pyapplebom.path_bloom(data, 1.0)
+    with pytest.raises(ValueError, match="not a path_bloom filter"):
+        pyapplebom.bloom_contains(b"not a filter", "/")
+
+
+def test_iter_paths_yields_entries_lazily() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom(data, path_order="sorted", include_tree_positions=True)
+
+    paths = pyapplebom.iter_paths(data, path_order="sorted", include_tree_positions=True)
+    assert iter(paths) is paths
+    assert operator.length_hint(paths) == len(doc["paths"])
+    assert next(paths) == doc["paths"][0]
+    assert operator.length_hint(paths) == len(doc["paths"]) - 1
+    assert list(paths) == doc["paths"][1:]
+    assert next(paths, None) is None
+
+    bom = pyapplebom.BomFile(data)
+    typed = bom.iter_paths(path_style="absolute", typed_paths=True)
+    assert isinstance(next(typed), pyapplebom.BomPathEntry)
+    assert list(bom.iter_paths()) == bom.paths()
+    with pytest.raises(ValueError, match="path_order"):
+        pyapplebom.iter_paths(data, path_order="reverse")
+
+
+def test_paths_tree_nests_entries_by_directory() -> None:
+    data = FIXTURE.read_bytes()
+    paths = pyapplebom.parse_bom(data, include_blocks=False)["paths"]
+    tree = pyapplebom.paths_tree(data)
+
+    def walk(node: dict, prefix: str) -> list:
+        found = [node["entry"]]
+        for name, child in node["children"].items():
+            assert child["entry"]["path"] == f"{prefix}/{name}"
+            found += walk(child, f"{prefix}/{name}")
+        return found
+
+    assert tree["entry"]["path"] == "."
+    found = walk(tree, ".")
+    assert len(found) == len(paths)
+    assert sorted(found, key=operator.itemgetter("path")) == sorted(
+        paths, key=operator.itemgetter("path")
+    )
+
+    typed = pyapplebom.BomFile(data).paths_tree(path_style="absolute", typed_paths=True)
+    readme = typed["children"]["Python 3.9"]["children"]["ReadMe.rtf"]["entry"]
+    assert isinstance(readme, pyapplebom.BomPathEntry)
+    assert readme.path == "/Python 3.9/ReadMe.rtf"
+    assert typed["children"]["._Python 3.9"]["children"] == {}
+    with pytest.raises(pyapplebom.BomParseError, match="no Paths variable"):
+        pyapplebom.paths_tree(pyapplebom.remove_variable(data, "Paths"))
+
+
+def test_walk_yields_directories_like_os_walk() -> None:
+    data = FIXTURE.read_bytes()
+    paths = pyapplebom.parse_bom(data, include_blocks=False)["paths"]
+
+    walked = list(pyapplebom.walk(data))
+    dirpath, dir_entries, file_entries = walked[0]
+    assert dirpath == "."
+    assert [entry["path"] for entry in dir_entries] == ["./Python 3.9"]
+    assert [entry["path"] for entry in file_entries] == ["./._Python 3.9"]
+    seen = [entry for _, dirs, files in walked for entry in dirs + files]
+    assert len(seen) == len(paths) - 1
+    assert all(entry["path_type"] == "directory" for _, dirs, _ in walked for entry in dirs)
+    assert sorted(dirpath for dirpath, _, _ in walked) == sorted(
+        ["."] + [entry["path"] for _, dirs, _ in walked for entry in dirs]
+    )
+
+    bottom_up = [dirpath for dirpath, _, _ in pyapplebom.walk(data, topdown=False)]
+    assert sorted(bottom_up) == sorted(dirpath for dirpath, _, _ in walked)
+    assert bottom_up[-1] == "." and bottom_up.index("./Python 3.9/IDLE.app") < bottom_up.index(
+        "./Python 3.9"
+    )
+
+    pruned = []
+    for dirpath, dir_entries, _ in pyapplebom.walk(data, "/Python 3.9", path_style="absolute"):
+        pruned.append(dirpath)
+        dir_entries[:] = [entry for entry in dir_entries if not entry["path"].endswith(".app")]
+    assert pruned[0] == "/Python 3.9"
+    assert not any(".app" in dirpath for dirpath in pruned)
+    assert list(pyapplebom.walk(data, "Python 3.9/ReadMe.rtf")) == []
+    assert list(pyapplebom.walk(data, "missing")) == []
+
+
+def test_get_path_finds_one_entry_by_exact_path() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom(data, path_style="absolute", path_root="/Applications")
+
+    for entry in doc["paths"][::7]:
+        found = pyapplebom.get_path(
+            data, entry["path"], path_style="absolute", path_root="/Applications"
+        )
+        assert found == entry
+    raw = pyapplebom.get_path(data, "./Python 3.9/ReadMe.rtf")
+    assert raw is not None and raw["path_type"] == "file"
+    relative = pyapplebom.get_path(data, "Python 3.9/ReadMe.rtf", path_style="relative")
+    assert relative == {**raw, "path": "Python 3.9/ReadMe.rtf"}
+    assert pyapplebom.get_path(data, ".")["path_type"] == "directory"
+    assert pyapplebom.get_path(data, "./Python 3.9/Missing.rtf") is None
+    assert pyapplebom.get_path(data, "Python 3.9/ReadMe.rtf") is None
+    assert pyapplebom.get_path(data, "/usr", path_style="absolute", path_root="/opt") is None
+
+    bom = pyapplebom.BomFile(data)
+    assert bom.get_path("./Python 3.9/ReadMe.rtf") == raw
+
+
+def test_get_path_reports_the_sizes_parse_bom_does_for_large_files() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./big.img", size=5_000_000_000, crc32=1)
+    builder.add_path("./small", size=3, crc32=2)
+    data = builder.build()
+    sizes = {entry["path"]: entry["size"] for entry in pyapplebom.parse_bom(data)["paths"]}
+
+    assert pyapplebom.get_path(data, "./big.img")["size"] == sizes["./big.img"]
+    if pyapplebom.build_info()["features"]["size64_trailer"]:
+        assert sizes["./big.img"] == 5_000_000_000
+    assert pyapplebom.get_path(data, "./small")["size"] == 3
+
+
+def test_bom_store_deduplicates_and_answers_queries(tmp_path: Path) -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./usr/local/bin/tool", mode=0o755, size=3, crc32=1)
+    builder.add_path("./usr/local/lib/libtool.dylib", size=4, crc32=2)
+    tool = builder.build()
+    (tmp_path / "tool.bom").write_bytes(tool)
+
+    store = pyapplebom.BomStore(tmp_path / "store")
+    digest = store.add(tmp_path / "tool.bom", metadata={"host": "mac-042"})
+    assert store.add(tool, source="upload") == digest
+    fixture = store.add(FIXTURE)
+
+    assert len(store) == 2 and digest in store
+    assert store.digests() == sorted([digest, fixture])
+    assert store.read(digest) == tool
+    assert store.open(fixture).paths() == pyapplebom.parse_bom_file(FIXTURE)["paths"]
+    sources = store.sources(digest)
+    assert [(s["source"], s["metadata"]) for s in sources] == [
+        (str(tmp_path / "tool.bom"), {"host": "mac-042"}),
+        ("upload", {}),
+    ]
+    assert sources[0]["ingested_at"] > 0
+
+    assert store.which_pkg("/usr/local/bin/tool", path_style="absolute") == [digest]
+    assert store.which_pkg("./Python 3.9/ReadMe.rtf") == [fixture]
+    assert store.which_pkg("./missing") == []
+    assert store.glob("**/*.dylib") == [{"digest": digest, "path": "./usr/local/lib/libtool.dylib"}]
+
+    reopened = pyapplebom.BomStore(tmp_path / "store")
+    assert reopened.digests() == store.digests()
+    assert reopened.sources(digest) == sources
+    reopened.remove(fixture)
+    assert fixture not in reopened
+    assert pyapplebom.BomStore(tmp_path / "store").digests() == [digest]
+    with pytest.raises(KeyError, match="no BOM"):
+        reopened.read(fixture)
+    with pytest.raises(pyapplebom.BomParseError):
+        reopened.add(b"this is not a bom file")
+
+
+def test_corpus_index_updates_incrementally(tmp_path: Path) -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./usr/local/bin/tool", mode=0o755, size=3, crc32=1)
+    builder.add_path("./usr/local/lib/libtool.dylib", size=4, crc32=2)
+    tool = builder.build()
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./usr/local/bin/tool", mode=0o755, size=5, crc32=3)
+    tool2 = builder.build()
+
+    index = pyapplebom.CorpusIndex()
+    index.add("tool", tool)
+    index.add("fixture", FIXTURE)
+    assert index.keys() == ["fixture", "tool"] and len(index) == 2 and "tool" in index
+    assert index.which_pkg("/usr/local/bin/tool", path_style="absolute") == ["tool"]
+    assert index.which_pkg("./Python 3.9/ReadMe.rtf") == ["fixture"]
+    assert index.glob("**/*.dylib") == [{"key": "tool", "path": "./usr/local/lib/libtool.dylib"}]
+
+    index.add("tool2", tool2)
+    assert index.which_pkg("./usr/local/bin/tool") == ["tool", "tool2"]
+    index.add("tool", tool2)
+    assert index.glob("**/*.dylib") == []
+    index.remove("tool2")
+    assert index.which_pkg("./usr/local/bin/tool") == ["tool"]
+    with pytest.raises(KeyError, match="no BOM"):
+        index.remove("tool2")
+    with pytest.raises(pyapplebom.BomParseError):
+        index.add("junk", b"not a bom")
+
+    store = pyapplebom.BomStore(tmp_path / "store")
+    digest = store.add(tool)
+    assert store.which_pkg("./usr/local/bin/tool") == [digest]
+    digest2 = store.add(tool2)
+    assert store.which_pkg("./usr/local/bin/tool") == sorted([digest, digest2])
+    store.remove(digest)
+    assert store.which_pkg("./usr/local/bin/tool") == [digest2]
+    assert store.glob("**/*.dylib") == []
+
+
+def test_build_info_describes_the_extension() -> None:
+    info = pyapplebom.build_info()
+    assert info["version"] == pyapplebom.__version__
+    assert info["apple_bom_version"].count(".") == 2
+    assert info["features"]["bundle"] and info["features"]["delta"]
+    assert set(info["features"]) == {"buffer", "bundle", "delta", "mmap", "size64_trailer"}
+    assert issubclass(pyapplebom.FeatureNotAvailable, Exception)
+    mapped = sys.platform in ("linux", "darwin") and sys.maxsize > 2**32
+    assert info["features"]["mmap"] is mapped
+
+
+def test_memory_footprint_reports_rust_side_memory(tmp_path: Path) -> None:
+    data = FIXTURE.read_bytes()
+    opened = pyapplebom.memory_footprint(pyapplebom.BomFile.open(FIXTURE))
+    assert opened["buffers"] >= len(data) and opened["shared"] == 0
+    assert opened["total"] == opened["buffers"] + opened["caches"] + opened["indices"]
+    shared = pyapplebom.memory_footprint(pyapplebom.BomFile(data))
+    assert shared["shared"] == len(data) and shared["total"] == 0
+
+    iterator = pyapplebom.iter_paths(data)
+    before = pyapplebom.memory_footprint(iterator)
+    assert before["caches"] > 0 and before["indices"] > 0
+    list(iterator)
+    assert pyapplebom.memory_footprint(iterator)["caches"] == 0
+
+    index = pyapplebom.CorpusIndex()
+    empty = pyapplebom.memory_footprint(index)["indices"]
+    index.add("fixture", data)
+    assert pyapplebom.memory_footprint(index)["indices"] > empty
+
+    store = pyapplebom.BomStore(tmp_path / "store")
+    store.add(data)
+    unindexed = pyapplebom.memory_footprint(store)
+    assert unindexed["caches"] > 0 and unindexed["indices"] == 0
+    store.which_pkg("./missing")
+    assert pyapplebom.memory_footprint(store)["indices"] > 0
+
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./usr/local/bin/tool", mode=0o755, size=3, crc32=1)
+    assert pyapplebom.memory_footprint(builder)["buffers"] > 0
+
+    assert pyapplebom.memory_footprint(pyapplebom.parse_bom(data))["total"] == 0
+    typed = pyapplebom.memory_footprint(pyapplebom.parse_bom(data, typed_paths=True))
+    assert typed["caches"] > 0
+    with pytest.raises(TypeError, match="memory_footprint expects"):
+        pyapplebom.memory_footprint(data)
+
+
+def test_handles_release_resources_on_close(tmp_path: Path) -> None:
+    with pyapplebom.BomFile.open(FIXTURE) as bom:
+        assert not bom.closed and bom.block_count > 0
+    assert bom.closed and repr(bom) == f'<closed BomFile.open("{FIXTURE}")>'
+    with pytest.raises(ValueError, match="closed BomFile"):
+        bom.paths()
+    assert pyapplebom.memory_footprint(bom)["total"] == 0
+    bom.close()
+
+    view = pyapplebom.BomFile(FIXTURE.read_bytes())
+    block = view.block_view(1)
+    view.close()
+    assert len(bytes(block)) == len(block)
+
+    with pyapplebom.CorpusIndex() as index:
+        index.add("fixture", FIXTURE)
+    assert index.closed and pyapplebom.memory_footprint(index)["indices"] == 0
+    with pytest.raises(ValueError, match="closed CorpusIndex"):
+        index.which_pkg("./Python 3.9/ReadMe.rtf")
+
+    with pyapplebom.BomStore(tmp_path / "store") as store:
+        digest = store.add(FIXTURE)
+    with pytest.raises(ValueError, match="closed BomStore"):
+        len(store)
+    with pytest.raises(ValueError, match="closed BomStore"):
+        store.read(digest)
+    assert pyapplebom.BomStore(tmp_path / "store").digests() == [digest]
+
+    iterator = pyapplebom.iter_paths(FIXTURE.read_bytes())
+    next(iterator)
+    iterator.close()
+    assert list(iterator) == [] and pyapplebom.memory_footprint(iterator)["total"] == 0
+
+
+def test_handles_serve_concurrent_queries_from_threads(tmp_path: Path) -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./usr/local/bin/tool", mode=0o755, size=3, crc32=1)
+    tool = builder.build()
+    index = pyapplebom.CorpusIndex()
+    index.add("fixture", FIXTURE)
+    store = pyapplebom.BomStore(tmp_path / "store")
+    digest = store.add(FIXTURE)
+    bom = pyapplebom.BomFile.open(FIXTURE)
+    expected_paths = bom.paths()
+
+    def query(n: int) -> None:
+        assert "fixture" in index.which_pkg("./Python 3.9/ReadMe.rtf")
+        assert store.which_pkg("./Python 3.9/ReadMe.rtf") == [digest]
+        assert bom.paths() == expected_paths
+        # Writers interleave with the readers without disturbing them.
+        index.add(f"tool-{n}", tool)
+        assert f"tool-{n}" in index.which_pkg("./usr/local/bin/tool")
+        index.remove(f"tool-{n}")
+
+    with ThreadPoolExecutor(max_workers=8) as pool:
+        list(pool.map(query, range(64)))
+    assert index.keys() == ["fixture"]
+
+
+def test_shared_path_table_is_readable_from_other_processes() -> None:
+    data = FIXTURE.read_bytes()
+    table = pyapplebom.SharedPathTable.create(data, path_style="absolute")
+    try:
+        path = "/Python 3.9/ReadMe.rtf"
+        expected = pyapplebom.get_path(data, path, path_style="absolute")
+        paths = pyapplebom.parse_bom(data, include_blocks=False, path_style="absolute")["paths"]
+        assert table.get_path(path) == expected and path in table
+        assert table.get_path("/missing") is None and "/missing" not in table
+        assert list(table) == sorted(entry["path"] for entry in paths) and len(table) == len(paths)
+
+        script = (
+            "import json, sys, pyapplebom\n"
+            "with pyapplebom.SharedPathTable(sys.argv[1]) as table:\n"
+            "    print(json.dumps(table.get_path(sys.argv[2])))\n"
+        )
+        # Run against the same copy of the package this test imported.
+        env = {**os.environ, "PYTHONPATH": str(Path(pyapplebom.__file__).parent.parent)}
+        for _ in range(2):
+            # A worker exiting leaves the segment for the next one.
+            output = subprocess.run(
+                [sys.executable, "-c", script, table.name, path],
+                check=True,
+                capture_output=True,
+                text=True,
+                env=env,
+            ).stdout
+            assert json.loads(output) == expected
+
+        table.close()
+        assert table.closed
+        with pytest.raises(ValueError, match="closed SharedPathTable"):
+            table.get_path(path)
+    finally:
+        table.unlink()
+    with pytest.raises(FileNotFoundError):
+        pyapplebom.SharedPathTable(table.name)
+
+
+def test_corpus_queries_export_to_ndjson_and_csv(tmp_path: Path) -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./usr/local/bin/tool", mode=0o755, size=3, crc32=1)
+    index = pyapplebom.CorpusIndex()
+    index.add('tool "a",b', builder.build())
+    index.add("fixture", FIXTURE)
+
+    count = index.export_glob("**/*.rtf", tmp_path / "out.ndjson", path_style="absolute")
+    rows = [json.loads(line) for line in (tmp_path / "out.ndjson").read_text().splitlines()]
+    assert count == len(rows) > 0
+    assert rows == index.glob("**/*.rtf", path_style="absolute")
+
+    assert index.export_glob("**/bin/tool", tmp_path / "out.csv", format="csv") == 1
+    with open(tmp_path / "out.csv", newline="") as f:
+        assert list(csv.reader(f)) == [["key", "path"], ['tool "a",b', "./usr/local/bin/tool"]]
+
+    store = pyapplebom.BomStore(tmp_path / "store")
+    digest = store.add(FIXTURE)
+    store.export_glob("**/*.rtf", tmp_path / "store.ndjson")
+    lines = (tmp_path / "store.ndjson").read_text().splitlines()
+    assert [json.loads(line) for line in lines] == store.glob("**/*.rtf")
+    assert all(json.loads(line)["digest"] == digest for line in lines)
+    with pytest.raises(ValueError, match="format must be 'ndjson' or 'csv'"):
+        store.export_glob("**", tmp_path / "out.db", format="sqlite")
+
+
+def test_bom_file_exposes_variable_blocks() -> None:
+    doc = pyapplebom.parse_bom_file(FIXTURE, include_raw_block_bytes=True)
+    bom = pyapplebom.BomFile.open(FIXTURE)
+
+    assert bom.variable_names() == [variable["name"] for variable in doc["variables"]]
+    for variable in doc["variables"]:
+        block = doc["blocks"][variable["block_index"]]
+        assert bom.get_variable(variable["name"]) == bytes.fromhex(block["raw_hex"])
+    assert bom.get_variable("BomInfo")[:4] == b"\0\0\0\1"
+    with pytest.raises(KeyError, match="not found"):
+        bom.get_variable("NoSuchVariable")
@@ -0,0 +1,241 @@
+from __future__ import annotations
+
+import pytest
+
+import pyapplebom
+
+
+def build_package() -> bytes:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./usr/local/bin/tool", mode=0o755)
+    builder.add_path("./usr/local/bin/helper", mode=0o4755)
+    builder.add_path("./usr/local/share/tool/data", mode=0o666)
+    builder.add_path("./usr/local/share/tool/cache", "directory", mode=0o1777)
+    builder.add_path("./Library/LaunchDaemons/com.example.tool.plist", mode=0o644)
+    builder.add_path("./Library/LaunchAgents/com.example.agent.plist", mode=0o644)
+    builder.add_path("./Library/Extensions/Tool.kext/Contents/Info.plist")
+    builder.add_path("./Library/Extensions/Tool.kext/Contents/PlugIns/Inner.kext", "directory")
+    builder.add_path("./Applications/Tool.app/Contents/MacOS/tool", mode=0o755)
+    return builder.build()
+
+
+def test_risk_score_breaks_down_categories() -> None:
+    report = pyapplebom.risk_score(pyapplebom.parse_bom(build_package()))
+    breakdown = report["breakdown"]
+
+    assert {name: item["paths"] for name, item in breakdown.items()} == {
+        "setuid": ["./usr/local/bin/helper"],
+        "world_writable": ["./usr/local/share/tool/data"],
+        "launch_daemons": ["./Library/LaunchDaemons/com.example.tool.plist"],
+        "launch_agents": ["./Library/LaunchAgents/com.example.agent.plist"],
+        "kernel_extensions": ["./Library/Extensions/Tool.kext"],
+        "path_executables": ["./usr/local/bin/helper", "./usr/local/bin/tool"],
+        "privacy_sensitive": [],
+    }
+    assert breakdown["path_executables"] == {
+        "count": 2,
+        "weight": 5,
+        "points": 10,
+        "paths": ["./usr/local/bin/helper", "./usr/local/bin/tool"],
+    }
+    assert report["score"] == 25 + 10 + 15 + 10 + 30 + 10
+    assert report["level"] == "high"
+
+
+def test_risk_score_accepts_weights_and_path_styles() -> None:
+    doc = pyapplebom.parse_bom(build_package(), path_style="absolute")
+
+    report = pyapplebom.risk_score(doc, weights={"kernel_extensions": 0, "setuid": 1})
+
+    assert report["breakdown"]["setuid"]["paths"] == ["/usr/local/bin/helper"]
+    assert report["breakdown"]["kernel_extensions"]["points"] == 0
+    assert report["score"] == 1 + 10 + 15 + 10 + 10
+    assert report["level"] == "medium"
+
+
+def test_risk_score_levels_and_validation() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./Applications/Tool.app/Contents/Info.plist")
+    quiet = pyapplebom.risk_score(pyapplebom.parse_bom(builder.build()))
+    assert (quiet["score"], quiet["level"]) == (0, "none")
+
+    with pytest.raises(ValueError, match='unknown risk category "kexts"'):
+        pyapplebom.risk_score({"paths": []}, weights={"kexts": 1})
+    with pytest.raises(ValueError, match="no 'paths' list"):
+        pyapplebom.risk_score({"paths": None})
+
+
+def test_shell_footprint_reports_environment_changes() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./usr/local/bin", "directory")
+    builder.add_path("./usr/local/bin/tool", "link", link_name="../lib/tool/tool")
+    builder.add_path("./usr/local/lib/tool/tool", mode=0o755)
+    builder.add_path("./private/etc/paths.d/tool")
+    builder.add_path("./etc/manpaths.d/tool")
+    builder.add_path("./etc/profile.d/tool.sh")
+    builder.add_path("./private/etc/zshrc")
+    builder.add_path("./etc/fish/conf.d/tool.fish")
+    builder.add_path("./etc/tool.conf")
+    builder.add_path("./Users/Shared/.zshrc")
+    builder.add_path("./Library/Application Support/Tool/bin/tool-helper", mode=0o755)
+    builder.add_path("./Library/Application Support/Tool/lib/libtool.dylib")
+
+    report = pyapplebom.shell_footprint(pyapplebom.parse_bom(builder.build()))
+
+    assert report["path_executables"] == ["./usr/local/bin/tool"]
+    assert report["path_entries"] == ["./etc/manpaths.d/tool", "./private/etc/paths.d/tool"]
+    assert report["shell_init"] == [
+        "./Users/Shared/.zshrc",
+        "./etc/fish/conf.d/tool.fish",
+        "./etc/profile.d/tool.sh",
+        "./private/etc/zshrc",
+    ]
+    assert report["support_binaries"] == ["./Library/Application Support/Tool/bin/tool-helper"]
+    assert report["summary"] == {
+        "ok": False,
+        "total": 8,
+        "counts": {
+            "path_executables": 1,
+            "path_entries": 2,
+            "shell_init": 4,
+            "support_binaries": 1,
+        },
+    }
+    assert pyapplebom.exit_code(report) == pyapplebom.EXIT_FINDINGS
+
+
+def test_shell_footprint_is_ok_without_matches() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./Applications/Tool.app/Contents/MacOS/tool", mode=0o755)
+
+    report = pyapplebom.shell_footprint(pyapplebom.parse_bom(builder.build()))
+
+    assert report["summary"]["ok"]
+    assert report["summary"]["total"] == 0
+
+
+def test_risk_score_flags_privacy_and_mdm_locations() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./Library/Application Support", "directory")
+    builder.add_path("./Library/Application Support/com.apple.TCC", "directory")
+    builder.add_path("./Library/Application Support/com.apple.TCC/TCC.db", mode=0o644)
+    builder.add_path("./Library/Managed Preferences/com.example.tool.plist")
+    builder.add_path("./private/var/db/ConfigurationProfiles/Settings/tool")
+    builder.add_path("./Users/admin/Library/Application Support/com.apple.TCC/TCC.db")
+    builder.add_path("./Library/Application Support/com.example.TCC/state")
+
+    report = pyapplebom.risk_score(pyapplebom.parse_bom(builder.build()))
+
+    # Parent directories the builder adds count too: the package creates them.
+    assert report["breakdown"]["privacy_sensitive"]["paths"] == [
+        "./Library/Managed Preferences",
+        "./Library/Application Support/com.apple.TCC",
+        "./Library/Application Support/com.apple.TCC/TCC.db",
+        "./Library/Managed Preferences/com.example.tool.plist",
+        "./Users/admin/Library/Application Support/com.apple.TCC",
+        "./Users/admin/Library/Application Support/com.apple.TCC/TCC.db",
+        "./private/var/db/ConfigurationProfiles",
+        "./private/var/db/ConfigurationProfiles/Settings",
+        "./private/var/db/ConfigurationProfiles/Settings/tool",
+    ]
+    assert report["score"] == 9 * 20
+    assert report["level"] == "high"
+
+
+def test_capabilities_manifest() -> None:
+    manifest = pyapplebom.capabilities(pyapplebom.parse_bom(build_package()))
+
+    assert manifest == {
+        "installs_daemons": ["./Library/LaunchDaemons/com.example.tool.plist"],
+        "installs_agents": ["./Library/LaunchAgents/com.example.agent.plist"],
+        "installs_kexts": ["./Library/Extensions/Tool.kext"],
+        "modifies_path": ["./usr/local/bin/helper", "./usr/local/bin/tool"],
+        "setuid_binaries": ["./usr/local/bin/helper"],
+        "app_bundles": ["./Applications/Tool.app"],
+        "frameworks": [],
+    }
+
+
+def test_capabilities_skip_nested_bundles() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./Applications/Tool.app/Contents/Library/LoginItems/Helper.app", "directory")
+    builder.add_path(
+        "./Library/Frameworks/Tool.framework/Versions/A/Frameworks/Core.framework", "directory"
+    )
+    builder.add_path("./etc/paths.d/tool")
+
+    manifest = pyapplebom.capabilities(pyapplebom.parse_bom(builder.build()))
+
+    assert manifest["app_bundles"] == ["./Applications/Tool.app"]
+    assert manifest["frameworks"] == ["./Library/Frameworks/Tool.framework"]
+    assert manifest["modifies_path"] == ["./etc/paths.d/tool"]
+    assert manifest["installs_kexts"] == []
+
+
+def test_effective_access_follows_parent_directories() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./Library", "directory", mode=0o755)
+    builder.add_path("./Library/Tool", "directory", mode=0o775, gid=80)
+    builder.add_path("./Library/Tool/bin/tool", mode=0o755)
+    builder.add_path("./Library/Tool/shared.db", mode=0o666)
+    builder.add_path("./private/tmp", "directory", mode=0o1777)
+    builder.add_path("./private/tmp/cache", mode=0o644, uid=501)
+    builder.add_path("./usr/bin/safe", mode=0o755)
+    builder.add_path("./usr/share/notes", "link", link_name="../../Library/Tool/shared.db")
+    data = builder.build()
+
+    report = pyapplebom.effective_access(data)
+    access = {
+        entry["path"]: {
+            (grant["principal"], grant["access"], grant["via"]) for grant in entry["access"]
+        }
+        for entry in report["paths"]
+    }
+
+    assert access == {
+        "./Library/Tool/bin/tool": {("group:admin", "replace", "./Library/Tool")},
+        "./Library/Tool/shared.db": {
+            ("group:admin", "replace", "./Library/Tool"),
+            ("everyone", "write", "./Library/Tool/shared.db"),
+        },
+        "./private/tmp/cache": {
+            ("user:501", "write", "./private/tmp/cache"),
+            ("user:501", "replace", "./private/tmp"),
+        },
+    }
+    assert report["summary"] == {
+        "ok": False,
+        "total": 5,
+        "counts": {"writable": 2, "replaceable": 3},
+    }
+
+
+def test_known_file_deviations_compare_recorded_values() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./usr/bin/python3", size=100, crc32=7)
+    builder.add_path("./usr/bin/true", size=20, crc32=9)
+    builder.add_path("./usr/share/man", "directory")
+    doc = pyapplebom.parse_bom(builder.build(), path_style="absolute")
+
+    known = pyapplebom.KnownFiles()
+    known.add("/usr/bin/python3", size=100, crc32=8, os_build="23E224")
+    known.add("/usr/bin/true", size=24)
+    known.add("/usr/share/man", size=0)
+    assert len(known) == 3
+    assert known.os_builds == ["23E224"]
+    assert known.lookup("./usr/bin/true") == {"size": 24, "crc32": None}
+    assert known.lookup("usr/bin/python3") is None
+
+    report = pyapplebom.known_file_deviations(doc, known, os_build="23E224")
+
+    assert report["checked"] == 2
+    assert report["deviations"] == [
+        {"path": "/usr/bin/python3", "field": "crc32", "expected": 8, "actual": 7},
+        {"path": "/usr/bin/true", "field": "size", "expected": 24, "actual": 20},
+    ]
+    assert report["summary"]["counts"] == {"size": 1, "crc32": 1}
+    assert pyapplebom.known_file_deviations(doc, known)["checked"] == 1
+    with pytest.raises(ValueError, match="needs a crc32 or a size"):
+        known.add("/usr/bin/false")
+    with pytest.raises(ValueError, match="entry 0 has no 'path'"):
+        known.extend([{"size": 1}])
@@ -0,0 +1,54 @@
+from __future__ import annotations
+
+import io
+import json
+from contextlib import redirect_stdout
+from pathlib import Path
+
+import pytest
+
+from pyapplebom import bench
+
+FIXTURE = Path(__file__).parent / "fixtures" / "python-applications.bom"
+
+
+def test_bench_times_each_case_over_the_given_files() -> None:
+    results = bench.run([FIXTURE, FIXTURE], cases=["parse", "paths-typed"], repeat=1)
+
+    assert [result["case"] for result in results] == ["parse", "paths-typed"]
+    for result in results:
+        assert result["files"] == 2
+        assert result["bytes"] == 2 * FIXTURE.stat().st_size
+        assert result["paths"] == 108
+        assert result["seconds"] > 0
+        assert result["paths_per_second"] == 108 / result["seconds"]
+
+    with pytest.raises(ValueError, match="unknown case 'arrow'"):
+        bench.run([FIXTURE], cases=["arrow"])
+
+
+def test_bench_compares_exporters_with_their_python_equivalents() -> None:
+    results = bench.run([FIXTURE], cases=["export-json", "export-json-dumps"], repeat=1)
+
+    json_result, dumps_result = results
+    assert json_result["speedup"] == dumps_result["seconds"] / json_result["seconds"]
+    assert dumps_result["speedup"] is None
+    [csv_result] = bench.run([FIXTURE], cases=["export-csv"], repeat=1)
+    assert csv_result["speedup"] is None
+
+
+def test_bench_main_prints_results() -> None:
+    with redirect_stdout(io.StringIO()) as out:
+        assert bench.main([str(FIXTURE), "--case", "export-json", "--repeat", "1", "--json"]) == 0
+    [result] = json.loads(out.getvalue())
+    assert result["case"] == "export-json"
+    assert result["group"] == "export"
+
+    with redirect_stdout(io.StringIO()) as out:
+        assert bench.main([str(FIXTURE), "--repeat", "1"]) == 0
+    lines = out.getvalue().splitlines()
+    assert lines[0] == f"1 files, {FIXTURE.stat().st_size:,} bytes, 54 paths; best of 1"
+    assert [line.split()[0] for line in lines[2:]] == [case.name for case in bench.CASES]
+    rows = {line.split()[0]: line.split()[-1] for line in lines[2:]}
+    assert rows["export-json"].endswith("x")
+    assert rows["export-json-dumps"] == "-"
@@ -0,0 +1,625 @@
+from __future__ import annotations
+
+import os
+import struct
+
+import pytest
+
+import pyapplebom
+
+# Whether this build writes and reads the full sizes of large files after
+# their path records, which isn't Apple's layout.
+SIZE64_TRAILER = pyapplebom.build_info()["features"]["size64_trailer"]
+
+
+def _paths_by_name(doc: dict) -> dict[str, dict]:
+    return {entry["path"]: entry for entry in doc["paths"]}
+
+
+def _owner(entry: dict) -> tuple[int, int]:
+    return entry["user_id"], entry["group_id"]
+
+
+def test_builder_round_trips_paths_and_synthesizes_parents() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./Applications/Tool.app/Contents/Info.plist", size=12, mode=0o600)
+    builder.add_path("usr/local/bin/tool", "link", link_name="../../../Applications/Tool.app")
+    assert len(builder) == 2
+
+    doc = pyapplebom.parse_bom(builder.build())
+    paths = _paths_by_name(doc)
+
+    assert doc["parse_errors"] is None
+    assert [variable["name"] for variable in doc["variables"]] == [
+        "BomInfo",
+        "Paths",
+        "HLIndex",
+        "VIndex",
+        "Size64",
+    ]
+    assert paths["."]["path_type"] == "directory"
+    assert paths["./Applications/Tool.app"]["file_mode"] == 0o40755
+    assert paths["./Applications/Tool.app/Contents/Info.plist"]["file_mode"] == 0o100600
+    assert paths["./Applications/Tool.app/Contents/Info.plist"]["size"] == 12
+    assert paths["./usr/local/bin/tool"]["link_name"] == "../../../Applications/Tool.app"
+    assert doc["bom_info"]["number_of_paths"] == len(paths) + 1
+
+
+def test_builder_splits_leaves_by_max_paths_per_leaf() -> None:
+    builder = pyapplebom.BomBuilder(block_size=128, max_paths_per_leaf=2)
+    for number in range(9):
+        builder.add_path(f"./data/file{number}.txt")
+
+    doc = pyapplebom.parse_bom(builder.build())
+    leaves = [
+        block
+        for block in doc["blocks"]
+        if block["kind"] == "Paths" and block["is_path_info"] and block["count"]
+    ]
+    paths_tree = doc["blocks"][2]
+
+    assert len(doc["paths"]) == 11
+    assert paths_tree["block_size"] == 128
+    assert len(leaves) == 6
+    assert all(leaf["length"] == 128 and leaf["count"] <= 2 for leaf in leaves)
+
+
+def test_builder_rejects_invalid_options() -> None:
+    with pytest.raises(ValueError, match="max_paths_per_leaf"):
+        pyapplebom.BomBuilder(block_size=64, max_paths_per_leaf=7)
+
+    builder = pyapplebom.BomBuilder()
+    with pytest.raises(ValueError, match="invalid BOM path"):
+        builder.add_path("./a/../b")
+    with pytest.raises(ValueError, match="unknown path_type"):
+        builder.add_path("./a", "socket")
+
+
+def test_registered_path_types_name_vendor_codes() -> None:
+    pyapplebom.register_path_type(9, "vendor-fifo")
+    try:
+        assert pyapplebom.registered_path_types() == {9: "vendor-fifo"}
+        builder = pyapplebom.BomBuilder()
+        builder.add_path("./dev/queue", "vendor-fifo")
+        data = builder.build()
+
+        entry = _paths_by_name(pyapplebom.parse_bom(data))["./dev/queue"]
+        assert (entry["path_type"], entry["path_type_raw"]) == ("vendor-fifo", 9)
+
+        with pytest.raises(ValueError, match="built in"):
+            pyapplebom.register_path_type(2, "folder")
+        with pytest.raises(ValueError, match="reserved"):
+            pyapplebom.register_path_type(10, "other")
+        with pytest.raises(ValueError, match="already registered for 9"):
+            pyapplebom.register_path_type(10, "vendor-fifo")
+    finally:
+        pyapplebom.unregister_path_type(9)
+
+    entry = _paths_by_name(pyapplebom.parse_bom(data))["./dev/queue"]
+    assert (entry["path_type"], entry["path_type_raw"]) == ("other", 9)
+    assert pyapplebom.registered_path_types() == {}
+    with pytest.raises(KeyError, match="not registered"):
+        pyapplebom.unregister_path_type(9)
+
+
+def test_builder_truncates_large_sizes_and_records_them_in_size64() -> None:
+    huge = 5 * 2**30 + 3
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./data/huge.bin", size=huge)
+    builder.add_path("./data/small.bin", size=7)
+
+    data = builder.build()
+    doc = pyapplebom.parse_bom(data)
+    paths = _paths_by_name(doc)
+    records = {block["size"] for block in doc["blocks"] if block["kind"] == "PathRecord"}
+    size = huge if SIZE64_TRAILER else huge % 2**32
+
+    assert paths["./data/huge.bin"]["size"] == size
+    assert paths["./data/small.bin"]["size"] == 7
+    assert [entry["path"] for entry in doc["size64"]] == ["./data/huge.bin"]
+    assert doc["size64"][0]["size"] == size
+    assert huge % 2**32 in records
+    # A payload with a 4 GiB file is too large to build here, so its sizes are
+    # passed to the native function directly.
+    size_report = pyapplebom._native.size_report
+    report = size_report(data, {"./data/huge.bin": huge, "./data/small.bin": 7})
+    assert report["size64"]["truncated"] == ([] if SIZE64_TRAILER else ["./data/huge.bin"])
+    assert report["discrepancies"] == []
+    wrong = size_report(data, {"./data/huge.bin": huge + 1, "./data/small.bin": 7})
+    assert [item["check"] for item in wrong["discrepancies"]] == ["payload_size"]
+
+
+@pytest.mark.skipif(SIZE64_TRAILER, reason="reads the size trailer")
+def test_parser_ignores_bytes_after_large_file_records() -> None:
+    huge = 6 * 2**30
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./huge.bin", size=huge)
+    builder.add_path("./small.bin", size=7)
+    data = bytearray(builder.build())
+
+    doc = pyapplebom.parse_bom(data)
+    records = [
+        block
+        for block in doc["blocks"]
+        if block["kind"] == "PathRecord" and block["path_type"] == "file"
+    ]
+    # Without the trailer, large file records end like any other.
+    assert len({record["length"] for record in records}) == 1
+    for record in records:
+        end = record["file_offset"] + record["length"]
+        data[end - 4 : end] = (huge).to_bytes(8, "big")[4:]
+
+    patched = pyapplebom.parse_bom(data)
+
+    assert _paths_by_name(patched)["./huge.bin"]["size"] == huge % 2**32
+    assert _paths_by_name(patched)["./small.bin"]["size"] == 7
+
+
+@pytest.mark.skipif(not SIZE64_TRAILER, reason="needs the size64-trailer feature")
+def test_parser_ignores_size64_values_that_disagree_with_the_record() -> None:
+    huge = 6 * 2**30
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./huge.bin", size=huge)
+    data = bytearray(builder.build())
+
+    doc = pyapplebom.parse_bom(data)
+    record = next(
+        block
+        for block in doc["blocks"]
+        if block["kind"] == "PathRecord" and block["path_type"] == "file"
+    )
+    trailer = record["file_offset"] + record["length"] - 8
+    data[trailer : trailer + 8] = (huge + 1).to_bytes(8, "big")
+
+    patched = pyapplebom.parse_bom(data)
+
+    assert _paths_by_name(patched)["./huge.bin"]["size"] == huge % 2**32
+
+
+def test_builder_extend_accepts_dicts_objects_and_parsed_entries() -> None:
+    class Entry:
+        def __init__(self, path: str, link_target: str) -> None:
+            self.path = path
+            self.type = "link"
+            self.link_target = link_target
+
+    builder = pyapplebom.BomBuilder()
+    builder.extend(
+        [
+            {"path": "usr/lib/libfoo.dylib", "size": 42, "uid": 501, "crc32": 1234},
+            Entry("usr/lib/libfoo.1.dylib", "libfoo.dylib"),
+        ]
+    )
+    doc = pyapplebom.parse_bom(builder.build())
+    paths = _paths_by_name(doc)
+
+    assert paths["./usr/lib/libfoo.dylib"]["user_id"] == 501
+    assert paths["./usr/lib/libfoo.dylib"]["crc32"] == 1234
+    assert paths["./usr/lib/libfoo.1.dylib"]["link_name"] == "libfoo.dylib"
+
+    rebuilt = pyapplebom.BomBuilder()
+    rebuilt.extend(doc["paths"])
+    assert pyapplebom.parse_bom(rebuilt.build())["paths"] == doc["paths"]
+
+
+def test_builder_extend_is_all_or_nothing() -> None:
+    builder = pyapplebom.BomBuilder()
+    with pytest.raises(ValueError, match="entry 1 has no 'path'"):
+        builder.extend([{"path": "./a"}, {"size": 1}])
+
+    assert len(builder) == 0
+
+
+def test_builder_write_streams_the_same_bytes_as_build(tmp_path) -> None:
+    builder = pyapplebom.BomBuilder(block_size=64, max_paths_per_leaf=3)
+    for directory in range(20):
+        for number in range(10):
+            builder.add_path(f"./lib/d{directory:02}/f{number}.dat", size=number, mtime=directory)
+    builder.add_path("./lib/huge.bin", size=2**33)
+
+    target = tmp_path / "streamed.bom"
+    written = builder.write(target)
+    data = target.read_bytes()
+
+    assert written == len(data)
+    assert data == builder.build()
+
+    doc = pyapplebom.parse_bom(data, include_blocks=False)
+    assert len(doc["paths"]) == 20 * 10 + 20 + 3
+    assert _paths_by_name(doc)["./lib/d19/f9.dat"]["mtime"] == 19
+    assert [entry["path"] for entry in doc["size64"]] == ["./lib/huge.bin"]
+
+
+def test_builder_spills_to_disk_and_writes_the_same_bytes(tmp_path) -> None:
+    tree = tmp_path / "tree"
+    (tree / "bin").mkdir(parents=True)
+    (tree / "bin" / "tool").write_bytes(b"tool")
+    os.link(tree / "bin" / "tool", tree / "bin" / "tool-alias")
+    spill_directory = tmp_path / "spill"
+    spill_directory.mkdir()
+
+    builders = [
+        pyapplebom.BomBuilder(block_size=64, max_paths_per_leaf=3),
+        pyapplebom.BomBuilder(
+            block_size=64,
+            max_paths_per_leaf=3,
+            spill_threshold=7,
+            spill_directory=spill_directory,
+        ),
+    ]
+    for builder in builders:
+        builder.add_directory(tree)
+        for directory in range(20):
+            for number in range(10):
+                builder.add_path(f"./lib/d{directory:02}/f{number}.dat", size=number)
+        builder.add_path("./lib/huge.bin", size=2**33)
+        # Replaces a path already written out in an earlier run.
+        builder.add_path("./lib/d00/f0.dat", size=99, mtime=5)
+        builder.add_override("./lib/d01/*", mode=0o600)
+
+    plain, spilled = builders
+    assert any(spill_directory.iterdir())
+    assert len(spilled) == len(plain)
+    assert spilled.build() == plain.build()
+    assert spilled.write(tmp_path / "spilled.bom") == len(plain.build())
+    assert (tmp_path / "spilled.bom").read_bytes() == plain.build()
+
+    doc = pyapplebom.parse_bom(spilled.build(), include_blocks=False)
+    assert _paths_by_name(doc)["./lib/d00/f0.dat"]["size"] == 99
+    assert len(doc["hl_index"]) == 2
+
+    del builders, builder, spilled
+    assert not any(spill_directory.iterdir())
+
+    with pytest.raises(ValueError, match="spill_threshold"):
+        pyapplebom.BomBuilder(spill_threshold=0)
+    with pytest.raises(ValueError, match="spill_threshold"):
+        pyapplebom.BomBuilder(spill_directory=spill_directory)
+
+
+def test_builder_applies_ownership_policy_and_overrides() -> None:
+    builder = pyapplebom.BomBuilder(default_uid=501, default_gid=20, umask=0o022)
+    builder.add_path("./usr/libexec/helper", mode=0o777)
+    builder.add_path("./usr/lib/libfoo.dylib", uid=0, gid=0, mode=0o666)
+    builder.add_override("./usr/libexec/helper", mode=0o4755, uid=0)
+    builder.add_override("usr/**/*.dylib", gid=80)
+
+    paths = _paths_by_name(pyapplebom.parse_bom(builder.build(), include_blocks=False))
+
+    assert _owner(paths["./usr"]) == (501, 20)
+    assert paths["./usr/libexec/helper"]["file_mode"] == 0o104755
+    assert _owner(paths["./usr/libexec/helper"]) == (0, 20)
+    assert paths["./usr/lib/libfoo.dylib"]["file_mode"] == 0o100644
+    assert _owner(paths["./usr/lib/libfoo.dylib"]) == (0, 80)
+
+
+def test_builder_force_owner_overrides_entry_owners() -> None:
+    builder = pyapplebom.BomBuilder(force_owner=(0, 0))
+    builder.add_path("./Applications/Tool.app", "directory", uid=501, gid=20)
+
+    paths = pyapplebom.parse_bom(builder.build(), include_blocks=False)["paths"]
+
+    assert {_owner(entry) for entry in paths} == {(0, 0)}
+    with pytest.raises(ValueError, match="at least one"):
+        builder.add_override("./Applications/*")
+
+
+def test_build_bom_from_directory_records_metadata_and_applies_exclusions(tmp_path) -> None:
+    staging = tmp_path / "staging"
+    (staging / "usr" / "lib").mkdir(parents=True)
+    (staging / ".git" / "objects").mkdir(parents=True)
+    (staging / "usr" / "lib" / "hello.txt").write_bytes(b"hello\n")
+    (staging / "usr" / "lib" / "cache.pyc").write_bytes(b"\0")
+    (staging / "usr" / ".DS_Store").write_bytes(b"\0")
+    (staging / ".git" / "objects" / "pack").write_bytes(b"\0")
+    (staging / "usr" / "lib" / "link").symlink_to("hello.txt")
+
+    data = pyapplebom.build_bom_from_directory(
+        staging,
+        exclude=[".DS_Store", "*.pyc", ".git/**"],
+        force_owner=(0, 0),
+    )
+    paths = _paths_by_name(pyapplebom.parse_bom(data, include_blocks=False))
+
+    assert sorted(paths) == [".", "./usr", "./usr/lib", "./usr/lib/hello.txt", "./usr/lib/link"]
+    assert paths["./usr/lib/hello.txt"]["size"] == 6
+    assert paths["./usr/lib/hello.txt"]["crc32"] == 3015617425
+    assert paths["./usr/lib/link"]["path_type"] == "link"
+    assert paths["./usr/lib/link"]["link_name"] == "hello.txt"
+    assert paths["./usr/lib/link"]["crc32"] == 233972757
+
+
+@pytest.mark.skipif(not hasattr(os, "mkfifo"), reason="requires FIFOs")
+def test_build_bom_from_directory_can_skip_fifos(tmp_path) -> None:
+    os.mkfifo(tmp_path / "pipe")
+    (tmp_path / "file").write_bytes(b"")
+
+    def build(**options) -> dict[str, dict]:
+        data = pyapplebom.build_bom_from_directory(tmp_path, **options)
+        return _paths_by_name(pyapplebom.parse_bom(data, include_blocks=False))
+
+    kept = build()
+    skipped = build(skip_special=True)
+
+    assert kept["./pipe"]["file_mode"] & 0o170000 == 0o010000
+    assert "./pipe" not in skipped
+    assert "./file" in skipped
+
+
+def test_build_bom_from_directory_can_follow_symlinks(tmp_path) -> None:
+    staging = tmp_path / "staging"
+    (staging / "shared").mkdir(parents=True)
+    (staging / "shared" / "data.txt").write_bytes(b"hello\n")
+    (staging / "current").symlink_to("shared")
+    (staging / "shared" / "loop").symlink_to("..")
+
+    def build(**options) -> dict[str, dict]:
+        data = pyapplebom.build_bom_from_directory(staging, **options)
+        return _paths_by_name(pyapplebom.parse_bom(data, include_blocks=False))
+
+    recorded = build()
+    followed = build(follow_symlinks=True)
+
+    assert recorded["./current"]["path_type"] == "link"
+    assert "./current/data.txt" not in recorded
+    assert followed["./current"]["path_type"] == "directory"
+    assert followed["./current/data.txt"]["crc32"] == 3015617425
+    assert followed["./shared/loop"]["path_type"] == "directory"
+    assert "./shared/loop/shared" not in followed
+
+
+CPU_TYPE_X86_64 = 0x01000007
+CPU_TYPE_ARM64 = 0x0100000C
+
+
+def _fat_binary(*slices: tuple[int, int]) -> bytes:
+    header = struct.pack(">II", 0xCAFEBABE, len(slices))
+    offset = 4096
+    body = b""
+    for cpu_type, size in slices:
+        header += struct.pack(">IIIII", cpu_type, 0, offset + len(body), size, 12)
+        body += b"\0" * size
+    return header.ljust(offset, b"\0") + body
+
+
+def test_builder_writes_bom_info_architecture_totals(tmp_path) -> None:
+    (tmp_path / "MacOS").mkdir()
+    universal = _fat_binary((CPU_TYPE_X86_64, 300), (CPU_TYPE_ARM64, 500))
+    thin = struct.pack("<III", 0xFEEDFACF, CPU_TYPE_ARM64, 0).ljust(64, b"\0")
+    (tmp_path / "MacOS" / "universal").write_bytes(universal)
+    (tmp_path / "MacOS" / "thin").write_bytes(thin)
+    (tmp_path / "Info.plist").write_bytes(b"x" * 10)
+    (tmp_path / "Current").symlink_to("MacOS")
+
+    data = pyapplebom.build_bom_from_directory(tmp_path)
+    doc = pyapplebom.parse_bom(data, include_blocks=False)
+
+    assert doc["bom_info"]["number_of_paths"] == 7
+    assert [(entry["a"], entry["c"]) for entry in doc["bom_info"]["entries"]] == [
+        (0, 10 + len("MacOS")),
+        (CPU_TYPE_X86_64, 300),
+        (CPU_TYPE_ARM64, 500 + 64),
+    ]
+
+
+def test_builder_ignores_java_class_files_for_architecture_totals(tmp_path) -> None:
+    class_file = struct.pack(">IHH", 0xCAFEBABE, 0, 52).ljust(32, b"\0")
+    (tmp_path / "Main.class").write_bytes(class_file)
+
+    data = pyapplebom.build_bom_from_directory(tmp_path)
+    doc = pyapplebom.parse_bom(data, include_blocks=False)
+
+    assert [(entry["a"], entry["c"]) for entry in doc["bom_info"]["entries"]] == [(0, 32)]
+
+
+def _cksum(data: bytes) -> int:
+    crc = 0
+    for byte in data + len(data).to_bytes(8, "little").rstrip(b"\0"):
+        crc ^= byte << 24
+        for _ in range(8):
+            crc = (crc << 1) ^ 0x04C11DB7 if crc & 0x80000000 else crc << 1
+            crc &= 0xFFFFFFFF
+    return crc ^ 0xFFFFFFFF
+
+
+def _records_by_size(data: bytes) -> dict[int, dict]:
+    doc = pyapplebom.parse_bom(data, include_raw_block_bytes=True)
+    return {
+        block["size"]: block
+        for block in doc["blocks"]
+        if block["kind"] == "PathRecord" and block["path_type"] == "file"
+    }
+
+
+def test_builder_lists_mach_o_slices_in_path_records(tmp_path) -> None:
+    universal = _fat_binary((CPU_TYPE_X86_64, 300), (CPU_TYPE_ARM64, 500))
+    thin = struct.pack("<III", 0xFEEDFACF, CPU_TYPE_ARM64, 2).ljust(64, b"\0")
+    (tmp_path / "universal").write_bytes(universal)
+    (tmp_path / "thin").write_bytes(thin)
+    (tmp_path / "data").write_bytes(b"x" * 10)
+
+    records = _records_by_size(pyapplebom.build_bom_from_directory(tmp_path))
+    fat_tail = bytes.fromhex(records[len(universal)]["raw_hex"])[27:]
+    thin_tail = bytes.fromhex(records[len(thin)]["raw_hex"])[27:]
+
+    assert records[len(universal)]["architecture"] == 0x200F
+    assert records[len(thin)]["architecture"] == 0x200F
+    assert records[10]["architecture"] == 15
+    assert fat_tail == struct.pack(
+        ">BIIIIIIIII",
+        1,
+        2,
+        CPU_TYPE_X86_64,
+        0,
+        300,
+        _cksum(b"\0" * 300),
+        CPU_TYPE_ARM64,
+        0,
+        500,
+        _cksum(b"\0" * 500),
+    ) + bytes(8)
+    assert thin_tail == struct.pack(
+        ">BIIIII", 1, 1, CPU_TYPE_ARM64, 2, 64, records[64]["checksum_or_type"]
+    ) + bytes(8)
+
+
+def test_build_bom_from_directory_can_skip_binary_inspection(tmp_path) -> None:
+    universal = _fat_binary((CPU_TYPE_X86_64, 300), (CPU_TYPE_ARM64, 500))
+    (tmp_path / "universal").write_bytes(universal)
+
+    data = pyapplebom.build_bom_from_directory(tmp_path, inspect_binaries=False)
+    record = _records_by_size(data)[len(universal)]
+    doc = pyapplebom.parse_bom(data, include_blocks=False)
+
+    assert record["architecture"] == 15
+    assert len(bytes.fromhex(record["raw_hex"])) == 35
+    assert [(entry["a"], entry["c"]) for entry in doc["bom_info"]["entries"]] == [
+        (0, len(universal))
+    ]
+
+
+def test_build_bom_from_directory_read_tuning_does_not_change_output(tmp_path) -> None:
+    for index in range(20):
+        (tmp_path / f"file{index}.bin").write_bytes(bytes([index]) * (1000 + index))
+    (tmp_path / "universal").write_bytes(_fat_binary((CPU_TYPE_ARM64, 300)))
+
+    default = pyapplebom.build_bom_from_directory(tmp_path)
+    tuned = pyapplebom.build_bom_from_directory(
+        tmp_path, read_buffer_size=7, per_device_concurrency=4
+    )
+
+    assert tuned == default
+    with pytest.raises(ValueError):
+        pyapplebom.build_bom_from_directory(tmp_path, read_buffer_size=0)
+    with pytest.raises(ValueError):
+        pyapplebom.build_bom_from_directory(tmp_path, per_device_concurrency=0)
+
+
+def test_build_bom_from_directory_lists_hard_links_in_hl_index(tmp_path) -> None:
+    (tmp_path / "bin").mkdir()
+    (tmp_path / "bin" / "tool").write_bytes(b"tool")
+    os.link(tmp_path / "bin" / "tool", tmp_path / "bin" / "tool-alias")
+    os.link(tmp_path / "bin" / "tool", tmp_path / "tool")
+    (tmp_path / "other").write_bytes(b"tool")
+
+    doc = pyapplebom.parse_bom(pyapplebom.build_bom_from_directory(tmp_path))
+    paths = _paths_by_name(doc)
+
+    assert sorted(entry["path"] for entry in doc["hl_index"]) == [
+        "./bin/tool",
+        "./bin/tool-alias",
+        "./tool",
+    ]
+    assert paths["./bin/tool-alias"]["crc32"] == paths["./bin/tool"]["crc32"]
+    assert doc["parse_errors"] is None
+
+
+def test_unified_paths_join_the_path_sections(tmp_path) -> None:
+    huge = 5 * 2**30 + 3
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./data/huge.bin", size=huge)
+    builder.add_path("./data/small.bin", size=7)
+    rows = {row["path"]: row for row in pyapplebom.unified_paths(builder.build())}
+
+    assert rows["./data/huge.bin"]["size"] == (huge if SIZE64_TRAILER else huge % 2**32)
+    assert rows["./data/huge.bin"]["in_size64"]
+    assert not rows["./data/small.bin"]["in_size64"]
+    assert not any(row["in_hl_index"] or row["in_vindex"] for row in rows.values())
+
+    (tmp_path / "tool").write_bytes(b"tool")
+    os.link(tmp_path / "tool", tmp_path / "tool-alias")
+    (tmp_path / "other").write_bytes(b"tool")
+    data = pyapplebom.build_bom_from_directory(tmp_path)
+    rows = pyapplebom.unified_paths(data, path_style="absolute", path_order="sorted")
+
+    assert [row["path"] for row in rows] == ["/", "/other", "/tool", "/tool-alias"]
+    group = ["/tool", "/tool-alias"]
+    assert [row["hard_link_group"] for row in rows] == [None, None, group, group]
+    assert [row["in_hl_index"] for row in rows] == [False, False, True, True]
+    assert {key: rows[1][key] for key in rows[1] if not key.startswith(("in_", "hard_"))} == (
+        pyapplebom.BomFile(data).get_path("/other", path_style="absolute")
+    )
+
+
+def test_format_lsbom_lists_paths_as_lsbom_does() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./bin/tool", mode=0o755, uid=0, gid=80, size=1234, crc32=0xDEADBEEF)
+    builder.add_path("./bin/alias", "link", uid=0, gid=0, size=4, crc32=7, link_name="tool")
+    data = builder.build()
+
+    assert pyapplebom.format_lsbom(data, path_order="sorted").splitlines() == [
+        ".\t40755\t0/0",
+        "./bin\t40755\t0/0",
+        "./bin/alias\t120755\t0/0\t4\t7\ttool",
+        "./bin/tool\t100755\t0/80\t1234\t3735928559",
+    ]
+    assert pyapplebom.format_lsbom(
+        data, include="f", columns="FMugst", path_style="absolute"
+    ) == '"/bin/tool"\t-rwxr-xr-x\t0\t80\t1234\t0\n'
+    assert pyapplebom.format_lsbom(data, include=("b", "c")) == ""
+
+    with pytest.raises(ValueError, match="include must hold"):
+        pyapplebom.format_lsbom(data, include=("x",))
+    with pytest.raises(ValueError, match="columns must use"):
+        pyapplebom.format_lsbom(data, columns="fU")
+
+
+def test_export_mtree_writes_an_mtree_spec() -> None:
+    builder = pyapplebom.BomBuilder()
+    builder.add_path("./bin/my tool", mode=0o4755, gid=80, mtime=1700000000, size=5, crc32=123)
+    builder.add_path("./bin/alias", "link", link_name="my tool")
+    builder.add_path("./dev/disk#0", "device", mode=0o060640)
+    data = builder.build()
+
+    lines = pyapplebom.export_mtree(data).splitlines()
+    assert lines[0] == "#mtree"
+    assert sorted(lines[1:]) == [
+        ". type=dir mode=0755 uid=0 gid=0 time=0.000000000",
+        "./bin type=dir mode=0755 uid=0 gid=0 time=0.000000000",
+        "./bin/alias type=link mode=0755 uid=0 gid=0 time=0.000000000 link=my\\040tool",
+        "./bin/my\\040tool type=file mode=4755 uid=0 gid=80 size=5"
+        " time=1700000000.000000000 cksum=123",
+        "./dev type=dir mode=0755 uid=0 gid=0 time=0.000000000",
+        "./dev/disk\\0430 type=block mode=0640 uid=0 gid=0 time=0.000000000",
+    ]
+    only = pyapplebom.export_mtree(data, keywords=("size", "type")).splitlines()
+    assert "./bin/my\\040tool type=file size=5" in only
+    assert "./bin type=dir" in only
+
+    with pytest.raises(ValueError, match="keywords must hold"):
+        pyapplebom.export_mtree(data, keywords=["sha256digest"])
+
+
+def test_symbolic_mode_spells_out_every_mode_bit() -> None:
+    expected = {
+        "./file": ("file", 0o644, "-rw-r--r--"),
+        "./none": ("file", 0o000, "----------"),
+        "./all": ("file", 0o777, "-rwxrwxrwx"),
+        "./setuid": ("file", 0o4755, "-rwsr-xr-x"),
+        "./setuid-noexec": ("file", 0o4644, "-rwSr--r--"),
+        "./setgid": ("file", 0o2755, "-rwxr-sr-x"),
+        "./setgid-noexec": ("file", 0o2644, "-rw-r-Sr--"),
+        "./special": ("file", 0o7777, "-rwsrwsrwt"),
+        "./special-noexec": ("file", 0o7666, "-rwSrwSrwT"),
+        "./sticky": ("directory", 0o1777, "drwxrwxrwt"),
+        "./sticky-noexec": ("directory", 0o1770, "drwxrwx--T"),
+        "./setgid-dir": ("directory", 0o2775, "drwxrwsr-x"),
+        "./setuid-dir": ("directory", 0o4700, "drws------"),
+        "./link": ("link", 0o755, "lrwxr-xr-x"),
+        "./char": ("device", 0o020620, "crw--w----"),
+        "./block": ("device", 0o060640, "brw-r-----"),
+        "./fifo": ("device", 0o010644, "prw-r--r--"),
+        "./socket": ("device", 0o140755, "srwxr-xr-x"),
+        "./unknown": ("device", 0o644, "?rw-r--r--"),
+    }
+    builder = pyapplebom.BomBuilder()
+    for path, (path_type, mode, _) in expected.items():
+        link_name = "file" if path_type == "link" else None
+        builder.add_path(path, path_type, mode=mode, link_name=link_name)
+
+    doc = pyapplebom.parse_bom(builder.build(), include_blocks=False, typed_paths=True)
+    modes = {entry.path: entry.symbolic_mode for entry in doc["paths"]}
+
+    assert modes["."] == "drwxr-xr-x"
+    for path, (_, _, symbolic) in expected.items():
+        assert modes[path] == symbolic, path
@@ -0,0 +1,163 @@
+from __future__ import annotations
+
+from pathlib import Path
+
+import pytest
+
+import pyapplebom
+
+FIXTURE = Path(__file__).parent / "fixtures" / "python-applications.bom"
+BUNDLE = "./Python 3.9/IDLE.app/"
+
+
+def materialize_bundle(root: Path) -> Path:
+    doc = pyapplebom.parse_bom_file(FIXTURE, include_blocks=False)
+    app = root / "IDLE.app"
+    app.mkdir()
+
+    for entry in doc["paths"]:
+        if not entry["path"].startswith(BUNDLE):
+            continue
+        target = app / entry["path"][len(BUNDLE) :]
+        if entry["path_type"] == "directory":
+            target.mkdir(parents=True, exist_ok=True)
+        else:
+            target.parent.mkdir(parents=True, exist_ok=True)
+            target.write_bytes(b"")
+
+    return app
+
+
+def test_compare_with_bundle_reports_added_and_missing_files(tmp_path: Path) -> None:
+    app = materialize_bundle(tmp_path)
+    (app / "Contents" / "PkgInfo").unlink()
+    (app / "Contents" / "MacOS" / "injected.dylib").write_bytes(b"\0")
+
+    report = pyapplebom.compare_with_bundle(FIXTURE.read_bytes(), app)
+
+    assert report["bundle_path"] == "./Python 3.9/IDLE.app"
+    assert report["only_in_bundle"] == ["Contents/MacOS/injected.dylib"]
+    assert report["only_in_bom"] == ["Contents/PkgInfo"]
+    assert report["matched"] > 0
+
+
+def test_compare_with_bundle_rejects_ambiguous_bundle(tmp_path: Path) -> None:
+    app = tmp_path / "Other.app"
+    app.mkdir()
+
+    with pytest.raises(ValueError):
+        pyapplebom.compare_with_bundle(FIXTURE.read_bytes(), app)
+
+
+def test_compare_with_bundle_case_insensitive(tmp_path: Path) -> None:
+    app = materialize_bundle(tmp_path)
+    (app / "Contents" / "Info.plist").rename(app / "Contents" / "INFO.plist")
+    data = FIXTURE.read_bytes()
+
+    exact = pyapplebom.compare_with_bundle(data, app)
+    folded = pyapplebom.compare_with_bundle(data, app, case_sensitive=False)
+
+    assert exact["only_in_bundle"] == ["Contents/INFO.plist"]
+    assert exact["only_in_bom"] == ["Contents/Info.plist"]
+    assert folded["only_in_bundle"] == []
+    assert folded["only_in_bom"] == []
+
+
+def test_compare_with_bundle_symlink_policy(tmp_path: Path) -> None:
+    app = materialize_bundle(tmp_path)
+    (tmp_path / "shared").mkdir()
+    (tmp_path / "shared" / "extra.txt").write_bytes(b"")
+    (app / "Contents" / "Shared").symlink_to(tmp_path / "shared")
+    data = FIXTURE.read_bytes()
+
+    recorded = pyapplebom.compare_with_bundle(data, app)
+    followed = pyapplebom.compare_with_bundle(data, app, follow_symlinks=True)
+
+    assert recorded["only_in_bundle"] == ["Contents/Shared"]
+    assert followed["only_in_bundle"] == ["Contents/Shared", "Contents/Shared/extra.txt"]
+
+
+def build_bundle(root: Path, **options) -> tuple[Path, bytes]:
+    contents = root / "Foo.app" / "Contents"
+    contents.mkdir(parents=True)
+    (contents / "data.txt").write_bytes(b"hello\n")
+    (contents / "other.txt").write_bytes(b"other\n")
+    (contents / "link").symlink_to("data.txt")
+    return root / "Foo.app", pyapplebom.build_bom_from_directory(root, **options)
+
+
+def test_compare_with_bundle_checks_checksums_on_request(tmp_path: Path) -> None:
+    app, data = build_bundle(tmp_path)
+    (app / "Contents" / "data.txt").write_bytes(b"HELLO\n")
+    (app / "Contents" / "link").unlink()
+    (app / "Contents" / "link").symlink_to("other.txt")
+
+    presence = pyapplebom.compare_with_bundle(data, app)
+    full = pyapplebom.compare_with_bundle(data, app, checksum="full")
+
+    assert presence["checksum"] == "none"
+    assert presence["checksum_mismatches"] == []
+    assert full["checksum"] == "full"
+    assert full["checksum_mismatches"] == ["Contents/data.txt", "Contents/link"]
+    assert full["only_in_bundle"] == full["only_in_bom"] == []
+
+
+def test_compare_with_bundle_sampled_checksums_skip_the_middle_of_large_files(
+    tmp_path: Path,
+) -> None:
+    app, _ = build_bundle(tmp_path)
+    with open(app / "Contents" / "data.txt", "wb") as large:
+        large.truncate(65 * 2**20)
+    data = pyapplebom.build_bom_from_directory(tmp_path, checksum="sampled")
+
+    with open(app / "Contents" / "data.txt", "r+b") as large:
+        large.seek(10 * 2**20)
+        large.write(b"x")
+    unsampled = pyapplebom.compare_with_bundle(data, app, checksum="sampled")
+    with open(app / "Contents" / "data.txt", "r+b") as large:
+        large.write(b"x")
+    sampled = pyapplebom.compare_with_bundle(data, app, checksum="sampled")
+
+    assert unsampled["checksum_mismatches"] == []
+    assert sampled["checksum_mismatches"] == ["Contents/data.txt"]
+
+
+def test_checksum_mode_is_validated(tmp_path: Path) -> None:
+    app, data = build_bundle(tmp_path, checksum="none")
+
+    with pytest.raises(ValueError):
+        pyapplebom.compare_with_bundle(data, app, checksum="crc32")
+    with pytest.raises(ValueError):
+        pyapplebom.build_bom_from_directory(tmp_path, checksum="crc32")
+
+
+def test_compare_with_bundle_read_tuning(tmp_path: Path) -> None:
+    app, data = build_bundle(tmp_path)
+    (app / "Contents" / "other.txt").write_bytes(b"OTHER\n")
+
+    report = pyapplebom.compare_with_bundle(
+        data, app, checksum="full", read_buffer_size=3, per_device_concurrency=8
+    )
+
+    assert report["checksum_mismatches"] == ["Contents/other.txt"]
+
+
+def test_compare_with_bundle_summary(tmp_path: Path) -> None:
+    app, data = build_bundle(tmp_path)
+    clean = pyapplebom.compare_with_bundle(data, app, checksum="full")
+    (app / "Contents" / "extra.txt").write_bytes(b"")
+    (app / "Contents" / "data.txt").write_bytes(b"HELLO\n")
+
+    dirty = pyapplebom.compare_with_bundle(data, app, checksum="full")
+
+    assert clean["summary"] == {
+        "ok": True,
+        "total": 0,
+        "counts": {"only_in_bundle": 0, "only_in_bom": 0, "checksum_mismatches": 0},
+    }
+    assert dirty["summary"]["counts"] == {
+        "only_in_bundle": 1,
+        "only_in_bom": 0,
+        "checksum_mismatches": 1,
+    }
+    assert pyapplebom.exit_code(dirty) == 1
@@ -0,0 +1,597 @@
+from __future__ import annotations
+
+import os
+import plistlib
+import struct
+import zlib
+from pathlib import Path
+from typing import Any
+
+import pytest
+
+import pyapplebom
+
+BASE_FILES = {
+    "./etc/app.conf": {"size": 10, "crc32": 1},
+    "./etc/hosts": {"size": 20, "crc32": 2},
+    "./bin/tool": {"size": 30, "crc32": 3, "mode": 0o755},
+    "./bin/helper": {"size": 40, "crc32": 4, "mode": 0o755},
+    "./share/readme": {"size": 50, "crc32": 5},
+}
+
+
+def build(files: dict[str, dict[str, Any]]) -> bytes:
+    builder = pyapplebom.BomBuilder()
+    for path, fields in files.items():
+        builder.add_path(path, **fields)
+    return builder.build()
+
+
+def variant(changes: dict[str, dict[str, Any] | None]) -> bytes:
+    files = {path: dict(fields) for path, fields in BASE_FILES.items()}
+    for path, fields in changes.items():
+        if fields is None:
+            del files[path]
+        else:
+            files[path] = {**files.get(path, {}), **fields}
+    return build(files)
+
+
+def _paths(report: dict[str, Any], category: str) -> list[str]:
+    return [entry["path"] for entry in report[category]]
+
+
+def test_diff3_classifies_changes() -> None:
+    base = build(BASE_FILES)
+    a = variant(
+        {
+            "./etc/app.conf": None,
+            "./bin/tool": {"size": 31, "crc32": 33},
+            "./share/readme": {"mode": 0o600},
+            "./share/new": {"size": 1},
+        }
+    )
+    b = variant(
+        {
+            "./etc/hosts": {"size": 21},
+            "./bin/tool": {"size": 31, "crc32": 33},
+            "./share/readme": {"mode": 0o640},
+        }
+    )
+
+    report = pyapplebom.diff3_boms(base, a, b)
+
+    assert _paths(report, "only_in_a") == ["./etc/app.conf", "./share/new"]
+    assert _paths(report, "only_in_b") == ["./etc/hosts"]
+    assert _paths(report, "both_same") == ["./bin/tool"]
+    assert _paths(report, "conflicting") == ["./share/readme"]
+    assert report["summary"] == {
+        "ok": False,
+        "total": 5,
+        "counts": {"only_in_a": 2, "only_in_b": 1, "both_same": 1, "conflicting": 1},
+    }
+    assert report["unchanged"] > 0
+
+    removed, added = report["only_in_a"]
+    assert (removed["a_change"], removed["b_change"]) == ("removed", None)
+    assert removed["base"]["size"] == 10
+    assert removed["a"] is None and removed["b"]["size"] == 10
+    assert (added["a_change"], added["a_fields"]) == ("added", [])
+    assert added["base"] is None and added["b"] is None
+
+    (same,) = report["both_same"]
+    assert same["a_fields"] == same["b_fields"] == ["size", "crc32"]
+
+    (conflict,) = report["conflicting"]
+    assert conflict["a_fields"] == conflict["b_fields"] == ["file_mode"]
+    assert conflict["a"]["file_mode"] & 0o777 == 0o600
+    assert conflict["b"]["file_mode"] & 0o777 == 0o640
+
+
+def test_diff3_add_add_conflicts_unless_identical() -> None:
+    base = build(BASE_FILES)
+    a = variant({"./share/x": {"size": 1}, "./share/y": {"size": 2}})
+    b = variant({"./share/x": {"size": 1}, "./share/y": {"size": 3}})
+
+    report = pyapplebom.diff3_boms(base, a, b)
+
+    assert [(e["path"], e["a_change"], e["b_change"]) for e in report["both_same"]] == [
+        ("./share/x", "added", "added"),
+    ]
+    assert _paths(report, "conflicting") == ["./share/y"]
+
+
+def test_diff3_identical_inputs_are_ok() -> None:
+    base = build(BASE_FILES)
+
+    report = pyapplebom.diff3_boms(base, bytearray(base), memoryview(base))
+
+    assert report["summary"]["ok"]
+    assert pyapplebom.exit_code(report) == pyapplebom.EXIT_OK
+    assert report["only_in_a"] == report["conflicting"] == []
+
+
+def test_diff3_ignore_fields() -> None:
+    base = build(BASE_FILES)
+    a = variant({"./etc/hosts": {"mtime": 1_700_000_000}})
+    b = variant({"./etc/hosts": {"mode": 0o600, "mtime": 1_600_000_000}})
+
+    assert pyapplebom.diff3_boms(base, a, base)["summary"]["ok"]
+
+    report = pyapplebom.diff3_boms(base, a, b, ignore_fields=())
+    assert _paths(report, "conflicting") == ["./etc/hosts"]
+    (conflict,) = report["conflicting"]
+    assert conflict["a_fields"] == ["mtime"]
+    assert conflict["b_fields"] == ["file_mode", "mtime"]
+
+    report = pyapplebom.diff3_boms(base, a, b, ignore_fields=["mtime", "file_mode"])
+    assert report["summary"]["ok"]
+
+
+def test_diff3_path_style() -> None:
+    base = build(BASE_FILES)
+    a = variant({"./etc/hosts": None})
+
+    report = pyapplebom.diff3_boms(base, a, base, path_style="absolute")
+
+    assert _paths(report, "only_in_a") == ["/etc/hosts"]
+    assert report["only_in_a"][0]["base"]["path"] == "/etc/hosts"
+
+
+def test_diff3_rejects_unknown_fields() -> None:
+    base = build(BASE_FILES)
+
+    with pytest.raises(ValueError, match='unknown field "owner"'):
+        pyapplebom.diff3_boms(base, base, base, ignore_fields=["owner"])
+
+
+def test_diff_boms_reports_changes() -> None:
+    old = build(BASE_FILES)
+    new = variant(
+        {
+            "./etc/hosts": None,
+            "./etc/app.conf": {"mode": 0o600, "uid": 501},
+            "./bin/tool": {"size": 31, "crc32": 33, "mode": 0o4755},
+            "./bin/su": {"size": 60, "mode": 0o4755},
+            "./share/readme": {"size": 51},
+        }
+    )
+
+    report = pyapplebom.diff_boms(old, new)
+
+    assert [entry["path"] for entry in report["added"]] == ["./bin/su"]
+    assert [entry["path"] for entry in report["removed"]] == ["./etc/hosts"]
+    assert [(entry["path"], entry["fields"]) for entry in report["modified"]] == [
+        ("./bin/tool", ["file_mode", "size", "crc32"]),
+        ("./etc/app.conf", ["file_mode", "user_id"]),
+        ("./share/readme", ["size"]),
+    ]
+    assert report["modified"][0]["old"]["size"] == 30
+    assert report["modified"][0]["new"]["size"] == 31
+    assert report["resized"] == ["./bin/tool", "./share/readme"]
+    assert report["permission_changes"] == ["./bin/tool", "./etc/app.conf"]
+    assert report["new_setuid"] == ["./bin/su", "./bin/tool"]
+    assert report["summary"]["counts"] == {
+        "added": 1,
+        "removed": 1,
+        "modified": 3,
+        "renamed": 0,
+        "type_changes": 0,
+        "link_changes": 0,
+    }
+    assert not report["summary"]["ok"]
+
+
+def test_diff_boms_against_none() -> None:
+    bom = build(BASE_FILES)
+
+    report = pyapplebom.diff_boms(None, bom, path_style="absolute")
+
+    assert report["removed"] == report["modified"] == []
+    assert "/etc/hosts" in [entry["path"] for entry in report["added"]]
+    assert pyapplebom.diff_boms(bom, bom)["summary"]["ok"]
+
+
+def test_diff_boms_case_insensitive() -> None:
+    old = build(BASE_FILES)
+    files = {path: dict(fields) for path, fields in BASE_FILES.items()}
+    files["./etc/Hosts"] = {**files.pop("./etc/hosts"), "size": 21}
+    files["./BIN/tool"] = files.pop("./bin/tool")
+    new = build(files)
+
+    report = pyapplebom.diff_boms(old, new, detect_renames=False)
+    assert _paths(report, "added") == ["./BIN", "./BIN/tool", "./etc/Hosts"]
+    assert _paths(report, "removed") == ["./bin/tool", "./etc/hosts"]
+
+    report = pyapplebom.diff_boms(old, new, case_sensitive=False)
+    assert report["added"] == report["removed"] == report["renamed"] == []
+    assert [(entry["path"], entry["fields"]) for entry in report["modified"]] == [
+        ("./etc/Hosts", ["size"]),
+    ]
+    assert report["unchanged"] == 8
+
+    report = pyapplebom.diff3_boms(old, new, old, case_sensitive=False)
+    assert _paths(report, "only_in_a") == ["./etc/Hosts"]
+    assert report["only_in_b"] == report["conflicting"] == []
+
+
+def build_xar(path: Path, files: dict[str, bytes]) -> None:
+    """Write a minimal xar archive; ``.gz`` names are stored zlib-encoded."""
+    heap = b""
+    tree: dict[str, Any] = {}
+    for name, data in files.items():
+        *parents, leaf = name.split("/")
+        node = tree
+        for parent in parents:
+            node = node.setdefault(parent, {})
+        encoded, style = data, "application/octet-stream"
+        if leaf.endswith(".gz"):
+            leaf, encoded, style = leaf[:-3], zlib.compress(data), "application/x-gzip"
+        node[leaf] = (len(heap), encoded, style)
+        heap += encoded
+
+    next_id = iter(range(1, 1000))
+
+    def entries(node: dict[str, Any]) -> str:
+        xml = ""
+        for name, value in node.items():
+            xml += f'<file id="{next(next_id)}"><name>{name}</name>'
+            if isinstance(value, dict):
+                xml += f"<type>directory</type>{entries(value)}</file>"
+            else:
+                offset, encoded, style = value
+                xml += (
+                    f"<type>file</type><data><offset>{offset}</offset>"
+                    f"<length>{len(encoded)}</length><size>{len(encoded)}</size>"
+                    f'<encoding style="{style}"/></data></file>'
+                )
+        return xml
+
+    toc = zlib.compress(f"<?xml version='1.0'?><xar><toc>{entries(tree)}</toc></xar>".encode())
+    header = struct.pack(">4sHHQQI", b"xar!", 28, 1, len(toc), 0, 0)
+    path.write_bytes(header + toc + heap)
+
+
+def test_compare_package_versions_component_packages(tmp_path: Path) -> None:
+    old_pkg, new_pkg = tmp_path / "old.pkg", tmp_path / "new.pkg"
+    build_xar(old_pkg, {"Bom": build(BASE_FILES), "Payload": b"payload"})
+    build_xar(new_pkg, {"Bom.gz": variant({"./bin/su": {"mode": 0o4755}})})
+
+    report = pyapplebom.compare_package_versions(old_pkg, new_pkg)
+
+    assert (report["old"], report["new"]) == (str(old_pkg), str(new_pkg))
+    assert list(report["components"]) == [""]
+    assert report["components"][""]["new_setuid"] == ["./bin/su"]
+    assert report["summary"] == {
+        "ok": False,
+        "total": 1,
+        "counts": {
+            "added": 1,
+            "removed": 0,
+            "modified": 0,
+            "renamed": 0,
+            "type_changes": 0,
+            "link_changes": 0,
+        },
+    }
+
+
+def test_compare_package_versions_product_archives(tmp_path: Path) -> None:
+    old_pkg, new_pkg = tmp_path / "old.pkg", tmp_path / "new.pkg"
+    build_xar(
+        old_pkg,
+        {
+            "Distribution": b"<installer-gui-script/>",
+            "app.pkg/Bom": build(BASE_FILES),
+            "legacy.pkg/Bom": build({"./lib/old.dylib": {"size": 5}}),
+        },
+    )
+    build_xar(
+        new_pkg,
+        {
+            "app.pkg/Bom": variant({"./share/readme": {"size": 51}}),
+            "cli.pkg/Bom": build({"./bin/cli": {"size": 7}}),
+        },
+    )
+
+    report = pyapplebom.compare_package_versions(new_pkg, new_pkg)
+    assert report["summary"]["ok"]
+
+    report = pyapplebom.compare_package_versions(old_pkg, new_pkg)
+
+    components = report["components"]
+    assert list(components) == ["app.pkg", "cli.pkg", "legacy.pkg"]
+    assert components["app.pkg"]["resized"] == ["./share/readme"]
+    assert "./bin/cli" in [entry["path"] for entry in components["cli.pkg"]["added"]]
+    assert "./lib/old.dylib" in [entry["path"] for entry in components["legacy.pkg"]["removed"]]
+    assert report["summary"]["counts"]["modified"] == 1
+
+
+def test_compare_package_versions_case_insensitive(tmp_path: Path) -> None:
+    files = {path: dict(fields) for path, fields in BASE_FILES.items()}
+    files["./BIN/tool"] = files.pop("./bin/tool")
+    old_pkg, new_pkg = tmp_path / "old.pkg", tmp_path / "new.pkg"
+    build_xar(old_pkg, {"Bom": build(BASE_FILES)})
+    build_xar(new_pkg, {"Bom": build(files)})
+
+    report = pyapplebom.compare_package_versions(old_pkg, new_pkg, detect_renames=False)
+    assert report["summary"]["counts"]["added"] == 2
+    report = pyapplebom.compare_package_versions(old_pkg, new_pkg, case_sensitive=False)
+    assert report["summary"]["ok"]
+
+
+def test_read_package_boms(tmp_path: Path) -> None:
+    bom = build(BASE_FILES)
+    bundle = tmp_path / "Old.pkg"
+    (bundle / "Contents").mkdir(parents=True)
+    (bundle / "Contents" / "Archive.bom").write_bytes(bom)
+    assert pyapplebom.read_package_boms(bundle) == {"": bom}
+
+    not_a_package = tmp_path / "plain.pkg"
+    not_a_package.write_bytes(b"PK\x03\x04")
+    with pytest.raises(ValueError, match="not a flat package"):
+        pyapplebom.read_package_boms(not_a_package)
+
+    empty = tmp_path / "empty.pkg"
+    build_xar(empty, {"Distribution": b""})
+    with pytest.raises(ValueError, match="contains no BOM"):
+        pyapplebom.read_package_boms(empty)
+
+
+def test_scan_receipts_finds_flat_and_bundle_receipts(tmp_path: Path) -> None:
+    bom = build(BASE_FILES)
+    flat = tmp_path / "var" / "db" / "receipts"
+    flat.mkdir(parents=True)
+    (flat / "com.example.tool.bom").write_bytes(bom)
+    info = {"PackageIdentifier": "com.example.tool", "PackageVersion": "2.1"}
+    (flat / "com.example.tool.plist").write_bytes(plistlib.dumps(info))
+    (flat / "com.example.bare.bom").write_bytes(bom)
+
+    bundles = tmp_path / "Library" / "Receipts"
+    legacy = bundles / "Legacy.pkg" / "Contents"
+    legacy.mkdir(parents=True)
+    (legacy / "Archive.bom").write_bytes(bom)
+    info = {"CFBundleIdentifier": "com.example.legacy", "CFBundleShortVersionString": "1.0"}
+    (legacy / "Info.plist").write_bytes(plistlib.dumps(info))
+    (bundles / "Empty.pkg").mkdir()
+
+    receipts = pyapplebom.scan_receipts(tmp_path)
+
+    assert [(r["form"], r["identifier"], r["version"]) for r in receipts] == [
+        ("flat", "com.example.bare", None),
+        ("flat", "com.example.tool", "2.1"),
+        ("bundle", "com.example.legacy", "1.0"),
+    ]
+    assert receipts[2]["receipt"] == str(bundles / "Legacy.pkg")
+    assert all(Path(r["bom_path"]).read_bytes() == bom for r in receipts)
+    assert pyapplebom.scan_receipts(tmp_path / "missing") == []
+
+
+def test_scan_receipts_quarantines_bad_boms(tmp_path: Path) -> None:
+    flat = tmp_path / "var" / "db" / "receipts"
+    flat.mkdir(parents=True)
+    (flat / "com.example.bad.bom").write_bytes(b"not a bom")
+    (flat / "com.example.good.bom").write_bytes(build(BASE_FILES))
+    quarantine = tmp_path / "quarantine"
+
+    bad, good = pyapplebom.scan_receipts(tmp_path, quarantine=quarantine)
+
+    assert good["error"] is None
+    assert bad["error"]["kind"] == "parse"
+    copy = Path(bad["error"]["quarantined"])
+    assert copy.parent == quarantine and copy.read_bytes() == b"not a bom"
+    assert "error" not in pyapplebom.scan_receipts(tmp_path)[0]
+
+
+def test_summarize_diff() -> None:
+    old = build(BASE_FILES)
+    new = variant(
+        {
+            "./etc/hosts": None,
+            "./share/readme": {"size": 51},
+            "./share/big.bin": {"size": 12_345_678},
+            "./Library/LaunchDaemons/com.example.b.plist": {"size": 100},
+            "./Library/LaunchDaemons/com.example.a.plist": {"size": 100},
+            "./Library/LaunchAgents/com.example.agent.plist": {"size": 100},
+            "./bin/su": {"mode": 0o4755},
+        }
+    )
+
+    summary = pyapplebom.summarize_diff(pyapplebom.diff_boms(old, new))
+
+    assert summary == (
+        "+5 files (12.3 MB), −1 file, 1 modified; "
+        "new launch daemons: com.example.a.plist, com.example.b.plist; "
+        "new launch agents: com.example.agent.plist; "
+        "new setuid binaries: ./bin/su"
+    )
+    assert pyapplebom.summarize_diff(pyapplebom.diff_boms(old, new), max_names=1).endswith(
+        "new launch daemons: com.example.a.plist, and 1 more; "
+        "new launch agents: com.example.agent.plist; new setuid binaries: ./bin/su"
+    )
+    assert pyapplebom.summarize_diff(pyapplebom.diff_boms(old, old)) == "no changes"
+
+
+def test_summarize_package_comparison(tmp_path: Path) -> None:
+    old_pkg, new_pkg = tmp_path / "old.pkg", tmp_path / "new.pkg"
+    build_xar(old_pkg, {"app.pkg/Bom": build(BASE_FILES)})
+    build_xar(
+        new_pkg,
+        {
+            "app.pkg/Bom": variant({"./share/readme": {"size": 51}}),
+            "cli.pkg/Bom": build({"./bin/cli": {"size": 700}}),
+        },
+    )
+
+    report = pyapplebom.compare_package_versions(old_pkg, new_pkg)
+
+    assert pyapplebom.summarize_diff(report) == "+1 file (700 bytes), 1 modified"
+
+
+def test_summarize_diff_rejects_other_reports() -> None:
+    with pytest.raises(ValueError, match='no "added" list'):
+        pyapplebom.summarize_diff({"summary": {}})
+
+
+def test_diff_boms_detects_renames() -> None:
+    old = build(
+        {
+            "./a/one.txt": {"size": 10, "crc32": 1},
+            "./a/two.txt": {"size": 10, "crc32": 1},
+            "./a/old-name": {"size": 20, "crc32": 2, "mode": 0o644},
+            "./a/empty": {"size": 0},
+            "./a/gone": {"size": 30, "crc32": 3},
+        }
+    )
+    new = build(
+        {
+            "./b/two.txt": {"size": 10, "crc32": 1},
+            "./b/one.txt": {"size": 10, "crc32": 1},
+            "./a/new-name": {"size": 20, "crc32": 2, "mode": 0o600},
+            "./b/empty": {"size": 0},
+            "./a/fresh": {"size": 30, "crc32": 4},
+        }
+    )
+
+    report = pyapplebom.diff_boms(old, new)
+
+    assert [(r["old_path"], r["new_path"], r["fields"]) for r in report["renamed"]] == [
+        ("./a/old-name", "./a/new-name", ["file_mode"]),
+        ("./a/one.txt", "./b/one.txt", []),
+        ("./a/two.txt", "./b/two.txt", []),
+    ]
+    assert report["renamed"][0]["new"]["file_mode"] & 0o777 == 0o600
+    assert [entry["path"] for entry in report["added"]] == ["./a/fresh", "./b", "./b/empty"]
+    assert [entry["path"] for entry in report["removed"]] == ["./a/empty", "./a/gone"]
+    assert report["summary"]["counts"]["renamed"] == 3
+    summary = pyapplebom.summarize_diff(report)
+    assert summary == "+2 files (30 bytes), −2 files, 3 renamed"
+
+    report = pyapplebom.diff_boms(old, new, detect_renames=False)
+    assert report["renamed"] == []
+    assert len(report["added"]) == 6
+
+
+def _linked_tree(root: Path, content: bytes, *, linked: tuple[str, ...]) -> bytes:
+    (root / "bin").mkdir(parents=True)
+    (root / "bin" / "tool").write_bytes(content)
+    for name in ("bin/alias", "tool"):
+        if name in linked:
+            os.link(root / "bin" / "tool", root / name)
+        else:
+            (root / name).write_bytes(content)
+    return pyapplebom.build_bom_from_directory(root, force_owner=(0, 0))
+
+
+def test_diff_boms_reports_hard_link_groups_once(tmp_path: Path) -> None:
+    old = _linked_tree(tmp_path / "old", b"v1\n", linked=("bin/alias", "tool"))
+    new = _linked_tree(tmp_path / "new", b"v2 is longer\n", linked=("bin/alias", "tool"))
+
+    report = pyapplebom.diff_boms(old, new, ignore_fields=("mtime", "file_mode"))
+
+    assert [(entry["path"], entry["hard_links"]) for entry in report["modified"]] == [
+        ("./bin/alias", ["./bin/tool", "./tool"]),
+    ]
+    assert report["resized"] == ["./bin/alias"]
+    assert report["link_changes"] == []
+
+    report = pyapplebom.diff_boms(None, new)
+    files = [entry for entry in report["added"] if entry["path_type"] == "file"]
+    assert [(entry["path"], entry["hard_links"]) for entry in files] == [
+        ("./bin/alias", ["./bin/tool", "./tool"]),
+    ]
+    assert pyapplebom.summarize_diff(report).startswith("+1 file (13 bytes)")
+
+
+def test_diff_boms_reports_link_membership_changes(tmp_path: Path) -> None:
+    old = _linked_tree(tmp_path / "old", b"v1\n", linked=("bin/alias", "tool"))
+    new = _linked_tree(tmp_path / "new", b"v1\n", linked=("bin/alias",))
+
+    report = pyapplebom.diff_boms(old, new, ignore_fields=("mtime", "file_mode"))
+
+    assert report["added"] == report["removed"] == report["modified"] == []
+    assert report["link_changes"] == [
+        {
+            "path": "./bin/alias",
+            "old_links": ["./bin/alias", "./bin/tool", "./tool"],
+            "new_links": ["./bin/alias", "./bin/tool"],
+        },
+        {
+            "path": "./tool",
+            "old_links": ["./bin/alias", "./bin/tool", "./tool"],
+            "new_links": ["./tool"],
+        },
+    ]
+    assert report["summary"]["counts"]["link_changes"] == 2
+    assert pyapplebom.summarize_diff(report) == "2 hard link changes"
+
+
+def test_diff_boms_flags_type_transitions() -> None:
+    old = build(BASE_FILES)
+    new = variant(
+        {
+            "./bin/tool": {"path_type": "link", "link_name": "/tmp/tool", "size": 9, "mode": None},
+            "./etc/hosts": {"size": 21},
+        }
+    )
+
+    report = pyapplebom.diff_boms(old, new)
+
+    assert [(e["path"], e["old_type"], e["new_type"]) for e in report["type_changes"]] == [
+        ("./bin/tool", "file", "link"),
+    ]
+    assert report["type_changes"][0]["new"]["link_name"] == "/tmp/tool"
+    assert [entry["path"] for entry in report["modified"]] == ["./etc/hosts"]
+    assert report["permission_changes"] == []
+    assert report["summary"]["counts"]["type_changes"] == 1
+    assert pyapplebom.summarize_diff(report) == "1 modified; type changes: ./bin/tool (file → link)"
+    unchecked = pyapplebom.diff_boms(old, new, ignore_fields=["mtime", "path_type"])
+    assert unchecked["type_changes"] == []
+
+
+def _listing(data: bytes) -> tuple[list[dict[str, Any]], list[str]]:
+    doc = pyapplebom.parse_bom(data, include_blocks=False)
+    paths = [
+        {key: value for key, value in entry.items() if key not in ("path_id", "parent_path_id")}
+        for entry in doc["paths"]
+    ]
+    return paths, sorted(entry["path"] for entry in doc["hl_index"] or [])
+
+
+def test_make_delta_round_trips_path_changes() -> None:
+    old = build(BASE_FILES)
+    new = variant(
+        {
+            "./etc/hosts": None,
+            "./bin/tool": {"size": 5_000_000_000, "crc32": 99},
+            "./bin/link": {"path_type": "link", "link_name": "tool", "crc32": 7},
+            "./var/log/app.log": {"mtime": 1_700_000_000, "uid": 501, "gid": 20},
+        }
+    )
+
+    delta = pyapplebom.make_delta(old, new)
+    assert len(delta) < len(new) // 20
+    assert _listing(pyapplebom.apply_delta(old, delta)) == _listing(new)
+    assert len(pyapplebom.make_delta(old, old)) < 24
+    assert _listing(pyapplebom.apply_delta(old, pyapplebom.make_delta(old, old))) == _listing(old)
+
+    with pytest.raises(ValueError, match="different BOM"):
+        pyapplebom.apply_delta(new, delta)
+    with pytest.raises(ValueError, match="malformed BOM delta"):
+        pyapplebom.apply_delta(old, delta[:-3])
+    corrupted = bytearray(delta)
+    corrupted[len(delta) // 2] ^= 0x01
+    with pytest.raises(ValueError, match="checksum mismatch"):
+        pyapplebom.apply_delta(old, bytes(corrupted))
+    with pytest.raises(ValueError, match="not a BOM delta"):
+        pyapplebom.apply_delta(old, b"\x00" * 16)
+
+
+def test_make_delta_carries_hard_link_groups(tmp_path: Path) -> None:
+    old = _linked_tree(tmp_path / "old", b"v1\n", linked=("bin/alias",))
+    new = _linked_tree(tmp_path / "new", b"v1\n", linked=("bin/alias", "tool"))
+
+    rebuilt = pyapplebom.apply_delta(old, pyapplebom.make_delta(old, new))
+
+    assert _listing(rebuilt) == _listing(new)
+    assert _listing(rebuilt)[1] == ["./bin/alias", "./bin/tool", "./tool"]
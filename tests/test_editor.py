@@ -0,0 +1,166 @@
+from __future__ import annotations
+
+import struct
+from pathlib import Path
+
+import pytest
+
+import pyapplebom
+
+FIXTURE = Path(__file__).parent / "fixtures" / "python-applications.bom"
+
+
+def test_rewrite_indexes_round_trips_document_entries() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom(data, include_blocks=False)
+
+    rewritten = pyapplebom.rewrite_indexes(
+        data,
+        version=2,
+        blocks=doc["blocks_index"]["entries"],
+        variables=doc["variables"],
+    )
+    patched = pyapplebom.parse_bom(rewritten, include_blocks=False)
+
+    assert len(rewritten) == len(data)
+    assert patched["header"]["version"] == 2
+    assert patched["variables"] == doc["variables"]
+    assert patched["paths"] == doc["paths"]
+
+
+def test_rewrite_indexes_appends_regions_that_outgrow_their_slot() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom(data, include_blocks=False)
+    variables = [(variable["name"], variable["block_index"]) for variable in doc["variables"]]
+
+    rewritten = pyapplebom.rewrite_indexes(data, variables=variables + [("Extra", 0)])
+    patched = pyapplebom.parse_bom(rewritten, include_blocks=False)
+
+    assert patched["header"]["vars_index_offset"] == len(data)
+    assert patched["variables"][-1]["name"] == "Extra"
+    assert patched["paths"] == doc["paths"]
+
+
+def test_rewrite_indexes_rejects_blocks_past_end_of_file() -> None:
+    data = FIXTURE.read_bytes()
+
+    with pytest.raises(ValueError):
+        pyapplebom.rewrite_indexes(data, blocks=[(0, 0), (len(data), 16)])
+
+
+def test_add_block_appends_block_and_binds_variable() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom(data, include_blocks=False)
+    block_index = doc["blocks_index"]["count"]
+
+    modified = pyapplebom.add_block(data, b"experimental", variable_name="Experiment")
+    patched = pyapplebom.parse_bom(modified, include_raw_block_bytes=True)
+
+    assert patched["blocks_index"]["count"] == block_index + 1
+    assert patched["header"]["number_of_blocks"] == doc["header"]["number_of_blocks"] + 1
+    assert patched["blocks"][block_index]["raw_hex"] == b"experimental".hex()
+    assert patched["variables"][-1] == {
+        "name": "Experiment",
+        "name_length": len("Experiment"),
+        "block_index": block_index,
+        "order": len(doc["variables"]),
+        "offset": patched["header"]["vars_index_length"] - 5 - len("Experiment"),
+    }
+    assert patched["paths"] == doc["paths"]
+
+    with pytest.raises(ValueError):
+        pyapplebom.add_block(modified, b"again", variable_name="Experiment")
+
+
+def test_added_block_declaring_more_bom_info_entries_than_it_holds_is_unknown() -> None:
+    data = FIXTURE.read_bytes()
+    # Read as BomInfo, this declares 0xffffffff entries, which apple-bom would
+    # preallocate 64 GiB for and abort the process.
+    modified = pyapplebom.add_block(data, b"\xff" * 12)
+    index = pyapplebom.parse_bom(data, include_blocks=False)["blocks_index"]["count"]
+
+    block = pyapplebom.parse_bom(modified)["blocks"][index]
+    assert block["kind"] == "Unknown" and block["parse_error"] == "unknown block type"
+    assert pyapplebom.get_block(modified, index) == block
+    assert pyapplebom.block_type_histogram([modified])["kinds"]["Unknown"]["blocks"] >= 1
+
+
+def test_remove_variable_with_gc_drops_owned_blocks() -> None:
+    data = FIXTURE.read_bytes()
+    doc = pyapplebom.parse_bom(data, include_blocks=False)
+    hl_index = next(var for var in doc["variables"] if var["name"] == "HLIndex")
+
+    stripped = pyapplebom.remove_variable(data, "HLIndex", gc=True)
+    patched = pyapplebom.parse_bom(stripped, include_blocks=False)
+    entries = patched["blocks_index"]["entries"]
+
+    assert [var["name"] for var in patched["variables"]] == [
+        "BomInfo",
+        "Paths",
+        "VIndex",
+        "Size64",
+    ]
+    assert patched["hl_index"] is None
+    assert entries[hl_index["block_index"]]["length"] == 0
+    assert patched["header"]["number_of_blocks"] == doc["header"]["number_of_blocks"] - 2
+    assert patched["paths"] == doc["paths"]
+
+    with pytest.raises(KeyError):
+        pyapplebom.remove_variable(stripped, "HLIndex")
+
+
+def test_rename_variable() -> None:
+    data = FIXTURE.read_bytes()
+
+    renamed = pyapplebom.rename_variable(data, "VIndex", "XIndex")
+    patched = pyapplebom.parse_bom(renamed, include_blocks=False)
+
+    assert [var["name"] for var in patched["variables"]][3] == "XIndex"
+    assert patched["vindex"] is None
+
+    with pytest.raises(ValueError):
+        pyapplebom.rename_variable(data, "VIndex", "Paths")
+
+
+def test_compact_drops_unreferenced_blocks_and_reports_reclaimed_bytes() -> None:
+    data = pyapplebom.remove_variable(FIXTURE.read_bytes(), "HLIndex")
+    doc = pyapplebom.parse_bom(data, include_blocks=False)
+
+    packed = pyapplebom.compact(data)
+    collected = pyapplebom.compact(data, drop_unreferenced=True)
+
+    assert packed["blocks_dropped"] == 0
+    assert packed["bytes_reclaimed"] > 0
+    assert collected["blocks_dropped"] == 2
+    assert collected["bytes_reclaimed"] > packed["bytes_reclaimed"]
+    assert len(collected["data"]) == len(data) - collected["bytes_reclaimed"]
+
+    for result in (packed, collected):
+        compacted = pyapplebom.parse_bom(result["data"], include_blocks=False)
+        assert compacted["variables"] == doc["variables"]
+        assert compacted["paths"] == doc["paths"]
+
+
+def _with_block_offset(data: bytes, index: int, offset: int) -> bytes:
+    """`data` with block `index` pointed at `offset`, without checking it."""
+    out = bytearray(data)
+    (blocks_index_offset,) = struct.unpack_from(">I", out, 16)
+    struct.pack_into(">I", out, blocks_index_offset + 4 + 8 * index, offset)
+    return bytes(out)
+
+
+def test_compact_rejects_block_pointers_past_end_of_file() -> None:
+    data = FIXTURE.read_bytes()
+    corrupted = _with_block_offset(data, 1, 0xFFFF00)
+
+    for drop_unreferenced in (False, True):
+        with pytest.raises(pyapplebom.BomParseError, match="out of range"):
+            pyapplebom.compact(corrupted, drop_unreferenced=drop_unreferenced)
+
+
+def test_remove_variable_with_gc_rejects_malformed_blocks() -> None:
+    # Block 2 is the tree the Paths variable names.
+    corrupted = _with_block_offset(FIXTURE.read_bytes(), 2, 0xFFFF00)
+
+    with pytest.raises(pyapplebom.BomParseError, match="out of range"):
+        pyapplebom.remove_variable(corrupted, "Paths", gc=True)
@@ -0,0 +1,489 @@
+from __future__ import annotations
+
+import hashlib
+import os
+from pathlib import Path
+
+import pytest
+
+import pyapplebom
+
+
+def _cksum(data: bytes) -> int:
+    crc = 0
+    for byte in data + len(data).to_bytes(8, "little").rstrip(b"\0"):
+        crc ^= byte << 24
+        for _ in range(8):
+            crc = (crc << 1) ^ 0x04C11DB7 if crc & 0x80000000 else crc << 1
+            crc &= 0xFFFFFFFF
+    return crc ^ 0xFFFFFFFF
+
+
+def _checkpoint(data: bytes, root: Path, verified: int, *findings: str) -> str:
+    lines = [
+        "pyapplebom-verify-checkpoint 1",
+        f"bom\t{_cksum(data)}\t{len(data)}",
+        f"root\t{root}",
+        "checksum\tfull",
+        f"verified\t{verified}",
+        *findings,
+    ]
+    return "\n".join(lines) + "\n"
+
+
+def build_install(root: Path) -> bytes:
+    (root / "bin").mkdir()
+    (root / "bin" / "tool").write_bytes(b"#!/bin/sh\n")
+    (root / "share").mkdir()
+    (root / "share" / "data.txt").write_bytes(b"hello\n")
+    (root / "share" / "current").symlink_to("data.txt")
+    for index in range(10):
+        (root / "share" / f"page{index}.txt").write_bytes(b"page")
+    return pyapplebom.build_bom_from_directory(root, force_owner=(os.getuid(), os.getgid()))
+
+
+def test_verify_bom_reports_nothing_for_an_intact_install(tmp_path: Path) -> None:
+    data = build_install(tmp_path)
+
+    report = pyapplebom.verify_bom(data, tmp_path)
+
+    assert report["root"] == str(tmp_path)
+    assert report["checksum"] == "full"
+    assert report["checked"] == 16
+    assert report["resumed"] == 0
+    assert report["findings"] == []
+
+
+def test_verify_bom_reports_each_kind_of_deviation(tmp_path: Path) -> None:
+    data = build_install(tmp_path)
+    (tmp_path / "bin" / "tool").chmod(0o700)
+    (tmp_path / "share" / "data.txt").write_bytes(b"HELLO\n")
+    (tmp_path / "share" / "page0.txt").write_bytes(b"longer page")
+    (tmp_path / "share" / "page1.txt").unlink()
+    (tmp_path / "share" / "page2.txt").unlink()
+    (tmp_path / "share" / "page2.txt").mkdir()
+    (tmp_path / "share" / "current").unlink()
+    (tmp_path / "share" / "current").symlink_to("page3.txt")
+
+    report = pyapplebom.verify_bom(data, tmp_path, path_style="relative")
+    findings = {(item["path"], item["kind"]): item for item in report["findings"]}
+
+    assert set(findings) == {
+        ("bin/tool", "mode"),
+        ("share/data.txt", "checksum"),
+        ("share/page0.txt", "size"),
+        ("share/page1.txt", "missing"),
+        ("share/page2.txt", "type"),
+        ("share/current", "link_target"),
+    }
+    assert findings[("bin/tool", "mode")]["actual"] == "100700"
+    assert findings[("share/page1.txt", "missing")]["actual"] is None
+    assert findings[("share/page2.txt", "type")]["actual"] == "directory"
+    assert findings[("share/current", "link_target")]["expected"] == "data.txt"
+    assert findings[("share/current", "link_target")]["actual"] == "page3.txt"
+
+
+def test_verify_bom_resumes_from_a_checkpoint(tmp_path: Path) -> None:
+    install = tmp_path / "install"
+    install.mkdir()
+    data = build_install(install)
+    (install / "share" / "data.txt").write_bytes(b"HELLO\n")
+    (install / "share" / "page9.txt").unlink()
+    checkpoint = tmp_path / "verify.checkpoint"
+    checkpoint.write_text(
+        _checkpoint(data, install, 8, "finding\t3\tmode\t=100755\t=100700")
+    )
+
+    report = pyapplebom.verify_bom(
+        data, install, path_style="relative", checkpoint=checkpoint, checkpoint_interval=3
+    )
+
+    # data.txt sorts before the checkpoint, so its new checksum isn't seen.
+    assert report["resumed"] == 8
+    assert report["checked"] == 16
+    assert [(item["path"], item["kind"], item["actual"]) for item in report["findings"]] == [
+        ("bin/tool", "mode", "100700"),
+        ("share/page9.txt", "missing", None),
+    ]
+    assert not checkpoint.exists()
+
+
+def test_verify_bom_rejects_checkpoints_from_other_runs(tmp_path: Path) -> None:
+    install = tmp_path / "install"
+    install.mkdir()
+    data = build_install(install)
+    checkpoint = tmp_path / "verify.checkpoint"
+
+    checkpoint.write_text(_checkpoint(data, tmp_path, 8))
+    with pytest.raises(ValueError, match="different"):
+        pyapplebom.verify_bom(data, install, checkpoint=checkpoint)
+
+    checkpoint.write_text("not a checkpoint\n")
+    with pytest.raises(ValueError, match="invalid"):
+        pyapplebom.verify_bom(data, install, checkpoint=checkpoint)
+
+    with pytest.raises(ValueError):
+        pyapplebom.verify_bom(data, install, checkpoint_interval=0)
+
+
+def test_verify_bom_streams_findings_to_a_callback(tmp_path: Path) -> None:
+    data = build_install(tmp_path)
+    for name in ("page1.txt", "page4.txt", "page7.txt"):
+        (tmp_path / "share" / name).unlink()
+
+    streamed: list[dict] = []
+    report = pyapplebom.verify_bom(
+        data, tmp_path, on_finding=streamed.append, per_device_concurrency=4
+    )
+    quiet: list[dict] = []
+    uncollected = pyapplebom.verify_bom(
+        data, tmp_path, on_finding=quiet.append, collect_findings=False
+    )
+
+    assert [item["path"] for item in streamed] == [
+        "./share/page1.txt",
+        "./share/page4.txt",
+        "./share/page7.txt",
+    ]
+    assert streamed == report["findings"]
+    assert quiet == streamed
+    assert uncollected["findings"] is None
+
+
+def test_verify_bom_resumes_after_the_callback_stops_the_run(tmp_path: Path) -> None:
+    install = tmp_path / "install"
+    install.mkdir()
+    data = build_install(install)
+    for name in ("page1.txt", "page7.txt"):
+        (install / "share" / name).unlink()
+    checkpoint = tmp_path / "verify.checkpoint"
+
+    def stop(finding: dict) -> None:
+        if finding["path"].endswith("page7.txt"):
+            raise RuntimeError("interrupted")
+
+    with pytest.raises(RuntimeError):
+        pyapplebom.verify_bom(
+            data, install, checkpoint=checkpoint, checkpoint_interval=4, on_finding=stop
+        )
+    assert checkpoint.exists()
+
+    report = pyapplebom.verify_bom(data, install, checkpoint=checkpoint)
+
+    assert report["resumed"] == 12
+    assert [item["path"] for item in report["findings"]] == [
+        "./share/page1.txt",
+        "./share/page7.txt",
+    ]
+    assert not checkpoint.exists()
+
+
+def test_verify_bom_summarizes_findings_and_maps_exit_codes(tmp_path: Path) -> None:
+    data = build_install(tmp_path)
+    clean = pyapplebom.verify_bom(data, tmp_path)
+    (tmp_path / "share" / "page1.txt").unlink()
+    (tmp_path / "share" / "page2.txt").unlink()
+    (tmp_path / "share" / "data.txt").write_bytes(b"HELLO\n")
+
+    dirty = pyapplebom.verify_bom(data, tmp_path, collect_findings=False)
+
+    assert clean["summary"]["ok"] is True
+    assert clean["summary"]["total"] == 0
+    assert set(clean["summary"]["counts"].values()) == {0}
+    assert dirty["summary"]["ok"] is False
+    assert dirty["summary"]["total"] == 3
+    assert dirty["summary"]["counts"]["missing"] == 2
+    assert dirty["summary"]["counts"]["checksum"] == 1
+    assert pyapplebom.exit_code(clean) == pyapplebom.EXIT_OK == 0
+    assert pyapplebom.exit_code(dirty) == pyapplebom.EXIT_FINDINGS == 1
+    assert pyapplebom.exit_code(dirty["summary"]) == 1
+    assert pyapplebom.exit_code(OSError("gone")) == pyapplebom.EXIT_ERROR == 2
+
+
+def test_verify_bom_checkpoints_keep_counts_without_findings(tmp_path: Path) -> None:
+    install = tmp_path / "install"
+    install.mkdir()
+    data = build_install(install)
+    for name in ("page1.txt", "page7.txt"):
+        (install / "share" / name).unlink()
+    checkpoint = tmp_path / "verify.checkpoint"
+
+    def stop(finding: dict) -> None:
+        if finding["path"].endswith("page7.txt"):
+            raise RuntimeError("interrupted")
+
+    with pytest.raises(RuntimeError):
+        pyapplebom.verify_bom(
+            data,
+            install,
+            checkpoint=checkpoint,
+            checkpoint_interval=4,
+            on_finding=stop,
+            collect_findings=False,
+        )
+    report = pyapplebom.verify_bom(
+        data, install, checkpoint=checkpoint, collect_findings=False
+    )
+
+    assert report["summary"]["counts"]["missing"] == 2
+
+
+def test_verify_bom_reports_suppressed_findings_separately(tmp_path: Path) -> None:
+    install = tmp_path / "install"
+    install.mkdir()
+    data = build_install(install)
+    (install / "bin" / "tool").chmod(0o700)
+    (install / "share" / "page1.txt").write_bytes(b"rewritten log")
+    (install / "share" / "page2.txt").unlink()
+    rules = [
+        {"path": "share/page*.txt", "kinds": ["size", "checksum"], "reason": "logs"},
+        {"path": "./bin/tool", "kinds": ["mode"], "actual": "100700"},
+    ]
+    streamed: list[dict] = []
+
+    report = pyapplebom.verify_bom(
+        data, install, suppressions=rules, on_finding=streamed.append
+    )
+
+    assert [(item["path"], item["kind"]) for item in report["findings"]] == [
+        ("./share/page2.txt", "missing")
+    ]
+    assert streamed == report["findings"]
+    assert [(item["path"], item["kind"], item["reason"]) for item in report["suppressed"]] == [
+        ("./bin/tool", "mode", None),
+        ("./share/page1.txt", "size", "logs"),
+    ]
+    assert report["summary"]["total"] == 1
+    assert report["summary"]["suppressed"] == 2
+
+    (install / "bin" / "tool").chmod(0o777)
+    changed = pyapplebom.verify_bom(data, install, suppressions=rules)
+    assert ("./bin/tool", "mode") in [
+        (item["path"], item["kind"]) for item in changed["findings"]
+    ]
+
+
+def test_load_suppressions_reads_json_and_toml(tmp_path: Path) -> None:
+    data = build_install(tmp_path)
+    (tmp_path / "share" / "page3.txt").unlink()
+    json_file = tmp_path / "rules.json"
+    json_file.write_text('{"suppress": ["share/page3.txt"]}')
+    toml_file = tmp_path / "rules.toml"
+    toml_file.write_text('[[suppress]]\npath = "**/page3.txt"\nkinds = ["missing"]\n')
+
+    assert pyapplebom.load_suppressions(json_file) == ["share/page3.txt"]
+    assert pyapplebom.load_suppressions(toml_file) == [
+        {"path": "**/page3.txt", "kinds": ["missing"]}
+    ]
+    for rules in (json_file, toml_file):
+        report = pyapplebom.verify_bom(data, tmp_path, suppressions=rules)
+        assert report["summary"]["ok"] is True
+        assert len(report["suppressed"]) == 1
+
+
+def test_verify_bom_rejects_malformed_suppressions(tmp_path: Path) -> None:
+    data = build_install(tmp_path)
+
+    with pytest.raises(ValueError, match="unknown kind"):
+        pyapplebom.verify_bom(data, tmp_path, suppressions=[{"path": "**", "kinds": ["x"]}])
+    with pytest.raises(ValueError, match="no 'path'"):
+        pyapplebom.verify_bom(data, tmp_path, suppressions=[{"kinds": ["mode"]}])
+
+
+def test_verify_bom_looks_up_paths_with_the_given_separator_and_case(tmp_path: Path) -> None:
+    data = build_install(tmp_path)
+    (tmp_path / "share").rename(tmp_path / "Share")
+
+    sensitive = pyapplebom.verify_bom(data, tmp_path, separator="posix")
+    assert sensitive["separator"] == "posix"
+    assert sensitive["case"] == "sensitive"
+    assert {finding["kind"] for finding in sensitive["findings"]} == {"missing"}
+
+    insensitive = pyapplebom.verify_bom(data, tmp_path, separator="posix", case="insensitive")
+    assert insensitive["case"] == "insensitive"
+    assert insensitive["findings"] == []
+
+    staged = tmp_path / "staged"
+    staged.mkdir()
+    (staged / "a\\b.txt").write_bytes(b"x")
+    data = pyapplebom.build_bom_from_directory(staged, force_owner=(os.getuid(), os.getgid()))
+    assert pyapplebom.verify_bom(data, staged)["findings"] == []
+    findings = pyapplebom.verify_bom(data, staged, separator="windows")["findings"]
+    assert [(finding["path"], finding["kind"]) for finding in findings] == [
+        ("./a\\b.txt", "invalid_path")
+    ]
+
+    with pytest.raises(ValueError, match="separator must be"):
+        pyapplebom.verify_bom(data, staged, separator="dos")
+    with pytest.raises(ValueError, match="case must be"):
+        pyapplebom.verify_bom(data, staged, case="folded")
+
+
+def test_verify_bom_follows_symlinks_when_asked(tmp_path: Path) -> None:
+    cache = tmp_path / "cache"
+    cache.mkdir()
+    (cache / "tool").write_bytes(b"#!/bin/sh\n")
+    (cache / "tool").chmod(0o755)
+    staged = tmp_path / "staged"
+    staged.mkdir()
+    (staged / "tool").symlink_to(cache / "tool")
+    (staged / "dangling").symlink_to(tmp_path / "nowhere")
+    owner = (os.getuid(), os.getgid())
+    data = pyapplebom.build_bom_from_directory(staged, follow_symlinks=True, force_owner=owner)
+
+    as_links = pyapplebom.verify_bom(data, staged)
+    assert as_links["follow_symlinks"] is False
+    assert [(finding["path"], finding["kind"]) for finding in as_links["findings"]] == [
+        ("./tool", "type")
+    ]
+    followed = pyapplebom.verify_bom(data, staged, follow_symlinks=True)
+    assert followed["follow_symlinks"] is True
+    assert followed["findings"] == []
+
+    checkpoint = tmp_path / "verify.checkpoint"
+    checkpoint.write_text(_checkpoint(data, staged, 1))
+    with pytest.raises(ValueError, match="different"):
+        pyapplebom.verify_bom(data, staged, follow_symlinks=True, checkpoint=checkpoint)
+    assert pyapplebom.verify_bom(data, staged, checkpoint=checkpoint)["resumed"] == 1
+
+
+def test_verify_bom_checks_hard_link_groups(tmp_path: Path) -> None:
+    (tmp_path / "bin").mkdir()
+    (tmp_path / "bin" / "tool").write_bytes(b"tool\n")
+    os.link(tmp_path / "bin" / "tool", tmp_path / "bin" / "alias")
+    os.link(tmp_path / "bin" / "tool", tmp_path / "bin" / "other")
+    data = pyapplebom.build_bom_from_directory(tmp_path, force_owner=(os.getuid(), os.getgid()))
+
+    assert pyapplebom.verify_bom(data, tmp_path)["findings"] == []
+
+    # Rewriting the shared content is one deviation, reported on the first link.
+    (tmp_path / "bin" / "tool").write_bytes(b"TOOL\n")
+    report = pyapplebom.verify_bom(data, tmp_path)
+    assert [(item["path"], item["kind"]) for item in report["findings"]] == [
+        ("./bin/alias", "checksum"),
+    ]
+
+    # An identical copy in place of a link breaks the group.
+    (tmp_path / "bin" / "tool").write_bytes(b"tool\n")
+    (tmp_path / "bin" / "other").unlink()
+    (tmp_path / "bin" / "other").write_bytes(b"tool\n")
+    report = pyapplebom.verify_bom(data, tmp_path)
+    assert [(item["path"], item["kind"], item["expected"]) for item in report["findings"]] == [
+        ("./bin/other", "hard_link", "./bin/alias"),
+    ]
+    assert report["summary"]["counts"]["hard_link"] == 1
+
+
+def test_verify_bom_compares_files_against_known_values(tmp_path: Path) -> None:
+    data = build_install(tmp_path)
+    known = pyapplebom.KnownFiles(
+        [
+            {"path": "/bin/tool", "size": 10, "crc32": _cksum(b"#!/bin/sh\n")},
+            {"path": "share/data.txt", "crc32": _cksum(b"hello\n"), "os_build": "23E224"},
+            {"path": "./share/data.txt", "crc32": _cksum(b"HELLO\n"), "os_build": "23F79"},
+        ]
+    )
+    known.add("./share/page0.txt", size=5)
+
+    report = pyapplebom.verify_bom(data, tmp_path, known_files=known, os_build="23F79")
+    findings = [(item["path"], item["kind"], item["expected"]) for item in report["findings"]]
+
+    assert findings == [
+        ("./share/data.txt", "known_checksum", str(_cksum(b"HELLO\n"))),
+        ("./share/page0.txt", "known_size", "5"),
+    ]
+    assert report["summary"]["counts"]["known_checksum"] == 1
+    unknown_build = pyapplebom.verify_bom(data, tmp_path, known_files=known, os_build="22A380")
+    assert [item["path"] for item in unknown_build["findings"]] == ["./share/page0.txt"]
+
+
+def test_verify_bom_reports_sha256_digests_across_checkpoints(tmp_path: Path) -> None:
+    install = tmp_path / "install"
+    install.mkdir()
+    (install / "big.bin").write_bytes(bytes(range(256)) * 1000)
+    data = build_install(install)
+    (install / "share" / "page7.txt").unlink()
+    checkpoint = tmp_path / "verify.checkpoint"
+    expected = {
+        f"/{path.relative_to(install)}": hashlib.sha256(path.read_bytes()).hexdigest()
+        for path in install.rglob("*")
+        if path.is_file() and not path.is_symlink()
+    }
+
+    def stop(finding: dict) -> None:
+        raise RuntimeError("interrupted")
+
+    with pytest.raises(RuntimeError):
+        pyapplebom.verify_bom(
+            data,
+            install,
+            path_style="absolute",
+            checkpoint=checkpoint,
+            checkpoint_interval=4,
+            on_finding=stop,
+            sha256=True,
+            read_buffer_size=1000,
+        )
+    with pytest.raises(ValueError, match="different"):
+        pyapplebom.verify_bom(data, install, checkpoint=checkpoint)
+    report = pyapplebom.verify_bom(
+        data, install, path_style="absolute", checkpoint=checkpoint, sha256=True
+    )
+
+    assert report["resumed"] > 0
+    assert report["sha256"] == expected
+    assert pyapplebom.verify_bom(data, install)["sha256"] is None
+
+
+@pytest.mark.skipif(
+    pyapplebom.build_info()["features"]["size64_trailer"], reason="reads full sizes"
+)
+def test_verify_bom_compares_truncated_sizes_by_their_low_bits(tmp_path: Path) -> None:
+    huge = 5 * 2**30 + 3
+    target = tmp_path / "huge.bin"
+    with target.open("wb") as stream:
+        stream.truncate(huge)
+    stat = target.stat()
+    builder = pyapplebom.BomBuilder()
+    builder.add_path(".", "directory", mode=0o755)
+    builder.add_path(
+        "./huge.bin", mode=stat.st_mode & 0o7777, uid=stat.st_uid, gid=stat.st_gid, size=huge
+    )
+    data = builder.build()
+
+    def size_findings() -> list[dict]:
+        report = pyapplebom.verify_bom(data, tmp_path, checksum="none")
+        return [item for item in report["findings"] if item["kind"] == "size"]
+
+    assert size_findings() == []
+    with target.open("r+b") as stream:
+        stream.truncate(huge % 2**32)
+    # The low 32 bits agree, but the file is under 4 GiB.
+    assert [(item["expected"], item["actual"]) for item in size_findings()] == [
+        (f"{huge % 2**32} (truncated to 32 bits)", str(huge % 2**32))
+    ]
+
+
+def test_export_hash_set_writes_rds_rows(tmp_path: Path) -> None:
+    data = build_install(tmp_path)
+    digests = pyapplebom.verify_bom(data, tmp_path, path_style="absolute", sha256=True)["sha256"]
+
+    exported = pyapplebom.export_hash_set(
+        data, path_root="/opt/tool", sha256={"/opt/tool/bin/tool": digests["/bin/tool"]}
+    )
+    lines = exported.splitlines()
+
+    assert lines[0] == (
+        '"SHA-1","MD5","CRC32","FileName","FileSize","ProductCode","OpSystemCode",'
+        '"SpecialCode","SHA-256"'
+    )
+    assert len(lines) == 1 + 12
+    assert lines[1] == (
+        f'"","","","/opt/tool/bin/tool",10,0,"","",'
+        f'"{digests["/bin/tool"].upper()}"'
+    )
+    assert lines[2].endswith(',"/opt/tool/share/data.txt",6,0,"","",""')
+    tagged = pyapplebom.export_hash_set(data, product_code=7, os_code='macOS "14"')
+    assert tagged.splitlines()[1].endswith(',10,7,"macOS ""14""","",""')
+    with pytest.raises(ValueError, match="not a hex SHA-256"):
+        pyapplebom.export_hash_set(data, sha256={"/bin/tool": "abc"})